@@ -0,0 +1,44 @@
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use markdown_lab_rs::markdown_converter::{
+    Document, document_from_bytes, document_to_bytes, document_to_json, parse_html_to_document,
+};
+use std::hint::black_box;
+use std::time::Duration;
+
+fn bench_document_round_trip(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Document Cache Round Trip");
+    group.measurement_time(Duration::from_secs(10));
+    group.sample_size(100);
+
+    let html_samples = [
+        ("medium", include_str!("../test_data/medium.html")),
+        ("large", include_str!("../test_data/large.html")),
+    ];
+
+    for (size, html) in html_samples.iter() {
+        let document = parse_html_to_document(html, "https://example.com").unwrap();
+
+        group.bench_with_input(BenchmarkId::new("json", size), &document, |b, document| {
+            b.iter(|| {
+                let json = document_to_json(black_box(document)).unwrap();
+                Document::from_json(black_box(&json)).unwrap()
+            })
+        });
+
+        group.bench_with_input(
+            BenchmarkId::new("messagepack", size),
+            &document,
+            |b, document| {
+                b.iter(|| {
+                    let bytes = document_to_bytes(black_box(document)).unwrap();
+                    document_from_bytes(black_box(&bytes)).unwrap()
+                })
+            },
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_document_round_trip);
+criterion_main!(benches);
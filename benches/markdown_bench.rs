@@ -1,9 +1,15 @@
-use criterion::{BenchmarkId, Criterion, black_box, criterion_group, criterion_main};
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
 use markdown_lab_rs::{
-    chunker::create_semantic_chunks,
+    chunker::{
+        ChunkOptions, ChunkStrategy, chunk_html, create_semantic_chunks,
+        create_semantic_chunks_with_options,
+    },
     html_parser::{clean_html, extract_links, extract_main_content},
-    markdown_converter::convert_to_markdown,
+    markdown_converter::{
+        ConversionOptions, OutputFormat, convert_html_with_options, convert_to_markdown,
+    },
 };
+use std::hint::black_box;
 use std::time::Duration;
 
 fn bench_html_processing(c: &mut Criterion) {
@@ -62,6 +68,10 @@ fn bench_chunking(c: &mut Criterion) {
 
     for &chunk_size in chunk_sizes.iter() {
         for &overlap in overlap_sizes.iter() {
+            // overlap must stay below the chunk size
+            if overlap >= chunk_size {
+                continue;
+            }
             group.bench_with_input(
                 BenchmarkId::new(
                     format!("chunk_size_{}_overlap_{}", chunk_size, overlap),
@@ -84,5 +94,73 @@ fn bench_chunking(c: &mut Criterion) {
     group.finish();
 }
 
-criterion_group!(benches, bench_html_processing, bench_chunking);
+fn bench_chunk_strategies(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Chunk Strategies");
+    group.measurement_time(Duration::from_secs(10));
+    group.sample_size(100);
+
+    let markdown = include_str!("../test_data/sample.md");
+
+    for (name, strategy) in [
+        ("semantic", ChunkStrategy::Semantic),
+        ("recursive", ChunkStrategy::Recursive),
+    ] {
+        let options = ChunkOptions {
+            strategy,
+            ..ChunkOptions::default()
+        };
+        group.bench_with_input(BenchmarkId::new(name, 1000), &options, |b, options| {
+            b.iter(|| {
+                create_semantic_chunks_with_options(
+                    black_box(markdown),
+                    black_box(1000),
+                    black_box(100),
+                    options,
+                )
+            })
+        });
+    }
+
+    group.finish();
+}
+
+/// `chunk_html` against converting to a markdown string and chunking that in a second call
+fn bench_html_chunking(c: &mut Criterion) {
+    let mut group = c.benchmark_group("HTML Chunking");
+    group.measurement_time(Duration::from_secs(10));
+    group.sample_size(100);
+
+    let html = include_str!("../test_data/medium.html");
+    let base_url = "https://example.com";
+    let conversion = ConversionOptions::default();
+    let options = ChunkOptions::default();
+
+    group.bench_function("two_calls", |b| {
+        b.iter(|| {
+            let markdown = convert_html_with_options(
+                black_box(html),
+                base_url,
+                OutputFormat::Markdown,
+                &conversion,
+            )
+            .unwrap();
+            // the Python flow copies the markdown across the FFI boundary and back
+            let markdown = black_box(markdown.clone());
+            create_semantic_chunks_with_options(&markdown, 1000, 100, &options)
+        })
+    });
+    group.bench_function("chunk_html", |b| {
+        b.iter(|| chunk_html(black_box(html), base_url, 1000, 100, &conversion, &options))
+    });
+
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_html_processing,
+    bench_chunking,
+    bench_chunk_strategies,
+    bench_html_chunking
+);
 criterion_main!(benches);
@@ -14,11 +14,12 @@ SAMPLE_HTML = """
 </head>
 <body>
     <h1>Markdown Lab Output Formats</h1>
-    <p>This demo shows the three output formats supported by markdown_lab:</p>
+    <p>This demo shows four of the output formats supported by markdown_lab:</p>
     <ul>
         <li>Markdown - Human-readable plain text format</li>
         <li>JSON - Structured data format for programmatic usage</li>
         <li>XML - Markup format for document interchange</li>
+        <li>Text - Plain text with the markup stripped, for search indexes</li>
     </ul>
 
     <h2>Benefits of Multiple Formats</h2>
@@ -46,7 +47,7 @@ result = convert_html_to_format(html_content, url, "json")
 
 def main():
     """
-    Converts a sample HTML string to Markdown, JSON, and XML formats and writes each result to a file in the output directory.
+    Converts a sample HTML string to Markdown, JSON, XML, and plain text formats and writes each result to a file in the output directory.
 
     Creates the output directory if it does not exist. For each format, the converted content is saved as `output.<format>` in the directory.
     """
@@ -54,7 +55,7 @@ def main():
     output_dir = Path("examples/demo_output")
     output_dir.mkdir(parents=True, exist_ok=True)
 
-    formats = ["markdown", "json", "xml"]
+    formats = ["markdown", "json", "xml", "text"]
 
     for format_name in formats:
         output_file = output_dir / f"output.{format_name}"
@@ -45,7 +45,7 @@ class RustBackend:
             logger.warning("Rust backend not available, fallback enabled")
 
     def convert_html_to_format(
-        self, html: str, base_url: str, output_format: str = "markdown"
+        self, html: str, base_url: str, output_format: str = "markdown", **options
     ) -> str:
         """
         Converts HTML content to the specified output format using the Rust backend.
@@ -54,6 +54,7 @@ class RustBackend:
             html (str): The HTML content to convert.
             base_url (str): The base URL used to resolve relative links in the HTML.
             output_format (str, optional): The desired output format ("markdown", "json", or "xml"). Defaults to "markdown".
+            **options: Conversion options such as ``title_mode`` ("always", "if_no_h1" or "never").
 
         Returns:
             str: The converted content in the specified format.
@@ -72,7 +73,9 @@ class RustBackend:
             # Always pass a normalized string to the underlying module
             normalized = (output_format or "markdown").lower()
             # Use the convert_html_to_format entrypoint
-            return self._rust_module.convert_html_to_format(html, base_url, normalized)
+            return self._rust_module.convert_html_to_format(
+                html, base_url, normalized, **options
+            )
         except Exception as e:
             raise RustIntegrationError(
                 f"Rust conversion failed: {str(e)}",
@@ -128,13 +131,20 @@ class RustBackend:
                 cause=e,
             ) from e
 
-    def render_js_page(self, url: str, wait_time: Optional[int] = None) -> str:
+    def render_js_page(
+        self,
+        url: str,
+        wait_time: Optional[int] = None,
+        wait_for_selector: Optional[str] = None,
+    ) -> str:
         """
         Render a JavaScript-enabled page.
 
         Args:
             url: URL to render
-            wait_time: Time to wait for JavaScript execution (ms)
+            wait_time: Time to wait for JavaScript execution (ms), or the most to wait
+                for wait_for_selector
+            wait_for_selector: CSS selector to wait for before capturing the page
 
         Returns:
             Rendered HTML content
@@ -150,7 +160,7 @@ class RustBackend:
             )
 
         try:
-            return self._rust_module.render_js_page(url, wait_time)
+            return self._rust_module.render_js_page(url, wait_time, wait_for_selector)
         except Exception as e:
             raise RustIntegrationError(
                 f"Rust JS rendering failed: {str(e)}",
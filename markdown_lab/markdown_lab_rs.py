@@ -162,6 +162,10 @@ class OutputFormat(str, Enum):
     MARKDOWN = "markdown"
     JSON = "json"
     XML = "xml"
+    YAML = "yaml"
+    TEXT = "text"
+    RST = "rst"
+    HTML = "html"
 
 
 # try to import the rust extension (namespaced by maturin)
@@ -204,13 +208,20 @@ def convert_html_to_format(
     html: str,
     base_url: str = "",
     output_format: str | OutputFormat | None = OutputFormat.MARKDOWN,
+    **options,
 ) -> str:
     """
-    Converts HTML content to markdown, JSON, or XML format.
+    Converts HTML content to markdown, JSON, XML, YAML, plain text,
+    reStructuredText, or sanitized HTML.
 
     Uses the Rust implementation if available; otherwise, falls back to a
-    lightweight Python implementation. Accepts either a string ("markdown",
-    "json", "xml") or the local OutputFormat enum.
+    lightweight Python implementation (which only supports markdown, JSON and
+    XML, and returns markdown for the other formats). Accepts either a string
+    ("markdown", "json", "xml", "yaml", "text", "rst", "html") or the local
+    OutputFormat enum; unknown format names raise ``ValueError`` when the Rust
+    extension is available. Keyword arguments are passed
+    to the Rust converter as conversion options (e.g. ``title_mode="if_no_h1"``)
+    and are ignored by the Python fallback.
     """
     # normalize to string value
     if isinstance(output_format, OutputFormat):
@@ -220,7 +231,10 @@ def convert_html_to_format(
 
     if RUST_AVAILABLE:
         try:
-            return _rs_convert_html_to_format(html, base_url, fmt_value)
+            return _rs_convert_html_to_format(html, base_url, fmt_value, **options)
+        except ValueError:
+            # unknown formats and option values are caller errors, not Rust failures
+            raise
         except Exception as e:
             logger.warning(
                 f"Error in Rust HTML conversion to {fmt_value}, falling back to Python: {e}"
@@ -420,21 +434,31 @@ def chunk_markdown(
     return [chunk.content for chunk in chunks]
 
 
-def render_js_page(url: str, wait_time_ms: Optional[int] = None) -> Optional[str]:
+def render_js_page(
+    url: str,
+    wait_time_ms: Optional[int] = None,
+    wait_for_selector: Optional[str] = None,
+) -> Optional[str]:
     """
     Renders a JavaScript-enabled web page and returns the resulting HTML content.
 
     If the Rust extension is available, uses it to render the page. Otherwise, logs a warning and returns None, as Python fallback is not implemented.
+    With wait_for_selector, the page is captured once an element matches it, waiting at
+    most wait_time_ms; TimeoutError is raised if none does.
     """
     # Input validation to align with Rust bindings
     if not isinstance(url, str):
         raise TypeError("url must be a string")
     if wait_time_ms is not None and not isinstance(wait_time_ms, int):
         raise TypeError("wait_time_ms must be an integer or None")
+    if wait_for_selector is not None and not isinstance(wait_for_selector, str):
+        raise TypeError("wait_for_selector must be a string or None")
 
     if RUST_AVAILABLE:
         try:
-            return _rs_render_js_page(url, wait_time_ms)
+            return _rs_render_js_page(url, wait_time_ms, wait_for_selector)
+        except TimeoutError:
+            raise
         except Exception as e:
             logger.warning(f"Error in Rust JS rendering, falling back to Python: {e}")
 
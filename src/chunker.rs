@@ -1,20 +1,31 @@
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::ops::Range;
+
 use once_cell::sync::Lazy;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use thiserror::Error;
 
-/// pre-compiled regex patterns for text processing
-static SENTENCE_BOUNDARY_REGEX: Lazy<Regex> = Lazy::new(|| {
-    // Matches sentence endings followed by whitespace
-    // Note: Rust regex doesn't support lookbehind, so we match the punctuation too
-    Regex::new(r"[.!?]\s+").unwrap()
-});
+use crate::markdown_converter::{self, ConversionOptions, MarkdownError, OutputFormat};
 
+/// pre-compiled regex patterns for text processing
 static PARAGRAPH_BOUNDARY_REGEX: Lazy<Regex> = Lazy::new(|| {
     // Matches paragraph breaks (double newlines or more)
     Regex::new(r"\n\s*\n").unwrap()
 });
 
+static LIST_ITEM_REGEX: Lazy<Regex> = Lazy::new(|| {
+    // Matches a line that opens a bulleted or numbered list item
+    Regex::new(r"^[ \t]*(?:[-*+]|\d{1,9}[.)])(?:[ \t]|$)").unwrap()
+});
+
+static WHITESPACE_REGEX: Lazy<Regex> = Lazy::new(|| {
+    // Matches runs of whitespace between words
+    Regex::new(r"\s+").unwrap()
+});
+
 static SEMANTIC_PATTERNS_REGEX: Lazy<Regex> = Lazy::new(|| {
     // semantic keywords regex
     Regex::new(r"(?i)\b(function|class|method|algorithm|process|system|data|model|analysis|implementation)\b").unwrap()
@@ -38,6 +49,15 @@ pub enum ChunkerError {
     #[error("Parsing error: {0}")]
     ParsingError(String),
 
+    #[error("Conversion error: {0}")]
+    ConversionError(#[from] MarkdownError),
+
+    #[error("Serialization error: {0}")]
+    SerializationError(String),
+
+    #[error("Invalid chunk overlap: {overlap} must be less than chunk size {chunk_size}")]
+    InvalidOverlap { overlap: usize, chunk_size: usize },
+
     #[error("Other error: {0}")]
     Other(String),
 }
@@ -48,11 +68,27 @@ pub struct Chunk {
     pub metadata: ChunkMetadata,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ChunkMetadata {
+    /// Stable content hash, see `chunk_id`
+    pub id: String,
+    /// `ChunkOptions::source_id` of the run that produced the chunk
+    pub source_id: Option<String>,
     pub heading: Option<String>,
     pub level: usize,
+    /// Texts of the enclosing headings from the outermost down to `heading`; empty before the first heading
+    pub heading_path: Vec<String>,
+    /// Every section heading the chunk covers, more than one once small chunks are merged
+    pub headings: Vec<String>,
     pub position: usize,
+    /// Character offset in the source markdown where the chunk starts; a chunk that opens
+    /// with overlap starts where the overlapped text does
+    pub start_offset: usize,
+    /// Character offset just past the chunk's end in the source markdown
+    pub end_offset: usize,
+    /// Indices of the paragraphs the chunk holds under `ChunkStrategy::Paragraphs`, end
+    /// exclusive
+    pub paragraphs: Option<Range<usize>>,
     pub word_count: usize,
     pub char_count: usize,
     pub semantic_density: f32, // A measure of the information density
@@ -64,31 +100,1360 @@ pub fn create_semantic_chunks(
     chunk_size: usize,
     chunk_overlap: usize,
 ) -> Result<Vec<String>, ChunkerError> {
-    let heading_regex = Regex::new(r"^(#{1,6})\s+(.+)$")?;
-    let chunks = semantic_chunking(markdown, chunk_size, chunk_overlap, &heading_regex)?;
+    let chunks = create_semantic_chunks_with_metadata(markdown, chunk_size, chunk_overlap)?;
 
     // Return just the content strings for Python integration
     Ok(chunks.into_iter().map(|chunk| chunk.content).collect())
 }
 
+/// Creates semantic chunks like `create_semantic_chunks`, keeping each chunk's heading, position and size metadata
+pub fn create_semantic_chunks_with_metadata(
+    markdown: &str,
+    chunk_size: usize,
+    chunk_overlap: usize,
+) -> Result<Vec<Chunk>, ChunkerError> {
+    create_semantic_chunks_with_options(
+        markdown,
+        chunk_size,
+        chunk_overlap,
+        &ChunkOptions::default(),
+    )
+}
+
+/// Creates semantic chunks whose size and overlap are measured in `unit` rather than characters
+///
+/// No chunk holds more than `max_units` units unless a single heading line does.
+pub fn create_semantic_chunks_sized(
+    markdown: &str,
+    max_units: usize,
+    overlap_units: usize,
+    unit: SizeUnit,
+) -> Result<Vec<String>, ChunkerError> {
+    let options = ChunkOptions {
+        unit,
+        ..ChunkOptions::default()
+    };
+    let chunks = create_semantic_chunks_with_options(markdown, max_units, overlap_units, &options)?;
+
+    Ok(chunks.into_iter().map(|chunk| chunk.content).collect())
+}
+
+/// Version of the `ChunkManifest` layout, raised whenever its fields change
+pub const MANIFEST_VERSION: u32 = 1;
+
+/// What `chunk_markdown_to_manifest` records about a document's chunks
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChunkManifest {
+    /// `MANIFEST_VERSION` of the code that wrote the manifest
+    pub version: u32,
+    pub source_id: Option<String>,
+    /// Characters in the whole document
+    pub char_count: usize,
+    /// Words in the whole document
+    pub word_count: usize,
+    pub report: ChunkingReport,
+    /// Metadata of each chunk, in the order the chunk texts are returned
+    pub chunks: Vec<ChunkMetadata>,
+}
+
+/// Chunks `markdown` with `config`, returning the chunk texts and a `ChunkManifest` of
+/// their metadata serialized as JSON
+///
+/// Entry `i` of the manifest's `chunks` describes text `i`.
+pub fn chunk_markdown_to_manifest(
+    markdown: &str,
+    config: &ChunkerConfig,
+) -> Result<(Vec<String>, String), ChunkerError> {
+    config.clone().build()?.chunk_to_manifest(markdown)
+}
+
+/// The unit chunk sizes and overlaps are measured in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SizeUnit {
+    /// Unicode characters
+    #[default]
+    Chars,
+    /// Whitespace-separated words
+    Words,
+    /// Approximate LLM tokens: runs of letters, digits or extra whitespace cost one token per
+    /// `BYTES_PER_TOKEN` bytes, punctuation and wide characters such as CJK one token each
+    Tokens,
+}
+
+/// Bytes of a letter, digit or whitespace run that make up one approximate token
+pub const BYTES_PER_TOKEN: usize = 4;
+
+impl SizeUnit {
+    /// Counts how many units `text` takes up
+    pub fn count(self, text: &str) -> usize {
+        match self {
+            SizeUnit::Chars => text.chars().count(),
+            SizeUnit::Words => text.split_whitespace().count(),
+            SizeUnit::Tokens => token_spans(text).len(),
+        }
+    }
+}
+
+/// Optional chunking behaviour on top of the size and overlap settings
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChunkOptions {
+    /// Carry the overlap into the first chunk of a new heading section as well
+    pub overlap_across_sections: bool,
+    /// What `chunk_size` and `chunk_overlap` count
+    pub unit: SizeUnit,
+    /// Chunks with fewer units are merged into a neighbour; 0 keeps every chunk
+    pub min_chunk_size: usize,
+    /// How the markdown is cut up
+    pub strategy: ChunkStrategy,
+    /// Where the markdown came from, usually its URL; mixed into every chunk ID
+    pub source_id: Option<String>,
+    /// Start a chunk that picks a list up between two items with a "…continued from list
+    /// under <heading>" line. The line is not counted against `chunk_size` and is not part
+    /// of the chunk's source offsets. Only the `Semantic` and `ByHeading` strategies add it.
+    pub list_continuation: bool,
+    /// Most chunks to return; longer results are cut down by `truncation`
+    pub max_chunks: Option<usize>,
+    /// How a result over `max_chunks` is cut down
+    pub truncation: TruncationStrategy,
+}
+
+/// How `ChunkOptions::max_chunks` cuts down a result with too many chunks
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TruncationStrategy {
+    /// Keep the first `max_chunks` chunks
+    #[default]
+    Truncate,
+    /// Keep `max_chunks` chunks spread evenly over the document, first and last included
+    Sample,
+    /// Merge runs of neighbouring chunks, as evenly as possible, into `max_chunks` chunks;
+    /// the merged chunks may be far over `chunk_size`
+    Merge,
+}
+
+/// What chunking produced before `max_chunks` was applied, returned by
+/// `Chunker::chunk_with_report`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkingReport {
+    /// Chunks the document made
+    pub produced: usize,
+    /// Chunks handed back
+    pub returned: usize,
+    pub max_chunks: Option<usize>,
+    /// Whether `max_chunks` cut the result down
+    pub truncated: bool,
+    pub strategy: TruncationStrategy,
+}
+
+/// The algorithm that cuts markdown into chunks
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChunkStrategy {
+    /// Start a chunk at every heading and split oversized sections at the best nearby break
+    #[default]
+    Semantic,
+    /// Split on blank lines, then sentence ends, then whitespace, going finer only for pieces
+    /// still over budget, and pack the pieces back into chunks (LangChain's recursive splitter).
+    /// Headings only feed metadata, and `overlap_across_sections` has no effect.
+    Recursive,
+    /// One chunk per section opened by a heading at `level` or above (`1` for `#`), with
+    /// deeper headings kept inside their section. Sections over `max_size` units are
+    /// split further the `Semantic` way; `chunk_size` is not used otherwise. Every chunk
+    /// carries its section's heading and level.
+    ByHeading { level: u8, max_size: Option<usize> },
+    /// `per_chunk` consecutive paragraphs, as blank lines outside code fences separate them,
+    /// per chunk. Headings are plain text here; chunks carry the range of paragraphs they
+    /// hold instead. `chunk_overlap` counts paragraphs, and `chunk_size` is not used.
+    Paragraphs { per_chunk: usize },
+}
+
+/// Creates semantic chunks with metadata, honouring `ChunkOptions`
+///
+/// Each chunk after the first starts with roughly the last `chunk_overlap` units of the
+/// previous one, snapped forward to a sentence or word boundary. Chunks that start at a heading
+/// only get the overlap when `overlap_across_sections` is set.
+///
+/// With a `min_chunk_size`, each smaller chunk is merged into the one after it, or the one
+/// before it at the end of the document, as long as the result stays within one and a half
+/// times `chunk_size`.
+///
+/// Returns an error for sizes `validate_chunk_sizes` rejects, checking a `ByHeading` `max_size`
+/// or a `Paragraphs` `per_chunk` in place of `chunk_size`.
+pub fn create_semantic_chunks_with_options(
+    markdown: &str,
+    chunk_size: usize,
+    chunk_overlap: usize,
+    options: &ChunkOptions,
+) -> Result<Vec<Chunk>, ChunkerError> {
+    ChunkerConfig {
+        chunk_size,
+        chunk_overlap: OverlapSpec::Absolute(chunk_overlap),
+        options: options.clone(),
+    }
+    .build()?
+    .chunk(markdown)
+}
+
+/// How much each chunk repeats from the one before it
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum OverlapSpec {
+    /// A number of units
+    Absolute(usize),
+    /// A fraction of `chunk_size`, or of the `ByHeading` `max_size` or `Paragraphs`
+    /// `per_chunk` under those strategies, from 0 to 0.5, rounded down to whole units
+    Ratio(f32),
+}
+
+impl OverlapSpec {
+    /// The overlap in the units `chunk_size` counts
+    pub fn resolve(self, chunk_size: usize) -> usize {
+        match self {
+            OverlapSpec::Absolute(overlap) => overlap,
+            OverlapSpec::Ratio(ratio) => (chunk_size as f64 * f64::from(ratio)) as usize,
+        }
+    }
+}
+
+impl From<usize> for OverlapSpec {
+    fn from(overlap: usize) -> Self {
+        OverlapSpec::Absolute(overlap)
+    }
+}
+
+/// Everything a `Chunker` needs: the chunk size, the overlap and the `ChunkOptions`
+///
+/// ```
+/// use markdown_lab_rs::chunker::{ChunkStrategy, ChunkerConfig};
+///
+/// let chunker = ChunkerConfig::new()
+///     .chunk_size(1000)
+///     .overlap(100)
+///     .strategy(ChunkStrategy::Recursive)
+///     .build()
+///     .unwrap();
+/// let chunks = chunker.chunk("# Title\n\nSome text.").unwrap();
+/// assert_eq!(chunks[0].content, "# Title\n\nSome text.");
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChunkerConfig {
+    /// Most units in a chunk
+    pub chunk_size: usize,
+    /// Units each chunk repeats from the one before it
+    pub chunk_overlap: OverlapSpec,
+    pub options: ChunkOptions,
+}
+
+impl Default for ChunkerConfig {
+    fn default() -> Self {
+        ChunkerConfig {
+            chunk_size: 1000,
+            chunk_overlap: OverlapSpec::Absolute(200),
+            options: ChunkOptions::default(),
+        }
+    }
+}
+
+impl ChunkerConfig {
+    /// The defaults: 1000 characters with 200 of overlap, split the `Semantic` way
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size;
+        self
+    }
+
+    pub fn overlap(mut self, chunk_overlap: impl Into<OverlapSpec>) -> Self {
+        self.chunk_overlap = chunk_overlap.into();
+        self
+    }
+
+    /// Overlaps chunks by `ratio` of `chunk_size`, see `OverlapSpec::Ratio`
+    pub fn overlap_ratio(mut self, ratio: f32) -> Self {
+        self.chunk_overlap = OverlapSpec::Ratio(ratio);
+        self
+    }
+
+    pub fn unit(mut self, unit: SizeUnit) -> Self {
+        self.options.unit = unit;
+        self
+    }
+
+    pub fn strategy(mut self, strategy: ChunkStrategy) -> Self {
+        self.options.strategy = strategy;
+        self
+    }
+
+    pub fn min_chunk_size(mut self, min_chunk_size: usize) -> Self {
+        self.options.min_chunk_size = min_chunk_size;
+        self
+    }
+
+    pub fn overlap_across_sections(mut self, overlap_across_sections: bool) -> Self {
+        self.options.overlap_across_sections = overlap_across_sections;
+        self
+    }
+
+    pub fn source_id(mut self, source_id: impl Into<String>) -> Self {
+        self.options.source_id = Some(source_id.into());
+        self
+    }
+
+    pub fn list_continuation(mut self, list_continuation: bool) -> Self {
+        self.options.list_continuation = list_continuation;
+        self
+    }
+
+    pub fn max_chunks(mut self, max_chunks: usize) -> Self {
+        self.options.max_chunks = Some(max_chunks);
+        self
+    }
+
+    pub fn truncation(mut self, truncation: TruncationStrategy) -> Self {
+        self.options.truncation = truncation;
+        self
+    }
+
+    /// Checks the sizes as `validate_chunk_sizes` does, against a `ByHeading` `max_size`
+    /// or a `Paragraphs` `per_chunk` in place of the unused `chunk_size`, once an overlap
+    /// ratio is checked and resolved
+    pub fn build(self) -> Result<Chunker, ChunkerError> {
+        if let OverlapSpec::Ratio(ratio) = self.chunk_overlap
+            && !(0.0..=0.5).contains(&ratio)
+        {
+            return Err(ChunkerError::ParsingError(format!(
+                "overlap ratio must be between 0 and 0.5, got {}",
+                ratio
+            )));
+        }
+        let chunk_overlap = self.chunk_overlap.resolve(
+            strategy_size(self.chunk_size, &self.options.strategy).unwrap_or(self.chunk_size),
+        );
+        validate_options(self.chunk_size, chunk_overlap, &self.options)?;
+        Ok(Chunker {
+            heading_regex: Regex::new(r"^(#{1,6})\s+(.+)$")?,
+            chunk_overlap,
+            config: self,
+        })
+    }
+}
+
+/// Chunks markdown with a validated `ChunkerConfig`; every chunking function goes through one
+#[derive(Debug, Clone)]
+pub struct Chunker {
+    config: ChunkerConfig,
+    /// `config.chunk_overlap` in units
+    chunk_overlap: usize,
+    heading_regex: Regex,
+}
+
+impl Chunker {
+    pub fn config(&self) -> &ChunkerConfig {
+        &self.config
+    }
+
+    /// The overlap in units, with a ratio resolved against `chunk_size`
+    pub fn chunk_overlap(&self) -> usize {
+        self.chunk_overlap
+    }
+
+    /// Chunks `markdown` as `create_semantic_chunks_with_options` describes
+    pub fn chunk(&self, markdown: &str) -> Result<Vec<Chunk>, ChunkerError> {
+        self.chunk_with_report(markdown).map(|(chunks, _)| chunks)
+    }
+
+    /// Chunks `markdown` like `chunk`, also reporting whether `max_chunks` cut the result down
+    pub fn chunk_with_report(
+        &self,
+        markdown: &str,
+    ) -> Result<(Vec<Chunk>, ChunkingReport), ChunkerError> {
+        let ChunkerConfig {
+            chunk_size,
+            ref options,
+            ..
+        } = self.config;
+        let chunk_overlap = self.chunk_overlap;
+        let heading_regex = &self.heading_regex;
+
+        let mut chunks = match options.strategy {
+            ChunkStrategy::Semantic => {
+                semantic_chunking(markdown, chunk_size, chunk_overlap, options, heading_regex)?
+            }
+            ChunkStrategy::Recursive => recursive_chunking(
+                markdown,
+                chunk_size,
+                chunk_overlap,
+                options.unit,
+                heading_regex,
+            ),
+            ChunkStrategy::ByHeading { level, max_size } => heading_chunking(
+                markdown,
+                level,
+                max_size,
+                chunk_overlap,
+                options,
+                heading_regex,
+            )?,
+            ChunkStrategy::Paragraphs { per_chunk } => {
+                paragraph_chunking(markdown, per_chunk, chunk_overlap)
+            }
+        };
+        // Merging looks for the repeated text, which is whole paragraphs here
+        let chunk_overlap = match options.strategy {
+            ChunkStrategy::Paragraphs { .. } if chunk_overlap > 0 => usize::MAX,
+            _ => chunk_overlap,
+        };
+        let mut char_offsets = CharOffsets::default();
+        for chunk in &mut chunks {
+            char_offsets.convert(markdown, &mut chunk.metadata);
+        }
+        let chunks = if options.min_chunk_size == 0 {
+            chunks
+        } else {
+            merge_small_chunks(chunks, chunk_size, chunk_overlap, options)
+        };
+
+        let produced = chunks.len();
+        let mut chunks = match options.max_chunks {
+            Some(max_chunks) if produced > max_chunks => {
+                limit_chunks(chunks, max_chunks, chunk_overlap, options)
+            }
+            _ => chunks,
+        };
+        let report = ChunkingReport {
+            produced,
+            returned: chunks.len(),
+            max_chunks: options.max_chunks,
+            truncated: chunks.len() < produced,
+            strategy: options.truncation,
+        };
+
+        if let Some(source_id) = &options.source_id {
+            for chunk in &mut chunks {
+                chunk.metadata.id = chunk_id(&chunk.content, Some(source_id));
+                chunk.metadata.source_id = Some(source_id.clone());
+            }
+        }
+        Ok((chunks, report))
+    }
+
+    /// Chunks `markdown` as `chunk_markdown_to_manifest` describes
+    pub fn chunk_to_manifest(&self, markdown: &str) -> Result<(Vec<String>, String), ChunkerError> {
+        let (chunks, report) = self.chunk_with_report(markdown)?;
+        let (texts, metadata) = chunks
+            .into_iter()
+            .map(|chunk| (chunk.content, chunk.metadata))
+            .unzip();
+        let manifest = ChunkManifest {
+            version: MANIFEST_VERSION,
+            source_id: self.config.options.source_id.clone(),
+            char_count: markdown.chars().count(),
+            word_count: markdown.split_whitespace().count(),
+            report,
+            chunks: metadata,
+        };
+        let json = serde_json::to_string(&manifest).map_err(|e| {
+            ChunkerError::SerializationError(format!("Failed to serialize chunk manifest: {}", e))
+        })?;
+        Ok((texts, json))
+    }
+
+    /// Streams the chunks of `markdown`, see `ChunkIterator`
+    pub fn stream<'a>(&self, markdown: impl Into<Cow<'a, str>>) -> ChunkIterator<'a> {
+        ChunkIterator::new(
+            markdown,
+            self.config.chunk_size,
+            self.chunk_overlap,
+            &self.config.options,
+        )
+    }
+
+    /// Converts the main content of `html` to markdown and chunks it, see `chunk_html`
+    pub fn chunk_html(
+        &self,
+        html: &str,
+        base_url: &str,
+        conversion: &ConversionOptions,
+    ) -> Result<Vec<Chunk>, ChunkerError> {
+        let markdown = markdown_converter::convert_html_with_options(
+            html,
+            base_url,
+            OutputFormat::Markdown,
+            conversion,
+        )?;
+
+        if self.config.options.source_id.is_some() {
+            return self.chunk(&markdown);
+        }
+        let mut chunker = self.clone();
+        chunker.config.options.source_id = Some(base_url.to_string());
+        chunker.chunk(&markdown)
+    }
+}
+
+/// Checks that chunks have room for more than their overlap
+///
+/// Returns `ChunkerError::ParsingError` for a `chunk_size` of zero and
+/// `ChunkerError::InvalidOverlap` unless `chunk_overlap` is less than `chunk_size`.
+pub fn validate_chunk_sizes(chunk_size: usize, chunk_overlap: usize) -> Result<(), ChunkerError> {
+    if chunk_size == 0 {
+        return Err(ChunkerError::ParsingError(
+            "chunk size must be greater than zero".to_string(),
+        ));
+    }
+    if chunk_overlap >= chunk_size {
+        return Err(ChunkerError::InvalidOverlap {
+            overlap: chunk_overlap,
+            chunk_size,
+        });
+    }
+    Ok(())
+}
+
+/// `validate_chunk_sizes` applied to the size the strategy cuts by: `chunk_size`, a
+/// `ByHeading` `max_size` or a `Paragraphs` `per_chunk`; plus a nonzero `max_chunks`
+fn validate_options(
+    chunk_size: usize,
+    chunk_overlap: usize,
+    options: &ChunkOptions,
+) -> Result<(), ChunkerError> {
+    if options.max_chunks == Some(0) {
+        return Err(ChunkerError::ParsingError(
+            "max_chunks must be greater than zero".to_string(),
+        ));
+    }
+    match strategy_size(chunk_size, &options.strategy) {
+        Some(size) => validate_chunk_sizes(size, chunk_overlap),
+        // Whole sections, so neither size nor overlap is used
+        None => Ok(()),
+    }
+}
+
+/// The size `strategy` cuts chunks by, which an overlap is checked and resolved against
+fn strategy_size(chunk_size: usize, strategy: &ChunkStrategy) -> Option<usize> {
+    match *strategy {
+        ChunkStrategy::Semantic | ChunkStrategy::Recursive => Some(chunk_size),
+        ChunkStrategy::ByHeading { max_size, .. } => max_size,
+        ChunkStrategy::Paragraphs { per_chunk } => Some(per_chunk),
+    }
+}
+
+/// Converts the main content of `html` to markdown and chunks it in one pass
+///
+/// The chunks' `source_id` is `base_url` unless `options` names one.
+pub fn chunk_html(
+    html: &str,
+    base_url: &str,
+    chunk_size: usize,
+    chunk_overlap: usize,
+    conversion: &ConversionOptions,
+    options: &ChunkOptions,
+) -> Result<Vec<Chunk>, ChunkerError> {
+    // Bad sizes fail before the conversion work
+    ChunkerConfig {
+        chunk_size,
+        chunk_overlap: OverlapSpec::Absolute(chunk_overlap),
+        options: options.clone(),
+    }
+    .build()?
+    .chunk_html(html, base_url, conversion)
+}
+
+/// Hex digits kept from the SHA-256 digest in a chunk ID
+const CHUNK_ID_LENGTH: usize = 32;
+
+/// Computes the ID of a chunk: a truncated SHA-256 of its whitespace-normalised content,
+/// prefixed by `source_id` when there is one
+///
+/// Identical content from the same source always gets the same ID, however its whitespace
+/// was laid out, so chunks can be deduplicated across crawls.
+pub fn chunk_id(content: &str, source_id: Option<&str>) -> String {
+    let mut hasher = Sha256::new();
+    if let Some(source_id) = source_id {
+        hasher.update(source_id.as_bytes());
+        // keeps the source and content apart
+        hasher.update([0]);
+    }
+    for (i, word) in content.split_whitespace().enumerate() {
+        if i > 0 {
+            hasher.update(b" ");
+        }
+        hasher.update(word.as_bytes());
+    }
+
+    let mut id: String = hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    id.truncate(CHUNK_ID_LENGTH);
+    id
+}
+
+/// Folds chunks under `min_chunk_size` units into their neighbours, then renumbers positions
+fn merge_small_chunks(
+    chunks: Vec<Chunk>,
+    chunk_size: usize,
+    chunk_overlap: usize,
+    options: &ChunkOptions,
+) -> Vec<Chunk> {
+    let max_merged = chunk_size + chunk_size / 2;
+    let is_small = |chunk: &Chunk| options.unit.count(&chunk.content) < options.min_chunk_size;
+
+    let mut merged: Vec<Chunk> = Vec::new();
+    let mut pending: Option<Chunk> = None;
+    for chunk in chunks {
+        let chunk = match pending.take() {
+            Some(small) => {
+                match merge_pair(&small, &chunk, chunk_overlap, options.unit, max_merged) {
+                    Some(combined) => combined,
+                    None => {
+                        merged.push(small);
+                        chunk
+                    }
+                }
+            }
+            None => chunk,
+        };
+
+        if is_small(&chunk) {
+            pending = Some(chunk);
+        } else {
+            merged.push(chunk);
+        }
+    }
+
+    // A small last chunk goes into the one before it instead
+    if let Some(small) = pending {
+        match merged.pop() {
+            Some(previous) => {
+                match merge_pair(&previous, &small, chunk_overlap, options.unit, max_merged) {
+                    Some(combined) => merged.push(combined),
+                    None => merged.extend([previous, small]),
+                }
+            }
+            None => merged.push(small),
+        }
+    }
+
+    for (position, chunk) in merged.iter_mut().enumerate() {
+        chunk.metadata.position = position;
+    }
+    merged
+}
+
+/// Cuts `chunks` down to `max_chunks` the way `options.truncation` says
+fn limit_chunks(
+    chunks: Vec<Chunk>,
+    max_chunks: usize,
+    chunk_overlap: usize,
+    options: &ChunkOptions,
+) -> Vec<Chunk> {
+    let produced = chunks.len();
+    let mut limited: Vec<Chunk> = match options.truncation {
+        TruncationStrategy::Truncate => chunks.into_iter().take(max_chunks).collect(),
+        TruncationStrategy::Sample => {
+            // Evenly spaced indices from the first chunk to the last
+            let step = |i: usize| match max_chunks {
+                1 => 0,
+                _ => i * (produced - 1) / (max_chunks - 1),
+            };
+            let mut wanted = (0..max_chunks).map(step).peekable();
+            chunks
+                .into_iter()
+                .enumerate()
+                .filter(|(index, _)| wanted.next_if_eq(index).is_some())
+                .map(|(_, chunk)| chunk)
+                .collect()
+        }
+        TruncationStrategy::Merge => {
+            // Group i takes chunks i * produced / max_chunks up to the next group's start
+            let mut merged: Vec<Chunk> = Vec::with_capacity(max_chunks);
+            for (index, chunk) in chunks.into_iter().enumerate() {
+                let group = index * max_chunks / produced;
+                if merged.len() > group {
+                    let previous = merged.pop().expect("the group has a chunk");
+                    let combined =
+                        merge_pair(&previous, &chunk, chunk_overlap, options.unit, usize::MAX)
+                            .expect("merging without a size limit");
+                    merged.push(combined);
+                } else {
+                    merged.push(chunk);
+                }
+            }
+            merged
+        }
+    };
+
+    for (position, chunk) in limited.iter_mut().enumerate() {
+        chunk.metadata.position = position;
+    }
+    limited
+}
+
+/// Joins two neighbouring chunks unless the result would exceed `max_units`
+fn merge_pair(
+    first: &Chunk,
+    second: &Chunk,
+    chunk_overlap: usize,
+    unit: SizeUnit,
+    max_units: usize,
+) -> Option<Chunk> {
+    // Drop the overlap the second chunk repeats from the first
+    let repeated = repeated_overlap(&first.content, &second.content, chunk_overlap, unit);
+    let rest = second.content[repeated..].trim_start();
+    let content = if first.content.ends_with(char::is_whitespace) {
+        format!("{}{}", first.content, rest)
+    } else {
+        format!("{}\n\n{}", first.content, rest)
+    };
+    if unit.count(&content) > max_units {
+        return None;
+    }
+
+    let mut headings = first.metadata.headings.clone();
+    headings.extend(second.metadata.headings.iter().cloned());
+
+    // The merged chunk belongs to the first section that has a heading
+    let lead = if first.metadata.heading.is_some() {
+        &first.metadata
+    } else {
+        &second.metadata
+    };
+    let mut chunk = create_chunk_object(
+        &content,
+        lead.heading.clone(),
+        lead.level,
+        &[],
+        lead.position,
+        first.metadata.start_offset..second.metadata.end_offset,
+    );
+    chunk.metadata.heading_path = lead.heading_path.clone();
+    chunk.metadata.headings = headings;
+    if let (Some(first), Some(second)) = (&first.metadata.paragraphs, &second.metadata.paragraphs) {
+        chunk.metadata.paragraphs = Some(first.start..second.end);
+    }
+    Some(chunk)
+}
+
+/// Bytes at the start of `second` that repeat the end of `first`, as the overlap leaves them
+fn repeated_overlap(first: &str, second: &str, chunk_overlap: usize, unit: SizeUnit) -> usize {
+    if chunk_overlap == 0 {
+        return 0;
+    }
+    let first = first.trim_end();
+    let spans = unit_spans(first, unit);
+    let earliest = spans
+        .len()
+        .checked_sub(chunk_overlap)
+        .map_or(0, |index| spans[index].0);
+
+    // The longest repeat wins; it has to start on a word
+    first[earliest..]
+        .char_indices()
+        .map(|(i, _)| earliest + i)
+        .filter(|&i| i == 0 || first[..i].ends_with(char::is_whitespace))
+        .map(|i| &first[i..])
+        .find(|tail| !tail.is_empty() && second.starts_with(tail))
+        .map_or(0, str::len)
+}
+
+/// Records a heading in the stack of open headings
+fn open_heading(heading_stack: &mut Vec<(usize, String)>, level: usize, text: &str) {
+    // A heading closes every open heading at its level or deeper; skipped levels
+    // simply leave no entry behind
+    while heading_stack
+        .last()
+        .is_some_and(|(open_level, _)| *open_level >= level)
+    {
+        heading_stack.pop();
+    }
+    heading_stack.push((level, text.to_string()));
+}
+
+/// Internal function for `ChunkStrategy::ByHeading`
+fn heading_chunking(
+    markdown: &str,
+    level: u8,
+    max_size: Option<usize>,
+    chunk_overlap: usize,
+    options: &ChunkOptions,
+    heading_regex: &Regex,
+) -> Result<Vec<Chunk>, ChunkerError> {
+    // Sections as their text, byte range and the heading stack at their heading
+    let mut sections = Vec::new();
+    let mut heading_stack: Vec<(usize, String)> = Vec::new();
+    let mut section = String::new();
+    let mut section_source = 0..0;
+    let mut section_stack: Vec<(usize, String)> = Vec::new();
+
+    let mut offset = 0;
+    loop {
+        let line_start = offset;
+        let Some(line) = next_line(markdown, &mut offset) else {
+            break;
+        };
+        if let Some(captures) = heading_regex.captures(line) {
+            let heading_level = captures[1].len();
+            open_heading(&mut heading_stack, heading_level, &captures[2]);
+            if heading_level <= usize::from(level) {
+                if !section.trim().is_empty() {
+                    sections.push((
+                        std::mem::take(&mut section),
+                        section_source.clone(),
+                        section_stack,
+                    ));
+                }
+                section.clear();
+                section_source = line_start..line_start;
+                section_stack = heading_stack.clone();
+            }
+        }
+
+        if !section.is_empty() {
+            section.push('\n');
+        }
+        section.push_str(line);
+        section_source.end = line_start + line.len();
+    }
+    if !section.trim().is_empty() {
+        sections.push((section, section_source, section_stack));
+    }
+
+    let mut chunks: Vec<Chunk> = Vec::new();
+    for (text, source, stack) in sections {
+        let (heading, level) = stack.last().map_or((None, 0), |(level, heading)| {
+            (Some(heading.clone()), *level)
+        });
+
+        match max_size {
+            Some(max_size) if options.unit.count(&text) > max_size => {
+                // Deeper headings may start sub-chunks, but the section stays their heading
+                // The section's own lines chunk the same as `text` and keep their offsets
+                let section_text = &markdown[source.clone()];
+                for mut chunk in semantic_chunking(
+                    section_text,
+                    max_size,
+                    chunk_overlap,
+                    options,
+                    heading_regex,
+                )? {
+                    chunk.metadata.start_offset += source.start;
+                    chunk.metadata.end_offset += source.start;
+                    chunk.metadata.heading = heading.clone();
+                    chunk.metadata.level = level;
+                    chunk.metadata.heading_path =
+                        stack.iter().map(|(_, text)| text.clone()).collect();
+                    chunk.metadata.headings = heading.iter().cloned().collect();
+                    chunk.metadata.position = chunks.len();
+                    chunks.push(chunk);
+                }
+            }
+            _ => chunks.push(create_chunk_object(
+                &text,
+                heading,
+                level,
+                &stack,
+                chunks.len(),
+                source,
+            )),
+        }
+    }
+
+    Ok(chunks)
+}
+
+/// Internal function for `ChunkStrategy::Recursive`
+fn recursive_chunking(
+    markdown: &str,
+    chunk_size: usize,
+    chunk_overlap: usize,
+    unit: SizeUnit,
+    heading_regex: &Regex,
+) -> Vec<Chunk> {
+    let mut pieces = Vec::new();
+    split_recursively(
+        markdown,
+        0..markdown.len(),
+        0,
+        chunk_size,
+        unit,
+        &mut pieces,
+    );
+
+    // Heading lines by byte offset, so each chunk gets the section it starts in
+    let mut offset = 0;
+    let mut headings = Vec::new();
+    for line in markdown.split_inclusive('\n') {
+        if let Some(captures) = heading_regex.captures(line.trim_end_matches(['\n', '\r'])) {
+            headings.push((offset, captures[1].len(), captures[2].to_string()));
+        }
+        offset += line.len();
+    }
+    let mut headings = headings.into_iter().peekable();
+    let mut heading_stack: Vec<(usize, String)> = Vec::new();
+
+    let mut chunks: Vec<Chunk> = Vec::new();
+    let mut push_chunk = |range: Range<usize>| {
+        let text = &markdown[range.clone()];
+        let content = text.trim();
+        if content.is_empty() {
+            return;
+        }
+        let start = range.start + (text.len() - text.trim_start().len());
+        let source = start..start + content.len();
+        while let Some((_, level, heading)) = headings.next_if(|(offset, ..)| *offset <= start) {
+            open_heading(&mut heading_stack, level, &heading);
+        }
+
+        let (heading, level) = heading_stack.last().map_or((None, 0), |(level, heading)| {
+            (Some(heading.clone()), *level)
+        });
+        chunks.push(create_chunk_object(
+            content,
+            heading,
+            level,
+            &heading_stack,
+            chunks.len(),
+            source,
+        ));
+    };
+
+    // Pack pieces into chunks, keeping whole trailing pieces as the overlap
+    let mut window: VecDeque<Range<usize>> = VecDeque::new();
+    for piece in pieces {
+        if let (Some(first), Some(last)) = (window.front(), window.back())
+            && unit.count(&markdown[first.start..piece.end]) > chunk_size
+        {
+            let end = last.end;
+            push_chunk(first.start..end);
+            while let Some(first) = window.front() {
+                if unit.count(&markdown[first.start..end]) <= chunk_overlap
+                    && unit.count(&markdown[first.start..piece.end]) <= chunk_size
+                {
+                    break;
+                }
+                window.pop_front();
+            }
+        }
+        window.push_back(piece);
+    }
+    if let (Some(first), Some(last)) = (window.front(), window.back()) {
+        push_chunk(first.start..last.end);
+    }
+
+    chunks
+}
+
+/// Cuts `range` of `text` into consecutive pieces of at most `chunk_size` units, trying
+/// paragraph breaks, then sentence ends, then whitespace, then a hard cut
+fn split_recursively(
+    text: &str,
+    range: Range<usize>,
+    depth: usize,
+    chunk_size: usize,
+    unit: SizeUnit,
+    pieces: &mut Vec<Range<usize>>,
+) {
+    let slice = &text[range.clone()];
+    if unit.count(slice) <= chunk_size {
+        pieces.push(range);
+        return;
+    }
+
+    let ends: Vec<usize> = match depth {
+        0 => PARAGRAPH_BOUNDARY_REGEX
+            .find_iter(slice)
+            .map(|mat| mat.end())
+            .collect(),
+        1 => sentence_ends(slice),
+        2 => WHITESPACE_REGEX
+            .find_iter(slice)
+            .map(|mat| mat.end())
+            .collect(),
+        _ => {
+            // No separator left: cut every `chunk_size` units
+            let spans = unit_spans(slice, unit);
+            let mut start = range.start;
+            for units in (chunk_size..spans.len()).step_by(chunk_size) {
+                let end = range.start + units_end(&spans, units);
+                pieces.push(start..end);
+                start = end;
+            }
+            pieces.push(start..range.end);
+            return;
+        }
+    };
+
+    // Each piece keeps the separator that ends it
+    let mut start = range.start;
+    for end in ends {
+        let end = range.start + end;
+        if end > start && end < range.end {
+            split_recursively(text, start..end, depth + 1, chunk_size, unit, pieces);
+            start = end;
+        }
+    }
+    split_recursively(text, start..range.end, depth + 1, chunk_size, unit, pieces);
+}
+
+/// Internal function for `ChunkStrategy::Paragraphs`
+fn paragraph_chunking(markdown: &str, per_chunk: usize, chunk_overlap: usize) -> Vec<Chunk> {
+    // Byte ranges of the paragraphs; a code fence keeps its blank lines
+    let mut paragraphs: Vec<Range<usize>> = Vec::new();
+    let mut paragraph: Option<Range<usize>> = None;
+    let mut in_fence = false;
+    let mut offset = 0;
+    loop {
+        let line_start = offset;
+        let Some(line) = next_line(markdown, &mut offset) else {
+            break;
+        };
+        let trimmed = line.trim();
+        if trimmed.is_empty() && !in_fence {
+            paragraphs.extend(paragraph.take());
+            continue;
+        }
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+        }
+        let line_end = line_start + line.len();
+        paragraph.get_or_insert(line_start..line_end).end = line_end;
+    }
+    paragraphs.extend(paragraph);
+
+    let mut chunks: Vec<Chunk> = Vec::new();
+    let mut start = 0;
+    while start < paragraphs.len() {
+        let end = (start + per_chunk).min(paragraphs.len());
+        let source = paragraphs[start].start..paragraphs[end - 1].end;
+        let mut chunk = create_chunk_object(
+            &markdown[source.clone()],
+            None,
+            0,
+            &[],
+            chunks.len(),
+            source,
+        );
+        chunk.metadata.paragraphs = Some(start..end);
+        chunks.push(chunk);
+        if end == paragraphs.len() {
+            break;
+        }
+        start = end - chunk_overlap;
+    }
+
+    chunks
+}
+
 /// Internal function that does the actual semantic chunking
 fn semantic_chunking(
     markdown: &str,
     chunk_size: usize,
     chunk_overlap: usize,
+    options: &ChunkOptions,
     heading_regex: &Regex,
 ) -> Result<Vec<Chunk>, ChunkerError> {
-    let lines: Vec<&str> = markdown.lines().collect();
+    let mut state = SemanticState::new(chunk_size, chunk_overlap, options);
     let mut chunks: Vec<Chunk> = Vec::new();
+    let mut offset = 0;
+    loop {
+        let line_start = offset;
+        let Some(line) = next_line(markdown, &mut offset) else {
+            break;
+        };
+        state.push_line(line, line_start..offset, heading_regex, &mut chunks);
+    }
+    state.finish(&mut chunks);
+
+    Ok(chunks)
+}
+
+/// Streams `ChunkStrategy::Semantic` chunks out of markdown one at a time
+///
+/// The markdown is scanned line by line and only the chunk being built, the open headings
+/// and any chunks not yet taken are held, so memory stays flat however long the document
+/// is. The chunks are the ones `create_semantic_chunks_with_options` returns.
+///
+/// Options that need the whole document, a `min_chunk_size`, any other strategy or a
+/// `max_chunks` with a `truncation` other than `Truncate`, yield a single
+/// `ChunkerError::Other`, as sizes `validate_chunk_sizes` rejects yield its error. With
+/// `Truncate`, the iterator stops after `max_chunks` chunks.
+pub struct ChunkIterator<'a> {
+    source: Cow<'a, str>,
+    /// Byte offset of the next line in `source`
+    offset: usize,
+    state: SemanticState,
+    heading_regex: Regex,
+    source_id: Option<String>,
+    char_offsets: CharOffsets,
+    // Chunks left before `max_chunks` is reached
+    remaining: Option<usize>,
+    ready: VecDeque<Chunk>,
+    error: Option<ChunkerError>,
+    finished: bool,
+}
+
+impl<'a> ChunkIterator<'a> {
+    /// Creates an iterator over the chunks of `markdown`, borrowed or owned
+    pub fn new(
+        markdown: impl Into<Cow<'a, str>>,
+        chunk_size: usize,
+        chunk_overlap: usize,
+        options: &ChunkOptions,
+    ) -> Self {
+        let error = if let Err(error) = validate_chunk_sizes(chunk_size, chunk_overlap) {
+            Some(error)
+        } else if options.strategy != ChunkStrategy::Semantic {
+            Some(ChunkerError::Other(format!(
+                "streaming supports only the semantic strategy, not {:?}",
+                options.strategy
+            )))
+        } else if options.min_chunk_size > 0 {
+            Some(ChunkerError::Other(
+                "streaming cannot merge small chunks; min_chunk_size must be 0".to_string(),
+            ))
+        } else if options.max_chunks.is_some() && options.truncation != TruncationStrategy::Truncate
+        {
+            Some(ChunkerError::Other(format!(
+                "streaming can only truncate to max_chunks, not {:?}",
+                options.truncation
+            )))
+        } else if options.max_chunks == Some(0) {
+            Some(ChunkerError::ParsingError(
+                "max_chunks must be greater than zero".to_string(),
+            ))
+        } else {
+            None
+        };
+
+        ChunkIterator {
+            source: markdown.into(),
+            offset: 0,
+            state: SemanticState::new(chunk_size, chunk_overlap, options),
+            heading_regex: Regex::new(r"^(#{1,6})\s+(.+)$").expect("valid heading regex"),
+            source_id: options.source_id.clone(),
+            char_offsets: CharOffsets::default(),
+            remaining: options.max_chunks,
+            ready: VecDeque::new(),
+            finished: error.is_some(),
+            error,
+        }
+    }
+
+    /// Bytes of text held besides the source: the chunk being built, the open headings and
+    /// chunks not yet taken
+    pub fn buffered_bytes(&self) -> usize {
+        self.state.current_chunk.len()
+            + self
+                .state
+                .heading_stack
+                .iter()
+                .map(|(_, text)| text.len())
+                .sum::<usize>()
+            + self
+                .ready
+                .iter()
+                .map(|chunk| chunk.content.len())
+                .sum::<usize>()
+    }
+}
+
+impl Iterator for ChunkIterator<'_> {
+    type Item = Result<Chunk, ChunkerError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(error) = self.error.take() {
+                return Some(Err(error));
+            }
+            if self.remaining == Some(0) {
+                return None;
+            }
+            if let Some(mut chunk) = self.ready.pop_front() {
+                if let Some(remaining) = &mut self.remaining {
+                    *remaining -= 1;
+                }
+                self.char_offsets.convert(&self.source, &mut chunk.metadata);
+                if let Some(source_id) = &self.source_id {
+                    chunk.metadata.id = chunk_id(&chunk.content, Some(source_id));
+                    chunk.metadata.source_id = Some(source_id.clone());
+                }
+                return Some(Ok(chunk));
+            }
+            if self.finished {
+                return None;
+            }
+
+            let line_start = self.offset;
+            match next_line(&self.source, &mut self.offset) {
+                Some(line) => self.state.push_line(
+                    line,
+                    line_start..self.offset,
+                    &self.heading_regex,
+                    &mut self.ready,
+                ),
+                None => {
+                    self.state.finish(&mut self.ready);
+                    self.finished = true;
+                }
+            }
+        }
+    }
+}
+
+/// The line of `text` at `offset`, split like `str::lines`, moving `offset` past it
+fn next_line<'t>(text: &'t str, offset: &mut usize) -> Option<&'t str> {
+    if *offset >= text.len() {
+        return None;
+    }
+
+    let rest = &text[*offset..];
+    match rest.find('\n') {
+        Some(end) => {
+            *offset += end + 1;
+            let line = &rest[..end];
+            Some(line.strip_suffix('\r').unwrap_or(line))
+        }
+        None => {
+            *offset = text.len();
+            Some(rest)
+        }
+    }
+}
+
+/// Turns byte offsets into the source markdown into character offsets, counting on from
+/// the last offset asked for
+#[derive(Default)]
+struct CharOffsets {
+    byte: usize,
+    char: usize,
+}
+
+impl CharOffsets {
+    fn char_offset(&mut self, text: &str, byte: usize) -> usize {
+        if byte < self.byte {
+            *self = CharOffsets::default();
+        }
+        self.char += text[self.byte..byte].chars().count();
+        self.byte = byte;
+        self.char
+    }
 
-    let mut current_chunk = String::new();
-    let mut current_heading: Option<String> = None;
-    let mut current_level = 0;
-    let mut current_position = 0;
+    /// Rewrites the byte range `create_chunk_object` recorded as character offsets
+    fn convert(&mut self, text: &str, metadata: &mut ChunkMetadata) {
+        let (start, end) = (metadata.start_offset, metadata.end_offset);
+        metadata.start_offset = self.char_offset(text, start);
+        metadata.end_offset = metadata.start_offset + text[start..end].chars().count();
+    }
+}
+
+/// Where a run of bytes in the chunk being built came from in the source
+#[derive(Clone, Copy)]
+struct SourceRun {
+    /// Byte offset of the run in the chunk
+    chunk: usize,
+    /// Byte offset of the run in the source
+    source: usize,
+    /// Bytes of source the run can be mapped onto; text past them, such as the blank line
+    /// after a carried overlap, maps to their end
+    len: usize,
+}
 
-    let mut i = 0;
-    while i < lines.len() {
-        let line = lines[i];
+/// Everything `semantic_chunking` carries from one line to the next
+struct SemanticState {
+    chunk_size: usize,
+    chunk_overlap: usize,
+    overlap_across_sections: bool,
+    list_continuation: bool,
+    unit: SizeUnit,
+    current_chunk: String,
+    current_heading: Option<String>,
+    current_level: usize,
+    // Open headings as (level, text), outermost first
+    heading_stack: Vec<(usize, String)>,
+    position: usize,
+    // Bytes at the start of the current chunk carried over from the previous one
+    carried: usize,
+    // Where the current chunk came from, one run per line, carried overlap included
+    runs: Vec<SourceRun>,
+    // The current chunk starts part-way through a list
+    continues_list: bool,
+}
+
+impl SemanticState {
+    fn new(chunk_size: usize, chunk_overlap: usize, options: &ChunkOptions) -> Self {
+        SemanticState {
+            chunk_size,
+            chunk_overlap,
+            overlap_across_sections: options.overlap_across_sections,
+            list_continuation: options.list_continuation,
+            unit: options.unit,
+            current_chunk: String::new(),
+            current_heading: None,
+            current_level: 0,
+            heading_stack: Vec::new(),
+            position: 0,
+            carried: 0,
+            runs: Vec::new(),
+            continues_list: false,
+        }
+    }
+
+    /// The source byte offset of byte `at` of the current chunk, rounded down to the run's
+    /// end when `at` is in text with no source of its own
+    fn source_offset(&self, at: usize, after: bool) -> usize {
+        // An end offset belongs to the run holding the byte before it
+        let run = self
+            .runs
+            .iter()
+            .rev()
+            .find(|run| run.chunk < at || (!after && run.chunk == at))
+            .or(self.runs.first());
+        run.map_or(0, |run| {
+            run.source + at.saturating_sub(run.chunk).min(run.len)
+        })
+    }
+
+    /// Saves `content`, the first `content.len()` bytes of the current chunk, as the next
+    /// chunk under the current heading
+    fn emit(&mut self, content: &str, chunks: &mut impl Extend<Chunk>) {
+        let source = self.source_offset(0, false)..self.source_offset(content.len(), true);
+        let marked;
+        let content = if std::mem::take(&mut self.continues_list) {
+            marked = match &self.current_heading {
+                Some(heading) => format!("…continued from list under {}\n{}", heading, content),
+                None => format!("…continued from list\n{}", content),
+            };
+            &marked
+        } else {
+            content
+        };
+        chunks.extend(Some(create_chunk_object(
+            content,
+            self.current_heading.clone(),
+            self.current_level,
+            &self.heading_stack,
+            self.position,
+            source,
+        )));
+        self.position += 1;
+    }
+
+    /// Adds one line, whose bytes in the source including its line break are `source`,
+    /// saving every chunk it completes to `chunks`
+    fn push_line(
+        &mut self,
+        line: &str,
+        source: Range<usize>,
+        heading_regex: &Regex,
+        chunks: &mut impl Extend<Chunk>,
+    ) {
+        // The line and the break after it, which the chunk turns into a single '\n'
+        let line_run = |chunk| SourceRun {
+            chunk,
+            source: source.start,
+            len: source.len().min(line.len() + 1),
+        };
 
         // Check if this is a heading
         if let Some(captures) = heading_regex.captures(line) {
@@ -96,69 +1461,136 @@ fn semantic_chunking(
             let heading_text = &captures[2];
 
             // If we've accumulated content, save it as a chunk before starting a new section
+            let mut current_chunk = std::mem::take(&mut self.current_chunk);
+            let mut overlap = "";
             if !current_chunk.is_empty() {
-                chunks.push(create_chunk_object(
-                    &current_chunk,
-                    current_heading.clone(),
-                    current_level,
-                    current_position,
-                ));
-                current_position += 1;
+                self.emit(&current_chunk, chunks);
+
+                if self.overlap_across_sections {
+                    overlap = overlap_tail(&current_chunk, self.chunk_overlap, self.unit).trim();
+                    if overlap.len() == current_chunk.trim().len() {
+                        // The whole chunk would come around again
+                        overlap = "";
+                    }
+                }
             }
 
             // Set the new heading info
-            current_heading = Some(heading_text.to_string());
-            current_level = heading_level;
-            current_chunk = line.to_string();
+            self.current_heading = Some(heading_text.to_string());
+            self.current_level = heading_level;
+            open_heading(&mut self.heading_stack, heading_level, heading_text);
+            (current_chunk, self.carried) = if overlap.is_empty() {
+                self.runs = vec![line_run(0)];
+                (line.to_string(), 0)
+            } else {
+                // The overlap keeps the offsets it had in the chunk just saved, line by line
+                let start = current_chunk.trim_end().len() - overlap.len();
+                self.rebase_runs(start);
+                self.runs.retain(|run| run.chunk < overlap.len());
+                for run in &mut self.runs {
+                    run.len = run.len.min(overlap.len() - run.chunk);
+                }
+                self.runs.push(line_run(overlap.len() + 2));
+                (format!("{}\n\n{}", overlap, line), overlap.len() + 2)
+            };
+            self.current_chunk = current_chunk;
         } else {
             // Add line to current chunk
-            if !current_chunk.is_empty() {
-                current_chunk.push('\n');
+            if self.current_chunk.is_empty() {
+                self.runs.clear();
+            } else {
+                self.current_chunk.push('\n');
             }
-            current_chunk.push_str(line);
+            self.runs.push(line_run(self.current_chunk.len()));
+            self.current_chunk.push_str(line);
+        }
 
-            // Check if current chunk is too large
-            if current_chunk.len() > chunk_size {
-                let split_point = find_good_split_point(&current_chunk, chunk_size - chunk_overlap);
+        // Split off chunks until the rest fits
+        while self.unit.count(&self.current_chunk) > self.chunk_size {
+            let current_chunk = std::mem::take(&mut self.current_chunk);
+            let split_point = find_good_split_point(
+                &current_chunk,
+                self.chunk_size,
+                self.chunk_overlap,
+                self.unit,
+                self.carried,
+            );
 
-                let (first_part, remaining) = current_chunk.split_at(split_point);
+            let (first_part, remaining) = current_chunk.split_at(split_point);
 
-                // Save the first part as a chunk
-                chunks.push(create_chunk_object(
-                    first_part,
-                    current_heading.clone(),
-                    current_level,
-                    current_position,
-                ));
-                current_position += 1;
+            // Save the first part as a chunk
+            self.emit(first_part, chunks);
+            self.continues_list = self.list_continuation
+                && list_items(first_part)
+                    .last()
+                    .is_some_and(|item| item.end == split_point)
+                && LIST_ITEM_REGEX.is_match(remaining.trim_start_matches(['\n', '\r']));
 
-                // Start a new chunk with the overlap
-                current_chunk = remaining.trim().to_string();
+            // Start a new chunk with the tail of the saved one; the split point keeps
+            // its whitespace, so the two parts join up as in the source
+            let mut overlap = overlap_tail(first_part, self.chunk_overlap, self.unit).trim_start();
+            if overlap.len() == first_part.trim_start().len() {
+                // The whole chunk would come around again
+                overlap = "";
             }
-        }
+            let end = split_point + remaining.trim_end().len();
+            let rest = end - remaining.trim().len();
+            if rest == end {
+                self.carried = 0;
+                break;
+            }
+            let mut start = split_point - overlap.len();
+            if overlap.is_empty() || self.unit.count(&current_chunk[start..rest]) >= self.chunk_size
+            {
+                // No overlap, or one that would fill the chunk on its own with the
+                // whitespace after it
+                start = rest;
+            }
+            self.carried = rest - start;
+            self.current_chunk = current_chunk[start..end].to_string();
 
-        i += 1;
+            // The new chunk is a slice of the old one, so its runs are too
+            self.rebase_runs(start);
+        }
     }
 
-    // Add the final chunk
-    if !current_chunk.is_empty() {
-        chunks.push(create_chunk_object(
-            &current_chunk,
-            current_heading,
-            current_level,
-            current_position,
-        ));
+    /// Keeps the runs of the current chunk's bytes from `start` on, for a chunk that
+    /// starts with them
+    fn rebase_runs(&mut self, start: usize) {
+        let first = self
+            .runs
+            .iter()
+            .rposition(|run| run.chunk <= start)
+            .unwrap_or(0);
+        self.runs.drain(..first);
+        for run in &mut self.runs {
+            let skipped = start.saturating_sub(run.chunk);
+            run.source += skipped.min(run.len);
+            run.len = run.len.saturating_sub(skipped);
+            run.chunk = run.chunk.saturating_sub(start);
+        }
     }
 
-    Ok(chunks)
+    /// Saves whatever is left as the final chunk
+    fn finish(&mut self, chunks: &mut impl Extend<Chunk>) {
+        if !self.current_chunk.is_empty() {
+            let current_chunk = std::mem::take(&mut self.current_chunk);
+            self.emit(&current_chunk, chunks);
+        }
+    }
 }
 
 /// Helper function to create a chunk object with metadata
+///
+/// `source` is the chunk's byte range in the markdown; `char_offsets` turns it into
+/// character offsets once the chunking is done.
 fn create_chunk_object(
     content: &str,
     heading: Option<String>,
     level: usize,
+    heading_stack: &[(usize, String)],
     position: usize,
+    source: Range<usize>,
 ) -> Chunk {
     let words = content.split_whitespace().count();
     let chars = content.chars().count();
@@ -170,9 +1602,16 @@ fn create_chunk_object(
     Chunk {
         content: content.to_string(),
         metadata: ChunkMetadata {
+            id: chunk_id(content, None),
+            source_id: None,
+            headings: heading.iter().cloned().collect(),
             heading,
             level,
+            heading_path: heading_stack.iter().map(|(_, text)| text.clone()).collect(),
             position,
+            start_offset: source.start,
+            end_offset: source.end,
+            paragraphs: None,
             word_count: words,
             char_count: chars,
             semantic_density,
@@ -180,38 +1619,407 @@ fn create_chunk_object(
     }
 }
 
+/// Byte ranges of the units in `text`; cutting `text` at the end of a range leaves a prefix
+/// that counts exactly the ranges before the cut
+fn unit_spans(text: &str, unit: SizeUnit) -> Vec<(usize, usize)> {
+    match unit {
+        SizeUnit::Chars => text
+            .char_indices()
+            .map(|(i, c)| (i, i + c.len_utf8()))
+            .collect(),
+        SizeUnit::Words => {
+            let mut spans = Vec::new();
+            let mut start = None;
+            for (i, c) in text.char_indices() {
+                match (c.is_whitespace(), start) {
+                    (true, Some(s)) => {
+                        spans.push((s, i));
+                        start = None;
+                    }
+                    (false, None) => start = Some(i),
+                    _ => {}
+                }
+            }
+            if let Some(s) = start {
+                spans.push((s, text.len()));
+            }
+            spans
+        }
+        SizeUnit::Tokens => token_spans(text),
+    }
+}
+
+/// Approximate token ranges, see `SizeUnit::Tokens`
+fn token_spans(text: &str) -> Vec<(usize, usize)> {
+    #[derive(PartialEq)]
+    enum Run {
+        Word,
+        Space,
+    }
+
+    let mut spans = Vec::new();
+    let mut run: Option<(Run, usize)> = None;
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let kind = if c.is_whitespace() {
+            Some(Run::Space)
+        } else if (c.is_alphanumeric() || c == '_') && c.len_utf8() < 3 {
+            Some(Run::Word)
+        } else {
+            None
+        };
+
+        match (&run, kind) {
+            (Some((current, _)), Some(kind)) if *current == kind => {}
+            (_, Some(Run::Space)) => {
+                close_run(&mut spans, text, run.take(), i);
+                // A single space rides along with the next token for free
+                run = Some((Run::Space, i + c.len_utf8()));
+            }
+            (_, Some(Run::Word)) => {
+                close_run(&mut spans, text, run.take(), i);
+                run = Some((Run::Word, i));
+            }
+            (_, None) => {
+                close_run(&mut spans, text, run.take(), i);
+                spans.push((i, i + c.len_utf8()));
+            }
+        }
+        if chars.peek().is_none() {
+            close_run(&mut spans, text, run.take(), text.len());
+        }
+    }
+    spans
+}
+
+/// Splits the run that started at `start` and stops at `end` into `BYTES_PER_TOKEN` pieces
+fn close_run<T>(spans: &mut Vec<(usize, usize)>, text: &str, run: Option<(T, usize)>, end: usize) {
+    let Some((_, mut start)) = run else {
+        return;
+    };
+    while start < end {
+        let mut piece_end = (start + BYTES_PER_TOKEN).min(end);
+        while !text.is_char_boundary(piece_end) {
+            piece_end += 1;
+        }
+        spans.push((start, piece_end));
+        start = piece_end;
+    }
+}
+
+/// Byte offset just past the first `units` units of `spans`
+fn units_end(spans: &[(usize, usize)], units: usize) -> usize {
+    match units.min(spans.len()) {
+        0 => 0,
+        n => spans[n - 1].1,
+    }
+}
+
 /// Find a good split point that doesn't break in the middle of a sentence or paragraph
-/// Optimized with regex for 40% performance improvement
-fn find_good_split_point(text: &str, approximate_position: usize) -> usize {
-    if approximate_position >= text.len() {
-        return text.len();
+///
+/// The first part holds at most `chunk_size` units and ends after byte `after`, so split
+/// chunks stay within budget and always take some text beyond what was carried over.
+/// Boundaries are looked for forward from `chunk_size - chunk_overlap` units first, then back.
+/// Breaks between list items rank with paragraph breaks, and a list item is only cut when
+/// no break outside one fits.
+///
+/// The result is always a character boundary greater than `after`, and so never 0.
+fn find_good_split_point(
+    text: &str,
+    chunk_size: usize,
+    chunk_overlap: usize,
+    unit: SizeUnit,
+    after: usize,
+) -> usize {
+    let spans = unit_spans(text, unit);
+    let limit = units_end(&spans, chunk_size);
+    if limit <= after {
+        // Nothing past the carried text fits; take its first unit anyway
+        return spans
+            .iter()
+            .map(|&(_, end)| end)
+            .find(|&end| end > after)
+            .unwrap_or(text.len());
     }
+    let approximate_position =
+        units_end(&spans, chunk_size.saturating_sub(chunk_overlap)).clamp(after, limit);
 
-    let search_text = &text[approximate_position..];
+    let items = list_items(text);
+    let outside_items = |pos: usize| !items.iter().any(|item| item.start < pos && pos < item.end);
+    let split = search_split_point(
+        text,
+        after,
+        approximate_position,
+        limit,
+        &items,
+        &outside_items,
+    )
+    .or_else(|| {
+        // A single item is over budget
+        if items.is_empty() {
+            return None;
+        }
+        search_split_point(text, after, approximate_position, limit, &[], &|_| true)
+    })
+    // Last resort: cut at the budget
+    .unwrap_or(limit);
 
-    // Look for paragraph break first (highest priority)
-    if let Some(mat) = PARAGRAPH_BOUNDARY_REGEX.find(search_text) {
-        return approximate_position + mat.end();
+    // Whatever was found, cut on a character boundary and take something past the carried text
+    let split = text.floor_char_boundary(split);
+    if split > after {
+        split
+    } else {
+        text.ceil_char_boundary(after + 1)
     }
+}
+
+/// The best break `allowed` accepts, looking forward from `target` to `limit`, then back to
+/// `after`; the edges of `items` count as paragraph breaks
+fn search_split_point(
+    text: &str,
+    after: usize,
+    target: usize,
+    limit: usize,
+    items: &[Range<usize>],
+    allowed: &dyn Fn(usize) -> bool,
+) -> Option<usize> {
+    let item_edges = || items.iter().flat_map(|item| [item.start, item.end]);
 
-    // Look for sentence break using optimized regex
-    if let Some(mat) = SENTENCE_BOUNDARY_REGEX.find(search_text) {
-        // Return position after the punctuation and whitespace
-        return approximate_position + mat.end();
+    // Look forward from the target, preferring paragraph, then sentence, line and word breaks
+    let search_text = &text[target..limit];
+    let at = |offset: usize| target + offset;
+    let forward = PARAGRAPH_BOUNDARY_REGEX
+        .find_iter(search_text)
+        .map(|mat| at(mat.end()))
+        .chain(item_edges().filter(|&edge| edge > target && edge <= limit))
+        .filter(|&pos| allowed(pos))
+        .min()
+        .or_else(|| {
+            sentence_ends(search_text)
+                .into_iter()
+                .map(at)
+                .find(|&pos| allowed(pos))
+        })
+        .or_else(|| {
+            search_text
+                .match_indices('\n')
+                .map(|(pos, _)| at(pos + 1))
+                .find(|&pos| allowed(pos))
+        })
+        .or_else(|| {
+            search_text
+                .char_indices()
+                .filter(|(_, c)| c.is_whitespace())
+                .map(|(i, c)| at(i + c.len_utf8()))
+                .find(|&pos| allowed(pos))
+        });
+    if forward.is_some() {
+        return forward;
     }
 
-    // Fall back to newline
-    if let Some(pos) = search_text.find('\n') {
-        return approximate_position + pos + 1;
+    // Then back towards the carried text, taking the latest break of each kind
+    let search_text = &text[after..target];
+    let at = |offset: usize| after + offset;
+    PARAGRAPH_BOUNDARY_REGEX
+        .find_iter(search_text)
+        .map(|mat| at(mat.end()))
+        .chain(item_edges().filter(|&edge| edge > after && edge <= target))
+        .filter(|&pos| allowed(pos))
+        .max()
+        .or_else(|| {
+            sentence_ends(search_text)
+                .into_iter()
+                .map(at)
+                .rfind(|&pos| allowed(pos))
+        })
+        .or_else(|| {
+            search_text
+                .rmatch_indices('\n')
+                .map(|(pos, _)| at(pos + 1))
+                .find(|&pos| allowed(pos))
+        })
+        .or_else(|| {
+            search_text
+                .char_indices()
+                .filter(|(_, c)| c.is_whitespace())
+                .map(|(i, c)| at(i + c.len_utf8()))
+                .rfind(|&pos| allowed(pos))
+        })
+}
+
+/// Byte ranges of the list items in `text`, each from its marker line up to the next item
+/// or the end of its list; continuation lines and blank lines inside the list belong to the
+/// item above them
+fn list_items(text: &str) -> Vec<Range<usize>> {
+    let mut items: Vec<Range<usize>> = Vec::new();
+    let mut open: Option<usize> = None;
+    let mut after_blank = false;
+
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        let line_start = offset;
+        offset += line.len();
+        let content = line.trim_end_matches(['\n', '\r']);
+
+        if LIST_ITEM_REGEX.is_match(content) {
+            if let Some(start) = open {
+                items.push(start..line_start);
+            }
+            open = Some(line_start);
+            after_blank = false;
+        } else if content.trim().is_empty() {
+            after_blank = true;
+        } else if let Some(start) = open {
+            // Indented lines continue the item, and so does unindented text straight after it
+            let continues =
+                content.starts_with([' ', '\t']) || (!after_blank && !content.starts_with('#'));
+            if !continues {
+                items.push(start..line_start);
+                open = None;
+            }
+            after_blank = false;
+        }
+    }
+    if let Some(start) = open {
+        items.push(start..text.len());
     }
+    items
+}
+
+/// Sentence-final punctuation that ends a sentence before whitespace or a capital letter
+fn is_sentence_terminal(c: char) -> bool {
+    matches!(c, '.' | '!' | '?' | '…' | '‼' | '⁇' | '⁈' | '⁉')
+}
+
+/// Full-width sentence-final punctuation, which ends a sentence with no space after it
+fn is_full_width_terminal(c: char) -> bool {
+    matches!(c, '。' | '？' | '！' | '｡')
+}
+
+/// Quotes and brackets that may close a sentence after its final punctuation
+fn is_sentence_closer(c: char) -> bool {
+    matches!(
+        c,
+        '"' | '\'' | ')' | ']' | '”' | '’' | '»' | '›' | '」' | '』' | '）' | '】' | '〉' | '》'
+    )
+}
 
-    // Fall back to word boundary (optimized with iterator)
-    if let Some((i, _)) = search_text.char_indices().find(|(_, c)| c.is_whitespace()) {
-        return approximate_position + i + 1;
+/// Han, kana and hangul, which start sentences without a capital
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30ff}'
+        | '\u{3400}'..='\u{4dbf}'
+        | '\u{4e00}'..='\u{9fff}'
+        | '\u{f900}'..='\u{faff}'
+        | '\u{ac00}'..='\u{d7af}')
+}
+
+/// Byte offsets in `text` where a sentence ends and the next one starts, after any closing
+/// quotes and whitespace
+///
+/// `.` `!` `?` and `…` end a sentence when whitespace follows and the next word does not start
+/// in lowercase, or when a capital or CJK character follows directly; `。` `？` `！` always
+/// do. With the `unicode_sentences` feature the UAX #29 sentence rules are used instead.
+fn sentence_ends(text: &str) -> Vec<usize> {
+    #[cfg(feature = "unicode_sentences")]
+    {
+        use unicode_segmentation::UnicodeSegmentation;
+
+        // The text's end only counts when the last sentence is actually finished
+        let mut ends: Vec<usize> = text
+            .split_sentence_bound_indices()
+            .map(|(start, sentence)| start + sentence.len())
+            .filter(|&end| end < text.len())
+            .collect();
+        if let Some(end) = rule_sentence_ends(text).pop()
+            && end == text.len()
+        {
+            ends.push(end);
+        }
+        ends
+    }
+
+    #[cfg(not(feature = "unicode_sentences"))]
+    rule_sentence_ends(text)
+}
+
+/// `sentence_ends` without the `unicode_sentences` feature
+fn rule_sentence_ends(text: &str) -> Vec<usize> {
+    let mut ends = Vec::new();
+    let mut chars = text.char_indices().peekable();
+    while let Some((_, c)) = chars.next() {
+        let mut full_width = is_full_width_terminal(c);
+        if !full_width && !is_sentence_terminal(c) {
+            continue;
+        }
+
+        // Runs like `?!` or `...` and closing quotes belong to the sentence
+        while let Some((_, next)) = chars.next_if(|&(_, next)| {
+            is_sentence_terminal(next) || is_full_width_terminal(next) || is_sentence_closer(next)
+        }) {
+            full_width |= is_full_width_terminal(next);
+        }
+        let punctuation_end = chars.peek().map_or(text.len(), |&(i, _)| i);
+        let mut spaced = false;
+        while chars.next_if(|&(_, next)| next.is_whitespace()).is_some() {
+            spaced = true;
+        }
+        let end = chars.peek().map_or(text.len(), |&(i, _)| i);
+        let next = chars.peek().map(|&(_, next)| next);
+
+        if full_width {
+            ends.push(end);
+        } else if spaced {
+            if !next.is_some_and(char::is_lowercase) {
+                ends.push(end);
+            }
+        } else if next.is_some_and(|next| next.is_uppercase() || is_cjk(next)) {
+            ends.push(punctuation_end);
+        }
+    }
+    ends
+}
+
+/// The last `overlap` or so units of `text`, moved forward to start on a list item, sentence,
+/// line or word
+fn overlap_tail(text: &str, overlap: usize, unit: SizeUnit) -> &str {
+    if overlap == 0 {
+        return "";
     }
+    let spans = unit_spans(text, unit);
+    if spans.len() <= overlap {
+        return text;
+    }
+
+    let start = spans[spans.len() - overlap].0;
+    let window = &text[start..];
+
+    // A list item that starts in the window is repeated whole
+    if let Some(item) = list_items(window).first()
+        && item.start > 0
+    {
+        return &window[item.start..];
+    }
+
+    // The earliest boundary keeps as much of the window as possible
+    let boundary = [
+        sentence_ends(window).first().copied(),
+        window.find('\n').map(|pos| pos + 1),
+    ]
+    .into_iter()
+    .flatten()
+    .min()
+    .or_else(|| {
+        window
+            .char_indices()
+            .find(|(_, c)| c.is_whitespace())
+            .map(|(i, c)| i + c.len_utf8())
+    });
 
-    // Last resort
-    approximate_position
+    match boundary {
+        Some(offset) => text[start + offset..].trim_start(),
+        None => window,
+    }
 }
 
 /// Calculate semantic density score with optimized regex patterns
@@ -1,6 +1,9 @@
 use once_cell::sync::Lazy;
+use regex::Regex;
 use scraper::{Html, Selector};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::Mutex;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -36,11 +39,43 @@ static SELECTOR_CACHE: Lazy<HashMap<&'static str, Selector>> = Lazy::new(|| {
         cache.insert("unwanted_elements", selector);
     }
 
+    // the markdown converter's unwanted elements: iframes stay, to become embeds, and
+    // `math/tex` scripts, to become math
+    if let Ok(selector) = Selector::parse(
+        "script:not([type^=\"math/tex\"]), style, noscript, .advertisement, .ad, .banner, \
+         #cookie-notice, header, footer, nav, .sidebar, .menu, .comments, \
+         .related, .share, .social",
+    ) {
+        cache.insert("converter_unwanted_elements", selector);
+    }
+
+    // form controls whose text (button labels, options) is not part of the content;
+    // checkboxes stay for task lists
+    if let Ok(selector) =
+        Selector::parse("button, select, textarea, input:not([type=\"checkbox\" i]), form label")
+    {
+        cache.insert("form_controls", selector);
+    }
+
+    // page furniture whose links are not part of the content
+    if let Ok(selector) = Selector::parse(
+        "nav, header, footer, aside, .menu, .sidebar, .navbar, .breadcrumb, .breadcrumbs, \
+         [role=\"navigation\"], [role=\"banner\"], [role=\"contentinfo\"], \
+         #cookie-notice, .cookie-banner",
+    ) {
+        cache.insert("boilerplate", selector);
+    }
+
     // link selector
     if let Ok(selector) = Selector::parse("a[href]") {
         cache.insert("links", selector);
     }
 
+    // frame selector
+    if let Ok(selector) = Selector::parse("iframe[src]") {
+        cache.insert("iframes", selector);
+    }
+
     // individual content selectors for fallback
     let selectors_to_cache = [
         ("main", "main"),
@@ -60,8 +95,38 @@ static SELECTOR_CACHE: Lazy<HashMap<&'static str, Selector>> = Lazy::new(|| {
 });
 
 /// extract main content from html using cached selectors
+///
+/// hidden elements are left out, see `is_hidden`
 pub fn extract_main_content(html: &str) -> Result<Html, ParserError> {
-    let document = Html::parse_document(html);
+    extract_main_content_with_options(html, &MainContentOptions::default())
+}
+
+/// per-call settings for `extract_main_content_with_options`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MainContentOptions {
+    /// keep elements `is_hidden` reports, such as collapsed accordion panels
+    pub include_hidden: bool,
+}
+
+/// `extract_main_content` with control over hidden elements
+///
+/// # Examples
+///
+/// ```
+/// use markdown_lab_rs::html_parser::{MainContentOptions, extract_main_content_with_options};
+/// let html = r#"<main><p>Shown</p><div hidden>Answer</div></main>"#;
+/// let options = MainContentOptions { include_hidden: true };
+/// let content = extract_main_content_with_options(html, &options).unwrap();
+/// assert!(content.root_element().html().contains("Answer"));
+/// ```
+pub fn extract_main_content_with_options(
+    html: &str,
+    options: &MainContentOptions,
+) -> Result<Html, ParserError> {
+    let mut document = Html::parse_document(html);
+    if !options.include_hidden {
+        remove_hidden_elements(&mut document);
+    }
 
     // first try the combined selector for efficiency
     if let Some(selector) = SELECTOR_CACHE.get("main_content")
@@ -85,12 +150,108 @@ pub fn extract_main_content(html: &str) -> Result<Html, ParserError> {
     Ok(document)
 }
 
+/// finds the main content container (`main`, `article`, `#content` or `.content`) of a document
+pub fn find_main_content(document: &Html) -> Option<scraper::ElementRef<'_>> {
+    let selector = SELECTOR_CACHE.get("main_content")?;
+    document.select(selector).next()
+}
+
+/// checks whether an element sits inside navigation, headers, footers or similar page furniture
+///
+/// # Examples
+///
+/// ```
+/// use markdown_lab_rs::html_parser::is_in_boilerplate;
+/// use scraper::{Html, Selector};
+/// let html = Html::parse_document(r#"<nav><a href="/">Home</a></nav><p><a href="/post">Post</a></p>"#);
+/// let links: Vec<_> = html.select(&Selector::parse("a").unwrap()).collect();
+/// assert!(is_in_boilerplate(&links[0]));
+/// assert!(!is_in_boilerplate(&links[1]));
+/// ```
+pub fn is_in_boilerplate(element: &scraper::ElementRef) -> bool {
+    let Some(selector) = SELECTOR_CACHE.get("boilerplate") else {
+        return false;
+    };
+    std::iter::once(*element)
+        .chain(element.ancestors().filter_map(scraper::ElementRef::wrap))
+        .any(|e| selector.matches(&e))
+}
+
+/// classes that hide content from sighted readers, mostly screen-reader-only text
+const HIDDEN_CLASSES: &[&str] = &[
+    "sr-only",
+    "visually-hidden",
+    "visuallyhidden",
+    "screen-reader-text",
+];
+
+/// checks whether an element or one of its ancestors is hidden
+///
+/// hidden means a `hidden` attribute (except `hidden="until-found"`, which the
+/// browser's find-in-page reveals), `aria-hidden="true"`, an inline `display: none`
+/// or `visibility: hidden`, or a screen-reader-only class like `.sr-only`
+///
+/// # Examples
+///
+/// ```
+/// use markdown_lab_rs::html_parser::is_hidden;
+/// use scraper::{Html, Selector};
+/// let html = Html::parse_document(r#"<div style="display: none"><p>Gone</p></div><p>Shown</p>"#);
+/// let paragraphs: Vec<_> = html.select(&Selector::parse("p").unwrap()).collect();
+/// assert!(is_hidden(&paragraphs[0]));
+/// assert!(!is_hidden(&paragraphs[1]));
+/// ```
+pub fn is_hidden(element: &scraper::ElementRef) -> bool {
+    std::iter::once(*element)
+        .chain(element.ancestors().filter_map(scraper::ElementRef::wrap))
+        .any(|e| hides_itself(e.value()))
+}
+
+/// drops hidden elements, with their subtrees, from the document
+pub(crate) fn remove_hidden_elements(document: &mut Html) {
+    remove_elements(document, |element| hides_itself(element.value()));
+}
+
+/// whether an element's own attributes hide it, regardless of its ancestors
+fn hides_itself(element: &scraper::node::Element) -> bool {
+    element
+        .attr("hidden")
+        .is_some_and(|value| !value.trim().eq_ignore_ascii_case("until-found"))
+        || element
+            .attr("aria-hidden")
+            .is_some_and(|value| value.trim().eq_ignore_ascii_case("true"))
+        || element.attr("style").is_some_and(style_hides)
+        || element
+            .classes()
+            .any(|class| HIDDEN_CLASSES.contains(&class))
+}
+
+/// whether inline CSS sets `display: none` or `visibility: hidden`
+fn style_hides(style: &str) -> bool {
+    style.split(';').any(|declaration| {
+        let Some((property, value)) = declaration.split_once(':') else {
+            return false;
+        };
+        let value = value.to_ascii_lowercase();
+        let value = value.trim().trim_end_matches("!important").trim();
+        match property.trim().to_ascii_lowercase().as_str() {
+            "display" => value == "none",
+            "visibility" => value == "hidden",
+            _ => false,
+        }
+    })
+}
+
 /// remove unwanted elements using cached selectors
 ///
 /// unwanted elements such as scripts, ads, banners, and navigation are identified using a cached selector and removed from the HTML. If the selector cache is unavailable, returns the original HTML.
 ///
 /// # Returns
-/// A cleaned HTML string with unwanted elements removed.
+/// A cleaned HTML string with unwanted elements, comments, `on*` event handlers and
+/// `javascript:`/`vbscript:`/`data:text/html` URLs removed.
+///
+/// Forms go with their controls (buttons, selects, text inputs, labels), except forms
+/// wrapping the page content, which only lose their controls.
 ///
 /// # Examples
 ///
@@ -102,29 +263,38 @@ pub fn extract_main_content(html: &str) -> Result<Html, ParserError> {
 /// assert!(!cleaned.contains("<script>"));
 /// ```
 pub fn clean_html(html: &str) -> Result<String, ParserError> {
-    let document = Html::parse_document(html);
+    let mut document = Html::parse_document(html);
 
     // use cached selector for better performance
     if let Some(unwanted_selector) = SELECTOR_CACHE.get("unwanted_elements") {
-        // collect elements to remove first (to avoid modification during iteration)
-        let elements_to_remove: Vec<String> = document
-            .select(unwanted_selector)
-            .map(|element| element.html())
-            .collect();
-
-        // remove elements by replacing their HTML
-        let mut cleaned_html = document.root_element().html();
-        for element_html in elements_to_remove {
-            cleaned_html = cleaned_html.replace(&element_html, "");
-        }
-
-        Ok(cleaned_html)
+        remove_elements(&mut document, |element| {
+            unwanted_selector.matches(element) || is_form_furniture(element)
+        });
+        remove_comments(&mut document);
+        sanitize_attributes(&mut document, &CleanOptions::default());
+        Ok(document.root_element().html())
     } else {
         // fallback: return original HTML if selector cache failed
         Ok(html.to_string())
     }
 }
 
+/// `clean_html` without the attribute pass, for the markdown converter
+///
+/// the converter never re-emits HTML: it reads alignment from `style`, languages from
+/// `class`, and drops `javascript:` anchors as UI controls, so it needs them as written.
+/// iframes are kept for the converter to turn into embeds.
+pub(crate) fn clean_html_keeping_attributes(html: &str) -> String {
+    let mut document = Html::parse_document(html);
+    if let Some(unwanted_selector) = SELECTOR_CACHE.get("converter_unwanted_elements") {
+        remove_elements(&mut document, |element| {
+            unwanted_selector.matches(element) || is_form_furniture(element)
+        });
+    }
+    remove_comments(&mut document);
+    document.root_element().html()
+}
+
 /// clean a parsed HTML document by removing unwanted elements
 ///
 /// this function works directly with the parsed DOM to remove unwanted elements
@@ -141,161 +311,3651 @@ pub fn clean_html(html: &str) -> Result<String, ParserError> {
 /// assert!(!cleaned.root_element().html().contains("<script>"));
 /// ```
 pub fn clean_parsed_html(document: &Html) -> Result<Html, ParserError> {
+    let mut cleaned = document.clone();
     // use cached selector for better performance
     if let Some(unwanted_selector) = SELECTOR_CACHE.get("unwanted_elements") {
-        // collect elements to remove first (to avoid modification during iteration)
-        let elements_to_remove: Vec<String> = document
-            .select(unwanted_selector)
-            .map(|element| element.html())
-            .collect();
+        remove_elements(&mut cleaned, |element| {
+            unwanted_selector.matches(element) || is_form_furniture(element)
+        });
+    }
+    remove_comments(&mut cleaned);
+    sanitize_attributes(&mut cleaned, &CleanOptions::default());
+    Ok(cleaned)
+}
 
-        // remove elements by replacing their HTML in the root element
-        let mut cleaned_html = document.root_element().html();
-        for element_html in elements_to_remove {
-            cleaned_html = cleaned_html.replace(&element_html, "");
-        }
+/// per-call changes to the elements `clean_html` removes
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CleanOptions {
+    /// CSS selectors removed on top of the built-in list, e.g. `.newsletter-signup`
+    pub extra_remove_selectors: Vec<String>,
+    /// CSS selectors for elements to keep even when the built-in list matches them
+    pub keep_selectors: Vec<String>,
+    /// leave HTML comments, including IE conditional comments, in place
+    pub keep_comments: bool,
+    /// drop `style` attributes; converters read table alignment from them
+    pub strip_style: bool,
+    /// drop `class` attributes; converters read code languages from them
+    pub strip_class: bool,
+    /// drop `id` attributes
+    pub strip_id: bool,
+    /// keep forms and form controls, which are removed by default (see `clean_html`)
+    pub keep_forms: bool,
+}
 
-        // parse the cleaned HTML back into a document
-        Ok(Html::parse_document(&cleaned_html))
-    } else {
-        // Fallback: return original document if selector cache failed
-        Ok(document.clone())
+// user selectors compiled by earlier calls, keyed by their source
+static USER_SELECTOR_CACHE: Lazy<Mutex<HashMap<String, Selector>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// most user selectors kept compiled; the cache starts over when it fills up
+const USER_SELECTOR_CACHE_LIMIT: usize = 256;
+
+/// `clean_html` with extra selectors to remove and selectors to keep
+///
+/// an element is removed when the built-in list or an extra selector matches it and
+/// no keep selector does. kept elements still go when an ancestor is removed.
+/// `on*` handlers and script URLs are always dropped, see `clean_html`.
+///
+/// # Errors
+///
+/// `ParserError::SelectorError` naming the first selector that does not parse
+///
+/// # Examples
+///
+/// ```
+/// use markdown_lab_rs::html_parser::{CleanOptions, clean_html_with_options};
+/// let html = r#"<header>Site</header><div id="paywall">Subscribe</div><p>Story</p>"#;
+/// let options = CleanOptions {
+///     extra_remove_selectors: vec!["#paywall".to_string()],
+///     keep_selectors: vec!["header".to_string()],
+///     ..Default::default()
+/// };
+/// let cleaned = clean_html_with_options(html, &options).unwrap();
+/// assert!(cleaned.contains("Site") && cleaned.contains("Story"));
+/// assert!(!cleaned.contains("Subscribe"));
+/// ```
+pub fn clean_html_with_options(html: &str, options: &CleanOptions) -> Result<String, ParserError> {
+    let extra = compile_user_selectors(&options.extra_remove_selectors)?;
+    let keep = compile_user_selectors(&options.keep_selectors)?;
+    let builtin = SELECTOR_CACHE.get("unwanted_elements");
+
+    let mut document = Html::parse_document(html);
+    remove_elements(&mut document, |element| {
+        (builtin.is_some_and(|selector| selector.matches(element))
+            || (!options.keep_forms && is_form_furniture(element))
+            || extra.iter().any(|selector| selector.matches(element)))
+            && !keep.iter().any(|selector| selector.matches(element))
+    });
+    if !options.keep_comments {
+        remove_comments(&mut document);
     }
+    sanitize_attributes(&mut document, options);
+    Ok(document.root_element().html())
 }
 
-/// More efficient version that works directly with the DOM structure
-/// cleans HTML content by removing unwanted elements
+/// compiles user-supplied selectors, reusing ones compiled by earlier calls
+fn compile_user_selectors(sources: &[String]) -> Result<Vec<Selector>, ParserError> {
+    let mut cache = USER_SELECTOR_CACHE
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    sources
+        .iter()
+        .map(|source| {
+            if let Some(selector) = cache.get(source) {
+                return Ok(selector.clone());
+            }
+            let selector = Selector::parse(source).map_err(|e| {
+                ParserError::SelectorError(format!("invalid selector {:?}: {}", source, e))
+            })?;
+            if cache.len() >= USER_SELECTOR_CACHE_LIMIT {
+                cache.clear();
+            }
+            cache.insert(source.clone(), selector.clone());
+            Ok(selector)
+        })
+        .collect()
+}
+
+/// replaces each `<noscript>` with the markup it holds, parsed as HTML
 ///
-/// Currently delegates to `clean_html`, but intended for future enhancement to perform more efficient DOM manipulation when supported.
+/// parsers with scripting enabled keep noscript content as raw text, and cleaning
+/// drops the element, so images that lazy-loading pages only give non-JS clients
+/// are lost unless they are lifted into the document first.
 ///
 /// # Examples
 ///
 /// ```
-/// use markdown_lab_rs::html_parser::clean_html_advanced;
-/// let html = r#"<html><body><script>bad()</script><main>Good Content</main></body></html>"#;
-/// let cleaned = clean_html_advanced(html).unwrap();
-/// assert!(cleaned.contains("Good Content"));
-/// assert!(!cleaned.contains("<script>"));
+/// use markdown_lab_rs::html_parser::lift_noscript;
+/// use scraper::Html;
+/// let mut document = Html::parse_document(
+///     r#"<p><img data-lazy="1"><noscript><img src="real.jpg"></noscript></p>"#,
+/// );
+/// lift_noscript(&mut document);
+/// let html = document.root_element().html();
+/// assert!(html.contains(r#"<img data-lazy="1"><img src="real.jpg"></p>"#));
 /// ```
-pub fn clean_html_advanced(html: &str) -> Result<String, ParserError> {
-    // In a future optimization, we could manipulate the DOM tree directly
-    // rather than using string replacement, but scraper crate has limited
-    // DOM modification capabilities currently.
+pub fn lift_noscript(document: &mut Html) {
+    let is_noscript = |node: ego_tree::NodeRef<scraper::Node>| {
+        node.value()
+            .as_element()
+            .is_some_and(|element| element.name() == "noscript")
+    };
+    if !document.tree.root().descendants().any(is_noscript) {
+        return;
+    }
+    // nodes are moved below, which needs consistent links
+    repair_tree(document);
+    let noscripts: Vec<(ego_tree::NodeId, String)> = document
+        .tree
+        .root()
+        .descendants()
+        .filter_map(scraper::ElementRef::wrap)
+        .filter(|element| element.value().name() == "noscript")
+        .map(|element| (element.id(), element.text().collect()))
+        .collect();
 
-    // For now, fall back to the cached selector approach
-    clean_html(html)
+    for (id, markup) in noscripts {
+        let mut fragment = Html::parse_fragment(&markup);
+        repair_tree(&mut fragment);
+        let fragment_root = document.tree.extend_tree(fragment.tree).id();
+        // a fragment's content sits below a synthetic `<html>` element
+        let content: Vec<ego_tree::NodeId> = document
+            .tree
+            .get(fragment_root)
+            .into_iter()
+            .flat_map(|root| root.children())
+            .flat_map(|html| html.children())
+            .map(|node| node.id())
+            .collect();
+        let Some(mut noscript) = document.tree.get_mut(id) else {
+            continue;
+        };
+        for child in content {
+            noscript.insert_id_before(child);
+        }
+        noscript.detach();
+    }
 }
 
-/// extracts unique absolute URLs from anchor elements
+/// extracts the resolved http(s) URL of every iframe, once each, in document order
 ///
-/// finds anchor tags with href, filters out javascript/fragment/empty links, resolves relative URLs
+/// these are the keys `splice_frames` looks fetched frame HTML up by
 ///
-/// # Parameters
-/// - `html`: The HTML content to parse.
-/// - `base_url`: The base URL used to resolve relative links.
+/// # Examples
 ///
-/// # Returns
-/// A vector of unique absolute URLs found in the document.
+/// ```
+/// use markdown_lab_rs::html_parser::extract_iframe_sources;
+/// let html = r#"<iframe src="/body.html"></iframe><iframe src="about:blank"></iframe>
+///     <iframe src="https://player.example.net/v/1"></iframe>"#;
+/// assert_eq!(
+///     extract_iframe_sources(html, "https://docs.example.com/guide/").unwrap(),
+///     ["https://docs.example.com/body.html", "https://player.example.net/v/1"]
+/// );
+/// ```
+pub fn extract_iframe_sources(html: &str, base_url: &str) -> Result<Vec<String>, ParserError> {
+    let document = Html::parse_document(html);
+    let page_url = url::Url::parse(base_url).map_err(|e| ParserError::UrlError(e.to_string()))?;
+    Ok(iframe_sources(
+        &document,
+        &effective_base_url(&document, &page_url),
+    ))
+}
+
+/// the resolved iframe URLs of a parsed document, see `extract_iframe_sources`
+pub(crate) fn iframe_sources(document: &Html, base_url: &url::Url) -> Vec<String> {
+    let Some(selector) = SELECTOR_CACHE.get("iframes") else {
+        return Vec::new();
+    };
+    let mut sources: Vec<String> = Vec::new();
+    for src in document
+        .select(selector)
+        .filter_map(|iframe| frame_url(base_url, iframe.value().attr("src")?))
+    {
+        let src = src.to_string();
+        if !sources.contains(&src) {
+            sources.push(src);
+        }
+    }
+    sources
+}
+
+/// an iframe `src` resolved against the base URL, when it is an http(s) URL
+fn frame_url(base_url: &url::Url, src: &str) -> Option<url::Url> {
+    let src = resolve_href(base_url, src, &LinkExtractionOptions::default())?;
+    url::Url::parse(&src)
+        .ok()
+        .filter(|url| matches!(url.scheme(), "http" | "https"))
+}
+
+/// most levels of frames inside spliced frames that are spliced in as well
+const MAX_FRAME_DEPTH: usize = 4;
+
+/// replaces same-origin iframes with the body of their pre-fetched HTML
 ///
-/// # Errors
-/// Returns `ParserError::UrlError` if the base URL is invalid, or `ParserError::SelectorError` if the link selector is missing from the cache.
+/// `frames` maps frame URLs, as listed by `extract_iframe_sources`, to their HTML.
+/// relative URLs in a frame are resolved against the frame's URL before it is spliced
+/// in, and frames inside frames are spliced too, up to four levels deep. frames from
+/// another origin, and frames missing from `frames`, stay as they are.
 ///
 /// # Examples
 ///
 /// ```
-/// use markdown_lab_rs::html_parser::extract_links;
-/// let html = r#"<a href="/about">About</a><a href="https://example.com/contact">Contact</a>"#;
-/// let links = extract_links(html, "https://example.com").unwrap();
-/// assert_eq!(links, vec![
-///     "https://example.com/about".to_string(),
-///     "https://example.com/contact".to_string()
-/// ]);
+/// use markdown_lab_rs::html_parser::splice_frames;
+/// use std::collections::HashMap;
+/// let html = r#"<main><iframe src="/docs/body.html"></iframe></main>"#;
+/// let frames = HashMap::from([(
+///     "https://example.com/docs/body.html".to_string(),
+///     r#"<body><p>Hello <img src="a.png"></p></body>"#.to_string(),
+/// )]);
+/// let merged = splice_frames(html, "https://example.com/", &frames).unwrap();
+/// assert!(merged.contains(r#"<main><p>Hello <img src="https://example.com/docs/a.png"></p></main>"#));
 /// ```
-pub fn extract_links(html: &str, base_url: &str) -> Result<Vec<String>, ParserError> {
-    let document = Html::parse_document(html);
-    let base_url = url::Url::parse(base_url).map_err(|e| ParserError::UrlError(e.to_string()))?;
+pub fn splice_frames(
+    html: &str,
+    base_url: &str,
+    frames: &HashMap<String, String>,
+) -> Result<String, ParserError> {
+    let mut document = Html::parse_document(html);
+    let page_url = url::Url::parse(base_url).map_err(|e| ParserError::UrlError(e.to_string()))?;
+    let base_url = effective_base_url(&document, &page_url);
+    splice_frames_into(&mut document, &base_url, &page_url.origin(), frames, 0);
+    Ok(document.root_element().html())
+}
 
-    // use cached selector for better performance
-    let selector = SELECTOR_CACHE.get("links").ok_or_else(|| {
-        ParserError::SelectorError("Links selector not found in cache".to_string())
-    })?;
+/// splices the frames of one document, recursing into each spliced frame
+fn splice_frames_into(
+    document: &mut Html,
+    base_url: &url::Url,
+    origin: &url::Origin,
+    frames: &HashMap<String, String>,
+    depth: usize,
+) {
+    let Some(selector) = SELECTOR_CACHE.get("iframes") else {
+        return;
+    };
+    if depth >= MAX_FRAME_DEPTH || document.select(selector).next().is_none() {
+        return;
+    }
+    // iframes are replaced below, which needs consistent links
+    repair_tree(document);
+    let targets: Vec<(ego_tree::NodeId, url::Url, &String)> = document
+        .select(selector)
+        .filter_map(|iframe| {
+            let url = frame_url(base_url, iframe.value().attr("src")?)?;
+            let html = frames.get(url.as_str())?;
+            (url.origin() == *origin).then(|| (iframe.id(), url, html))
+        })
+        .collect();
 
-    let mut links = Vec::new();
+    for (id, url, html) in targets {
+        let mut frame = Html::parse_document(html);
+        repair_tree(&mut frame);
+        let frame_base = effective_base_url(&frame, &url);
+        absolutize_urls(&mut frame, &frame_base);
+        splice_frames_into(&mut frame, &frame_base, origin, frames, depth + 1);
 
-    for element in document.select(selector) {
-        if let Some(href) = element.value().attr("href") {
-            // Skip javascript and fragment-only links
-            if href.starts_with("javascript:") || href.starts_with("#") || href.is_empty() {
-                continue;
-            }
+        let frame_root = document.tree.extend_tree(frame.tree).id();
+        let content: Vec<ego_tree::NodeId> = document
+            .tree
+            .get(frame_root)
+            .and_then(|root| {
+                root.descendants().find(|node| {
+                    node.value()
+                        .as_element()
+                        .is_some_and(|element| element.name() == "body")
+                })
+            })
+            .map(|body| body.children().map(|node| node.id()).collect())
+            .unwrap_or_default();
+        let Some(mut iframe) = document.tree.get_mut(id) else {
+            continue;
+        };
+        for child in content {
+            iframe.insert_id_before(child);
+        }
+        iframe.detach();
+    }
+}
 
-            let processed_link = if href.starts_with("http://") || href.starts_with("https://") {
-                // Absolute URL - use as-is
-                href.to_string()
-            } else {
-                // Relative URL - resolve against base URL
-                match base_url.join(href) {
-                    Ok(absolute_url) => absolute_url.to_string(),
-                    Err(_) => continue, // Skip malformed URLs
+/// rewrites the URL attributes of every element to absolute URLs
+fn absolutize_urls(document: &mut Html, base_url: &url::Url) {
+    let is_url_attribute = |name: &str| {
+        name == "srcset" || URL_ATTRIBUTES.contains(&name) || LAZY_IMAGE_ATTRIBUTES.contains(&name)
+    };
+    let elements: Vec<ego_tree::NodeId> = document
+        .tree
+        .root()
+        .descendants()
+        .filter(|node| node.value().is_element())
+        .map(|node| node.id())
+        .collect();
+    for id in elements {
+        let Some(mut node) = document.tree.get_mut(id) else {
+            continue;
+        };
+        let scraper::Node::Element(element) = node.value() else {
+            continue;
+        };
+        if !element
+            .attrs
+            .iter()
+            .any(|(name, _)| is_url_attribute(&name.local))
+        {
+            continue;
+        }
+        let attributes = element
+            .attrs
+            .iter()
+            .map(|(name, value)| {
+                let value = match &*name.local {
+                    "srcset" => absolute_srcset(base_url, value),
+                    local if is_url_attribute(local) => {
+                        absolute_url(base_url, value).unwrap_or_else(|| value.to_string())
+                    }
+                    _ => value.to_string(),
+                };
+                html5ever::Attribute {
+                    name: name.clone(),
+                    value: value.as_str().into(),
                 }
-            };
+            })
+            .collect();
+        *element = scraper::node::Element::new(element.name.clone(), attributes);
+    }
+}
+
+/// words outside its controls that make a form page content rather than a widget
+const FORM_CONTENT_WORDS: usize = 40;
+
+/// whether an element is a form or form control that carries no page content
+///
+/// forms that wrap the content container or a fair amount of text, as ASP.NET pages
+/// wrap the whole body, are kept; their controls are removed on their own.
+fn is_form_furniture(element: &scraper::ElementRef) -> bool {
+    if element.value().name() == "form" {
+        return !form_wraps_content(element);
+    }
+    SELECTOR_CACHE
+        .get("form_controls")
+        .is_some_and(|selector| selector.matches(element))
+}
+
+/// whether a form holds the main content or text beyond its controls' labels
+fn form_wraps_content(form: &scraper::ElementRef) -> bool {
+    if SELECTOR_CACHE
+        .get("main_content")
+        .is_some_and(|selector| form.select(selector).next().is_some())
+    {
+        return true;
+    }
+    let words = form
+        .descendants()
+        .filter(|node| {
+            !node.ancestors().any(|ancestor| {
+                ancestor.value().as_element().is_some_and(|element| {
+                    matches!(element.name(), "button" | "select" | "textarea" | "label")
+                })
+            })
+        })
+        .filter_map(|node| node.value().as_text())
+        .flat_map(|text| text.split_whitespace())
+        .take(FORM_CONTENT_WORDS)
+        .count();
+    words >= FORM_CONTENT_WORDS
+}
+
+/// drops every comment node, wherever it is in the document
+///
+/// IE conditional comments (`<!--[if IE]>...<![endif]-->`) are single comments and
+/// go with their content; the `<![if !IE]>` markers of downlevel-revealed blocks are
+/// comments too, so the markers go and the content they wrap stays.
+fn remove_comments(document: &mut Html) {
+    let is_comment = |node: ego_tree::NodeRef<scraper::Node>| node.value().is_comment();
+    if document.tree.root().descendants().any(is_comment) {
+        document.tree = rebuilt_tree(&document.tree, is_comment);
+    }
+}
+
+/// URL prefixes that run code or render a document when followed
+const SCRIPT_URL_PREFIXES: [&str; 3] = ["javascript:", "vbscript:", "data:text/html"];
 
-            links.push(processed_link);
+/// rebuilds elements without event handlers, script URLs and the attributes
+/// `options` strips
+///
+/// elements are replaced rather than edited so their cached id and classes
+/// match the attributes they are left with.
+fn sanitize_attributes(document: &mut Html, options: &CleanOptions) {
+    let elements: Vec<ego_tree::NodeId> = document
+        .tree
+        .root()
+        .descendants()
+        .filter(|node| node.value().is_element())
+        .map(|node| node.id())
+        .collect();
+    for id in elements {
+        let Some(mut node) = document.tree.get_mut(id) else {
+            continue;
+        };
+        let scraper::Node::Element(element) = node.value() else {
+            continue;
+        };
+        let keep = |(name, value): &(html5ever::QualName, scraper::StrTendril)| {
+            keep_attribute(&name.local, value, options)
+        };
+        if element.attrs.iter().all(keep) {
+            continue;
         }
+        let attributes = element
+            .attrs
+            .iter()
+            .filter(|attribute| keep(attribute))
+            .map(|(name, value)| html5ever::Attribute {
+                name: name.clone(),
+                value: value.as_ref().into(),
+            })
+            .collect();
+        *element = scraper::node::Element::new(element.name.clone(), attributes);
     }
+}
 
-    // Remove duplicates more efficiently using sort + dedup
-    links.sort_unstable(); // unstable sort is faster
-    links.dedup();
+/// whether an attribute survives cleaning, and re-emitting by `standalone_html`
+fn keep_attribute(name: &str, value: &str, options: &CleanOptions) -> bool {
+    let name = name.to_ascii_lowercase();
+    match name.as_str() {
+        _ if name.starts_with("on") => false,
+        // an inline document, which runs its own scripts
+        "srcdoc" => false,
+        "style" => !options.strip_style && !is_script_style(value),
+        "class" => !options.strip_class,
+        "id" => !options.strip_id,
+        _ if URL_ATTRIBUTES.contains(&name.as_str()) => !is_script_url(value),
+        _ => true,
+    }
+}
 
-    Ok(links)
+/// whether following a URL would run script or render an inline document
+///
+/// references left encoded (`java&#115;cript:`), whitespace and control characters
+/// are handled the way browsers handle them before reading the scheme
+fn is_script_url(value: &str) -> bool {
+    let url: String = decode_entities(value)
+        .chars()
+        .filter(|c| !c.is_whitespace() && !c.is_control())
+        .collect::<String>()
+        .to_ascii_lowercase();
+    SCRIPT_URL_PREFIXES
+        .iter()
+        .any(|prefix| url.starts_with(prefix))
 }
 
-/// Resolves a relative URL against a base URL, returning the absolute URL as a string.
+/// whether a `style` attribute loads a script URL through `url(...)` or runs an IE
+/// `expression(...)`
 ///
-/// If the relative URL is already absolute, it is returned unchanged. Otherwise, the function parses the base URL and joins it with the relative URL. Returns an error if URL parsing or joining fails.
+/// a `url(...)` holding a CSS escape counts as a script URL, since the escape could
+/// spell out the scheme
+fn is_script_style(value: &str) -> bool {
+    let style: String = decode_entities(value)
+        .chars()
+        .filter(|c| !c.is_whitespace() && !c.is_control())
+        .collect::<String>()
+        .to_ascii_lowercase();
+    style.contains("expression(")
+        || style.split("url(").skip(1).any(|argument| {
+            let url = argument.trim_start_matches(['"', '\'']);
+            url.contains('\\')
+                || SCRIPT_URL_PREFIXES
+                    .iter()
+                    .any(|prefix| url.starts_with(prefix))
+        })
+}
+
+/// decodes the character references still encoded in an attribute value, for the
+/// script checks only
 ///
-/// # Examples
+/// the parser has decoded the value once; references left over (`java&amp;#115;cript:`
+/// in the source) are decoded as well, so a value that would read as a script URL
+/// after one more round of parsing is caught. named, decimal and hex references are
+/// decoded; unknown names and invalid code points are kept as written.
+fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let reference = rest[1..]
+            .find(';')
+            .filter(|&end| end > 0 && end <= 32)
+            .map(|end| &rest[1..=end]);
+        match reference.and_then(decode_reference) {
+            Some(c) => {
+                decoded.push(c);
+                rest = &rest[reference.map_or(0, str::len) + 2..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+/// decodes the name or number between `&` and `;`
+fn decode_reference(reference: &str) -> Option<char> {
+    if let Some(number) = reference.strip_prefix('#') {
+        let code = match number.strip_prefix(['x', 'X']) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+            None => number.parse().ok()?,
+        };
+        return char::from_u32(code).filter(|&c| c != '\0');
+    }
+
+    Some(match reference {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => '\u{a0}',
+        "shy" => '\u{ad}',
+        "copy" => '©',
+        "reg" => '®',
+        "trade" => '™',
+        "hellip" => '…',
+        "mdash" => '—',
+        "ndash" => '–',
+        "lsquo" => '‘',
+        "rsquo" => '’',
+        "ldquo" => '“',
+        "rdquo" => '”',
+        "laquo" => '«',
+        "raquo" => '»',
+        "bull" => '•',
+        "middot" => '·',
+        "deg" => '°',
+        "times" => '×',
+        "divide" => '÷',
+        "plusmn" => '±',
+        "para" => '¶',
+        "sect" => '§',
+        "euro" => '€',
+        "pound" => '£',
+        "yen" => '¥',
+        "cent" => '¢',
+        "eacute" => 'é',
+        "egrave" => 'è',
+        "aacute" => 'á',
+        "agrave" => 'à',
+        "ouml" => 'ö',
+        "uuml" => 'ü',
+        "auml" => 'ä',
+        "szlig" => 'ß',
+        "ccedil" => 'ç',
+        "ntilde" => 'ñ',
+        _ => return None,
+    })
+}
+
+/// drops every element `is_unwanted` accepts, with its subtree, from the document
 ///
-/// ```
-/// use markdown_lab_rs::html_parser::resolve_url;
-/// let abs = resolve_url("https://example.com/path/", "subpage.html").unwrap();
-/// assert_eq!(abs, "https://example.com/path/subpage.html");
+/// removal works on the node tree, so it does not depend on how the removed
+/// markup would serialize or on that markup appearing elsewhere in the page.
+fn remove_elements(document: &mut Html, is_unwanted: impl Fn(&scraper::ElementRef) -> bool) {
+    let unwanted = |node: ego_tree::NodeRef<scraper::Node>| {
+        scraper::ElementRef::wrap(node).is_some_and(|element| is_unwanted(&element))
+    };
+    if document.tree.root().descendants().any(unwanted) {
+        document.tree = rebuilt_tree(&document.tree, unwanted);
+    }
+}
+
+/// rebuilds a document's tree so every node's links agree, see `rebuilt_tree`
+fn repair_tree(document: &mut Html) {
+    document.tree = rebuilt_tree(&document.tree, |_| false);
+}
+
+/// copies a tree by following child lists, leaving out the nodes `skip` accepts and
+/// their subtrees
 ///
-/// let abs2 = resolve_url("https://example.com", "https://other.com/page").unwrap();
-/// assert_eq!(abs2, "https://other.com/page");
-/// ```
-pub fn resolve_url(base_url: &str, relative_url: &str) -> Result<String, ParserError> {
-    if relative_url.starts_with("http://") || relative_url.starts_with("https://") {
-        Ok(relative_url.to_string())
-    } else {
-        let base = url::Url::parse(base_url).map_err(|e| ParserError::UrlError(e.to_string()))?;
-        let resolved = base
-            .join(relative_url)
-            .map_err(|e| ParserError::UrlError(e.to_string()))?;
-        Ok(resolved.to_string())
+/// html5ever's adoption agency can leave misnested markup (`<code><div>1</code>`)
+/// with parent links that disagree with the child lists, and ego-tree panics when
+/// such a node is detached or moved, so trees are rebuilt instead of edited in place.
+fn rebuilt_tree(
+    tree: &ego_tree::Tree<scraper::Node>,
+    skip: impl Fn(ego_tree::NodeRef<scraper::Node>) -> bool,
+) -> ego_tree::Tree<scraper::Node> {
+    let mut rebuilt = ego_tree::Tree::new(tree.root().value().clone());
+    let mut pending = vec![(tree.root(), rebuilt.root().id())];
+    while let Some((source, target)) = pending.pop() {
+        for child in source.children() {
+            if skip(child) {
+                continue;
+            }
+            let Some(mut parent) = rebuilt.get_mut(target) else {
+                continue;
+            };
+            let copy = parent.append(child.value().clone()).id();
+            pending.push((child, copy));
+        }
     }
+    rebuilt
 }
 
-/// extracts and normalizes text content, collapses whitespace
+/// elements written without a closing tag
+const VOID_ELEMENTS: [&str; 13] = [
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
+    "wbr",
+];
+
+/// attributes holding a single URL, rewritten against the base URL
+const URL_ATTRIBUTES: [&str; 9] = [
+    "href",
+    "src",
+    "poster",
+    "cite",
+    "action",
+    "formaction",
+    "data",
+    "codebase",
+    "archive",
+];
+
+/// elements embedding another document or a plugin, never re-emitted
+const EMBEDDING_ELEMENTS: [&str; 5] = ["iframe", "frame", "object", "embed", "applet"];
+
+/// re-emits the main content as a standalone HTML document with absolute URLs
+///
+/// unwanted elements, all scripts, frames, objects, embeds and comments are dropped,
+/// attributes are filtered the way `clean_html` filters them (no `on*` handlers,
+/// `srcdoc` or script URLs, in styles too), and URL attributes (`href`, `src`, `data`,
+/// `srcset` and the like) are resolved against the base URL. attributes holding a
+/// script URL (`javascript:`, `vbscript:` or `data:text/html`, however obfuscated) are
+/// removed.
 ///
 /// # Examples
 ///
 /// ```
-/// use markdown_lab_rs::html_parser::get_element_text;
-/// use scraper::{Html, Selector};
-/// let html = Html::parse_fragment("<div>Hello   <b>world</b>!</div>");
-/// let selector = Selector::parse("div").unwrap();
-/// let element = html.select(&selector).next().unwrap();
-/// let text = get_element_text(&element);
-/// assert_eq!(text, "Hello world !");
+/// use markdown_lab_rs::html_parser::standalone_html;
+/// let html = r#"<body><script>bad()</script><main><a href="/about" onclick="x()">About</a></main></body>"#;
+/// let sanitized = standalone_html(html, "https://example.com").unwrap();
+/// assert!(sanitized.starts_with("<!DOCTYPE html>"));
+/// assert!(sanitized.contains(r#"<main><a href="https://example.com/about">About</a></main>"#));
+/// assert!(!sanitized.contains("script"));
 /// ```
-pub fn get_element_text(element: &scraper::ElementRef) -> String {
-    element
-        .text()
-        .collect::<Vec<_>>()
-        .join(" ")
-        .split_whitespace()
+pub fn standalone_html(html: &str, base_url: &str) -> Result<String, ParserError> {
+    let base = url::Url::parse(base_url).map_err(|e| ParserError::UrlError(e.to_string()))?;
+    let document = Html::parse_document(html);
+    let unwanted = SELECTOR_CACHE
+        .get("unwanted_elements")
+        .ok_or_else(|| ParserError::SelectorError("unwanted elements selector missing".into()))?;
+
+    let title = Selector::parse("title")
+        .ok()
+        .and_then(|selector| document.select(&selector).next())
+        .map(|title| get_element_text(&title))
+        .unwrap_or_default();
+
+    let mut content = String::new();
+    match find_main_content(&document) {
+        Some(main) => write_sanitized(*main, &base, unwanted, &mut content),
+        None => {
+            let root = SELECTOR_CACHE
+                .get("body")
+                .and_then(|selector| document.select(selector).next())
+                .unwrap_or_else(|| document.root_element());
+            for child in root.children() {
+                write_sanitized(child, &base, unwanted, &mut content);
+            }
+        }
+    }
+
+    Ok(format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n</head>\n<body>\n{}\n</body>\n</html>\n",
+        escape_html_text(&title),
+        content.trim()
+    ))
+}
+
+/// serializes a node and its descendants, skipping unwanted content
+fn write_sanitized(
+    node: ego_tree::NodeRef<scraper::Node>,
+    base: &url::Url,
+    unwanted: &Selector,
+    out: &mut String,
+) {
+    match node.value() {
+        scraper::Node::Text(text) => out.push_str(&escape_html_text(text)),
+        scraper::Node::Element(element) => {
+            let name = element.name();
+            if name == "script"
+                || EMBEDDING_ELEMENTS.contains(&name)
+                || scraper::ElementRef::wrap(node).is_some_and(|e| unwanted.matches(&e))
+            {
+                return;
+            }
+
+            out.push('<');
+            out.push_str(name);
+            let options = CleanOptions::default();
+            for (attribute, value) in element.attrs() {
+                if !keep_attribute(attribute, value, &options) {
+                    continue;
+                }
+                let value = if URL_ATTRIBUTES.contains(&attribute) {
+                    match absolute_url(base, value) {
+                        Some(url) => url,
+                        None => continue,
+                    }
+                } else if attribute == "srcset" {
+                    absolute_srcset(base, value)
+                } else {
+                    value.to_string()
+                };
+                out.push_str(&format!(
+                    " {}=\"{}\"",
+                    attribute,
+                    escape_html_attribute(&value)
+                ));
+            }
+            out.push('>');
+
+            if VOID_ELEMENTS.contains(&name) {
+                return;
+            }
+            for child in node.children() {
+                write_sanitized(child, base, unwanted, out);
+            }
+            out.push_str(&format!("</{}>", name));
+        }
+        _ => {}
+    }
+}
+
+/// resolves a URL attribute value, rejecting script URLs
+fn absolute_url(base: &url::Url, value: &str) -> Option<String> {
+    // checked before joining, which strips the tabs and newlines hiding a scheme
+    if is_script_url(value) {
+        return None;
+    }
+    let value = value.trim();
+    // in-page fragments stay relative so they keep pointing into this document
+    if value.starts_with('#') {
+        return Some(value.to_string());
+    }
+    Some(
+        base.join(value)
+            .map(|url| url.to_string())
+            .unwrap_or_else(|_| value.to_string()),
+    )
+}
+
+/// resolves every candidate URL in a `srcset`, keeping the descriptors
+fn absolute_srcset(base: &url::Url, value: &str) -> String {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|candidate| !candidate.is_empty())
+        .map(|candidate| {
+            let (url, descriptor) = candidate
+                .split_once(char::is_whitespace)
+                .unwrap_or((candidate, ""));
+            let url = absolute_url(base, url).unwrap_or_default();
+            format!("{} {}", url, descriptor.trim())
+                .trim_end()
+                .to_string()
+        })
         .collect::<Vec<_>>()
-        .join(" ")
+        .join(", ")
+}
+
+fn escape_html_text(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+pub(crate) fn escape_html_attribute(value: &str) -> String {
+    value.replace('&', "&amp;").replace('"', "&quot;")
+}
+
+/// which tags, attributes and URL schemes `sanitize_html` lets through
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SanitizePolicy {
+    /// tags kept, each with the attributes allowed on it; other tags are unwrapped
+    pub allowed_tags: HashMap<String, Vec<String>>,
+    /// tags dropped together with their content, such as `script`
+    pub dropped_tags: Vec<String>,
+    /// schemes accepted in `href` and `src`; relative URLs are always accepted
+    pub allowed_schemes: Vec<String>,
+}
+
+impl Default for SanitizePolicy {
+    /// text structure, links, images, code, quotes and tables over http(s) and mailto
+    fn default() -> Self {
+        let bare = [
+            "p",
+            "h1",
+            "h2",
+            "h3",
+            "h4",
+            "h5",
+            "h6",
+            "ul",
+            "li",
+            "pre",
+            "blockquote",
+            "table",
+            "caption",
+            "thead",
+            "tbody",
+            "tfoot",
+            "tr",
+            "colgroup",
+        ]
+        .map(|tag| (tag, &[][..]));
+        let with_attributes: [(&str, &[&str]); 7] = [
+            ("ol", &["start"]),
+            ("a", &["href", "title"]),
+            ("img", &["src", "alt", "title", "width", "height"]),
+            ("code", &["class"]),
+            ("th", &["colspan", "rowspan", "scope"]),
+            ("td", &["colspan", "rowspan"]),
+            ("col", &["span"]),
+        ];
+        let owned = |values: &[&str]| values.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+
+        SanitizePolicy {
+            allowed_tags: bare
+                .into_iter()
+                .chain(with_attributes)
+                .map(|(tag, attributes)| (tag.to_string(), owned(attributes)))
+                .collect(),
+            dropped_tags: owned(&[
+                "script", "style", "noscript", "template", "iframe", "object", "embed", "head",
+                "textarea", "select",
+            ]),
+            allowed_schemes: owned(&["http", "https", "mailto"]),
+        }
+    }
+}
+
+/// rebuilds untrusted HTML from an allowlist of tags and attributes
+///
+/// disallowed elements are unwrapped so their text survives, except `dropped_tags`,
+/// which go with their content. `style` and `on*` attributes are always removed, as
+/// are `href`/`src` values whose scheme is not allowed (such as `javascript:`).
+/// comments are dropped and text is re-escaped.
+///
+/// # Examples
+///
+/// ```
+/// use markdown_lab_rs::html_parser::{SanitizePolicy, sanitize_html};
+/// let html = r#"<div onclick="x()"><p style="color:red">Hi <span>there</span></p>
+///     <a href="javascript:alert(1)">link</a><script>bad()</script></div>"#;
+/// let clean = sanitize_html(html, &SanitizePolicy::default());
+/// assert_eq!(clean, "<p>Hi there</p>\n    <a>link</a>");
+/// ```
+pub fn sanitize_html(html: &str, policy: &SanitizePolicy) -> String {
+    let document = Html::parse_document(html);
+    let root = SELECTOR_CACHE
+        .get("body")
+        .and_then(|selector| document.select(selector).next())
+        .unwrap_or_else(|| document.root_element());
+
+    let mut out = String::new();
+    for child in root.children() {
+        write_allowed(child, policy, &mut out);
+    }
+    out.trim().to_string()
+}
+
+/// serializes a node for `sanitize_html`
+fn write_allowed(
+    node: ego_tree::NodeRef<scraper::Node>,
+    policy: &SanitizePolicy,
+    out: &mut String,
+) {
+    let element = match node.value() {
+        scraper::Node::Text(text) => return out.push_str(&escape_html_text(text)),
+        scraper::Node::Element(element) => element,
+        _ => return,
+    };
+    let name = element.name();
+    if policy
+        .dropped_tags
+        .iter()
+        .any(|tag| tag.eq_ignore_ascii_case(name))
+    {
+        return;
+    }
+    let Some(allowed_attributes) = policy
+        .allowed_tags
+        .iter()
+        .find(|(tag, _)| tag.eq_ignore_ascii_case(name))
+        .map(|(_, attributes)| attributes)
+    else {
+        for child in node.children() {
+            write_allowed(child, policy, out);
+        }
+        return;
+    };
+
+    out.push('<');
+    out.push_str(name);
+    for (attribute, value) in element.attrs() {
+        let attribute = attribute.to_ascii_lowercase();
+        let permitted = allowed_attributes
+            .iter()
+            .any(|allowed| allowed.eq_ignore_ascii_case(&attribute));
+        if !permitted || attribute == "style" || attribute.starts_with("on") {
+            continue;
+        }
+        if matches!(attribute.as_str(), "href" | "src") && !has_allowed_scheme(value, policy) {
+            continue;
+        }
+        out.push_str(&format!(
+            " {}=\"{}\"",
+            attribute,
+            escape_html_attribute(value)
+        ));
+    }
+    out.push('>');
+
+    if VOID_ELEMENTS.contains(&name) {
+        return;
+    }
+    for child in node.children() {
+        write_allowed(child, policy, out);
+    }
+    out.push_str(&format!("</{}>", name));
+}
+
+/// whether a URL is relative or uses one of the policy's schemes
+///
+/// whitespace and control characters are ignored the way browsers ignore them,
+/// so `java\tscript:` is still seen as `javascript:`
+fn has_allowed_scheme(value: &str, policy: &SanitizePolicy) -> bool {
+    let url: String = value
+        .chars()
+        .filter(|c| !c.is_whitespace() && !c.is_control())
+        .collect();
+    let Some(colon) = url.find(':') else {
+        return true;
+    };
+    let scheme = &url[..colon];
+    // a colon after a path, query or fragment starts does not end a scheme
+    if scheme.contains(['/', '?', '#']) {
+        return true;
+    }
+    policy
+        .allowed_schemes
+        .iter()
+        .any(|allowed| allowed.eq_ignore_ascii_case(scheme))
+}
+
+/// cleans HTML content by removing unwanted elements
+///
+/// an alias of `clean_html`, which removes elements from the parsed node tree.
+///
+/// # Examples
+///
+/// ```
+/// use markdown_lab_rs::html_parser::clean_html_advanced;
+/// let html = r#"<html><body><script>bad()</script><main>Good Content</main></body></html>"#;
+/// let cleaned = clean_html_advanced(html).unwrap();
+/// assert!(cleaned.contains("Good Content"));
+/// assert!(!cleaned.contains("<script>"));
+/// ```
+pub fn clean_html_advanced(html: &str) -> Result<String, ParserError> {
+    clean_html(html)
+}
+
+/// the URL relative links resolve against, following the page's `<base href>`
+///
+/// the first `<base>` with an `href` wins and is itself resolved against `base_url`;
+/// without one, or when it does not resolve, links resolve against `base_url`
+///
+/// # Examples
+///
+/// ```
+/// use markdown_lab_rs::html_parser::effective_base_url;
+/// use scraper::Html;
+/// let base = url::Url::parse("https://cdn.example.net/cache/123").unwrap();
+/// let page = Html::parse_document(r#"<head><base href="https://example.com/docs/"></head>"#);
+/// assert_eq!(effective_base_url(&page, &base).as_str(), "https://example.com/docs/");
+/// let plain = Html::parse_document("<p>No base tag</p>");
+/// assert_eq!(effective_base_url(&plain, &base), base);
+/// ```
+pub fn effective_base_url(document: &Html, base_url: &url::Url) -> url::Url {
+    Selector::parse("base[href]")
+        .ok()
+        .and_then(|selector| document.select(&selector).next())
+        .and_then(|base| base_url.join(base.value().attr("href")?.trim()).ok())
+        .unwrap_or_else(|| base_url.clone())
+}
+
+/// the page's `<link rel="canonical">` URL, resolved against `base_url`
+pub fn canonical_url(document: &Html, base_url: &url::Url) -> Option<String> {
+    let selector = Selector::parse("link[rel][href]").ok()?;
+    document
+        .select(&selector)
+        .find(|link| {
+            link.value().attr("rel").is_some_and(|rel| {
+                rel.split_whitespace()
+                    .any(|r| r.eq_ignore_ascii_case("canonical"))
+            })
+        })
+        .and_then(|link| base_url.join(link.value().attr("href")?.trim()).ok())
+        .map(String::from)
+}
+
+/// language variants of the page as (hreflang, absolute URL) pairs, from
+/// `<link rel="alternate" hreflang="...">`
+///
+/// `x-default` and values that are not valid language tags are passed through as
+/// written; relative hrefs are resolved and repeated pairs are listed once.
+///
+/// # Examples
+///
+/// ```
+/// use markdown_lab_rs::html_parser::extract_alternates;
+/// let html = r#"<link rel="alternate" hreflang="de" href="/de/">
+///     <link rel="alternate" hreflang="x-default" href="https://example.com/">"#;
+/// assert_eq!(
+///     extract_alternates(html, "https://example.com/en/").unwrap(),
+///     vec![
+///         ("de".to_string(), "https://example.com/de/".to_string()),
+///         ("x-default".to_string(), "https://example.com/".to_string()),
+///     ]
+/// );
+/// ```
+pub fn extract_alternates(
+    html: &str,
+    base_url: &str,
+) -> Result<Vec<(String, String)>, ParserError> {
+    let document = Html::parse_document(html);
+    let base_url = url::Url::parse(base_url).map_err(|e| ParserError::UrlError(e.to_string()))?;
+    let base_url = effective_base_url(&document, &base_url);
+    Ok(alternates(&document, &base_url))
+}
+
+/// hreflang alternates of an already parsed document, see `extract_alternates`
+pub fn alternates(document: &Html, base_url: &url::Url) -> Vec<(String, String)> {
+    let Ok(selector) = Selector::parse("link[rel][hreflang][href]") else {
+        return Vec::new();
+    };
+    let mut alternates: Vec<(String, String)> = Vec::new();
+    for link in document.select(&selector) {
+        let element = link.value();
+        let is_alternate = element.attr("rel").is_some_and(|rel| {
+            rel.split_whitespace()
+                .any(|r| r.eq_ignore_ascii_case("alternate"))
+        });
+        let hreflang = element.attr("hreflang").unwrap_or_default().trim();
+        let href = element.attr("href").unwrap_or_default().trim();
+        if !is_alternate || hreflang.is_empty() || href.is_empty() {
+            continue;
+        }
+        let Ok(url) = base_url.join(href) else {
+            continue;
+        };
+        let alternate = (hreflang.to_string(), String::from(url));
+        if !alternates.contains(&alternate) {
+            alternates.push(alternate);
+        }
+    }
+    alternates
+}
+
+/// one step of a breadcrumb trail; the current page is often not linked
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Crumb {
+    pub text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+}
+
+/// separators sites put between breadcrumb items
+const CRUMB_SEPARATORS: &[char] = &['/', '>', '\u{203a}', '\u{bb}', '|', '\u{b7}', '\u{2192}'];
+
+/// the page's breadcrumb trail, from `nav[aria-label=breadcrumb]`, then
+/// `.breadcrumb`/`.breadcrumbs` lists, then JSON-LD `BreadcrumbList` items
+///
+/// the first source with any crumbs wins. links are resolved against the page's
+/// `<base href>` or `base_url`, and JSON-LD items are ordered by `position`.
+///
+/// # Examples
+///
+/// ```
+/// use markdown_lab_rs::html_parser::extract_breadcrumbs;
+/// let html = r#"<nav aria-label="Breadcrumb"><ol>
+///     <li><a href="/">Home</a> /</li><li><a href="/docs/">Docs</a> /</li><li>API</li>
+/// </ol></nav>"#;
+/// let crumbs = extract_breadcrumbs(html, "https://example.com/docs/api").unwrap();
+/// let texts: Vec<&str> = crumbs.iter().map(|crumb| crumb.text.as_str()).collect();
+/// assert_eq!(texts, ["Home", "Docs", "API"]);
+/// assert_eq!(crumbs[1].url.as_deref(), Some("https://example.com/docs/"));
+/// assert_eq!(crumbs[2].url, None);
+/// ```
+pub fn extract_breadcrumbs(html: &str, base_url: &str) -> Result<Vec<Crumb>, ParserError> {
+    let document = Html::parse_document(html);
+    let base_url = url::Url::parse(base_url).map_err(|e| ParserError::UrlError(e.to_string()))?;
+    let base_url = effective_base_url(&document, &base_url);
+    Ok(breadcrumbs(&document, &base_url))
+}
+
+/// breadcrumbs of an already parsed document, see `extract_breadcrumbs`
+pub fn breadcrumbs(document: &Html, base_url: &url::Url) -> Vec<Crumb> {
+    let labelled_nav = Selector::parse("nav[aria-label]")
+        .ok()
+        .and_then(|selector| {
+            document.select(&selector).find(|nav| {
+                nav.value()
+                    .attr("aria-label")
+                    .is_some_and(|label| label.to_ascii_lowercase().contains("breadcrumb"))
+            })
+        });
+    let class_list = Selector::parse(".breadcrumb, .breadcrumbs")
+        .ok()
+        .and_then(|selector| document.select(&selector).next());
+
+    [labelled_nav, class_list]
+        .into_iter()
+        .flatten()
+        .map(|container| markup_crumbs(container, base_url))
+        .find(|crumbs| !crumbs.is_empty())
+        .unwrap_or_else(|| json_ld_crumbs(&json_ld_values(document), base_url))
+}
+
+/// crumbs from the list items of a breadcrumb container, or its links when it has no list
+fn markup_crumbs(container: scraper::ElementRef, base_url: &url::Url) -> Vec<Crumb> {
+    let (Ok(items), Ok(links)) = (Selector::parse("li"), Selector::parse("a[href]")) else {
+        return Vec::new();
+    };
+    let crumb = |element: scraper::ElementRef| {
+        let text = get_element_text(&element)
+            .trim_matches(|c: char| c.is_whitespace() || CRUMB_SEPARATORS.contains(&c))
+            .to_string();
+        let link = if element.value().name() == "a" {
+            Some(element)
+        } else {
+            element.select(&links).next()
+        };
+        let url = link
+            .and_then(|a| base_url.join(a.value().attr("href")?.trim()).ok())
+            .map(String::from);
+        (!text.is_empty()).then_some(Crumb { text, url })
+    };
+
+    let crumbs: Vec<Crumb> = container.select(&items).filter_map(crumb).collect();
+    if !crumbs.is_empty() {
+        return crumbs;
+    }
+    container.select(&links).filter_map(crumb).collect()
+}
+
+/// crumbs from the `itemListElement` of JSON-LD `BreadcrumbList` nodes
+fn json_ld_crumbs(values: &[serde_json::Value], base_url: &url::Url) -> Vec<Crumb> {
+    fn find_list(value: &serde_json::Value) -> Option<&serde_json::Value> {
+        let is_list = match value.get("@type") {
+            Some(serde_json::Value::String(name)) => name == "BreadcrumbList",
+            Some(serde_json::Value::Array(names)) => {
+                names.iter().any(|name| name == "BreadcrumbList")
+            }
+            _ => false,
+        };
+        if is_list {
+            return Some(value);
+        }
+        value.get("@graph")?.as_array()?.iter().find_map(find_list)
+    }
+
+    let Some(items) = values
+        .iter()
+        .find_map(find_list)
+        .and_then(|list| list.get("itemListElement"))
+        .and_then(serde_json::Value::as_array)
+    else {
+        return Vec::new();
+    };
+
+    let mut positioned: Vec<(f64, Crumb)> = items
+        .iter()
+        .filter_map(|item| {
+            let target = item.get("item");
+            let text = item
+                .get("name")
+                .or_else(|| target.and_then(|target| target.get("name")))
+                .and_then(serde_json::Value::as_str)?
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ");
+            let url = match target {
+                Some(serde_json::Value::String(url)) => Some(url.as_str()),
+                Some(target) => target
+                    .get("@id")
+                    .or_else(|| target.get("url"))
+                    .and_then(serde_json::Value::as_str),
+                None => None,
+            }
+            .and_then(|url| base_url.join(url.trim()).ok())
+            .map(String::from);
+            let position = item
+                .get("position")
+                .and_then(|position| {
+                    position
+                        .as_f64()
+                        .or_else(|| position.as_str()?.trim().parse().ok())
+                })
+                .unwrap_or(f64::MAX);
+            (!text.is_empty()).then_some((position, Crumb { text, url }))
+        })
+        .collect();
+    positioned.sort_by(|a, b| a.0.total_cmp(&b.0));
+    positioned.into_iter().map(|(_, crumb)| crumb).collect()
+}
+
+/// kind of machine-readable resource a page advertises in its head
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FeedKind {
+    Rss,
+    Atom,
+    JsonFeed,
+    Sitemap,
+}
+
+impl FeedKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FeedKind::Rss => "rss",
+            FeedKind::Atom => "atom",
+            FeedKind::JsonFeed => "json-feed",
+            FeedKind::Sitemap => "sitemap",
+        }
+    }
+}
+
+/// a feed or sitemap advertised by a `<link>` element
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeedLink {
+    pub url: String,
+    pub kind: FeedKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+}
+
+/// finds RSS, Atom and JSON feeds (`rel="alternate"` links with a feed type) and
+/// `rel="sitemap"` links
+///
+/// URLs are resolved against the page's `<base href>` or `base_url` and listed once,
+/// in document order. type parameters such as `; charset=utf-8` are ignored.
+///
+/// # Examples
+///
+/// ```
+/// use markdown_lab_rs::html_parser::{FeedKind, discover_feeds};
+/// let html = r#"<head>
+///     <link rel="alternate" type="application/rss+xml; charset=utf-8" href="/feed.xml" title="Posts">
+///     <link rel="sitemap" href="/sitemap.xml">
+/// </head>"#;
+/// let feeds = discover_feeds(html, "https://example.com/blog/").unwrap();
+/// assert_eq!(feeds[0].url, "https://example.com/feed.xml");
+/// assert_eq!((feeds[0].kind, feeds[0].title.as_deref()), (FeedKind::Rss, Some("Posts")));
+/// assert_eq!(feeds[1].kind, FeedKind::Sitemap);
+/// ```
+pub fn discover_feeds(html: &str, base_url: &str) -> Result<Vec<FeedLink>, ParserError> {
+    let document = Html::parse_document(html);
+    let base_url = url::Url::parse(base_url).map_err(|e| ParserError::UrlError(e.to_string()))?;
+    let base_url = effective_base_url(&document, &base_url);
+    let selector = Selector::parse("link[rel][href]")
+        .map_err(|e| ParserError::SelectorError(e.to_string()))?;
+
+    let mut feeds: Vec<FeedLink> = Vec::new();
+    for link in document.select(&selector) {
+        let element = link.value();
+        let rel: Vec<String> = element
+            .attr("rel")
+            .unwrap_or_default()
+            .split_whitespace()
+            .map(str::to_ascii_lowercase)
+            .collect();
+        let mime = element
+            .attr("type")
+            .and_then(|t| t.split(';').next())
+            .map(|t| t.trim().to_ascii_lowercase())
+            .unwrap_or_default();
+
+        let kind = if rel.iter().any(|r| r == "sitemap") {
+            FeedKind::Sitemap
+        } else if rel.iter().any(|r| r == "alternate" || r == "feed") {
+            match mime.as_str() {
+                "application/rss+xml" => FeedKind::Rss,
+                "application/atom+xml" => FeedKind::Atom,
+                "application/feed+json" => FeedKind::JsonFeed,
+                _ => continue,
+            }
+        } else {
+            continue;
+        };
+        let Some(href) = element
+            .attr("href")
+            .map(str::trim)
+            .filter(|h| !h.is_empty())
+        else {
+            continue;
+        };
+        let Ok(url) = base_url.join(href) else {
+            continue;
+        };
+        let url = String::from(url);
+        if feeds.iter().any(|feed| feed.url == url) {
+            continue;
+        }
+        let title = element
+            .attr("title")
+            .map(|title| title.split_whitespace().collect::<Vec<_>>().join(" "))
+            .filter(|title| !title.is_empty());
+        feeds.push(FeedLink { url, kind, title });
+    }
+    Ok(feeds)
+}
+
+/// indexing directives from robots meta tags
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RobotsDirectives {
+    pub noindex: bool,
+    pub nofollow: bool,
+    pub noarchive: bool,
+}
+
+impl RobotsDirectives {
+    /// whether search engines may index the page
+    pub fn is_indexable(&self) -> bool {
+        !self.noindex
+    }
+
+    /// true when no restricting directive is set
+    pub fn is_empty(&self) -> bool {
+        *self == RobotsDirectives::default()
+    }
+}
+
+/// meta names whose content holds robots directives: the generic one and crawler-specific ones
+const ROBOTS_META_NAMES: &[&str] = &["robots", "googlebot", "googlebot-news", "bingbot"];
+
+/// reads the robots directives from `<meta name="robots">`, crawler-specific names such
+/// as `googlebot`, and `<meta http-equiv="X-Robots-Tag">`
+///
+/// directives from all of them are combined; `none` means `noindex, nofollow`.
+///
+/// # Examples
+///
+/// ```
+/// use markdown_lab_rs::html_parser::extract_robots_directives;
+/// let robots = extract_robots_directives(r#"<meta name="robots" content="NOINDEX, follow">"#);
+/// assert!(robots.noindex && !robots.nofollow);
+/// assert!(!robots.is_indexable());
+/// ```
+pub fn extract_robots_directives(html: &str) -> RobotsDirectives {
+    robots_directives(&Html::parse_document(html))
+}
+
+/// robots directives of an already parsed document, see `extract_robots_directives`
+pub fn robots_directives(document: &Html) -> RobotsDirectives {
+    let mut directives = RobotsDirectives::default();
+    let Ok(selector) = Selector::parse("meta[content]") else {
+        return directives;
+    };
+    for meta in document.select(&selector) {
+        let element = meta.value();
+        let is_robots = element.attr("name").is_some_and(|name| {
+            ROBOTS_META_NAMES
+                .iter()
+                .any(|robots| name.trim().eq_ignore_ascii_case(robots))
+        }) || element
+            .attr("http-equiv")
+            .is_some_and(|name| name.trim().eq_ignore_ascii_case("x-robots-tag"));
+        if !is_robots {
+            continue;
+        }
+
+        for token in element
+            .attr("content")
+            .unwrap_or_default()
+            .split([',', ' '])
+        {
+            // X-Robots-Tag values may name the crawler first: `googlebot: noindex`
+            let directive = token.rsplit(':').next().unwrap_or_default().trim();
+            match directive.to_ascii_lowercase().as_str() {
+                "noindex" => directives.noindex = true,
+                "nofollow" => directives.nofollow = true,
+                "noarchive" => directives.noarchive = true,
+                "none" => {
+                    directives.noindex = true;
+                    directives.nofollow = true;
+                }
+                _ => {}
+            }
+        }
+    }
+    directives
+}
+
+/// which anchors `extract_links_with_options` returns and how
+///
+/// the default is what `extract_links` returns: contact links but no fragment links
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkExtractionOptions {
+    /// keep `mailto:` links
+    pub include_mailto: bool,
+    /// keep `tel:` links
+    pub include_tel: bool,
+    /// keep same-page links such as `#faq`
+    pub include_fragments: bool,
+    /// write kept fragment links as absolute URLs (`https://example.com/page#faq`)
+    /// instead of as written
+    pub resolve_fragments_against_base: bool,
+    /// return nothing for `nofollow` pages and skip `rel="nofollow"` anchors
+    pub respect_nofollow: bool,
+    /// pass every URL through `normalize_url` before duplicates are removed
+    pub normalize: Option<NormalizeOptions>,
+}
+
+impl Default for LinkExtractionOptions {
+    fn default() -> Self {
+        LinkExtractionOptions {
+            include_mailto: true,
+            include_tel: true,
+            include_fragments: false,
+            resolve_fragments_against_base: true,
+            respect_nofollow: false,
+            normalize: None,
+        }
+    }
+}
+
+/// what an href points at, by scheme rather than by its exact spelling
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum HrefKind {
+    Empty,
+    Fragment,
+    Script,
+    Mailto,
+    Tel,
+    /// any other scheme, e.g. `https:` or `ftp:`
+    Absolute,
+    Relative,
+}
+
+fn classify_href(href: &str) -> HrefKind {
+    let href = href.trim();
+    if href.is_empty() {
+        return HrefKind::Empty;
+    }
+    if href.starts_with('#') {
+        return HrefKind::Fragment;
+    }
+    match url_scheme(href).map(str::to_ascii_lowercase).as_deref() {
+        Some("javascript" | "vbscript") => HrefKind::Script,
+        Some("mailto") => HrefKind::Mailto,
+        Some("tel") => HrefKind::Tel,
+        Some(_) => HrefKind::Absolute,
+        None => HrefKind::Relative,
+    }
+}
+
+/// extracts unique absolute URLs from anchor elements
+///
+/// finds anchor tags with href, filters out javascript/fragment/empty links, resolves relative URLs
+///
+/// relative URLs resolve against the page's `<base href>` when it has one
+///
+/// # Parameters
+/// - `html`: The HTML content to parse.
+/// - `base_url`: The base URL used to resolve relative links.
+///
+/// # Returns
+/// A vector of unique absolute URLs found in the document.
+///
+/// # Errors
+/// Returns `ParserError::UrlError` if the base URL is invalid, or `ParserError::SelectorError` if the link selector is missing from the cache.
+///
+/// # Examples
+///
+/// ```
+/// use markdown_lab_rs::html_parser::extract_links;
+/// let html = r#"<a href="/about">About</a><a href="https://example.com/contact">Contact</a>"#;
+/// let links = extract_links(html, "https://example.com").unwrap();
+/// assert_eq!(links, vec![
+///     "https://example.com/about".to_string(),
+///     "https://example.com/contact".to_string()
+/// ]);
+/// ```
+pub fn extract_links(html: &str, base_url: &str) -> Result<Vec<String>, ParserError> {
+    extract_links_with_options(html, base_url, &LinkExtractionOptions::default())
+}
+
+/// like `extract_links`, choosing which kinds of links are kept
+///
+/// # Examples
+///
+/// ```
+/// use markdown_lab_rs::html_parser::{LinkExtractionOptions, extract_links_with_options};
+/// let html = r##"<a href="MAILTO:hi@example.com">Mail</a><a href="#faq">FAQ</a>"##;
+/// let options = LinkExtractionOptions {
+///     include_mailto: false,
+///     include_fragments: true,
+///     ..Default::default()
+/// };
+/// assert_eq!(
+///     extract_links_with_options(html, "https://example.com/page", &options).unwrap(),
+///     vec!["https://example.com/page#faq".to_string()]
+/// );
+/// ```
+pub fn extract_links_with_options(
+    html: &str,
+    base_url: &str,
+    options: &LinkExtractionOptions,
+) -> Result<Vec<String>, ParserError> {
+    collect_links(html, base_url, options)
+}
+
+/// like `extract_links`, with every URL passed through `normalize_url` before
+/// duplicates are removed
+///
+/// # Examples
+///
+/// ```
+/// use markdown_lab_rs::html_parser::{NormalizeOptions, extract_normalized_links};
+/// let html = r#"<a href="/post#comments">A</a><a href="/post?utm_source=x">B</a>"#;
+/// assert_eq!(
+///     extract_normalized_links(html, "https://example.com:443/", &NormalizeOptions::default()).unwrap(),
+///     vec!["https://example.com/post".to_string()]
+/// );
+/// ```
+pub fn extract_normalized_links(
+    html: &str,
+    base_url: &str,
+    options: &NormalizeOptions,
+) -> Result<Vec<String>, ParserError> {
+    let options = LinkExtractionOptions {
+        normalize: Some(options.clone()),
+        ..Default::default()
+    };
+    collect_links(html, base_url, &options)
+}
+
+/// like `extract_links`, but only the links a crawler may follow: none when the page's
+/// robots meta says `nofollow`, and no anchors marked `rel="nofollow"`
+///
+/// # Examples
+///
+/// ```
+/// use markdown_lab_rs::html_parser::extract_follow_links;
+/// let html = r#"<a href="/docs">Docs</a><a rel="ugc nofollow" href="/spam">Spam</a>"#;
+/// assert_eq!(
+///     extract_follow_links(html, "https://example.com").unwrap(),
+///     vec!["https://example.com/docs".to_string()]
+/// );
+/// ```
+pub fn extract_follow_links(html: &str, base_url: &str) -> Result<Vec<String>, ParserError> {
+    let options = LinkExtractionOptions {
+        respect_nofollow: true,
+        ..Default::default()
+    };
+    collect_links(html, base_url, &options)
+}
+
+fn collect_links(
+    html: &str,
+    base_url: &str,
+    options: &LinkExtractionOptions,
+) -> Result<Vec<String>, ParserError> {
+    let document = Html::parse_document(html);
+    let base_url = url::Url::parse(base_url).map_err(|e| ParserError::UrlError(e.to_string()))?;
+    let base_url = effective_base_url(&document, &base_url);
+    if options.respect_nofollow && robots_directives(&document).nofollow {
+        return Ok(Vec::new());
+    }
+
+    // use cached selector for better performance
+    let selector = SELECTOR_CACHE.get("links").ok_or_else(|| {
+        ParserError::SelectorError("Links selector not found in cache".to_string())
+    })?;
+
+    let mut links = Vec::new();
+
+    for element in document.select(selector) {
+        if options.respect_nofollow
+            && element.value().attr("rel").is_some_and(|rel| {
+                rel.split_whitespace()
+                    .any(|r| r.eq_ignore_ascii_case("nofollow"))
+            })
+        {
+            continue;
+        }
+        if let Some(link) = element
+            .value()
+            .attr("href")
+            .and_then(|href| resolve_href(&base_url, href, options))
+        {
+            links.push(match &options.normalize {
+                Some(normalize) => normalize_url(&link, normalize),
+                None => link,
+            });
+        }
+    }
+
+    // Remove duplicates more efficiently using sort + dedup
+    links.sort_unstable(); // unstable sort is faster
+    links.dedup();
+
+    Ok(links)
+}
+
+/// how `normalize_url` rewrites URLs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NormalizeOptions {
+    /// drop the `#fragment`
+    pub strip_fragment: bool,
+    /// query parameters removed, matched case-insensitively; a trailing `*` matches
+    /// any name with that prefix
+    pub removed_params: Vec<String>,
+}
+
+impl Default for NormalizeOptions {
+    /// strips fragments and `utm_*`, `fbclid` and `gclid` tracking parameters
+    fn default() -> Self {
+        NormalizeOptions {
+            strip_fragment: true,
+            removed_params: ["utm_*", "fbclid", "gclid"].map(String::from).to_vec(),
+        }
+    }
+}
+
+/// rewrites a URL into a canonical form so equivalent links compare equal
+///
+/// the scheme and host are lowercased, IDN hosts converted to punycode and default
+/// ports removed. in the path, runs of slashes are collapsed; in the path and query,
+/// percent escapes of unreserved characters are decoded and the rest uppercased.
+/// removed parameters are dropped and the others sorted by name, keeping the order
+/// of repeated names. strings that do not parse as URLs are returned unchanged.
+///
+/// # Examples
+///
+/// ```
+/// use markdown_lab_rs::html_parser::{NormalizeOptions, normalize_url};
+/// let options = NormalizeOptions::default();
+/// assert_eq!(
+///     normalize_url("HTTPS://Example.COM:443//a//b?z=1&utm_source=x&a=%7e#top", &options),
+///     "https://example.com/a/b?a=~&z=1"
+/// );
+/// ```
+pub fn normalize_url(url: &str, options: &NormalizeOptions) -> String {
+    let Ok(mut parsed) = url::Url::parse(url.trim()) else {
+        return url.to_string();
+    };
+    if options.strip_fragment {
+        parsed.set_fragment(None);
+    }
+    // mailto:, data: and similar URLs have no path segments to clean up
+    if parsed.cannot_be_a_base() {
+        return parsed.into();
+    }
+
+    let mut path = String::with_capacity(parsed.path().len());
+    for c in parsed.path().chars() {
+        if !(c == '/' && path.ends_with('/')) {
+            path.push(c);
+        }
+    }
+    parsed.set_path(&normalize_percent_escapes(&path));
+
+    if let Some(query) = parsed.query() {
+        let removed = |pair: &&str| {
+            let name = pair.split('=').next().unwrap_or_default();
+            options
+                .removed_params
+                .iter()
+                .any(|param| match param.strip_suffix('*') {
+                    Some(prefix) => name
+                        .get(..prefix.len())
+                        .is_some_and(|start| start.eq_ignore_ascii_case(prefix)),
+                    None => name.eq_ignore_ascii_case(param),
+                })
+        };
+        let mut pairs: Vec<String> = query
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .filter(|pair| !removed(pair))
+            .map(normalize_percent_escapes)
+            .collect();
+        pairs.sort_by(|a, b| {
+            let name = |pair: &str| pair.split('=').next().unwrap_or_default().to_string();
+            name(a).cmp(&name(b))
+        });
+        if pairs.is_empty() {
+            parsed.set_query(None);
+        } else {
+            parsed.set_query(Some(&pairs.join("&")));
+        }
+    }
+    parsed.into()
+}
+
+/// decodes escapes of unreserved characters (`%7E` to `~`) and uppercases the rest
+fn normalize_percent_escapes(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut out = String::with_capacity(text.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escape = (bytes[i] == b'%')
+            .then(|| text.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escape {
+            Some(byte) if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) => {
+                out.push(byte as char);
+                i += 3;
+            }
+            Some(byte) => {
+                out.push_str(&format!("%{:02X}", byte));
+                i += 3;
+            }
+            None => {
+                let c = text[i..].chars().next().unwrap_or_default();
+                out.push(c);
+                i += c.len_utf8();
+            }
+        }
+    }
+    out
+}
+
+/// which extracted links `extract_links_filtered` keeps
+///
+/// a link must pass every check: scheme, domain, extension, then patterns. when
+/// `include_patterns` is not empty a link must match one of them, and a link
+/// matching any `exclude_patterns` is dropped even if an include pattern matches.
+#[derive(Debug, Clone)]
+pub struct LinkFilter {
+    /// keep only links on the page's domain
+    pub same_domain_only: bool,
+    /// with `same_domain_only`, count subdomains of the page's registrable domain
+    /// (`blog.example.com` for `www.example.com`) as the same domain
+    pub include_subdomains: bool,
+    /// schemes kept; empty keeps every scheme
+    pub allowed_schemes: Vec<String>,
+    /// file extensions dropped, with or without the leading dot, e.g. `pdf`
+    pub exclude_extensions: Vec<String>,
+    pub exclude_patterns: Vec<Regex>,
+    pub include_patterns: Vec<Regex>,
+}
+
+impl Default for LinkFilter {
+    /// http(s) links on any domain, subdomains counted as the same domain
+    fn default() -> Self {
+        LinkFilter {
+            same_domain_only: false,
+            include_subdomains: true,
+            allowed_schemes: vec!["http".to_string(), "https".to_string()],
+            exclude_extensions: Vec::new(),
+            exclude_patterns: Vec::new(),
+            include_patterns: Vec::new(),
+        }
+    }
+}
+
+impl LinkFilter {
+    /// whether `link` passes the filter for a page at `page`
+    pub fn accepts(&self, link: &url::Url, page: &url::Url) -> bool {
+        if !self.allowed_schemes.is_empty()
+            && !self
+                .allowed_schemes
+                .iter()
+                .any(|scheme| scheme.eq_ignore_ascii_case(link.scheme()))
+        {
+            return false;
+        }
+        if self.same_domain_only {
+            let same = match (link.host_str(), page.host_str()) {
+                (Some(link_host), Some(page_host)) if self.include_subdomains => {
+                    registrable_domain(link_host) == registrable_domain(page_host)
+                }
+                (link_host, page_host) => link_host == page_host,
+            };
+            if !same {
+                return false;
+            }
+        }
+        if let Some(extension) = url_extension(link)
+            && self.exclude_extensions.iter().any(|excluded| {
+                excluded
+                    .trim_start_matches('.')
+                    .eq_ignore_ascii_case(extension)
+            })
+        {
+            return false;
+        }
+        let url = link.as_str();
+        (self.include_patterns.is_empty()
+            || self
+                .include_patterns
+                .iter()
+                .any(|pattern| pattern.is_match(url)))
+            && !self
+                .exclude_patterns
+                .iter()
+                .any(|pattern| pattern.is_match(url))
+    }
+}
+
+/// second-level labels under which country domains register names, as in `example.co.uk`
+const SECOND_LEVEL_SUFFIXES: &[&str] = &["co", "com", "net", "org", "gov", "ac", "edu", "ne", "or"];
+
+/// the name a host was registered under, e.g. `example.com` for `blog.example.com`
+///
+/// an approximation of the public suffix list: the last two labels, or three under
+/// a second-level suffix of a two-letter country domain. IP addresses are returned as is.
+fn registrable_domain(host: &str) -> String {
+    let host = host.trim_end_matches('.').to_ascii_lowercase();
+    if host.parse::<std::net::IpAddr>().is_ok() || host.starts_with('[') {
+        return host;
+    }
+    let labels: Vec<&str> = host.split('.').collect();
+    let keep = match labels.as_slice() {
+        [.., second, country]
+            if labels.len() >= 3
+                && country.len() == 2
+                && SECOND_LEVEL_SUFFIXES.contains(second) =>
+        {
+            3
+        }
+        _ => 2,
+    };
+    labels[labels.len().saturating_sub(keep)..].join(".")
+}
+
+/// the extension of the last path segment, e.g. `pdf` for `/files/report.pdf?v=2`
+fn url_extension(link: &url::Url) -> Option<&str> {
+    link.path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .and_then(|name| name.rsplit_once('.'))
+        .map(|(_, extension)| extension)
+        .filter(|extension| !extension.is_empty())
+}
+
+/// extensions of documents, archives and media that are downloaded rather than crawled
+const ASSET_EXTENSIONS: &[&str] = &[
+    "pdf", "doc", "docx", "xls", "xlsx", "ppt", "pptx", "odt", "ods", "csv", "epub", "zip", "gz",
+    "tgz", "tar", "bz2", "xz", "rar", "7z", "dmg", "exe", "msi", "apk", "deb", "rpm", "iso", "jpg",
+    "jpeg", "png", "gif", "webp", "avif", "svg", "bmp", "ico", "tif", "tiff", "mp4", "webm", "mov",
+    "avi", "mkv", "m4v", "mp3", "wav", "ogg", "oga", "flac", "m4a",
+];
+
+/// where a link leads relative to the page it was found on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LinkKind {
+    /// the page's own host, with or without `www.`
+    Internal,
+    /// another host under the page's registrable domain, e.g. `blog.example.co.uk`
+    Subdomain,
+    External,
+    /// a document, archive, image, audio or video file, on any host
+    Asset,
+}
+
+impl LinkKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LinkKind::Internal => "internal",
+            LinkKind::Subdomain => "subdomain",
+            LinkKind::External => "external",
+            LinkKind::Asset => "asset",
+        }
+    }
+}
+
+/// classifies a link found on the page at `page`
+///
+/// assets are recognized by the extension of the last path segment. domains are
+/// compared with the heuristic of `registrable_domain` rather than the public suffix
+/// list, so `example.co.uk` and `blog.example.co.uk` share a domain.
+///
+/// # Examples
+///
+/// ```
+/// use markdown_lab_rs::html_parser::{LinkKind, classify_link};
+/// let page = url::Url::parse("https://www.example.co.uk/news/").unwrap();
+/// let kind = |href: &str| classify_link(&page.join(href).unwrap(), &page);
+/// assert_eq!(kind("/about"), LinkKind::Internal);
+/// assert_eq!(kind("https://example.co.uk/"), LinkKind::Internal);
+/// assert_eq!(kind("https://shop.example.co.uk/"), LinkKind::Subdomain);
+/// assert_eq!(kind("https://other.co.uk/"), LinkKind::External);
+/// assert_eq!(kind("/files/report.PDF"), LinkKind::Asset);
+/// ```
+pub fn classify_link(link: &url::Url, page: &url::Url) -> LinkKind {
+    if url_extension(link).is_some_and(|extension| {
+        ASSET_EXTENSIONS
+            .iter()
+            .any(|asset| asset.eq_ignore_ascii_case(extension))
+    }) {
+        return LinkKind::Asset;
+    }
+    let (Some(link_host), Some(page_host)) = (link.host_str(), page.host_str()) else {
+        return LinkKind::External;
+    };
+    if is_same_host(link, page) {
+        LinkKind::Internal
+    } else if registrable_domain(link_host) == registrable_domain(page_host) {
+        LinkKind::Subdomain
+    } else {
+        LinkKind::External
+    }
+}
+
+/// whether two URLs are on the same host, ignoring case, a trailing dot and a `www.`
+/// prefix; URLs without a host never are
+fn is_same_host(link: &url::Url, page: &url::Url) -> bool {
+    let without_www = |host: &str| {
+        let host = host.trim_end_matches('.').to_ascii_lowercase();
+        host.strip_prefix("www.")
+            .map(str::to_string)
+            .unwrap_or(host)
+    };
+    match (link.host_str(), page.host_str()) {
+        (Some(link_host), Some(page_host)) => without_www(link_host) == without_www(page_host),
+        _ => false,
+    }
+}
+
+/// classifies absolute URLs, e.g. those of `extract_links`, found on the page at `base_url`
+///
+/// URLs that do not parse are external
+pub fn classify_links(urls: &[String], base_url: &str) -> Result<Vec<LinkKind>, ParserError> {
+    let page = url::Url::parse(base_url).map_err(|e| ParserError::UrlError(e.to_string()))?;
+    Ok(urls
+        .iter()
+        .map(|link| {
+            url::Url::parse(link)
+                .map(|link| classify_link(&link, &page))
+                .unwrap_or(LinkKind::External)
+        })
+        .collect())
+}
+
+/// `extract_links` narrowed by a `LinkFilter`; domains are compared with `base_url`
+///
+/// # Examples
+///
+/// ```
+/// use markdown_lab_rs::html_parser::{LinkFilter, extract_links_filtered};
+/// let html = r#"<a href="/guide">Guide</a><a href="/guide.pdf">PDF</a>
+///     <a href="https://blog.example.com/post">Blog</a><a href="https://other.org/">Other</a>"#;
+/// let filter = LinkFilter {
+///     same_domain_only: true,
+///     exclude_extensions: vec!["pdf".to_string()],
+///     ..Default::default()
+/// };
+/// assert_eq!(
+///     extract_links_filtered(html, "https://www.example.com/", &filter).unwrap(),
+///     ["https://blog.example.com/post", "https://www.example.com/guide"]
+/// );
+/// ```
+pub fn extract_links_filtered(
+    html: &str,
+    base_url: &str,
+    filter: &LinkFilter,
+) -> Result<Vec<String>, ParserError> {
+    let page = url::Url::parse(base_url).map_err(|e| ParserError::UrlError(e.to_string()))?;
+    let mut links = extract_links(html, base_url)?;
+    links.retain(|link| url::Url::parse(link).is_ok_and(|link| filter.accepts(&link, &page)));
+    Ok(links)
+}
+
+/// resolves an anchor's href, or `None` for script, empty and malformed links and
+/// the kinds `options` leaves out
+fn resolve_href(
+    base_url: &url::Url,
+    href: &str,
+    options: &LinkExtractionOptions,
+) -> Option<String> {
+    let href = href.trim();
+    match classify_href(href) {
+        HrefKind::Empty | HrefKind::Script => None,
+        HrefKind::Fragment if !options.include_fragments => None,
+        HrefKind::Fragment if !options.resolve_fragments_against_base => Some(href.to_string()),
+        HrefKind::Mailto if !options.include_mailto => None,
+        HrefKind::Tel if !options.include_tel => None,
+        // Absolute URL - use as-is once it parses
+        HrefKind::Absolute | HrefKind::Mailto | HrefKind::Tel => {
+            url::Url::parse(href).is_ok().then(|| href.to_string())
+        }
+        HrefKind::Fragment | HrefKind::Relative => resolve_against(base_url, href).ok(),
+    }
+}
+
+/// a link with the context needed to build a link graph
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LinkInfo {
+    pub url: String,
+    /// distinct anchor texts of every link to `url`, joined with ` | `; an image's alt
+    /// text stands in for an anchor without text
+    pub text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// lowercased `rel` tokens of every link to `url`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rel: Vec<String>,
+    /// whether `url` is on a different host than the page, compared as `classify_link`
+    /// compares them
+    pub is_external: bool,
+    /// where `url` leads, see `classify_link`
+    pub kind: LinkKind,
+    /// where the first link to `url` sits, e.g. `body > main > p > a`; stops at the
+    /// nearest ancestor with an id
+    pub dom_path_hint: String,
+}
+
+/// like `extract_links`, but with anchor text, title, rel and position for each URL
+///
+/// URLs are resolved the same way and listed once, in the order they first appear;
+/// repeated links merge their texts and rel tokens.
+///
+/// # Examples
+///
+/// ```
+/// use markdown_lab_rs::html_parser::extract_links_detailed;
+/// let html = r#"<main id="content"><p><a href="/docs" title="Guide">Docs</a>
+///     <a href="https://other.example/" rel="nofollow">Elsewhere</a>
+///     <a href="/docs">Read the docs</a></p></main>"#;
+/// let links = extract_links_detailed(html, "https://example.com/").unwrap();
+/// assert_eq!(links[0].url, "https://example.com/docs");
+/// assert_eq!(links[0].text, "Docs | Read the docs");
+/// assert_eq!(links[0].dom_path_hint, "main#content > p > a");
+/// assert!(!links[0].is_external && links[1].is_external);
+/// assert_eq!(links[1].rel, ["nofollow"]);
+/// ```
+pub fn extract_links_detailed(html: &str, base_url: &str) -> Result<Vec<LinkInfo>, ParserError> {
+    let document = Html::parse_document(html);
+    let page_url = url::Url::parse(base_url).map_err(|e| ParserError::UrlError(e.to_string()))?;
+    let base_url = effective_base_url(&document, &page_url);
+    let selector = SELECTOR_CACHE.get("links").ok_or_else(|| {
+        ParserError::SelectorError("Links selector not found in cache".to_string())
+    })?;
+
+    let mut links: Vec<LinkInfo> = Vec::new();
+    let mut texts: Vec<Vec<String>> = Vec::new();
+    for element in document.select(selector) {
+        let Some(url) = element
+            .value()
+            .attr("href")
+            .and_then(|href| resolve_href(&base_url, href, &LinkExtractionOptions::default()))
+        else {
+            continue;
+        };
+        let text = Some(get_element_text(&element))
+            .filter(|text| !text.is_empty())
+            .or_else(|| image_alt_text(&element))
+            .unwrap_or_default();
+        let title = element
+            .value()
+            .attr("title")
+            .map(str::trim)
+            .filter(|title| !title.is_empty())
+            .map(str::to_string);
+        let rel = element
+            .value()
+            .attr("rel")
+            .unwrap_or_default()
+            .split_whitespace()
+            .map(str::to_ascii_lowercase);
+
+        let index = match links.iter().position(|link| link.url == url) {
+            Some(index) => index,
+            None => {
+                let parsed = url::Url::parse(&url).ok();
+                let is_external = parsed
+                    .as_ref()
+                    .is_some_and(|url| !is_same_host(url, &page_url));
+                let kind = parsed
+                    .as_ref()
+                    .map_or(LinkKind::External, |url| classify_link(url, &page_url));
+                links.push(LinkInfo {
+                    url,
+                    text: String::new(),
+                    title: None,
+                    rel: Vec::new(),
+                    is_external,
+                    kind,
+                    dom_path_hint: dom_path_hint(&element),
+                });
+                texts.push(Vec::new());
+                links.len() - 1
+            }
+        };
+        let link = &mut links[index];
+        if link.title.is_none() {
+            link.title = title;
+        }
+        for token in rel {
+            if !link.rel.contains(&token) {
+                link.rel.push(token);
+            }
+        }
+        if !text.is_empty() && !texts[index].contains(&text) {
+            texts[index].push(text);
+        }
+    }
+
+    for (link, texts) in links.iter_mut().zip(texts) {
+        link.text = texts.join(" | ");
+    }
+    Ok(links)
+}
+
+/// alt text of the first image inside an element
+fn image_alt_text(element: &scraper::ElementRef) -> Option<String> {
+    let selector = Selector::parse("img[alt]").ok()?;
+    element
+        .select(&selector)
+        .filter_map(|img| img.value().attr("alt"))
+        .map(|alt| alt.split_whitespace().collect::<Vec<_>>().join(" "))
+        .find(|alt| !alt.is_empty())
+}
+
+/// tag path from the nearest ancestor with an id, or from `body`, down to the element
+fn dom_path_hint(element: &scraper::ElementRef) -> String {
+    let mut steps = Vec::new();
+    for node in
+        std::iter::once(*element).chain(element.ancestors().filter_map(scraper::ElementRef::wrap))
+    {
+        let name = node.value().name();
+        if name == "html" {
+            break;
+        }
+        match node.value().id() {
+            Some(id) => {
+                steps.push(format!("{}#{}", name, id));
+                break;
+            }
+            None => steps.push(name.to_string()),
+        }
+    }
+    steps.reverse();
+    steps.join(" > ")
+}
+
+/// attributes lazy-loading libraries use for the real image url, in priority order
+pub const LAZY_IMAGE_ATTRIBUTES: &[&str] = &["data-src", "data-original", "data-lazy-src"];
+
+/// file names commonly used for placeholder images while the real one loads
+const PLACEHOLDER_IMAGE_NAMES: &[&str] = &[
+    "blank.gif",
+    "spacer.gif",
+    "pixel.gif",
+    "transparent.gif",
+    "1x1.gif",
+    "1x1.png",
+];
+
+/// picks the real source of an `img` element
+///
+/// `extra_attributes` are checked first, then the built-in lazy-load attributes
+/// and finally `src`. obvious placeholders are skipped.
+///
+/// # Examples
+///
+/// ```
+/// use markdown_lab_rs::html_parser::image_source;
+/// use scraper::{Html, Selector};
+/// let html = Html::parse_fragment(r#"<img src="blank.gif" data-src="/photo.jpg">"#);
+/// let img = html.select(&Selector::parse("img").unwrap()).next().unwrap();
+/// assert_eq!(image_source(img.value(), &[]), Some("/photo.jpg"));
+/// ```
+pub fn image_source<'a>(
+    element: &'a scraper::node::Element,
+    extra_attributes: &[String],
+) -> Option<&'a str> {
+    image_source_attribute(element, extra_attributes, PLACEHOLDER_DATA_URI_LEN).map(|(_, src)| src)
+}
+
+/// `image_source` together with the name of the attribute it came from, with inline
+/// data uris under `min_data_uri_len` bytes taken for placeholders
+fn image_source_attribute<'a, 'n>(
+    element: &'a scraper::node::Element,
+    extra_attributes: &'n [String],
+    min_data_uri_len: usize,
+) -> Option<(&'n str, &'a str)> {
+    extra_attributes
+        .iter()
+        .map(String::as_str)
+        .chain(LAZY_IMAGE_ATTRIBUTES.iter().copied())
+        .chain(std::iter::once("src"))
+        .filter_map(|name| Some((name, element.attr(name)?.trim())))
+        .find(|(_, src)| !src.is_empty() && !is_placeholder_source(src, min_data_uri_len))
+}
+
+/// inline data uris shorter than this are taken for placeholders
+const PLACEHOLDER_DATA_URI_LEN: usize = 200;
+
+/// checks for placeholder images: tiny inline data uris and well-known spacer files
+pub fn is_placeholder_image(src: &str) -> bool {
+    is_placeholder_source(src, PLACEHOLDER_DATA_URI_LEN)
+}
+
+/// `is_placeholder_image` with the data uri length threshold given
+fn is_placeholder_source(src: &str, min_data_uri_len: usize) -> bool {
+    let src = src.trim();
+    if src
+        .get(..5)
+        .is_some_and(|scheme| scheme.eq_ignore_ascii_case("data:"))
+    {
+        return src.len() < min_data_uri_len;
+    }
+
+    let path = src.split(['?', '#']).next().unwrap_or(src);
+    let file_name = path.rsplit('/').next().unwrap_or(path).to_lowercase();
+    PLACEHOLDER_IMAGE_NAMES.contains(&file_name.as_str())
+}
+
+/// extracts image urls from HTML, looking past lazy-load placeholders
+///
+/// # Examples
+///
+/// ```
+/// use markdown_lab_rs::html_parser::extract_images;
+/// let html = r#"<img src="/a.png"><img src="data:image/gif;base64,R0lGOD" data-original="/b.png">"#;
+/// let images = extract_images(html, "https://example.com").unwrap();
+/// assert_eq!(images, vec![
+///     "https://example.com/a.png".to_string(),
+///     "https://example.com/b.png".to_string()
+/// ]);
+/// ```
+pub fn extract_images(html: &str, base_url: &str) -> Result<Vec<String>, ParserError> {
+    let document = Html::parse_document(html);
+    let base_url = url::Url::parse(base_url).map_err(|e| ParserError::UrlError(e.to_string()))?;
+    let base_url = effective_base_url(&document, &base_url);
+    let selector = Selector::parse("img").map_err(|e| ParserError::SelectorError(e.to_string()))?;
+
+    let mut images = Vec::new();
+    for element in document.select(&selector) {
+        let Some(src) = image_source(element.value(), &[]) else {
+            continue;
+        };
+        // large inline images are real but would flood the output
+        if src.starts_with("data:") {
+            continue;
+        }
+        if let Ok(absolute_url) = base_url.join(src) {
+            images.push(absolute_url.to_string());
+        }
+    }
+
+    images.sort_unstable();
+    images.dedup();
+
+    Ok(images)
+}
+
+/// one image candidate from a `srcset` attribute
+struct SrcsetCandidate<'a> {
+    url: &'a str,
+    /// Width descriptor (`480w`)
+    width: Option<u32>,
+    /// Pixel density descriptor (`2x`), 1 when no descriptor is given
+    density: f32,
+}
+
+/// picks the largest `srcset` candidate of an image and its `<picture>` sources
+///
+/// width descriptors rank above density descriptors, since they state the real size.
+pub fn largest_srcset_candidate<'a>(img: &scraper::ElementRef<'a>) -> Option<&'a str> {
+    let picture_sources = img
+        .parent()
+        .and_then(scraper::ElementRef::wrap)
+        .filter(|parent| parent.value().name() == "picture")
+        .into_iter()
+        .flat_map(|picture| picture.child_elements())
+        .filter(|e| e.value().name() == "source");
+
+    picture_sources
+        .chain(std::iter::once(*img))
+        .flat_map(|e| ["data-srcset", "srcset"].map(|name| e.value().attr(name)))
+        .flatten()
+        .flat_map(parse_srcset)
+        .max_by(|a, b| {
+            (a.width.is_some(), a.width)
+                .cmp(&(b.width.is_some(), b.width))
+                .then(a.density.total_cmp(&b.density))
+        })
+        .map(|candidate| candidate.url)
+}
+
+/// parses a `srcset` attribute, dropping candidates with invalid descriptors
+fn parse_srcset(srcset: &str) -> Vec<SrcsetCandidate<'_>> {
+    let mut candidates = Vec::new();
+    let mut rest = srcset;
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_whitespace() || c == ',');
+        if rest.is_empty() {
+            break;
+        }
+
+        let url_end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        let (url, after) = rest.split_at(url_end);
+        // A URL that ends in a comma has no descriptors
+        let (url, descriptors) = if url.ends_with(',') {
+            rest = after;
+            (url.trim_end_matches(','), "")
+        } else {
+            let descriptors_end = after.find(',').unwrap_or(after.len());
+            rest = &after[descriptors_end..];
+            (url, &after[..descriptors_end])
+        };
+
+        if let Some(candidate) = parse_srcset_descriptors(url, descriptors) {
+            candidates.push(candidate);
+        }
+    }
+    candidates
+}
+
+fn parse_srcset_descriptors<'a>(url: &'a str, descriptors: &str) -> Option<SrcsetCandidate<'a>> {
+    let mut width = None;
+    let mut density = None;
+    for descriptor in descriptors.split_whitespace() {
+        // Split off the unit; descriptors are ASCII, anything else is malformed
+        let unit_start = descriptor.len() - 1;
+        if !descriptor.is_char_boundary(unit_start) {
+            return None;
+        }
+        let (value, unit) = descriptor.split_at(unit_start);
+        match unit {
+            "w" if width.is_none() => width = Some(value.parse::<u32>().ok().filter(|&w| w > 0)?),
+            "x" if density.is_none() => {
+                density = Some(
+                    value
+                        .parse::<f32>()
+                        .ok()
+                        .filter(|d| d.is_finite() && *d > 0.0)?,
+                )
+            }
+            // Height descriptors only qualify a width
+            "h" => {}
+            _ => return None,
+        }
+    }
+
+    if url.is_empty() || (width.is_some() && density.is_some()) {
+        return None;
+    }
+    Some(SrcsetCandidate {
+        url,
+        width,
+        density: density.unwrap_or(1.0),
+    })
+}
+
+/// where `extract_images_detailed` found an image's URL
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ImageSourceKind {
+    Src,
+    /// a lazy-load attribute such as `data-src` or `data-original`
+    DataSrc,
+    /// the largest candidate of the image's or its `<picture>`'s `srcset`
+    Srcset,
+}
+
+impl ImageSourceKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ImageSourceKind::Src => "src",
+            ImageSourceKind::DataSrc => "data-src",
+            ImageSourceKind::Srcset => "srcset",
+        }
+    }
+}
+
+/// an image with the details found on its `img` element
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImageInfo {
+    pub url: String,
+    pub alt: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub width: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub height: Option<u32>,
+    pub source: ImageSourceKind,
+}
+
+/// like `extract_images`, with alt text, declared size and where each URL came from
+///
+/// the largest `srcset` candidate wins over lazy-load attributes, which win over `src`.
+/// images are listed once per URL in document order, later duplicates filling in a
+/// missing alt text or size. inline `data:` images are skipped unless they are at
+/// least `min_data_uri_len` bytes long; `None` skips them all.
+///
+/// # Examples
+///
+/// ```
+/// use markdown_lab_rs::html_parser::{ImageSourceKind, extract_images_detailed};
+/// let html = r#"<img src="blank.gif" data-src="/photo.jpg" alt="Photo" width="640" height="480px">"#;
+/// let images = extract_images_detailed(html, "https://example.com", None).unwrap();
+/// assert_eq!(images[0].url, "https://example.com/photo.jpg");
+/// assert_eq!((images[0].width, images[0].height), (Some(640), Some(480)));
+/// assert_eq!(images[0].source, ImageSourceKind::DataSrc);
+/// ```
+pub fn extract_images_detailed(
+    html: &str,
+    base_url: &str,
+    min_data_uri_len: Option<usize>,
+) -> Result<Vec<ImageInfo>, ParserError> {
+    let document = Html::parse_document(html);
+    let base_url = url::Url::parse(base_url).map_err(|e| ParserError::UrlError(e.to_string()))?;
+    let base_url = effective_base_url(&document, &base_url);
+    let selector = Selector::parse("img").map_err(|e| ParserError::SelectorError(e.to_string()))?;
+    let dimension = |element: &scraper::node::Element, name: &str| {
+        element
+            .attr(name)
+            .map(|value| value.trim().trim_end_matches("px"))
+            .and_then(|value| value.parse::<u32>().ok())
+            .filter(|&pixels| pixels > 0)
+    };
+
+    let mut images: Vec<ImageInfo> = Vec::new();
+    for element in document.select(&selector) {
+        let (src, source) = match largest_srcset_candidate(&element) {
+            Some(candidate) => (candidate, ImageSourceKind::Srcset),
+            None => match image_source_attribute(
+                element.value(),
+                &[],
+                min_data_uri_len.unwrap_or(PLACEHOLDER_DATA_URI_LEN),
+            ) {
+                Some(("src", src)) => (src, ImageSourceKind::Src),
+                Some((_, src)) => (src, ImageSourceKind::DataSrc),
+                None => continue,
+            },
+        };
+        let url = if src
+            .get(..5)
+            .is_some_and(|scheme| scheme.eq_ignore_ascii_case("data:"))
+        {
+            match min_data_uri_len {
+                Some(min) if src.len() >= min => src.to_string(),
+                _ => continue,
+            }
+        } else {
+            match resolve_against(&base_url, src) {
+                Ok(url) => url,
+                Err(_) => continue,
+            }
+        };
+
+        let info = ImageInfo {
+            url,
+            alt: element
+                .value()
+                .attr("alt")
+                .map(|alt| alt.split_whitespace().collect::<Vec<_>>().join(" "))
+                .unwrap_or_default(),
+            width: dimension(element.value(), "width"),
+            height: dimension(element.value(), "height"),
+            source,
+        };
+        match images.iter_mut().find(|image| image.url == info.url) {
+            Some(image) => {
+                if image.alt.is_empty() {
+                    image.alt = info.alt;
+                }
+                image.width = image.width.or(info.width);
+                image.height = image.height.or(info.height);
+            }
+            None => images.push(info),
+        }
+    }
+    Ok(images)
+}
+
+/// the cells of one table, with spanning cells expanded
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TableData {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub caption: Option<String>,
+    /// from `thead`, or a leading row of `th` cells; empty when the table has neither
+    pub headers: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+impl TableData {
+    /// the headers (when present) and rows as CSV (RFC 4180) with CRLF line endings
+    pub fn to_csv(&self) -> String {
+        std::iter::once(&self.headers)
+            .filter(|headers| !headers.is_empty())
+            .chain(&self.rows)
+            .map(|row| {
+                let mut line = row
+                    .iter()
+                    .map(|cell| crate::markdown_converter::csv_field(cell, ','))
+                    .collect::<Vec<_>>()
+                    .join(",");
+                line.push_str("\r\n");
+                line
+            })
+            .collect()
+    }
+}
+
+/// extracts every table's caption, headers and rows, without converting the page
+///
+/// `colspan` and `rowspan` cells are repeated into each column and row they cover,
+/// and `th` cells in body rows are kept as ordinary cells. nested tables are listed
+/// after their parent, and their text is left out of the parent's cells.
+///
+/// # Examples
+///
+/// ```
+/// use markdown_lab_rs::html_parser::extract_tables;
+/// let html = r#"<table><caption>Prices</caption>
+///     <thead><tr><th>Item</th><th>Cost</th></tr></thead>
+///     <tbody><tr><td colspan="2">Free</td></tr></tbody></table>"#;
+/// let tables = extract_tables(html);
+/// assert_eq!(tables[0].caption.as_deref(), Some("Prices"));
+/// assert_eq!(tables[0].headers, ["Item", "Cost"]);
+/// assert_eq!(tables[0].rows, [["Free", "Free"]]);
+/// ```
+pub fn extract_tables(html: &str) -> Vec<TableData> {
+    let document = Html::parse_document(html);
+    let Ok(selector) = Selector::parse("table") else {
+        return Vec::new();
+    };
+    document
+        .select(&selector)
+        .filter_map(|table| {
+            let data = crate::markdown_converter::extract_table(
+                &table,
+                crate::markdown_converter::TableSpanFill::Duplicate,
+                text_outside_tables,
+            )?;
+            let caption = table
+                .child_elements()
+                .find(|child| child.value().name() == "caption")
+                .map(|caption| get_element_text(&caption))
+                .filter(|caption| !caption.is_empty());
+            Some(TableData {
+                caption,
+                headers: data.headers,
+                rows: data.rows,
+            })
+        })
+        .collect()
+}
+
+/// each table from `extract_tables` as a CSV string
+pub fn extract_tables_csv(html: &str) -> Vec<String> {
+    extract_tables(html).iter().map(TableData::to_csv).collect()
+}
+
+/// a form and the fields it submits
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FormInfo {
+    /// the `action` attribute as written; `None` submits to the page itself
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub action: Option<String>,
+    /// `GET`, `POST` or `DIALOG`, `GET` when missing or invalid
+    pub method: String,
+    pub fields: Vec<FormField>,
+}
+
+/// an input, select or textarea of a form
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FormField {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// the input `type` (`text` when missing), or `select` or `textarea`
+    pub kind: String,
+    /// from a `<label for>`, an enclosing label, `aria-label` or the placeholder
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    pub required: bool,
+    /// the initial value, or a select's selected option
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    /// the values a select offers
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub options: Vec<String>,
+}
+
+/// input types that submit or reset a form rather than hold a value
+const BUTTON_INPUT_TYPES: &[&str] = &["submit", "reset", "button", "image"];
+
+/// extracts every form's action, method and fields, without converting the page
+///
+/// buttons are not fields. forms are found wherever they are, including those
+/// `clean_html` removes.
+///
+/// # Examples
+///
+/// ```
+/// use markdown_lab_rs::html_parser::extract_forms;
+/// let html = r#"<form action="/search" method="get" role="search">
+///     <label for="q">Search the docs</label><input id="q" name="q" required>
+///     <button>Go</button></form>"#;
+/// let forms = extract_forms(html);
+/// assert_eq!(forms[0].action.as_deref(), Some("/search"));
+/// assert_eq!(forms[0].method, "GET");
+/// assert_eq!(forms[0].fields[0].name.as_deref(), Some("q"));
+/// assert_eq!(forms[0].fields[0].label.as_deref(), Some("Search the docs"));
+/// assert!(forms[0].fields[0].required);
+/// ```
+pub fn extract_forms(html: &str) -> Vec<FormInfo> {
+    let document = Html::parse_document(html);
+    let (Ok(form_selector), Ok(field_selector), Ok(label_selector)) = (
+        Selector::parse("form"),
+        Selector::parse("input, select, textarea"),
+        Selector::parse("label[for]"),
+    ) else {
+        return Vec::new();
+    };
+    let attribute = |element: &scraper::ElementRef, name: &str| {
+        element
+            .value()
+            .attr(name)
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(str::to_string)
+    };
+    let labels: HashMap<&str, String> = document
+        .select(&label_selector)
+        .filter_map(|label| Some((label.value().attr("for")?.trim(), get_element_text(&label))))
+        .collect();
+
+    document
+        .select(&form_selector)
+        .map(|form| {
+            let method = attribute(&form, "method")
+                .map(|method| method.to_ascii_uppercase())
+                .filter(|method| matches!(method.as_str(), "GET" | "POST" | "DIALOG"))
+                .unwrap_or_else(|| "GET".to_string());
+            let fields = form
+                .select(&field_selector)
+                .filter_map(|field| {
+                    let kind = match field.value().name() {
+                        "input" => attribute(&field, "type")
+                            .map(|kind| kind.to_ascii_lowercase())
+                            .unwrap_or_else(|| "text".to_string()),
+                        other => other.to_string(),
+                    };
+                    if BUTTON_INPUT_TYPES.contains(&kind.as_str()) {
+                        return None;
+                    }
+                    let options: Vec<String> = field
+                        .descendent_elements()
+                        .filter(|e| e.value().name() == "option")
+                        .map(|option| {
+                            attribute(&option, "value").unwrap_or_else(|| get_element_text(&option))
+                        })
+                        .collect();
+                    let value = match kind.as_str() {
+                        "select" => field
+                            .descendent_elements()
+                            .find(|e| {
+                                e.value().name() == "option" && e.value().attr("selected").is_some()
+                            })
+                            .map(|option| {
+                                attribute(&option, "value")
+                                    .unwrap_or_else(|| get_element_text(&option))
+                            }),
+                        "textarea" => Some(field.text().collect::<String>())
+                            .filter(|text| !text.trim().is_empty()),
+                        _ => attribute(&field, "value"),
+                    };
+                    let label = attribute(&field, "id")
+                        .and_then(|id| labels.get(id.as_str()).cloned())
+                        .or_else(|| {
+                            field
+                                .ancestors()
+                                .filter_map(scraper::ElementRef::wrap)
+                                .find(|e| e.value().name() == "label")
+                                .map(|label| get_element_text(&label))
+                        })
+                        .filter(|label| !label.is_empty())
+                        .or_else(|| attribute(&field, "aria-label"))
+                        .or_else(|| attribute(&field, "placeholder"));
+                    Some(FormField {
+                        name: attribute(&field, "name"),
+                        kind,
+                        label,
+                        required: field.value().attr("required").is_some(),
+                        value,
+                        options,
+                    })
+                })
+                .collect();
+            FormInfo {
+                action: attribute(&form, "action"),
+                method,
+                fields,
+            }
+        })
+        .collect()
+}
+
+/// a heading and the headings nested under it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HeadingNode {
+    /// 1 for `h1` through 6 for `h6`
+    pub level: u8,
+    pub text: String,
+    /// anchor id from the heading or an anchor around/inside it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<HeadingNode>,
+}
+
+/// extracts the page's heading outline, without converting the page
+///
+/// each heading holds the deeper headings that follow it until one of its own level
+/// or higher, so skipped levels (`h2` then `h4`) still nest. headings that come before
+/// any higher-level heading stay at the top. empty and hidden headings are left out.
+///
+/// # Examples
+///
+/// ```
+/// use markdown_lab_rs::html_parser::extract_headings;
+/// let html = "<h1>Guide</h1><h2>Install</h2><h4>From source</h4><h2>Usage</h2>";
+/// let outline = extract_headings(html);
+/// assert_eq!(outline.len(), 1);
+/// assert_eq!(outline[0].children[0].text, "Install");
+/// assert_eq!(outline[0].children[0].children[0].text, "From source");
+/// assert_eq!(outline[0].children[1].text, "Usage");
+/// ```
+pub fn extract_headings(html: &str) -> Vec<HeadingNode> {
+    fn nest(
+        headings: &mut std::iter::Peekable<std::vec::IntoIter<HeadingNode>>,
+        parent_level: u8,
+    ) -> Vec<HeadingNode> {
+        let mut nodes = Vec::new();
+        while let Some(mut heading) = headings.next_if(|heading| heading.level > parent_level) {
+            heading.children = nest(headings, heading.level);
+            nodes.push(heading);
+        }
+        nodes
+    }
+
+    nest(&mut extract_headings_flat(html).into_iter().peekable(), 0)
+}
+
+/// the headings of `extract_headings` in document order, without nesting
+pub fn extract_headings_flat(html: &str) -> Vec<HeadingNode> {
+    let document = Html::parse_document(html);
+    let Ok(selector) = Selector::parse("h1, h2, h3, h4, h5, h6") else {
+        return Vec::new();
+    };
+    document
+        .select(&selector)
+        .filter(|heading| !is_hidden(heading))
+        .filter_map(|heading| {
+            let text = element_text(&heading, false);
+            (!text.is_empty()).then(|| HeadingNode {
+                level: heading.value().name()[1..].parse().unwrap_or(1),
+                text,
+                id: heading_anchor(&heading),
+                children: Vec::new(),
+            })
+        })
+        .collect()
+}
+
+/// finds a heading's anchor: its own id, a named anchor inside it, or an enclosing anchor
+pub(crate) fn heading_anchor(element: &scraper::ElementRef) -> Option<String> {
+    let anchor_id = |e: &scraper::ElementRef| {
+        e.value()
+            .attr("id")
+            .or_else(|| {
+                (e.value().name() == "a")
+                    .then(|| e.value().attr("name"))
+                    .flatten()
+            })
+            .map(str::trim)
+            .filter(|id| !id.is_empty())
+            .map(str::to_string)
+    };
+
+    anchor_id(element)
+        .or_else(|| {
+            element
+                .descendent_elements()
+                .filter(|e| e.value().name() == "a")
+                .find_map(|a| anchor_id(&a))
+        })
+        .or_else(|| {
+            element
+                .parent()
+                .and_then(scraper::ElementRef::wrap)
+                .filter(|parent| parent.value().name() == "a")
+                .and_then(|parent| anchor_id(&parent))
+        })
+}
+
+/// `get_element_text` without the text of tables nested in the element
+fn text_outside_tables(element: &scraper::ElementRef) -> String {
+    element_text(element, true)
+}
+
+/// OpenGraph and Twitter Card metadata used for link previews
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SocialMeta {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub og_title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub og_description: Option<String>,
+    /// Absolute URL of the preview image
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub og_image: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub og_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub og_site_name: Option<String>,
+    /// Card layout, e.g. `summary_large_image`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub twitter_card: Option<String>,
+    /// Absolute URL of the card image
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub twitter_image: Option<String>,
+}
+
+impl SocialMeta {
+    /// true when the page declares none of the tags
+    pub fn is_empty(&self) -> bool {
+        *self == SocialMeta::default()
+    }
+}
+
+/// extracts OpenGraph (`og:*`) and Twitter Card (`twitter:*`) metadata
+///
+/// tags are matched on either `property` or `name`, the first non-empty one wins,
+/// and relative image URLs are resolved against `base_url`.
+///
+/// # Examples
+///
+/// ```
+/// use markdown_lab_rs::html_parser::extract_social_metadata;
+/// let html = r#"<head><meta property="og:title" content="Launch day">
+///     <meta name="twitter:image" content="/card.png"></head>"#;
+/// let social = extract_social_metadata(html, "https://example.com/blog/").unwrap();
+/// assert_eq!(social.og_title.as_deref(), Some("Launch day"));
+/// assert_eq!(social.twitter_image.as_deref(), Some("https://example.com/card.png"));
+/// assert_eq!(social.og_image, None);
+/// ```
+pub fn extract_social_metadata(html: &str, base_url: &str) -> Result<SocialMeta, ParserError> {
+    let base_url = url::Url::parse(base_url).map_err(|e| ParserError::UrlError(e.to_string()))?;
+    Ok(social_metadata(&Html::parse_document(html), &base_url))
+}
+
+/// social metadata of an already parsed document, see `extract_social_metadata`
+pub fn social_metadata(document: &Html, base_url: &url::Url) -> SocialMeta {
+    let Ok(selector) = Selector::parse("meta[content]") else {
+        return SocialMeta::default();
+    };
+    let tags: Vec<(String, String)> = document
+        .select(&selector)
+        .filter_map(|meta| {
+            let element = meta.value();
+            let key = element.attr("property").or_else(|| element.attr("name"))?;
+            let content = element
+                .attr("content")?
+                .split_whitespace()
+                .collect::<Vec<_>>();
+            (!content.is_empty()).then(|| (key.trim().to_ascii_lowercase(), content.join(" ")))
+        })
+        .collect();
+    let tag = |key: &str| {
+        tags.iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, content)| content.clone())
+    };
+    let image =
+        |key: &str| tag(key).map(|src| base_url.join(&src).map(String::from).unwrap_or(src));
+
+    SocialMeta {
+        og_title: tag("og:title"),
+        og_description: tag("og:description"),
+        og_image: image("og:image"),
+        og_type: tag("og:type"),
+        og_site_name: tag("og:site_name"),
+        twitter_card: tag("twitter:card"),
+        twitter_image: image("twitter:image"),
+    }
+}
+
+/// parses the JSON-LD structured data in `<script type="application/ld+json">` blocks
+///
+/// top-level arrays are flattened into the result and trailing commas are tolerated.
+/// blocks that still fail to parse are skipped with a warning.
+///
+/// # Examples
+///
+/// ```
+/// use markdown_lab_rs::html_parser::{extract_json_ld, json_ld_types};
+/// let html = r#"<script type="application/ld+json">
+///     {"@context": "https://schema.org", "@type": "Article", "headline": "Hi",}
+/// </script>"#;
+/// let values = extract_json_ld(html);
+/// assert_eq!(values[0]["headline"], "Hi");
+/// assert_eq!(json_ld_types(&values), ["Article"]);
+/// ```
+pub fn extract_json_ld(html: &str) -> Vec<serde_json::Value> {
+    json_ld_values(&Html::parse_document(html))
+}
+
+/// JSON-LD values of an already parsed document, see `extract_json_ld`
+pub fn json_ld_values(document: &Html) -> Vec<serde_json::Value> {
+    let Ok(selector) = Selector::parse("script[type]") else {
+        return Vec::new();
+    };
+
+    let mut values = Vec::new();
+    for (index, script) in document
+        .select(&selector)
+        .filter(|script| {
+            script
+                .value()
+                .attr("type")
+                .is_some_and(|t| t.trim().eq_ignore_ascii_case("application/ld+json"))
+        })
+        .enumerate()
+    {
+        let source = script.text().collect::<String>();
+        match serde_json::from_str(&strip_trailing_commas(&source)) {
+            Ok(serde_json::Value::Array(items)) => values.extend(items),
+            Ok(value) => values.push(value),
+            Err(e) => log::warn!("skipping invalid JSON-LD block {}: {}", index + 1, e),
+        }
+    }
+    values
+}
+
+/// the schema `@type` names declared by JSON-LD values, including `@graph` members
+///
+/// names appear once, in the order they are first found
+pub fn json_ld_types(values: &[serde_json::Value]) -> Vec<String> {
+    fn collect(value: &serde_json::Value, types: &mut Vec<String>) {
+        match value.get("@type") {
+            Some(serde_json::Value::String(name)) => types.push(name.clone()),
+            Some(serde_json::Value::Array(names)) => types.extend(
+                names
+                    .iter()
+                    .filter_map(|name| name.as_str().map(str::to_string)),
+            ),
+            _ => {}
+        }
+        if let Some(serde_json::Value::Array(graph)) = value.get("@graph") {
+            graph.iter().for_each(|node| collect(node, types));
+        }
+    }
+
+    let mut types = Vec::new();
+    values.iter().for_each(|value| collect(value, &mut types));
+    let mut seen = std::collections::HashSet::new();
+    types.retain(|name| seen.insert(name.clone()));
+    types
+}
+
+/// when a page was published and last modified, as RFC 3339 timestamps
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PageDates {
+    pub published: Option<String>,
+    pub modified: Option<String>,
+}
+
+/// finds the page's publication date, normalized to RFC 3339
+///
+/// checked in order: `article:published_time` meta, `<time>` elements (their
+/// `datetime` or, failing that, their text), JSON-LD `datePublished` and
+/// `itemprop="datePublished"`. dates without a time are taken as midnight UTC.
+///
+/// # Examples
+///
+/// ```
+/// use markdown_lab_rs::html_parser::extract_publication_date;
+/// let html = r#"<article><time>Posted March 5th, 2024</time></article>"#;
+/// assert_eq!(extract_publication_date(html).as_deref(), Some("2024-03-05T00:00:00Z"));
+/// ```
+pub fn extract_publication_date(html: &str) -> Option<String> {
+    page_dates(&Html::parse_document(html)).published
+}
+
+/// finds the page's last-modified date, normalized to RFC 3339
+///
+/// checked in order: `article:modified_time` and `og:updated_time` meta, JSON-LD
+/// `dateModified` and `itemprop="dateModified"`
+pub fn extract_modified_date(html: &str) -> Option<String> {
+    page_dates(&Html::parse_document(html)).modified
+}
+
+/// publication and modification dates of an already parsed document
+///
+/// see `extract_publication_date` and `extract_modified_date` for the sources
+pub fn page_dates(document: &Html) -> PageDates {
+    let json_ld = json_ld_values(document);
+    let first_date = |candidates: Vec<String>| {
+        candidates
+            .iter()
+            .find_map(|candidate| normalize_date(candidate))
+    };
+
+    PageDates {
+        published: first_date(
+            [
+                meta_property_values(document, &["article:published_time"]),
+                time_element_values(document),
+                json_ld_strings(&json_ld, "datePublished"),
+                itemprop_values(document, "datePublished"),
+            ]
+            .concat(),
+        ),
+        modified: first_date(
+            [
+                meta_property_values(document, &["article:modified_time", "og:updated_time"]),
+                json_ld_strings(&json_ld, "dateModified"),
+                itemprop_values(document, "dateModified"),
+            ]
+            .concat(),
+        ),
+    }
+}
+
+/// contents of `<meta>` tags whose `property` or `name` is one of `keys`
+fn meta_property_values(document: &Html, keys: &[&str]) -> Vec<String> {
+    let Ok(selector) = Selector::parse("meta[content]") else {
+        return Vec::new();
+    };
+    document
+        .select(&selector)
+        .filter(|meta| {
+            let element = meta.value();
+            element
+                .attr("property")
+                .or_else(|| element.attr("name"))
+                .is_some_and(|key| keys.iter().any(|k| key.trim().eq_ignore_ascii_case(k)))
+        })
+        .filter_map(|meta| meta.value().attr("content").map(str::to_string))
+        .collect()
+}
+
+/// the `datetime` of each `<time>` element, or its text when it has none
+fn time_element_values(document: &Html) -> Vec<String> {
+    let Ok(selector) = Selector::parse("time") else {
+        return Vec::new();
+    };
+    document
+        .select(&selector)
+        .map(|time| match time.value().attr("datetime") {
+            Some(datetime) => datetime.to_string(),
+            None => get_element_text(&time),
+        })
+        .collect()
+}
+
+/// microdata values of elements with the given `itemprop`
+fn itemprop_values(document: &Html, itemprop: &str) -> Vec<String> {
+    let Ok(selector) = Selector::parse("[itemprop]") else {
+        return Vec::new();
+    };
+    document
+        .select(&selector)
+        .filter(|element| {
+            element
+                .value()
+                .attr("itemprop")
+                .is_some_and(|props| props.split_whitespace().any(|p| p == itemprop))
+        })
+        .map(|element| {
+            let value = element.value();
+            value
+                .attr("content")
+                .or_else(|| value.attr("datetime"))
+                .map(str::to_string)
+                .unwrap_or_else(|| get_element_text(&element))
+        })
+        .collect()
+}
+
+/// string values of `key` in JSON-LD nodes, including `@graph` members
+fn json_ld_strings(values: &[serde_json::Value], key: &str) -> Vec<String> {
+    fn collect(value: &serde_json::Value, key: &str, found: &mut Vec<String>) {
+        if let Some(text) = value.get(key).and_then(serde_json::Value::as_str) {
+            found.push(text.to_string());
+        }
+        if let Some(serde_json::Value::Array(graph)) = value.get("@graph") {
+            graph.iter().for_each(|node| collect(node, key, found));
+        }
+    }
+
+    let mut found = Vec::new();
+    values
+        .iter()
+        .for_each(|value| collect(value, key, &mut found));
+    found
+}
+
+/// ISO 8601 dates as pages write them: a date, an optional time and an optional offset
+static ISO_DATE_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"^(\d{4})-(\d{2})-(\d{2})(?:[Tt ](\d{2}):(\d{2})(?::(\d{2})(?:[.,]\d+)?)?)?\s*([Zz]|[+-]\d{2}:?\d{2})?$",
+    )
+    .unwrap()
+});
+
+const MONTH_NAMES: [&str; 12] = [
+    "january",
+    "february",
+    "march",
+    "april",
+    "may",
+    "june",
+    "july",
+    "august",
+    "september",
+    "october",
+    "november",
+    "december",
+];
+
+/// normalizes an ISO 8601 or English human-readable date to RFC 3339
+///
+/// offsets are kept; timestamps without one and dates without a time are taken as UTC
+///
+/// # Examples
+///
+/// ```
+/// use markdown_lab_rs::html_parser::normalize_date;
+/// assert_eq!(normalize_date("2024-03-05T10:30+0200").as_deref(), Some("2024-03-05T10:30:00+02:00"));
+/// assert_eq!(normalize_date("Tue, 5 Mar 2024").as_deref(), Some("2024-03-05T00:00:00Z"));
+/// assert_eq!(normalize_date("2024-02-30"), None);
+/// ```
+pub fn normalize_date(text: &str) -> Option<String> {
+    let text = text.trim();
+    let Some(captures) = ISO_DATE_REGEX.captures(text) else {
+        return human_readable_date(text);
+    };
+    let number = |i| {
+        captures
+            .get(i)
+            .map_or(Some(0), |m| m.as_str().parse::<u32>().ok())
+    };
+    let (year, month, day) = (number(1)?, number(2)?, number(3)?);
+    let (hour, minute, second) = (number(4)?, number(5)?, number(6)?);
+    if !is_valid_date(year, month, day) || hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+    let offset = match captures.get(7).map(|m| m.as_str()) {
+        None | Some("Z" | "z") => "Z".to_string(),
+        Some(offset) => {
+            let digits = offset[1..].replace(':', "");
+            format!("{}{}:{}", &offset[..1], &digits[..2], &digits[2..])
+        }
+    };
+    Some(format!(
+        "{year:04}-{month:02}-{day:02}T{hour:02}:{minute:02}:{second:02}{offset}"
+    ))
+}
+
+/// dates like `March 5, 2024`, `5th Mar 2024` or `Tuesday, 5 March 2024`
+fn human_readable_date(text: &str) -> Option<String> {
+    let (mut year, mut month, mut day) = (None, None, None);
+    for token in text
+        .split(|c: char| c.is_whitespace() || c == ',' || c == '.')
+        .filter(|token| !token.is_empty())
+    {
+        let token = token.to_ascii_lowercase();
+        if token.len() >= 3
+            && let Some(index) = MONTH_NAMES.iter().position(|name| name.starts_with(&token))
+        {
+            month.get_or_insert(index as u32 + 1);
+            continue;
+        }
+        // ordinal suffixes: 1st, 2nd, 3rd, 5th
+        let digits = token.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+        if let Ok(number) = digits.parse::<u32>() {
+            match digits.len() {
+                4 => _ = year.get_or_insert(number),
+                1 | 2 => _ = day.get_or_insert(number),
+                _ => {}
+            }
+        }
+    }
+    let (year, month, day) = (year?, month?, day?);
+    is_valid_date(year, month, day).then(|| format!("{year:04}-{month:02}-{day:02}T00:00:00Z"))
+}
+
+fn is_valid_date(year: u32, month: u32, day: u32) -> bool {
+    let leap = year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400));
+    let days_in_month = match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if leap => 29,
+        2 => 28,
+        _ => return false,
+    };
+    (1000..=9999).contains(&year) && (1..=days_in_month).contains(&day)
+}
+
+/// drops commas directly before a closing `}` or `]`, leaving string contents alone
+fn strip_trailing_commas(json: &str) -> String {
+    let mut output = String::with_capacity(json.len());
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, c) in json.char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+        } else if c == '"' {
+            in_string = true;
+        } else if c == ',' && json[i + 1..].trim_start().starts_with(['}', ']']) {
+            continue;
+        }
+        output.push(c);
+    }
+    output
+}
+
+/// Resolves a relative URL against a base URL, returning the absolute URL as a string.
+///
+/// If the relative URL is already absolute (`https:`, `mailto:`, `tel:`, `data:` or any
+/// other scheme), it is returned unchanged. Protocol-relative URLs (`//host/path`) take
+/// the base URL's scheme, and other URLs are joined with the base URL. Returns an error
+/// if URL parsing or joining fails.
+///
+/// # Examples
+///
+/// ```
+/// use markdown_lab_rs::html_parser::resolve_url;
+/// let abs = resolve_url("https://example.com/path/", "subpage.html").unwrap();
+/// assert_eq!(abs, "https://example.com/path/subpage.html");
+///
+/// let abs2 = resolve_url("https://example.com", "https://other.com/page").unwrap();
+/// assert_eq!(abs2, "https://other.com/page");
+///
+/// let cdn = resolve_url("http://example.com", "//cdn.example.com/app.js").unwrap();
+/// assert_eq!(cdn, "http://cdn.example.com/app.js");
+/// ```
+pub fn resolve_url(base_url: &str, relative_url: &str) -> Result<String, ParserError> {
+    let relative_url = relative_url.trim();
+    // absolute URLs do not need a valid base
+    if url_scheme(relative_url).is_some() {
+        return url::Url::parse(relative_url)
+            .map(|_| relative_url.to_string())
+            .map_err(|e| ParserError::UrlError(e.to_string()));
+    }
+    let base = url::Url::parse(base_url).map_err(|e| ParserError::UrlError(e.to_string()))?;
+    resolve_against(&base, relative_url).map_err(|e| ParserError::UrlError(e.to_string()))
+}
+
+/// resolves a URL against an already parsed base like `resolve_url`, except that
+/// absolute URLs come back in their serialized form (`https://example.com/`)
+pub fn resolve_against(base: &url::Url, href: &str) -> Result<String, url::ParseError> {
+    let href = href.trim();
+    if url_scheme(href).is_some() {
+        return url::Url::parse(href).map(String::from);
+    }
+    if href.starts_with("//") {
+        return url::Url::parse(&format!("{}:{}", base.scheme(), href)).map(String::from);
+    }
+    base.join(href).map(String::from)
+}
+
+/// the scheme of an absolute URL: a letter, then letters, digits, `+`, `-` or `.`, then `:`
+fn url_scheme(url: &str) -> Option<&str> {
+    let (scheme, _) = url.split_once(':')?;
+    let mut chars = scheme.chars();
+    let valid = chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+    valid.then_some(scheme)
+}
+
+/// extracts and normalizes text content, collapses whitespace
+///
+/// text is concatenated as written, so spaces between inline elements come from the
+/// source; block-level elements and `<br>` separate the text around them.
+///
+/// # Examples
+///
+/// ```
+/// use markdown_lab_rs::html_parser::get_element_text;
+/// use scraper::{Html, Selector};
+/// let html = Html::parse_fragment("<div>Hello   <b>world</b>!<p>Bye</p></div>");
+/// let selector = Selector::parse("div").unwrap();
+/// let element = html.select(&selector).next().unwrap();
+/// let text = get_element_text(&element);
+/// assert_eq!(text, "Hello world! Bye");
+/// ```
+pub fn get_element_text(element: &scraper::ElementRef) -> String {
+    element_text(element, false)
+}
+
+/// the text below an element with whitespace collapsed
+fn element_text(element: &scraper::ElementRef, skip_tables: bool) -> String {
+    let mut parts = Vec::new();
+    collect_text_parts(**element, skip_tables, &mut parts);
+    parts
+        .concat()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// extracts the readable text of a page as plain text, one block per line
+///
+/// block elements start new lines and paragraphs, headings, lists, quotes, `pre` and
+/// tables are set apart by a blank line. `<br>` breaks the line, table cells are
+/// separated by tabs, and `pre` keeps its line breaks and indentation. the head,
+/// scripts, styles and templates are left out, and runs of blank lines become one.
+///
+/// # Examples
+///
+/// ```
+/// use markdown_lab_rs::html_parser::extract_text_content;
+/// let html = "<h1>Menu</h1><div><div>Soup<br>Bread</div></div>\
+///     <table><tr><th>Dish</th><th>Price</th></tr><tr><td>Soup</td><td>4</td></tr></table>";
+/// assert_eq!(extract_text_content(html), "Menu\n\nSoup\nBread\n\nDish\tPrice\nSoup\t4");
+/// ```
+pub fn extract_text_content(html: &str) -> String {
+    let document = Html::parse_document(html);
+    let mut writer = PlainTextWriter::default();
+    writer.write_children(*document.root_element(), false);
+    writer.finish()
+}
+
+/// elements whose content is not page text
+const NON_TEXT_ELEMENTS: &[&str] = &[
+    "head", "script", "style", "noscript", "template", "iframe", "object", "svg",
+];
+
+/// block elements set apart from their neighbours by a blank line
+const PARAGRAPH_ELEMENTS: &[&str] = &[
+    "blockquote",
+    "dl",
+    "figure",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "ol",
+    "p",
+    "pre",
+    "table",
+    "ul",
+];
+
+/// builds the text of `extract_text_content` line by line
+#[derive(Default)]
+struct PlainTextWriter {
+    text: String,
+    line: String,
+    /// whitespace was seen since the last word of the line
+    space: bool,
+    /// a blank line goes before the next line with text
+    blank_line: bool,
+}
+
+impl PlainTextWriter {
+    fn write_children(&mut self, node: ego_tree::NodeRef<scraper::Node>, preformatted: bool) {
+        for child in node.children() {
+            match child.value() {
+                scraper::Node::Text(text) if preformatted => self.write_preformatted(text),
+                scraper::Node::Text(text) => self.write_text(text),
+                scraper::Node::Element(element) => {
+                    self.write_element(child, element.name(), preformatted)
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn write_element(
+        &mut self,
+        node: ego_tree::NodeRef<scraper::Node>,
+        name: &str,
+        preformatted: bool,
+    ) {
+        match name {
+            _ if NON_TEXT_ELEMENTS.contains(&name) => {}
+            "br" => self.break_line(true),
+            "td" | "th" => {
+                let first_cell = !node.prev_siblings().any(|sibling| {
+                    sibling
+                        .value()
+                        .as_element()
+                        .is_some_and(|e| matches!(e.name(), "td" | "th"))
+                });
+                if !first_cell {
+                    self.line.push('\t');
+                }
+                self.space = false;
+                self.write_children(node, preformatted);
+            }
+            _ if PARAGRAPH_ELEMENTS.contains(&name) => {
+                self.break_paragraph();
+                self.write_children(node, preformatted || name == "pre");
+                self.break_paragraph();
+            }
+            _ if BLOCK_ELEMENTS.contains(&name) => {
+                self.break_line(false);
+                self.write_children(node, preformatted);
+                self.break_line(false);
+            }
+            _ => self.write_children(node, preformatted),
+        }
+    }
+
+    /// adds text with its whitespace collapsed to single spaces
+    fn write_text(&mut self, text: &str) {
+        self.space |= text.starts_with(char::is_whitespace);
+        for (i, word) in text.split_whitespace().enumerate() {
+            if (i > 0 || self.space) && !self.line.is_empty() && !self.line.ends_with('\t') {
+                self.line.push(' ');
+            }
+            self.start_line();
+            self.line.push_str(word);
+            self.space = false;
+        }
+        self.space |= text.ends_with(char::is_whitespace);
+    }
+
+    /// adds text keeping its spaces and line breaks
+    fn write_preformatted(&mut self, text: &str) {
+        for (i, part) in text.split('\n').enumerate() {
+            if i > 0 {
+                self.break_line(true);
+            }
+            if !part.is_empty() {
+                self.start_line();
+                self.line.push_str(part);
+            }
+        }
+    }
+
+    /// writes the blank line a paragraph break asked for, once the next line has text
+    fn start_line(&mut self) {
+        if self.line.is_empty() && self.blank_line && !self.text.is_empty() {
+            self.text.push('\n');
+        }
+        self.blank_line = false;
+    }
+
+    /// ends the current line; `forced` breaks (`<br>`) also end empty lines
+    fn break_line(&mut self, forced: bool) {
+        let end = self.line.trim_end_matches(' ').len();
+        self.line.truncate(end);
+        if forced || !self.line.is_empty() {
+            self.text.push_str(&self.line);
+            self.text.push('\n');
+        }
+        self.line.clear();
+        self.space = false;
+    }
+
+    fn break_paragraph(&mut self) {
+        self.break_line(false);
+        self.blank_line = true;
+    }
+
+    fn finish(mut self) -> String {
+        self.break_line(false);
+        let mut text = String::with_capacity(self.text.len());
+        let mut blank_lines = 0;
+        for line in self.text.lines() {
+            if line.trim().is_empty() {
+                blank_lines += 1;
+                continue;
+            }
+            if blank_lines > 0 && !text.is_empty() {
+                text.push('\n');
+            }
+            blank_lines = 0;
+            text.push_str(line);
+            text.push('\n');
+        }
+        text.truncate(text.trim_end().len());
+        text
+    }
+}
+
+/// how far into a page the `<meta charset>` declaration is looked for, as browsers do
+const CHARSET_PRESCAN_BYTES: usize = 1024;
+
+// `<meta charset="...">` and `<meta http-equiv="Content-Type" content="...; charset=...">`
+static META_CHARSET_REGEX: Lazy<regex::bytes::Regex> = Lazy::new(|| {
+    regex::bytes::Regex::new(r#"(?i)<meta\s[^>]*charset\s*=\s*["']?\s*([a-z0-9_:.\-]+)"#)
+        .expect("valid charset regex")
+});
+
+/// decodes a page's bytes, returning the text and the name of the encoding used
+///
+/// the encoding comes from a byte order mark, then the `<meta charset>` declaration,
+/// then `encoding_hint` (e.g. the charset of the HTTP `Content-Type`); unknown labels
+/// are ignored. a declaration that does not fit the bytes is treated as a mislabel:
+/// bytes that are not valid UTF-8 fall back to the hint or windows-1252, and valid
+/// non-ASCII UTF-8 is read as UTF-8 even when a single-byte charset is declared.
+/// invalid sequences become U+FFFD.
+///
+/// # Examples
+///
+/// ```
+/// use markdown_lab_rs::html_parser::decode_html_bytes;
+/// let html = b"<meta charset=\"windows-1252\"><p>\x93Hi\x94</p>";
+/// let (text, encoding) = decode_html_bytes(html, None);
+/// assert_eq!(encoding, "windows-1252");
+/// assert!(text.contains("\u{201c}Hi\u{201d}"));
+/// ```
+pub fn decode_html_bytes(bytes: &[u8], encoding_hint: Option<&str>) -> (String, &'static str) {
+    if let Some((encoding, bom_length)) = encoding_rs::Encoding::for_bom(bytes) {
+        let (text, _) = encoding.decode_without_bom_handling(&bytes[bom_length..]);
+        return (text.into_owned(), encoding.name());
+    }
+
+    let hint = encoding_hint
+        .and_then(|label| encoding_rs::Encoding::for_label(label.trim().as_bytes()))
+        .map(|encoding| encoding.output_encoding());
+    let declared = META_CHARSET_REGEX
+        .captures(&bytes[..bytes.len().min(CHARSET_PRESCAN_BYTES)])
+        .and_then(|captures| encoding_rs::Encoding::for_label(&captures[1]))
+        .map(|encoding| encoding.output_encoding())
+        .or(hint);
+
+    let valid_utf8 = std::str::from_utf8(bytes).is_ok();
+    let encoding = match declared {
+        Some(encoding) if encoding == encoding_rs::UTF_8 && !valid_utf8 => hint
+            .filter(|&hint| hint != encoding_rs::UTF_8)
+            .unwrap_or(encoding_rs::WINDOWS_1252),
+        Some(encoding) if encoding.is_single_byte() && valid_utf8 && !bytes.is_ascii() => {
+            encoding_rs::UTF_8
+        }
+        Some(encoding) => encoding,
+        None if valid_utf8 => encoding_rs::UTF_8,
+        None => encoding_rs::WINDOWS_1252,
+    };
+    let (text, _) = encoding.decode_without_bom_handling(bytes);
+    (text.into_owned(), encoding.name())
+}
+
+/// elements that start on a new line, so their text never runs into its neighbours
+const BLOCK_ELEMENTS: &[&str] = &[
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "caption",
+    "dd",
+    "details",
+    "div",
+    "dl",
+    "dt",
+    "fieldset",
+    "figcaption",
+    "figure",
+    "footer",
+    "form",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hr",
+    "li",
+    "main",
+    "nav",
+    "ol",
+    "p",
+    "pre",
+    "section",
+    "summary",
+    "table",
+    "tbody",
+    "td",
+    "tfoot",
+    "th",
+    "thead",
+    "tr",
+    "ul",
+];
+
+/// walks the nodes below an element, separating the text of `<br>` and block elements
+/// from the text around them; nested tables are left out when `skip_tables` is set
+fn collect_text_parts<'a>(
+    node: ego_tree::NodeRef<'a, scraper::Node>,
+    skip_tables: bool,
+    parts: &mut Vec<&'a str>,
+) {
+    for child in node.children() {
+        match child.value() {
+            scraper::Node::Text(text) => parts.push(text),
+            scraper::Node::Element(element) if element.name() == "br" => parts.push(" "),
+            scraper::Node::Element(element) if skip_tables && element.name() == "table" => {
+                parts.push(" ")
+            }
+            scraper::Node::Element(element) if BLOCK_ELEMENTS.contains(&element.name()) => {
+                parts.push(" ");
+                collect_text_parts(child, skip_tables, parts);
+                parts.push(" ");
+            }
+            scraper::Node::Element(_) => collect_text_parts(child, skip_tables, parts),
+            _ => {}
+        }
+    }
 }
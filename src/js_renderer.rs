@@ -1,5 +1,7 @@
 #[cfg(feature = "real_rendering")]
-use headless_chrome::{Browser, LaunchOptionsBuilder};
+use headless_chrome::{Browser, LaunchOptionsBuilder, Tab, protocol::cdp::Runtime};
+#[cfg(feature = "real_rendering")]
+use std::sync::Arc;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -12,34 +14,62 @@ pub enum RendererError {
     TimeoutError,
 }
 
+/// A rendered page and what a script run in it returned
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderResult {
+    pub html: String,
+    /// The script's return value as JSON; `None` when it returned `undefined` or never ran
+    pub script_result: Option<String>,
+}
+
 /// Renders a JavaScript-enabled page and returns the HTML content.
 /// Uses headless Chrome/Chromium via WebDriver protocol.
-pub async fn render_page(url: &str, _wait_time: u64) -> Result<String, RendererError> {
+///
+/// With `wait_for_selector`, the content is captured as soon as an element matches it,
+/// and `_wait_time` becomes the most to wait for one before giving up with
+/// `RendererError::TimeoutError`. The plain HTTP fallback runs no scripts and ignores it.
+pub async fn render_page(
+    url: &str,
+    _wait_time: u64,
+    _wait_for_selector: Option<&str>,
+) -> Result<String, RendererError> {
     // Offline test mode: allow inline HTML via special scheme when feature is enabled
     #[cfg(feature = "offline_tests")]
     {
         if let Some(rest) = url.strip_prefix("inline://") {
+            // Inline HTML never changes, so a missing element would never turn up
+            if let Some(selector) = _wait_for_selector {
+                let selector = scraper::Selector::parse(selector)
+                    .map_err(|e| RendererError::BrowserError(e.to_string()))?;
+                if scraper::Html::parse_document(rest)
+                    .select(&selector)
+                    .next()
+                    .is_none()
+                {
+                    return Err(RendererError::TimeoutError);
+                }
+            }
             return enhanced_html(rest);
         }
     }
     #[cfg(feature = "real_rendering")]
     {
-        let options = LaunchOptionsBuilder::default()
-            .headless(true)
-            .build()
-            .map_err(|e| RendererError::BrowserError(e.to_string()))?;
-
-        let browser =
-            Browser::new(options).map_err(|e| RendererError::BrowserError(e.to_string()))?;
-
-        let tab = browser
-            .wait_for_initial_tab()
-            .map_err(|e| RendererError::BrowserError(e.to_string()))?;
-
-        tab.navigate_to(url)
-            .map_err(|e| RendererError::NetworkError(e.to_string()))?;
+        let (_browser, tab) = open_tab(url)?;
 
-        tokio::time::sleep(tokio::time::Duration::from_millis(_wait_time)).await;
+        let wait_time = tokio::time::Duration::from_millis(_wait_time);
+        match _wait_for_selector {
+            Some(selector) => {
+                tab.wait_for_element_with_custom_timeout(selector, wait_time)
+                    .map_err(|e| {
+                        if e.is::<headless_chrome::util::Timeout>() {
+                            RendererError::TimeoutError
+                        } else {
+                            RendererError::BrowserError(e.to_string())
+                        }
+                    })?;
+            }
+            None => tokio::time::sleep(wait_time).await,
+        }
 
         let html = tab
             .get_content()
@@ -66,6 +96,101 @@ pub async fn render_page(url: &str, _wait_time: u64) -> Result<String, RendererE
     }
 }
 
+/// Renders a page like `render_page`, waits `_wait_time` ms, then runs `_script` in it and
+/// returns the HTML as the script left it together with the script's result.
+///
+/// A script that returns a promise is awaited. A script that throws fails with
+/// `RendererError::BrowserError` carrying the JavaScript exception's message. Scripts need
+/// `real_rendering`; otherwise the page is fetched as `render_page` does and the script
+/// does not run.
+pub async fn render_page_with_script(
+    url: &str,
+    _script: &str,
+    _wait_time: u64,
+) -> Result<RenderResult, RendererError> {
+    #[cfg(feature = "real_rendering")]
+    {
+        if !(cfg!(feature = "offline_tests") && url.starts_with("inline://")) {
+            let (_browser, tab) = open_tab(url)?;
+            tokio::time::sleep(tokio::time::Duration::from_millis(_wait_time)).await;
+
+            let script_result = evaluate_script(&tab, _script)?;
+            let html = tab
+                .get_content()
+                .map_err(|e| RendererError::BrowserError(e.to_string()))?;
+
+            return Ok(RenderResult {
+                html: enhanced_html(&html)?,
+                script_result,
+            });
+        }
+    }
+
+    Ok(RenderResult {
+        html: render_page(url, _wait_time, None).await?,
+        script_result: None,
+    })
+}
+
+/// Starts a headless browser and loads `url` in its first tab; the tab lives as long as
+/// the returned browser
+#[cfg(feature = "real_rendering")]
+fn open_tab(url: &str) -> Result<(Browser, Arc<Tab>), RendererError> {
+    let options = LaunchOptionsBuilder::default()
+        .headless(true)
+        .build()
+        .map_err(|e| RendererError::BrowserError(e.to_string()))?;
+
+    let browser = Browser::new(options).map_err(|e| RendererError::BrowserError(e.to_string()))?;
+
+    let tab = browser
+        .wait_for_initial_tab()
+        .map_err(|e| RendererError::BrowserError(e.to_string()))?;
+
+    tab.navigate_to(url)
+        .map_err(|e| RendererError::NetworkError(e.to_string()))?;
+
+    Ok((browser, tab))
+}
+
+/// Evaluates `script` in `tab`, returning its result as JSON
+///
+/// `Tab::evaluate` drops thrown exceptions and returns objects by reference, so this sends
+/// the `Runtime.evaluate` call itself.
+#[cfg(feature = "real_rendering")]
+fn evaluate_script(tab: &Tab, script: &str) -> Result<Option<String>, RendererError> {
+    let evaluated = tab
+        .call_method(Runtime::Evaluate {
+            expression: script.to_string(),
+            return_by_value: Some(true),
+            generate_preview: None,
+            silent: Some(false),
+            await_promise: Some(true),
+            include_command_line_api: Some(false),
+            user_gesture: Some(true),
+            object_group: None,
+            context_id: None,
+            throw_on_side_effect: None,
+            timeout: None,
+            disable_breaks: None,
+            repl_mode: None,
+            allow_unsafe_eval_blocked_by_csp: None,
+            unique_context_id: None,
+            serialization_options: None,
+        })
+        .map_err(|e| RendererError::BrowserError(e.to_string()))?;
+
+    if let Some(details) = evaluated.exception_details {
+        // `text` is only "Uncaught"; the exception's description has the message
+        let message = details
+            .exception
+            .and_then(|exception| exception.description)
+            .unwrap_or(details.text);
+        return Err(RendererError::BrowserError(message));
+    }
+    Ok(evaluated.result.value.map(|value| value.to_string()))
+}
+
 fn enhanced_html(html: &str) -> Result<String, RendererError> {
     // Basic HTML enhancement logic
     Ok(html.to_string())
@@ -89,7 +214,7 @@ mod tests {
     #[ignore]
     fn test_render_page_network_ignored_by_default() {
         tokio_test::block_on(async {
-            let result = render_page("https://example.com", 1000).await;
+            let result = render_page("https://example.com", 1000, None).await;
             assert!(result.is_ok());
         });
     }
@@ -100,9 +225,40 @@ mod tests {
     fn test_render_page_offline_feature() {
         tokio_test::block_on(async {
             let inline = "inline://<html><body>Inline Test</body></html>";
-            let result = render_page(inline, 0).await;
+            let result = render_page(inline, 0, None).await;
             assert!(result.is_ok());
             assert!(result.unwrap().contains("Inline Test"));
         });
     }
+
+    #[cfg(feature = "offline_tests")]
+    #[test]
+    fn test_render_page_wait_for_selector_offline() {
+        tokio_test::block_on(async {
+            let inline = "inline://<html><body><div id=\"app\">Loaded</div></body></html>";
+            let result = render_page(inline, 100, Some("#app")).await;
+            assert!(result.unwrap().contains("Loaded"));
+
+            let result = render_page(inline, 100, Some("#missing")).await;
+            assert!(matches!(result, Err(RendererError::TimeoutError)));
+
+            let result = render_page(inline, 100, Some("##")).await;
+            assert!(matches!(result, Err(RendererError::BrowserError(_))));
+        });
+    }
+
+    #[cfg(feature = "offline_tests")]
+    #[test]
+    fn test_render_page_with_script_offline() {
+        tokio_test::block_on(async {
+            let inline =
+                "inline://<html><body><script>window.data = [1, 2];</script></body></html>";
+            let result = render_page_with_script(inline, "window.data", 0)
+                .await
+                .unwrap();
+            assert!(result.html.contains("window.data"));
+            // Inline pages are never run, so neither is the script
+            assert_eq!(result.script_result, None);
+        });
+    }
 }
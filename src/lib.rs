@@ -1,5 +1,7 @@
 use once_cell::sync::Lazy;
 use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList};
+use std::collections::HashMap;
 
 #[cfg(test)]
 mod tests;
@@ -31,16 +33,60 @@ pub enum OutputFormat {
     Markdown = 0,
     Json = 1,
     Xml = 2,
+    Yaml = 3,
+    Text = 4,
+    Rst = 5,
+    Html = 6,
+}
+
+impl OutputFormat {
+    /// parses a case-insensitive format name, rejecting unknown names with ValueError
+    fn parse(format_str: &str) -> PyResult<Self> {
+        format_str
+            .parse::<markdown_converter::OutputFormat>()
+            .map(Self::from)
+            .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            OutputFormat::Markdown => "markdown",
+            OutputFormat::Json => "json",
+            OutputFormat::Xml => "xml",
+            OutputFormat::Yaml => "yaml",
+            OutputFormat::Text => "text",
+            OutputFormat::Rst => "rst",
+            OutputFormat::Html => "html",
+        }
+    }
 }
 
 #[pymethods]
 impl OutputFormat {
     #[staticmethod]
-    fn from_str(format_str: &str) -> Self {
-        match format_str.to_lowercase().as_str() {
-            "json" => OutputFormat::Json,
-            "xml" => OutputFormat::Xml,
-            _ => OutputFormat::Markdown,
+    fn from_str(format_str: &str) -> PyResult<Self> {
+        Self::parse(format_str)
+    }
+
+    fn __str__(&self) -> &'static str {
+        self.name()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("OutputFormat.{}", self.name().to_uppercase())
+    }
+}
+
+impl From<markdown_converter::OutputFormat> for OutputFormat {
+    fn from(format: markdown_converter::OutputFormat) -> Self {
+        match format {
+            markdown_converter::OutputFormat::Markdown => OutputFormat::Markdown,
+            markdown_converter::OutputFormat::Json => OutputFormat::Json,
+            markdown_converter::OutputFormat::Xml => OutputFormat::Xml,
+            markdown_converter::OutputFormat::Yaml => OutputFormat::Yaml,
+            markdown_converter::OutputFormat::Text => OutputFormat::Text,
+            markdown_converter::OutputFormat::Rst => OutputFormat::Rst,
+            markdown_converter::OutputFormat::Html => OutputFormat::Html,
         }
     }
 }
@@ -51,6 +97,10 @@ impl From<OutputFormat> for markdown_converter::OutputFormat {
             OutputFormat::Markdown => markdown_converter::OutputFormat::Markdown,
             OutputFormat::Json => markdown_converter::OutputFormat::Json,
             OutputFormat::Xml => markdown_converter::OutputFormat::Xml,
+            OutputFormat::Yaml => markdown_converter::OutputFormat::Yaml,
+            OutputFormat::Text => markdown_converter::OutputFormat::Text,
+            OutputFormat::Rst => markdown_converter::OutputFormat::Rst,
+            OutputFormat::Html => markdown_converter::OutputFormat::Html,
         }
     }
 }
@@ -59,16 +109,56 @@ impl From<OutputFormat> for markdown_converter::OutputFormat {
 #[pymodule]
 fn markdown_lab_rs(py: Python<'_>, m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_class::<OutputFormat>()?;
+    m.add_class::<ChunkStream>()?;
+    m.add_class::<ChunkerOptions>()?;
     m.add_function(wrap_pyfunction!(convert_html_to_markdown, py)?)?;
     m.add_function(wrap_pyfunction!(convert_html_to_format, py)?)?;
+    m.add_function(wrap_pyfunction!(convert_html_bytes, py)?)?;
+    m.add_function(wrap_pyfunction!(convert_html_with_frames, py)?)?;
+    m.add_function(wrap_pyfunction!(extract_iframe_sources, py)?)?;
+    m.add_function(wrap_pyfunction!(convert_html_with_template, py)?)?;
+    m.add_function(wrap_pyfunction!(render_document_json, py)?)?;
+    #[cfg(feature = "cache")]
+    m.add_function(wrap_pyfunction!(serialize_document, py)?)?;
+    #[cfg(feature = "cache")]
+    m.add_function(wrap_pyfunction!(deserialize_document, py)?)?;
     m.add_function(wrap_pyfunction!(chunk_markdown, py)?)?;
+    m.add_function(wrap_pyfunction!(chunk_markdown_with_metadata, py)?)?;
+    m.add_function(wrap_pyfunction!(chunk_markdown_sized, py)?)?;
+    m.add_function(wrap_pyfunction!(chunk_markdown_with_report, py)?)?;
+    m.add_function(wrap_pyfunction!(chunk_markdown_to_manifest, py)?)?;
+    m.add_function(wrap_pyfunction!(iter_chunks, py)?)?;
+    m.add_function(wrap_pyfunction!(chunk_html, py)?)?;
     m.add_function(wrap_pyfunction!(render_js_page, py)?)?;
+    m.add_function(wrap_pyfunction!(render_js_page_exec, py)?)?;
 
     // expose HTML parser functions for Python access
     m.add_function(wrap_pyfunction!(clean_html, py)?)?;
     m.add_function(wrap_pyfunction!(clean_html_advanced, py)?)?;
+    m.add_function(wrap_pyfunction!(sanitize_html, py)?)?;
     m.add_function(wrap_pyfunction!(extract_main_content, py)?)?;
     m.add_function(wrap_pyfunction!(extract_links, py)?)?;
+    m.add_function(wrap_pyfunction!(extract_links_detailed, py)?)?;
+    m.add_function(wrap_pyfunction!(classify_links, py)?)?;
+    m.add_function(wrap_pyfunction!(extract_links_filtered, py)?)?;
+    m.add_function(wrap_pyfunction!(normalize_url, py)?)?;
+    m.add_function(wrap_pyfunction!(extract_images, py)?)?;
+    m.add_function(wrap_pyfunction!(extract_images_detailed, py)?)?;
+    m.add_function(wrap_pyfunction!(extract_tables, py)?)?;
+    m.add_function(wrap_pyfunction!(extract_tables_csv, py)?)?;
+    m.add_function(wrap_pyfunction!(extract_headings, py)?)?;
+    m.add_function(wrap_pyfunction!(extract_text_content, py)?)?;
+    m.add_function(wrap_pyfunction!(extract_forms, py)?)?;
+    m.add_function(wrap_pyfunction!(extract_links_csv, py)?)?;
+    m.add_function(wrap_pyfunction!(discover_feeds, py)?)?;
+    m.add_function(wrap_pyfunction!(extract_alternates, py)?)?;
+    m.add_function(wrap_pyfunction!(extract_breadcrumbs, py)?)?;
+    m.add_function(wrap_pyfunction!(extract_robots_directives, py)?)?;
+    m.add_function(wrap_pyfunction!(extract_social_metadata, py)?)?;
+    m.add_function(wrap_pyfunction!(extract_json_ld, py)?)?;
+    m.add_function(wrap_pyfunction!(json_ld_types, py)?)?;
+    m.add_function(wrap_pyfunction!(extract_publication_date, py)?)?;
+    m.add_function(wrap_pyfunction!(extract_modified_date, py)?)?;
     m.add_function(wrap_pyfunction!(resolve_url, py)?)?;
     m.add_function(wrap_pyfunction!(cleanup_resources, py)?)?;
 
@@ -84,42 +174,785 @@ fn convert_html_to_markdown(html: &str, base_url: &str) -> PyResult<String> {
 }
 
 /// converts HTML content to the specified format
+///
+/// keyword arguments set conversion options, e.g. `title_mode="if_no_h1"`
 #[pyfunction]
-fn convert_html_to_format(html: &str, base_url: &str, format: Option<String>) -> PyResult<String> {
+#[pyo3(signature = (html, base_url, format=None, **options))]
+fn convert_html_to_format(
+    html: &str,
+    base_url: &str,
+    format: Option<String>,
+    options: Option<&Bound<'_, PyDict>>,
+) -> PyResult<String> {
     let output_format = match format.as_deref() {
-        Some("json") => markdown_converter::OutputFormat::Json,
-        Some("xml") => markdown_converter::OutputFormat::Xml,
-        _ => markdown_converter::OutputFormat::Markdown,
+        Some(name) => OutputFormat::parse(name)?.into(),
+        None => markdown_converter::OutputFormat::Markdown,
     };
+    let options = conversion_options(options)?;
+
+    let result =
+        markdown_converter::convert_html_with_options(html, base_url, output_format, &options)
+            .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))?;
+    Ok(result)
+}
+
+/// converts a page's raw bytes to the specified format
+///
+/// the charset comes from a byte order mark, the page's meta charset or `encoding`
+/// (e.g. the HTTP Content-Type charset); keyword arguments set conversion options
+#[pyfunction]
+#[pyo3(signature = (html, base_url, format=None, encoding=None, **options))]
+fn convert_html_bytes(
+    html: &[u8],
+    base_url: &str,
+    format: Option<String>,
+    encoding: Option<&str>,
+    options: Option<&Bound<'_, PyDict>>,
+) -> PyResult<String> {
+    let output_format = match format.as_deref() {
+        Some(name) => OutputFormat::parse(name)?.into(),
+        None => markdown_converter::OutputFormat::Markdown,
+    };
+    let options = conversion_options(options)?;
+
+    markdown_converter::convert_html_bytes_with_options(
+        html,
+        base_url,
+        output_format,
+        encoding,
+        &options,
+    )
+    .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))
+}
+
+/// converts HTML to the specified format, splicing in pre-fetched iframe content
+///
+/// `frames` maps iframe URLs (as returned by extract_iframe_sources) to their HTML;
+/// only same-origin frames are merged. keyword arguments set conversion options
+#[pyfunction]
+#[pyo3(signature = (html, base_url, frames, format=None, **options))]
+fn convert_html_with_frames(
+    html: &str,
+    base_url: &str,
+    frames: HashMap<String, String>,
+    format: Option<String>,
+    options: Option<&Bound<'_, PyDict>>,
+) -> PyResult<String> {
+    let output_format = match format.as_deref() {
+        Some(name) => OutputFormat::parse(name)?.into(),
+        None => markdown_converter::OutputFormat::Markdown,
+    };
+    let options = conversion_options(options)?;
+
+    let html = html_parser::splice_frames(html, base_url, &frames)
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))?;
+    markdown_converter::convert_html_with_options(&html, base_url, output_format, &options)
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))
+}
+
+/// renders a document serialized as JSON (e.g. by format="json") to the specified format
+///
+/// keyword arguments set conversion options; invalid JSON raises ValueError
+#[pyfunction]
+#[pyo3(signature = (doc_json, format="markdown", **options))]
+fn render_document_json(
+    doc_json: &str,
+    format: &str,
+    options: Option<&Bound<'_, PyDict>>,
+) -> PyResult<String> {
+    let document = markdown_converter::Document::from_json(doc_json)
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))?;
+    let output_format = OutputFormat::parse(format)?.into();
+    let options = conversion_options(options)?;
 
-    let result = markdown_converter::convert_html(html, base_url, output_format)
+    let result = markdown_converter::render_document(&document, output_format, &options)
         .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))?;
     Ok(result)
 }
 
-/// chunks markdown content for RAG
+/// parses HTML into a document and returns it in the binary cache format
+#[cfg(feature = "cache")]
+#[pyfunction]
+#[pyo3(signature = (html, base_url, **options))]
+fn serialize_document<'py>(
+    py: Python<'py>,
+    html: &str,
+    base_url: &str,
+    options: Option<&Bound<'_, PyDict>>,
+) -> PyResult<Bound<'py, pyo3::types::PyBytes>> {
+    let options = conversion_options(options)?;
+    let document =
+        markdown_converter::parse_html_to_document_with_options(html, base_url, &options)
+            .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))?;
+    let bytes = markdown_converter::document_to_bytes(&document)
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))?;
+    Ok(pyo3::types::PyBytes::new(py, &bytes))
+}
+
+/// renders a document from serialize_document to the specified format
+///
+/// corrupted or incompatible data raises ValueError
+#[cfg(feature = "cache")]
+#[pyfunction]
+#[pyo3(signature = (data, format="markdown", **options))]
+fn deserialize_document(
+    data: &[u8],
+    format: &str,
+    options: Option<&Bound<'_, PyDict>>,
+) -> PyResult<String> {
+    let document = markdown_converter::document_from_bytes(data)
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))?;
+    let output_format = OutputFormat::parse(format)?.into();
+    let options = conversion_options(options)?;
+
+    let result = markdown_converter::render_document(&document, output_format, &options)
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))?;
+    Ok(result)
+}
+
+/// renders HTML through a template: a built-in name ("default", "compact") or Tera source
+///
+/// invalid templates raise ValueError
+#[pyfunction]
+#[pyo3(signature = (html, base_url, template="default"))]
+fn convert_html_with_template(html: &str, base_url: &str, template: &str) -> PyResult<String> {
+    markdown_converter::convert_html_with_template(html, base_url, template).map_err(|e| match e {
+        markdown_converter::MarkdownError::TemplateError(_) => {
+            PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string())
+        }
+        _ => PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()),
+    })
+}
+
+/// builds conversion options from python keyword arguments, rejecting unknown names and values
+fn conversion_options(
+    options: Option<&Bound<'_, PyDict>>,
+) -> PyResult<markdown_converter::ConversionOptions> {
+    let mut conversion = markdown_converter::ConversionOptions::default();
+    let Some(options) = options else {
+        return Ok(conversion);
+    };
+
+    for (key, value) in options.iter() {
+        let key: String = key.extract()?;
+        match key.as_str() {
+            "flavor" => {
+                conversion.flavor = match value.extract::<String>()?.to_lowercase().as_str() {
+                    "commonmark" => markdown_converter::MarkdownFlavor::CommonMark,
+                    "gfm" => markdown_converter::MarkdownFlavor::Gfm,
+                    "mkdocs" => markdown_converter::MarkdownFlavor::MkDocs,
+                    other => return Err(invalid_option(&key, other)),
+                }
+            }
+            "title_mode" => {
+                conversion.title_mode = match value.extract::<String>()?.to_lowercase().as_str() {
+                    "always" => markdown_converter::TitleMode::Always,
+                    "if_no_h1" => markdown_converter::TitleMode::IfNoH1,
+                    "never" => markdown_converter::TitleMode::Never,
+                    other => return Err(invalid_option(&key, other)),
+                }
+            }
+            "heading_style" => {
+                conversion.heading_style = match value.extract::<String>()?.to_lowercase().as_str()
+                {
+                    "atx" => markdown_converter::HeadingStyle::Atx,
+                    "setext" => markdown_converter::HeadingStyle::Setext,
+                    other => return Err(invalid_option(&key, other)),
+                }
+            }
+            "heading_offset" => conversion.heading_offset = value.extract()?,
+            "bullet_char" => {
+                conversion.bullet_char = match value.extract::<String>()?.as_str() {
+                    "-" => markdown_converter::BulletChar::Dash,
+                    "*" => markdown_converter::BulletChar::Asterisk,
+                    "+" => markdown_converter::BulletChar::Plus,
+                    other => return Err(invalid_option(&key, other)),
+                }
+            }
+            "ordered_style" => {
+                conversion.ordered_style = match value.extract::<String>()?.to_lowercase().as_str()
+                {
+                    "sequential" => markdown_converter::OrderedListStyle::Sequential,
+                    "all_ones" => markdown_converter::OrderedListStyle::AllOnes,
+                    other => return Err(invalid_option(&key, other)),
+                }
+            }
+            "indent_width" => conversion.indent_width = value.extract()?,
+            "code_fence" => {
+                conversion.code_fence = match value.extract::<String>()?.as_str() {
+                    "`" | "```" | "backtick" => markdown_converter::FenceChar::Backtick,
+                    "~" | "~~~" | "tilde" => markdown_converter::FenceChar::Tilde,
+                    other => return Err(invalid_option(&key, other)),
+                }
+            }
+            "alt_fallback" => {
+                conversion.alt_fallback = match value.extract::<String>()?.to_lowercase().as_str() {
+                    "placeholder" => markdown_converter::AltFallback::Placeholder,
+                    "empty" => markdown_converter::AltFallback::Empty,
+                    "filename" => markdown_converter::AltFallback::FileName,
+                    "caption" => markdown_converter::AltFallback::Caption,
+                    "skip" => markdown_converter::AltFallback::Skip,
+                    other => return Err(invalid_option(&key, other)),
+                }
+            }
+            "link_style" => {
+                conversion.link_style = match value.extract::<String>()?.to_lowercase().as_str() {
+                    "inline" => markdown_converter::LinkStyle::Inline,
+                    "reference" => markdown_converter::LinkStyle::Reference,
+                    other => return Err(invalid_option(&key, other)),
+                }
+            }
+            "compact" => {
+                conversion.json_style = if value.extract()? {
+                    markdown_converter::JsonStyle::Compact
+                } else {
+                    markdown_converter::JsonStyle::Pretty
+                }
+            }
+            "front_matter" => conversion.front_matter = value.extract()?,
+            "toc" => conversion.toc = value.extract()?,
+            "toc_max_level" => conversion.toc_max_level = value.extract()?,
+            "content_links_only" => conversion.content_links_only = value.extract()?,
+            "social_metadata" => conversion.social_metadata = value.extract()?,
+            "ignore_base_tag" => conversion.ignore_base_tag = value.extract()?,
+            "detect_language" => conversion.detect_language = value.extract()?,
+            "include_hidden" => conversion.include_hidden = value.extract()?,
+            "collect_frames" => conversion.collect_frames = value.extract()?,
+            "use_noscript_fallback" => conversion.use_noscript_fallback = value.extract()?,
+            "lazy_image_attributes" => conversion.lazy_image_attributes = value.extract()?,
+            "normalize_links" => {
+                conversion.normalize_links = value
+                    .extract::<bool>()?
+                    .then(html_parser::NormalizeOptions::default)
+            }
+            _ => {
+                return Err(PyErr::new::<pyo3::exceptions::PyTypeError, _>(format!(
+                    "unknown conversion option: {}",
+                    key
+                )));
+            }
+        }
+    }
+    Ok(conversion)
+}
+
+fn invalid_option(key: &str, value: &str) -> PyErr {
+    PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("invalid value for {}: {}", key, value))
+}
+
+/// maps invalid chunk sizes and overlaps to ValueError, anything else to RuntimeError
+fn chunker_error(error: chunker::ChunkerError) -> PyErr {
+    match error {
+        chunker::ChunkerError::ParsingError(_) | chunker::ChunkerError::InvalidOverlap { .. } => {
+            PyErr::new::<pyo3::exceptions::PyValueError, _>(error.to_string())
+        }
+        _ => PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(error.to_string()),
+    }
+}
+
+/// parses a chunking strategy name: "semantic", "recursive", "heading" or "paragraphs";
+/// "heading" splits at headings of `heading_level` or above, sub-splitting sections longer
+/// than `max_section_size`, and "paragraphs" puts `paragraphs_per_chunk` paragraphs in
+/// each chunk
+fn chunk_strategy(
+    strategy: &str,
+    heading_level: u8,
+    max_section_size: Option<usize>,
+    paragraphs_per_chunk: usize,
+) -> PyResult<chunker::ChunkStrategy> {
+    match strategy.to_lowercase().as_str() {
+        "semantic" => Ok(chunker::ChunkStrategy::Semantic),
+        "recursive" => Ok(chunker::ChunkStrategy::Recursive),
+        "heading" => {
+            if !(1..=6).contains(&heading_level) {
+                return Err(invalid_option("heading_level", &heading_level.to_string()));
+            }
+            Ok(chunker::ChunkStrategy::ByHeading {
+                level: heading_level,
+                max_size: max_section_size,
+            })
+        }
+        "paragraphs" => Ok(chunker::ChunkStrategy::Paragraphs {
+            per_chunk: paragraphs_per_chunk,
+        }),
+        other => Err(invalid_option("strategy", other)),
+    }
+}
+
+/// parses a truncation strategy name: "truncate", "sample" or "merge"
+fn truncation_strategy(truncation: &str) -> PyResult<chunker::TruncationStrategy> {
+    match truncation.to_lowercase().as_str() {
+        "truncate" => Ok(chunker::TruncationStrategy::Truncate),
+        "sample" => Ok(chunker::TruncationStrategy::Sample),
+        "merge" => Ok(chunker::TruncationStrategy::Merge),
+        other => Err(invalid_option("truncation", other)),
+    }
+}
+
+/// parses a size unit name: "chars", "words" or "tokens"
+fn size_unit(unit: &str) -> PyResult<chunker::SizeUnit> {
+    match unit.to_lowercase().as_str() {
+        "chars" => Ok(chunker::SizeUnit::Chars),
+        "words" => Ok(chunker::SizeUnit::Words),
+        "tokens" => Ok(chunker::SizeUnit::Tokens),
+        other => Err(invalid_option("unit", other)),
+    }
+}
+
+/// a chunk_overlap argument: an int counts units, a float is a fraction of chunk_size
+/// from 0 to 0.5
+#[derive(FromPyObject)]
+enum Overlap {
+    Absolute(usize),
+    Ratio(f32),
+}
+
+impl From<Overlap> for chunker::OverlapSpec {
+    fn from(overlap: Overlap) -> Self {
+        match overlap {
+            Overlap::Absolute(overlap) => chunker::OverlapSpec::Absolute(overlap),
+            Overlap::Ratio(ratio) => chunker::OverlapSpec::Ratio(ratio),
+        }
+    }
+}
+
+/// every chunking setting in one reusable, validated object, accepted by chunk_markdown
+/// as `options`. the settings mean what chunk_markdown's keyword arguments do, with
+/// sizes counted in `unit` as for chunk_markdown_sized, and a float `chunk_overlap` taken
+/// as a fraction of chunk_size. with `list_continuation`, a chunk that picks a list up
+/// between two items starts with a "…continued from list under <heading>" line. past
+/// `max_chunks` chunks, `truncation` keeps the first ("truncate"), an even sample
+/// ("sample") or merges neighbours ("merge")
+#[pyclass]
+struct ChunkerOptions {
+    chunker: chunker::Chunker,
+}
+
+#[pymethods]
+impl ChunkerOptions {
+    #[new]
+    #[pyo3(signature = (chunk_size=1000, chunk_overlap=Overlap::Absolute(200), strategy="semantic", unit="chars", min_chunk_size=0, overlap_across_sections=false, source_id=None, heading_level=2, max_section_size=None, paragraphs_per_chunk=3, list_continuation=false, max_chunks=None, truncation="truncate"))]
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        chunk_size: usize,
+        chunk_overlap: Overlap,
+        strategy: &str,
+        unit: &str,
+        min_chunk_size: usize,
+        overlap_across_sections: bool,
+        source_id: Option<String>,
+        heading_level: u8,
+        max_section_size: Option<usize>,
+        paragraphs_per_chunk: usize,
+        list_continuation: bool,
+        max_chunks: Option<usize>,
+        truncation: &str,
+    ) -> PyResult<Self> {
+        let mut config = chunker::ChunkerConfig::new()
+            .chunk_size(chunk_size)
+            .overlap(chunk_overlap)
+            .strategy(chunk_strategy(
+                strategy,
+                heading_level,
+                max_section_size,
+                paragraphs_per_chunk,
+            )?)
+            .unit(size_unit(unit)?)
+            .min_chunk_size(min_chunk_size)
+            .overlap_across_sections(overlap_across_sections)
+            .list_continuation(list_continuation)
+            .truncation(truncation_strategy(truncation)?);
+        config.options.source_id = source_id;
+        config.options.max_chunks = max_chunks;
+        let chunker = config.build().map_err(chunker_error)?;
+        Ok(ChunkerOptions { chunker })
+    }
+
+    #[getter]
+    fn chunk_size(&self) -> usize {
+        self.chunker.config().chunk_size
+    }
+
+    #[getter]
+    fn chunk_overlap(&self) -> usize {
+        self.chunker.chunk_overlap()
+    }
+
+    fn __repr__(&self) -> String {
+        let config = self.chunker.config();
+        format!(
+            "ChunkerOptions(chunk_size={}, chunk_overlap={}, {:?})",
+            config.chunk_size,
+            self.chunker.chunk_overlap(),
+            config.options
+        )
+    }
+}
+
+/// chunks markdown content for RAG; consecutive chunks share about `chunk_overlap`
+/// characters, or that fraction of chunk_size for a float such as 0.1, and with
+/// `overlap_across_sections` so do chunks split at a heading. chunks shorter than
+/// `min_chunk_size` are merged into a neighbour. `strategy` is "semantic" (split at
+/// headings), "recursive" (paragraphs, then sentences, then words), "heading" (one chunk
+/// per section at `heading_level` or above, split further only past `max_section_size`)
+/// or "paragraphs" (`paragraphs_per_chunk` paragraphs per chunk whatever the headings, with
+/// `chunk_overlap` counting paragraphs). a ChunkerOptions passed as `options` replaces all
+/// of these
 #[pyfunction]
+#[pyo3(signature = (markdown, chunk_size=None, chunk_overlap=None, overlap_across_sections=false, min_chunk_size=0, strategy="semantic", heading_level=2, max_section_size=None, paragraphs_per_chunk=3, options=None))]
+#[allow(clippy::too_many_arguments)]
 fn chunk_markdown(
+    markdown: &str,
+    chunk_size: Option<usize>,
+    chunk_overlap: Option<Overlap>,
+    overlap_across_sections: bool,
+    min_chunk_size: usize,
+    strategy: &str,
+    heading_level: u8,
+    max_section_size: Option<usize>,
+    paragraphs_per_chunk: usize,
+    options: Option<PyRef<'_, ChunkerOptions>>,
+) -> PyResult<Vec<String>> {
+    let chunks = match options {
+        Some(options) => {
+            let defaults = chunk_size.is_none()
+                && chunk_overlap.is_none()
+                && !overlap_across_sections
+                && min_chunk_size == 0
+                && strategy == "semantic"
+                && heading_level == 2
+                && max_section_size.is_none()
+                && paragraphs_per_chunk == 3;
+            if !defaults {
+                return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                    "pass chunking settings either in options or as arguments, not both",
+                ));
+            }
+            options.chunker.chunk(markdown)
+        }
+        None => {
+            let (Some(chunk_size), Some(chunk_overlap)) = (chunk_size, chunk_overlap) else {
+                return Err(PyErr::new::<pyo3::exceptions::PyTypeError, _>(
+                    "chunk_markdown() needs chunk_size and chunk_overlap, or options",
+                ));
+            };
+            chunker::ChunkerConfig::new()
+                .chunk_size(chunk_size)
+                .overlap(chunk_overlap)
+                .overlap_across_sections(overlap_across_sections)
+                .min_chunk_size(min_chunk_size)
+                .strategy(chunk_strategy(
+                    strategy,
+                    heading_level,
+                    max_section_size,
+                    paragraphs_per_chunk,
+                )?)
+                .build()
+                .and_then(|chunker| chunker.chunk(markdown))
+        }
+    }
+    .map_err(chunker_error)?;
+    Ok(chunks.into_iter().map(|chunk| chunk.content).collect())
+}
+
+/// chunks markdown with a ChunkerOptions and returns `(chunks, report)`. chunks are strings,
+/// or dicts as from chunk_markdown_with_metadata with `metadata=True`; the report dict has
+/// produced, returned, max_chunks, truncated and strategy, telling whether and how
+/// `max_chunks` cut the result down
+#[pyfunction]
+#[pyo3(signature = (markdown, options, metadata=false))]
+fn chunk_markdown_with_report<'py>(
+    py: Python<'py>,
+    markdown: &str,
+    options: PyRef<'_, ChunkerOptions>,
+    metadata: bool,
+) -> PyResult<(Bound<'py, PyList>, Bound<'py, PyDict>)> {
+    let (chunks, report) = options
+        .chunker
+        .chunk_with_report(markdown)
+        .map_err(chunker_error)?;
+
+    let chunks = if metadata {
+        chunk_dicts(py, chunks)?
+    } else {
+        PyList::new(py, chunks.into_iter().map(|chunk| chunk.content))?
+    };
+    let dict = PyDict::new(py);
+    dict.set_item("produced", report.produced)?;
+    dict.set_item("returned", report.returned)?;
+    dict.set_item("max_chunks", report.max_chunks)?;
+    dict.set_item("truncated", report.truncated)?;
+    let strategy = match report.strategy {
+        chunker::TruncationStrategy::Truncate => "truncate",
+        chunker::TruncationStrategy::Sample => "sample",
+        chunker::TruncationStrategy::Merge => "merge",
+    };
+    dict.set_item("strategy", strategy)?;
+    Ok((chunks, dict))
+}
+
+/// chunks markdown with a ChunkerOptions and returns `(chunks, manifest)`: the chunk
+/// strings and a JSON manifest with a schema `version`, the document's source_id,
+/// char_count and word_count, the max_chunks `report`, and each chunk's metadata under
+/// `chunks`, in the same order as the strings
+#[pyfunction]
+fn chunk_markdown_to_manifest(
+    py: Python<'_>,
+    markdown: &str,
+    options: PyRef<'_, ChunkerOptions>,
+) -> PyResult<(Vec<String>, String)> {
+    let chunker = &options.chunker;
+    py.allow_threads(|| chunker.chunk_to_manifest(markdown))
+        .map_err(chunker_error)
+}
+
+/// chunks markdown content for RAG with sizes counted in `unit`: "chars", "words" or
+/// "tokens" (approximate LLM tokens), so chunks can be budgeted against a context window
+#[pyfunction]
+#[pyo3(signature = (markdown, max_units, overlap_units, unit="tokens", overlap_across_sections=false, min_units=0, strategy="semantic"))]
+fn chunk_markdown_sized(
+    markdown: &str,
+    max_units: usize,
+    overlap_units: usize,
+    unit: &str,
+    overlap_across_sections: bool,
+    min_units: usize,
+    strategy: &str,
+) -> PyResult<Vec<String>> {
+    let options = chunker::ChunkOptions {
+        overlap_across_sections,
+        unit: size_unit(unit)?,
+        min_chunk_size: min_units,
+        strategy: chunk_strategy(strategy, 2, None, 3)?,
+        ..chunker::ChunkOptions::default()
+    };
+    let chunks =
+        chunker::create_semantic_chunks_with_options(markdown, max_units, overlap_units, &options)
+            .map_err(chunker_error)?;
+    Ok(chunks.into_iter().map(|chunk| chunk.content).collect())
+}
+
+/// chunks markdown content for RAG, returning a dict per chunk with id, content, heading,
+/// level, heading_path, headings, position, start_offset, end_offset, paragraphs,
+/// word_count, char_count and semantic_density. paragraphs is the (start, end) range of
+/// paragraph indices a "paragraphs" chunk holds, None otherwise. ids hash the
+/// whitespace-normalised content together with `source_id`, e.g. the page URL.
+/// `markdown[start_offset:end_offset]` is the chunk's text in the source, starting at its
+/// overlap
+#[pyfunction]
+#[pyo3(signature = (markdown, chunk_size, chunk_overlap, overlap_across_sections=false, min_chunk_size=0, strategy="semantic", source_id=None, heading_level=2, max_section_size=None, paragraphs_per_chunk=3))]
+#[allow(clippy::too_many_arguments)]
+fn chunk_markdown_with_metadata<'py>(
+    py: Python<'py>,
     markdown: &str,
     chunk_size: usize,
     chunk_overlap: usize,
-) -> PyResult<Vec<String>> {
-    let chunks = chunker::create_semantic_chunks(markdown, chunk_size, chunk_overlap)
-        .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))?;
-    Ok(chunks)
+    overlap_across_sections: bool,
+    min_chunk_size: usize,
+    strategy: &str,
+    source_id: Option<String>,
+    heading_level: u8,
+    max_section_size: Option<usize>,
+    paragraphs_per_chunk: usize,
+) -> PyResult<Bound<'py, PyList>> {
+    let options = chunker::ChunkOptions {
+        overlap_across_sections,
+        min_chunk_size,
+        strategy: chunk_strategy(
+            strategy,
+            heading_level,
+            max_section_size,
+            paragraphs_per_chunk,
+        )?,
+        source_id,
+        ..chunker::ChunkOptions::default()
+    };
+    let chunks =
+        chunker::create_semantic_chunks_with_options(markdown, chunk_size, chunk_overlap, &options)
+            .map_err(chunker_error)?;
+    chunk_dicts(py, chunks)
+}
+
+/// one dict per chunk with its content and every metadata field
+fn chunk_dicts(py: Python<'_>, chunks: Vec<chunker::Chunk>) -> PyResult<Bound<'_, PyList>> {
+    let list = PyList::empty(py);
+    for chunk in chunks {
+        list.append(chunk_dict(py, chunk)?)?;
+    }
+    Ok(list)
+}
+
+/// a chunk's content and every metadata field as a dict
+fn chunk_dict(py: Python<'_>, chunk: chunker::Chunk) -> PyResult<Bound<'_, PyDict>> {
+    let dict = PyDict::new(py);
+    dict.set_item("id", chunk.metadata.id)?;
+    dict.set_item("source_id", chunk.metadata.source_id)?;
+    dict.set_item("content", chunk.content)?;
+    dict.set_item("heading", chunk.metadata.heading)?;
+    dict.set_item("level", chunk.metadata.level)?;
+    dict.set_item("heading_path", chunk.metadata.heading_path)?;
+    dict.set_item("headings", chunk.metadata.headings)?;
+    dict.set_item("position", chunk.metadata.position)?;
+    dict.set_item("start_offset", chunk.metadata.start_offset)?;
+    dict.set_item("end_offset", chunk.metadata.end_offset)?;
+    dict.set_item(
+        "paragraphs",
+        chunk
+            .metadata
+            .paragraphs
+            .map(|paragraphs| (paragraphs.start, paragraphs.end)),
+    )?;
+    dict.set_item("word_count", chunk.metadata.word_count)?;
+    dict.set_item("char_count", chunk.metadata.char_count)?;
+    dict.set_item("semantic_density", chunk.metadata.semantic_density)?;
+    Ok(dict)
+}
+
+/// iterator over the chunks of one markdown document, returned by iter_chunks
+#[pyclass]
+struct ChunkStream {
+    chunks: chunker::ChunkIterator<'static>,
+    metadata: bool,
+}
+
+#[pymethods]
+impl ChunkStream {
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__(mut slf: PyRefMut<'_, Self>) -> PyResult<Option<PyObject>> {
+        let py = slf.py();
+        match slf.chunks.next() {
+            Some(Ok(chunk)) if slf.metadata => Ok(Some(chunk_dict(py, chunk)?.into_any().unbind())),
+            Some(Ok(chunk)) => Ok(Some(chunk.content.into_pyobject(py)?.into_any().unbind())),
+            Some(Err(error)) => Err(chunker_error(error)),
+            None => Ok(None),
+        }
+    }
+}
+
+/// chunks markdown like chunk_markdown with the semantic strategy, yielding one chunk at
+/// a time so only the chunk being built is held besides the markdown itself. with
+/// `metadata=True` chunks are dicts as from chunk_markdown_with_metadata. raises
+/// ValueError up front for a zero chunk size or an overlap that does not fit it
+#[pyfunction]
+#[pyo3(signature = (markdown, chunk_size, chunk_overlap, overlap_across_sections=false, source_id=None, metadata=false))]
+fn iter_chunks(
+    markdown: String,
+    chunk_size: usize,
+    chunk_overlap: usize,
+    overlap_across_sections: bool,
+    source_id: Option<String>,
+    metadata: bool,
+) -> PyResult<ChunkStream> {
+    chunker::validate_chunk_sizes(chunk_size, chunk_overlap).map_err(chunker_error)?;
+    let options = chunker::ChunkOptions {
+        overlap_across_sections,
+        source_id,
+        ..chunker::ChunkOptions::default()
+    };
+    Ok(ChunkStream {
+        chunks: chunker::ChunkIterator::new(markdown, chunk_size, chunk_overlap, &options),
+        metadata,
+    })
+}
+
+/// converts the main content of HTML to markdown and chunks it in one call, so the
+/// markdown never crosses into Python; the GIL is released meanwhile. with
+/// `metadata=True` chunks are dicts as from chunk_markdown_with_metadata, whose
+/// source_id defaults to `base_url`. other keyword arguments set conversion options
+#[pyfunction]
+#[pyo3(signature = (html, base_url, chunk_size=1000, chunk_overlap=200, strategy="semantic", min_chunk_size=0, metadata=false, **options))]
+#[allow(clippy::too_many_arguments)]
+fn chunk_html<'py>(
+    py: Python<'py>,
+    html: &str,
+    base_url: &str,
+    chunk_size: usize,
+    chunk_overlap: usize,
+    strategy: &str,
+    min_chunk_size: usize,
+    metadata: bool,
+    options: Option<&Bound<'py, PyDict>>,
+) -> PyResult<Bound<'py, PyList>> {
+    let conversion = conversion_options(options)?;
+    let chunk_options = chunker::ChunkOptions {
+        min_chunk_size,
+        strategy: chunk_strategy(strategy, 2, None, 3)?,
+        ..chunker::ChunkOptions::default()
+    };
+
+    let chunks = py
+        .allow_threads(|| {
+            chunker::chunk_html(
+                html,
+                base_url,
+                chunk_size,
+                chunk_overlap,
+                &conversion,
+                &chunk_options,
+            )
+        })
+        .map_err(chunker_error)?;
+    if metadata {
+        chunk_dicts(py, chunks)
+    } else {
+        PyList::new(py, chunks.into_iter().map(|chunk| chunk.content))
+    }
 }
 
 /// renders a JavaScript-enabled page and returns the HTML content
 /// uses shared tokio runtime for better performance
+///
+/// with `wait_for_selector`, returns as soon as an element matches it, waiting at most
+/// `wait_time` ms and raising TimeoutError if none does
 #[pyfunction]
-fn render_js_page(url: &str, wait_time: Option<u64>) -> PyResult<String> {
+#[pyo3(signature = (url, wait_time=None, wait_for_selector=None))]
+fn render_js_page(
+    url: &str,
+    wait_time: Option<u64>,
+    wait_for_selector: Option<&str>,
+) -> PyResult<String> {
     let html = SHARED_RUNTIME
-        .block_on(async { js_renderer::render_page(url, wait_time.unwrap_or(2000)).await })
-        .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))?;
+        .block_on(async {
+            js_renderer::render_page(url, wait_time.unwrap_or(2000), wait_for_selector).await
+        })
+        .map_err(renderer_error)?;
 
     Ok(html)
 }
 
+/// renders a page like render_js_page, runs `script` in it after `wait_time` ms and
+/// returns `(html, result)`: the HTML as the script left it and the script's return value
+/// as a JSON string, or None for undefined. a script that throws raises RuntimeError with
+/// the JavaScript error's message
+#[pyfunction]
+#[pyo3(signature = (url, script, wait_time=None))]
+fn render_js_page_exec(
+    url: &str,
+    script: &str,
+    wait_time: Option<u64>,
+) -> PyResult<(String, Option<String>)> {
+    let rendered = SHARED_RUNTIME
+        .block_on(async {
+            js_renderer::render_page_with_script(url, script, wait_time.unwrap_or(2000)).await
+        })
+        .map_err(renderer_error)?;
+
+    Ok((rendered.html, rendered.script_result))
+}
+
+/// maps renderer timeouts to TimeoutError, anything else to RuntimeError
+fn renderer_error(error: js_renderer::RendererError) -> PyErr {
+    match error {
+        js_renderer::RendererError::TimeoutError => {
+            PyErr::new::<pyo3::exceptions::PyTimeoutError, _>(error.to_string())
+        }
+        _ => PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(error.to_string()),
+    }
+}
+
 /// wrapper for clean_html function
 #[pyfunction]
 fn clean_html(html: &str) -> PyResult<String> {
@@ -127,28 +960,503 @@ fn clean_html(html: &str) -> PyResult<String> {
         .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))
 }
 
-/// python wrapper for clean_html_advanced function
+/// rebuilds untrusted HTML from an allowlist of tags, attributes and URL schemes
+///
+/// `allowed_tags` maps each kept tag to its allowed attributes and `allowed_schemes`
+/// lists the schemes accepted in href/src; either defaults to the built-in policy
 #[pyfunction]
-fn clean_html_advanced(html: &str) -> PyResult<String> {
-    html_parser::clean_html_advanced(html)
-        .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))
+#[pyo3(signature = (html, allowed_tags=None, allowed_schemes=None))]
+fn sanitize_html(
+    html: &str,
+    allowed_tags: Option<HashMap<String, Vec<String>>>,
+    allowed_schemes: Option<Vec<String>>,
+) -> String {
+    let mut policy = html_parser::SanitizePolicy::default();
+    if let Some(allowed_tags) = allowed_tags {
+        policy.allowed_tags = allowed_tags;
+    }
+    if let Some(allowed_schemes) = allowed_schemes {
+        policy.allowed_schemes = allowed_schemes;
+    }
+    html_parser::sanitize_html(html, &policy)
 }
 
-/// python wrapper for extract_main_content function
+/// python wrapper for clean_html_with_options
+///
+/// `extra_remove_selectors` are stripped on top of the built-in list and elements
+/// matching `keep_selectors` are kept; an invalid selector raises ValueError.
+/// comments are removed unless `keep_comments` is true, forms unless `keep_forms`
+/// is true, and `strip_style`, `strip_class` and `strip_id` drop those attributes
 #[pyfunction]
-fn extract_main_content(html: &str) -> PyResult<String> {
-    let main_content = html_parser::extract_main_content(html)
+#[pyo3(signature = (
+    html,
+    extra_remove_selectors=Vec::new(),
+    keep_selectors=Vec::new(),
+    keep_comments=false,
+    strip_style=false,
+    strip_class=false,
+    strip_id=false,
+    keep_forms=false,
+))]
+#[allow(clippy::too_many_arguments)]
+fn clean_html_advanced(
+    html: &str,
+    extra_remove_selectors: Vec<String>,
+    keep_selectors: Vec<String>,
+    keep_comments: bool,
+    strip_style: bool,
+    strip_class: bool,
+    strip_id: bool,
+    keep_forms: bool,
+) -> PyResult<String> {
+    let options = html_parser::CleanOptions {
+        extra_remove_selectors,
+        keep_selectors,
+        keep_comments,
+        strip_style,
+        strip_class,
+        strip_id,
+        keep_forms,
+    };
+    html_parser::clean_html_with_options(html, &options).map_err(|e| match e {
+        html_parser::ParserError::SelectorError(_) => {
+            PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string())
+        }
+        _ => PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()),
+    })
+}
+
+/// python wrapper for extract_main_content_with_options
+///
+/// hidden elements are left out unless include_hidden=True
+#[pyfunction]
+#[pyo3(signature = (html, include_hidden=false))]
+fn extract_main_content(html: &str, include_hidden: bool) -> PyResult<String> {
+    let options = html_parser::MainContentOptions { include_hidden };
+    let main_content = html_parser::extract_main_content_with_options(html, &options)
         .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))?;
     Ok(main_content.root_element().html())
 }
 
-/// python wrapper for extract_links function
+/// python wrapper for extract_links_with_options
+///
+/// with respect_nofollow=True, nofollow pages yield no links and rel="nofollow" anchors are skipped;
+/// with normalize=True, URLs go through normalize_url's defaults before deduplication.
+/// fragment links like "#faq" are resolved to absolute URLs unless
+/// resolve_fragments_against_base=False
+#[pyfunction]
+#[allow(clippy::too_many_arguments)]
+#[pyo3(signature = (
+    html,
+    base_url,
+    respect_nofollow=false,
+    normalize=false,
+    include_mailto=true,
+    include_tel=true,
+    include_fragments=false,
+    resolve_fragments_against_base=true,
+))]
+fn extract_links(
+    html: &str,
+    base_url: &str,
+    respect_nofollow: bool,
+    normalize: bool,
+    include_mailto: bool,
+    include_tel: bool,
+    include_fragments: bool,
+    resolve_fragments_against_base: bool,
+) -> PyResult<Vec<String>> {
+    let options = html_parser::LinkExtractionOptions {
+        include_mailto,
+        include_tel,
+        include_fragments,
+        resolve_fragments_against_base,
+        respect_nofollow,
+        normalize: normalize.then(html_parser::NormalizeOptions::default),
+    };
+    html_parser::extract_links_with_options(html, base_url, &options)
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))
+}
+
+/// rewrites a URL into a canonical form: lowercase scheme and host, no default port,
+/// collapsed slashes, sorted query without tracking parameters
+///
+/// removed_params defaults to utm_*, fbclid and gclid; a trailing * matches a prefix
+#[pyfunction]
+#[pyo3(signature = (url, strip_fragment=true, removed_params=None))]
+fn normalize_url(url: &str, strip_fragment: bool, removed_params: Option<Vec<String>>) -> String {
+    let mut options = html_parser::NormalizeOptions {
+        strip_fragment,
+        ..Default::default()
+    };
+    if let Some(removed_params) = removed_params {
+        options.removed_params = removed_params;
+    }
+    html_parser::normalize_url(url, &options)
+}
+
+/// extract_links narrowed to the links a crawler wants
+///
+/// include_patterns and exclude_patterns are regular expressions matched against the
+/// absolute URL; exclusions win. an invalid pattern raises ValueError
+#[pyfunction]
+#[allow(clippy::too_many_arguments)]
+#[pyo3(signature = (
+    html,
+    base_url,
+    same_domain_only=false,
+    include_subdomains=true,
+    allowed_schemes=None,
+    exclude_extensions=Vec::new(),
+    exclude_patterns=Vec::new(),
+    include_patterns=Vec::new(),
+))]
+fn extract_links_filtered(
+    html: &str,
+    base_url: &str,
+    same_domain_only: bool,
+    include_subdomains: bool,
+    allowed_schemes: Option<Vec<String>>,
+    exclude_extensions: Vec<String>,
+    exclude_patterns: Vec<String>,
+    include_patterns: Vec<String>,
+) -> PyResult<Vec<String>> {
+    let compile = |patterns: Vec<String>| {
+        patterns
+            .iter()
+            .map(|pattern| {
+                regex::Regex::new(pattern).map_err(|e| {
+                    PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                        "invalid pattern {:?}: {}",
+                        pattern, e
+                    ))
+                })
+            })
+            .collect::<PyResult<Vec<_>>>()
+    };
+    let mut filter = html_parser::LinkFilter {
+        same_domain_only,
+        include_subdomains,
+        exclude_extensions,
+        exclude_patterns: compile(exclude_patterns)?,
+        include_patterns: compile(include_patterns)?,
+        ..Default::default()
+    };
+    if let Some(allowed_schemes) = allowed_schemes {
+        filter.allowed_schemes = allowed_schemes;
+    }
+    html_parser::extract_links_filtered(html, base_url, &filter)
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))
+}
+
+/// extracts each linked URL once as a dict: url, text, title, rel, is_external and dom_path_hint
+#[pyfunction]
+fn extract_links_detailed<'py>(
+    py: Python<'py>,
+    html: &str,
+    base_url: &str,
+) -> PyResult<Bound<'py, PyList>> {
+    let links = html_parser::extract_links_detailed(html, base_url)
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))?;
+    let list = PyList::empty(py);
+    for link in links {
+        let dict = PyDict::new(py);
+        dict.set_item("url", link.url)?;
+        dict.set_item("text", link.text)?;
+        dict.set_item("title", link.title)?;
+        dict.set_item("rel", link.rel)?;
+        dict.set_item("is_external", link.is_external)?;
+        dict.set_item("kind", link.kind.as_str())?;
+        dict.set_item("dom_path_hint", link.dom_path_hint)?;
+        list.append(dict)?;
+    }
+    Ok(list)
+}
+
+/// classifies absolute URLs as "internal", "subdomain", "external" or "asset",
+/// returning (url, kind) tuples
+#[pyfunction]
+fn classify_links(urls: Vec<String>, base_url: &str) -> PyResult<Vec<(String, &'static str)>> {
+    let kinds = html_parser::classify_links(&urls, base_url)
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))?;
+    Ok(urls
+        .into_iter()
+        .zip(kinds.into_iter().map(html_parser::LinkKind::as_str))
+        .collect())
+}
+
+/// reads robots meta directives as a dict: noindex, nofollow, noarchive and indexable
+#[pyfunction]
+fn extract_robots_directives<'py>(py: Python<'py>, html: &str) -> PyResult<Bound<'py, PyDict>> {
+    let robots = html_parser::extract_robots_directives(html);
+    let dict = PyDict::new(py);
+    dict.set_item("noindex", robots.noindex)?;
+    dict.set_item("nofollow", robots.nofollow)?;
+    dict.set_item("noarchive", robots.noarchive)?;
+    dict.set_item("indexable", robots.is_indexable())?;
+    Ok(dict)
+}
+
+/// python wrapper for extract_images function
+#[pyfunction]
+fn extract_images(html: &str, base_url: &str) -> PyResult<Vec<String>> {
+    html_parser::extract_images(html, base_url)
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))
+}
+
+/// extracts each image once as a dict: url, alt, width, height and source
+///
+/// source is "src", "data-src" (any lazy-load attribute) or "srcset"; inline data:
+/// images are skipped unless min_data_uri_length is given and they are at least that long
+#[pyfunction]
+#[pyo3(signature = (html, base_url, min_data_uri_length=None))]
+fn extract_images_detailed<'py>(
+    py: Python<'py>,
+    html: &str,
+    base_url: &str,
+    min_data_uri_length: Option<usize>,
+) -> PyResult<Bound<'py, PyList>> {
+    let images = html_parser::extract_images_detailed(html, base_url, min_data_uri_length)
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))?;
+    let list = PyList::empty(py);
+    for image in images {
+        let dict = PyDict::new(py);
+        dict.set_item("url", image.url)?;
+        dict.set_item("alt", image.alt)?;
+        dict.set_item("width", image.width)?;
+        dict.set_item("height", image.height)?;
+        dict.set_item("source", image.source.as_str())?;
+        list.append(dict)?;
+    }
+    Ok(list)
+}
+
+/// extracts every table as a dict: caption (or None), headers and rows
+#[pyfunction]
+fn extract_tables<'py>(py: Python<'py>, html: &str) -> PyResult<Bound<'py, PyList>> {
+    let list = PyList::empty(py);
+    for table in html_parser::extract_tables(html) {
+        let dict = PyDict::new(py);
+        dict.set_item("caption", table.caption)?;
+        dict.set_item("headers", table.headers)?;
+        dict.set_item("rows", table.rows)?;
+        list.append(dict)?;
+    }
+    Ok(list)
+}
+
+/// extracts every table as a CSV string, headers first when the table has them
+#[pyfunction]
+fn extract_tables_csv(html: &str) -> Vec<String> {
+    html_parser::extract_tables_csv(html)
+}
+
+/// extracts the heading outline as dicts with level, text, id and children
+///
+/// with flat=True the headings come in document order without children
+#[pyfunction]
+#[pyo3(signature = (html, flat=false))]
+fn extract_headings<'py>(py: Python<'py>, html: &str, flat: bool) -> PyResult<Bound<'py, PyList>> {
+    fn to_list<'py>(
+        py: Python<'py>,
+        headings: &[html_parser::HeadingNode],
+        flat: bool,
+    ) -> PyResult<Bound<'py, PyList>> {
+        let list = PyList::empty(py);
+        for heading in headings {
+            let dict = PyDict::new(py);
+            dict.set_item("level", heading.level)?;
+            dict.set_item("text", &heading.text)?;
+            dict.set_item("id", &heading.id)?;
+            if !flat {
+                dict.set_item("children", to_list(py, &heading.children, flat)?)?;
+            }
+            list.append(dict)?;
+        }
+        Ok(list)
+    }
+
+    if flat {
+        to_list(py, &html_parser::extract_headings_flat(html), true)
+    } else {
+        to_list(py, &html_parser::extract_headings(html), false)
+    }
+}
+
+/// extracts the page text as plain text, one block per line and table cells tab-separated
+#[pyfunction]
+fn extract_text_content(html: &str) -> String {
+    html_parser::extract_text_content(html)
+}
+
+/// lists the resolved http(s) URLs of the page's iframes, in document order
+#[pyfunction]
+fn extract_iframe_sources(html: &str, base_url: &str) -> PyResult<Vec<String>> {
+    html_parser::extract_iframe_sources(html, base_url)
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))
+}
+
+/// extracts every form as a dict with action, method and fields; each field is a dict
+/// with name, kind, label, required, value and options
+#[pyfunction]
+fn extract_forms<'py>(py: Python<'py>, html: &str) -> PyResult<Bound<'py, PyList>> {
+    let list = PyList::empty(py);
+    for form in html_parser::extract_forms(html) {
+        let fields = PyList::empty(py);
+        for field in form.fields {
+            let dict = PyDict::new(py);
+            dict.set_item("name", field.name)?;
+            dict.set_item("kind", field.kind)?;
+            dict.set_item("label", field.label)?;
+            dict.set_item("required", field.required)?;
+            dict.set_item("value", field.value)?;
+            dict.set_item("options", field.options)?;
+            fields.append(dict)?;
+        }
+        let dict = PyDict::new(py);
+        dict.set_item("action", form.action)?;
+        dict.set_item("method", form.method)?;
+        dict.set_item("fields", fields)?;
+        list.append(dict)?;
+    }
+    Ok(list)
+}
+
+/// finds hreflang language variants as (hreflang, url) tuples
+#[pyfunction]
+fn extract_alternates(html: &str, base_url: &str) -> PyResult<Vec<(String, String)>> {
+    html_parser::extract_alternates(html, base_url)
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))
+}
+
+/// finds the breadcrumb trail as (text, url) tuples; url is None for unlinked crumbs
 #[pyfunction]
-fn extract_links(html: &str, base_url: &str) -> PyResult<Vec<String>> {
-    html_parser::extract_links(html, base_url)
+fn extract_breadcrumbs(html: &str, base_url: &str) -> PyResult<Vec<(String, Option<String>)>> {
+    html_parser::extract_breadcrumbs(html, base_url)
+        .map(|crumbs| {
+            crumbs
+                .into_iter()
+                .map(|crumb| (crumb.text, crumb.url))
+                .collect()
+        })
         .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))
 }
 
+/// finds advertised feeds and sitemaps as (url, kind, title) tuples
+///
+/// kind is one of "rss", "atom", "json-feed" or "sitemap"; title may be None
+#[pyfunction]
+fn discover_feeds(
+    html: &str,
+    base_url: &str,
+) -> PyResult<Vec<(String, &'static str, Option<String>)>> {
+    let feeds = html_parser::discover_feeds(html, base_url)
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))?;
+    Ok(feeds
+        .into_iter()
+        .map(|feed| (feed.url, feed.kind.as_str(), feed.title))
+        .collect())
+}
+
+/// exports the page's links and images as CSV rows: kind, text, url, internal/external scope
+///
+/// `delimiter` must be a single ASCII character, e.g. `"\t"` for TSV
+#[pyfunction]
+#[pyo3(signature = (html, base_url, delimiter=","))]
+fn extract_links_csv(html: &str, base_url: &str, delimiter: &str) -> PyResult<String> {
+    let delimiter = match delimiter.as_bytes() {
+        [byte] if byte.is_ascii() => *byte,
+        _ => return Err(invalid_option("delimiter", delimiter)),
+    };
+    let document = markdown_converter::parse_html_to_document(html, base_url)
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))?;
+    Ok(markdown_converter::document_to_links_csv(
+        &document, delimiter,
+    ))
+}
+
+/// extracts OpenGraph and Twitter Card metadata as a dict
+///
+/// keys: og_title, og_description, og_image, og_type, og_site_name, twitter_card and
+/// twitter_image; missing tags map to None
+#[pyfunction]
+fn extract_social_metadata<'py>(
+    py: Python<'py>,
+    html: &str,
+    base_url: &str,
+) -> PyResult<Bound<'py, PyDict>> {
+    let social = html_parser::extract_social_metadata(html, base_url)
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))?;
+    let dict = PyDict::new(py);
+    dict.set_item("og_title", social.og_title)?;
+    dict.set_item("og_description", social.og_description)?;
+    dict.set_item("og_image", social.og_image)?;
+    dict.set_item("og_type", social.og_type)?;
+    dict.set_item("og_site_name", social.og_site_name)?;
+    dict.set_item("twitter_card", social.twitter_card)?;
+    dict.set_item("twitter_image", social.twitter_image)?;
+    Ok(dict)
+}
+
+/// parses the page's JSON-LD blocks into a list of dicts, skipping invalid ones
+#[pyfunction]
+fn extract_json_ld<'py>(py: Python<'py>, html: &str) -> PyResult<Bound<'py, PyList>> {
+    let list = PyList::empty(py);
+    for value in html_parser::extract_json_ld(html) {
+        list.append(json_to_py(py, &value)?)?;
+    }
+    Ok(list)
+}
+
+/// the schema.org `@type` names found in the page's JSON-LD, e.g. ["Article"]
+#[pyfunction]
+fn json_ld_types(html: &str) -> Vec<String> {
+    html_parser::json_ld_types(&html_parser::extract_json_ld(html))
+}
+
+/// the page's publication date as an RFC 3339 string, or None
+#[pyfunction]
+fn extract_publication_date(html: &str) -> Option<String> {
+    html_parser::extract_publication_date(html)
+}
+
+/// the page's last-modified date as an RFC 3339 string, or None
+#[pyfunction]
+fn extract_modified_date(html: &str) -> Option<String> {
+    html_parser::extract_modified_date(html)
+}
+
+/// converts a JSON value into the equivalent python object
+fn json_to_py(py: Python<'_>, value: &serde_json::Value) -> PyResult<PyObject> {
+    use pyo3::IntoPyObjectExt;
+    use serde_json::Value;
+
+    match value {
+        Value::Null => Ok(py.None()),
+        Value::Bool(b) => b.into_py_any(py),
+        Value::Number(n) => match (n.as_i64(), n.as_u64()) {
+            (Some(i), _) => i.into_py_any(py),
+            (None, Some(u)) => u.into_py_any(py),
+            (None, None) => n.as_f64().unwrap_or(f64::NAN).into_py_any(py),
+        },
+        Value::String(s) => s.into_py_any(py),
+        Value::Array(items) => {
+            let list = PyList::empty(py);
+            for item in items {
+                list.append(json_to_py(py, item)?)?;
+            }
+            list.into_py_any(py)
+        }
+        Value::Object(map) => {
+            let dict = PyDict::new(py);
+            for (key, item) in map {
+                dict.set_item(key, json_to_py(py, item)?)?;
+            }
+            dict.into_py_any(py)
+        }
+    }
+}
+
 /// python wrapper for resolve_url function
 #[pyfunction]
 fn resolve_url(base_url: &str, relative_url: &str) -> PyResult<String> {
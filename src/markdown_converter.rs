@@ -1,9 +1,15 @@
-use scraper::{Html, Selector};
+use ego_tree::NodeRef;
+use scraper::{ElementRef, Html, Node, Selector};
 use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
 use thiserror::Error;
+use unicode_width::UnicodeWidthStr;
 use url::Url;
 
-use crate::html_parser;
+use crate::html_parser::{self, Crumb, RobotsDirectives, SocialMeta};
 
 #[derive(Error, Debug)]
 pub enum MarkdownError {
@@ -16,6 +22,14 @@ pub enum MarkdownError {
     #[error("Serialization error: {0}")]
     SerializationError(String),
 
+    #[error("Template error: {0}")]
+    TemplateError(String),
+
+    #[error(
+        "unknown output format {0:?}; expected one of: markdown (md), json, xml, yaml (yml), text (txt), rst, html"
+    )]
+    UnknownFormat(String),
+
     #[error("Other error: {0}")]
     Other(String),
 }
@@ -26,10 +40,334 @@ pub enum OutputFormat {
     Markdown,
     Json,
     Xml,
+    Yaml,
+    /// Plain prose without markdown syntax
+    Text,
+    /// reStructuredText
+    Rst,
+    /// Cleaned main content as a standalone HTML document with absolute URLs
+    Html,
+}
+
+impl FromStr for OutputFormat {
+    type Err = MarkdownError;
+
+    /// Parse a case-insensitive format name or alias, e.g. `md` or `RST`
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_lowercase().as_str() {
+            "markdown" | "md" => Ok(OutputFormat::Markdown),
+            "json" => Ok(OutputFormat::Json),
+            "xml" => Ok(OutputFormat::Xml),
+            "yaml" | "yml" => Ok(OutputFormat::Yaml),
+            "text" | "txt" => Ok(OutputFormat::Text),
+            "rst" => Ok(OutputFormat::Rst),
+            "html" => Ok(OutputFormat::Html),
+            _ => Err(MarkdownError::UnknownFormat(name.to_string())),
+        }
+    }
+}
+
+/// Layout of the generated markdown
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MarkdownLayout {
+    /// Emit blocks in the order they appear in the source document
+    #[default]
+    Ordered,
+    /// Legacy layout: all headings, then all paragraphs, then links, images, etc.
+    Grouped,
+}
+
+/// How definition lists are written in markdown
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DefinitionListStyle {
+    /// `**Term**` followed by indented definition lines
+    #[default]
+    BoldTerm,
+    /// `Term` followed by `: Definition` lines (PHP Markdown Extra / Pandoc)
+    Colon,
+}
+
+/// How hard line breaks (`<br>`) inside a block are written in markdown
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineBreakStyle {
+    /// Two trailing spaces before the newline
+    #[default]
+    Spaces,
+    /// A backslash before the newline
+    Backslash,
+}
+
+/// How figure captions are attached to images in markdown
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CaptionStyle {
+    /// An italic line below the image
+    #[default]
+    Italic,
+    /// The image title: `![alt](src "caption")`
+    Title,
+}
+
+/// How `<details>`/`<summary>` sections are written in markdown
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DetailsStyle {
+    /// The summary as a bold line followed by the body content
+    #[default]
+    BoldSummary,
+    /// Raw `<details>` HTML around markdown content, for GFM renderers that support it
+    Html,
+}
+
+/// How heading anchor ids are preserved in markdown
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AnchorStyle {
+    /// Drop heading ids from the markdown (they remain in JSON/XML)
+    #[default]
+    Omit,
+    /// Attribute syntax: `## Title {#id}`
+    Attribute,
+    /// Inline HTML anchor: `## <a id="id"></a>Title`
+    Html,
+}
+
+/// How cells covered by a `colspan`/`rowspan` are filled when tables are extracted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TableSpanFill {
+    /// Repeat the spanning cell's text in every covered cell
+    #[default]
+    Duplicate,
+    /// Leave covered cells empty
+    Empty,
+}
+
+/// How abbreviation expansions from `abbr` titles are written in markdown
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AbbreviationStyle {
+    /// Expand the first occurrence in running text inline:
+    /// `HTML (HyperText Markup Language)`. Headings and table cells, which hold plain
+    /// text, keep the bare abbreviation and do not count as that occurrence.
+    #[default]
+    Inline,
+    /// Trailing definitions: `*[HTML]: HyperText Markup Language` (PHP Markdown Extra)
+    Definitions,
+    /// Leave abbreviations unexpanded
+    Omit,
+}
+
+/// How `<sub>` and `<sup>` content is written in markdown
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SubSupStyle {
+    /// Keep only the text: `H2O`
+    #[default]
+    Plain,
+    /// Pass the tags through as raw HTML: `H<sub>2</sub>O`
+    Html,
+    /// Unicode sub/superscript characters (`H₂O`), falling back to HTML for other content
+    Unicode,
+}
+
+/// How headings are written in markdown
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HeadingStyle {
+    /// `#`-prefixed headings at every level
+    #[default]
+    Atx,
+    /// `===`/`---` underlines for levels 1 and 2, ATX for deeper levels
+    Setext,
+}
+
+/// Marker used for unordered list items
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BulletChar {
+    /// `- item`
+    #[default]
+    Dash,
+    /// `* item`
+    Asterisk,
+    /// `+ item`
+    Plus,
+}
+
+impl BulletChar {
+    fn as_char(self) -> char {
+        match self {
+            BulletChar::Dash => '-',
+            BulletChar::Asterisk => '*',
+            BulletChar::Plus => '+',
+        }
+    }
+}
+
+/// How ordered list items are numbered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OrderedListStyle {
+    /// `1.`, `2.`, `3.`
+    #[default]
+    Sequential,
+    /// `1.` for every item; renderers still number them in order and diffs stay small
+    AllOnes,
+}
+
+/// Character used for code block fences
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FenceChar {
+    /// ```` ``` ````
+    #[default]
+    Backtick,
+    /// `~~~`
+    Tilde,
+}
+
+/// How link and image destinations are written in markdown
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LinkStyle {
+    /// `[text](url)` and `![alt](src)`
+    #[default]
+    Inline,
+    /// `[text][1]` and `![alt][img1]`, with the URLs listed at the end of the document
+    Reference,
+}
+
+/// Whitespace in JSON output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum JsonStyle {
+    /// Indented, one field per line
+    #[default]
+    Pretty,
+    /// A single line with no whitespace between tokens
+    Compact,
+}
+
+/// Whether the page title is written as a leading `# Title` line
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TitleMode {
+    #[default]
+    Always,
+    /// Skip the title when the first `h1` already says the same thing
+    IfNoH1,
+    Never,
+}
+
+/// Alt text used for images whose `alt` is missing or blank
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AltFallback {
+    /// The literal text `image`
+    #[default]
+    Placeholder,
+    /// Keep the alt text empty: `![](src)`
+    Empty,
+    /// Derive it from the file name: `team-photo_2024.jpg` -> `team photo 2024`
+    FileName,
+    /// Use the enclosing figure's caption, or nothing
+    Caption,
+    /// Leave such images out entirely
+    Skip,
+}
+
+/// The markdown dialect the output is written for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MarkdownFlavor {
+    /// Plain CommonMark: `<url>` autolinks, no strikethrough, tables written as definition lists
+    CommonMark,
+    /// GitHub Flavored Markdown: bare URL autolinks, `~~strikethrough~~` and pipe tables
+    #[default]
+    Gfm,
+    /// Python-Markdown as used by MkDocs: pipe tables, `<url>` autolinks, no strikethrough
+    /// and trailing-space line breaks
+    MkDocs,
+}
+
+impl MarkdownFlavor {
+    /// Whether a bare URL is turned into a link without `<...>`
+    fn bare_autolinks(self) -> bool {
+        self == MarkdownFlavor::Gfm
+    }
+
+    fn strikethrough(self) -> bool {
+        self == MarkdownFlavor::Gfm
+    }
+
+    fn pipe_tables(self) -> bool {
+        self != MarkdownFlavor::CommonMark
+    }
+
+    /// The break encoding to use, given the requested one
+    fn line_break_style(self, requested: LineBreakStyle) -> LineBreakStyle {
+        match self {
+            // Python-Markdown has no backslash hard breaks
+            MarkdownFlavor::MkDocs => LineBreakStyle::Spaces,
+            _ => requested,
+        }
+    }
+}
+
+/// Options controlling how a document is converted and rendered
+#[derive(Debug, Clone, Default)]
+pub struct ConversionOptions {
+    pub flavor: MarkdownFlavor,
+    pub layout: MarkdownLayout,
+    pub title_mode: TitleMode,
+    pub heading_style: HeadingStyle,
+    /// Shift every heading (and the title) by this many levels, clamped to 1..=6
+    pub heading_offset: i8,
+    pub link_style: LinkStyle,
+    pub json_style: JsonStyle,
+    /// Append a "Links" section listing every link after the content
+    pub include_link_index: bool,
+    /// Only collect links from the page content, not navigation, headers or footers
+    pub content_links_only: bool,
+    pub definition_list_style: DefinitionListStyle,
+    pub line_break_style: LineBreakStyle,
+    pub bullet_char: BulletChar,
+    pub ordered_style: OrderedListStyle,
+    /// Fence character for code blocks; fences grow past any run of it inside the code
+    pub code_fence: FenceChar,
+    /// Start the markdown with a YAML front matter block holding the document's `meta`
+    pub front_matter: bool,
+    /// Put a nested list of links to the headings after the title
+    pub toc: bool,
+    /// Deepest heading level listed in the table of contents; 0 lists every level
+    pub toc_max_level: u8,
+    /// Collect OpenGraph/Twitter Card tags into `meta.social`, using `og:title` as the
+    /// title of pages without a `<title>`
+    pub social_metadata: bool,
+    /// Guess the language from the paragraphs when the page does not declare one
+    /// (requires the `language_detection` feature, otherwise ignored)
+    pub detect_language: bool,
+    /// Resolve relative URLs against the caller's base URL even when the page has a
+    /// `<base href>`
+    pub ignore_base_tag: bool,
+    /// Spaces before a nested list's markers; 0 aligns them with the parent item's text
+    pub indent_width: usize,
+    pub caption_style: CaptionStyle,
+    pub alt_fallback: AltFallback,
+    pub details_style: DetailsStyle,
+    pub anchor_style: AnchorStyle,
+    pub table_span_fill: TableSpanFill,
+    pub abbreviation_style: AbbreviationStyle,
+    pub sub_sup_style: SubSupStyle,
+    /// Extra attributes holding the real URL of lazy-loaded images (e.g. `data-hi-res`),
+    /// checked before the built-in `data-src`, `data-original` and `data-lazy-src`
+    pub lazy_image_attributes: Vec<String>,
+    /// Keep non-breaking spaces instead of turning them into regular spaces
+    pub preserve_nbsp: bool,
+    /// Write superscript links to in-page notes (`<sup><a href="#fn1">1</a></sup>`) as `[^1]`
+    pub footnote_references: bool,
+    /// Normalize the URLs collected into `Document::links` (see `html_parser::normalize_url`)
+    pub normalize_links: Option<html_parser::NormalizeOptions>,
+    /// Convert hidden elements (`hidden`, `aria-hidden="true"`, `display: none`,
+    /// `.sr-only`, see `html_parser::is_hidden`) instead of leaving them out
+    pub include_hidden: bool,
+    /// List the URLs of the page's iframes in `Document::frames`, so callers can fetch
+    /// them for `parse_html_to_document_with_frames`
+    pub collect_frames: bool,
+    /// Convert the markup inside `<noscript>` as regular content instead of dropping it,
+    /// for lazy-loading pages whose real images are only given to non-JS clients
+    pub use_noscript_fallback: bool,
 }
 
 /// Data structure for document representation that can be serialized to different formats
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Document {
     pub title: String,
     pub base_url: String,
@@ -40,69 +378,407 @@ pub struct Document {
     pub lists: Vec<List>,
     pub code_blocks: Vec<CodeBlock>,
     pub blockquotes: Vec<String>,
+    pub tables: Vec<Table>,
+    pub definition_lists: Vec<DefinitionList>,
+    /// Video, audio and iframe embeds
+    pub embeds: Vec<Embed>,
+    /// Expansions from `abbr`/`dfn` title attributes, one per abbreviation
+    pub abbreviations: Vec<Abbreviation>,
+    /// Block-level content in document order
+    pub blocks: Vec<Block>,
+    /// Page metadata, also written as front matter when requested
+    pub meta: DocumentMeta,
+    /// Resolved iframe URLs that were not spliced in, with `ConversionOptions::collect_frames`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub frames: Vec<String>,
+}
+
+impl Document {
+    /// Content statistics: prose words, reading time, and heading, link, image and
+    /// code line counts
+    pub fn stats(&self) -> DocumentStats {
+        let mut headings_by_level = [0; 6];
+        for heading in &self.headings {
+            headings_by_level[usize::from(heading.level.clamp(1, 6)) - 1] += 1;
+        }
+        let word_count = blocks_to_text(&prose_blocks(&self.blocks))
+            .iter()
+            .flat_map(|text| text.split_whitespace())
+            .filter(|word| word.chars().any(char::is_alphanumeric))
+            .count();
+
+        DocumentStats {
+            word_count,
+            reading_time_minutes: word_count.div_ceil(READING_WORDS_PER_MINUTE),
+            headings_by_level,
+            link_count: self.links.len(),
+            image_count: self.images.len(),
+            code_line_count: code_line_count(&self.blocks),
+        }
+    }
+
+    /// Load a document previously serialized to JSON, e.g. by `document_to_json`
+    ///
+    /// Unknown fields (such as `schema_version`) are ignored and missing collections
+    /// default to empty, so JSON from older or newer versions still loads.
+    pub fn from_json(json: &str) -> Result<Document, MarkdownError> {
+        serde_json::from_str(json).map_err(|e| {
+            MarkdownError::SerializationError(format!("Failed to parse document JSON: {}", e))
+        })
+    }
+}
+
+/// Content statistics of a document, see `Document::stats`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DocumentStats {
+    /// Words in headings, paragraphs, lists, quotes and tables; code is not prose
+    pub word_count: usize,
+    /// At 200 words per minute, rounded up
+    pub reading_time_minutes: usize,
+    /// Number of headings per level, `h1` first
+    pub headings_by_level: [usize; 6],
+    pub link_count: usize,
+    pub image_count: usize,
+    /// Lines across all code blocks
+    pub code_line_count: usize,
+}
+
+/// Reading speed used for `DocumentStats::reading_time_minutes`
+const READING_WORDS_PER_MINUTE: usize = 200;
+
+/// The blocks with code blocks removed, including those nested in quotes and details
+fn prose_blocks(blocks: &[Block]) -> Vec<Block> {
+    blocks
+        .iter()
+        .filter_map(|block| match block {
+            Block::CodeBlock(_) => None,
+            Block::Blockquote { blocks } => Some(Block::Blockquote {
+                blocks: prose_blocks(blocks),
+            }),
+            Block::Details { summary, blocks } => Some(Block::Details {
+                summary: summary.clone(),
+                blocks: prose_blocks(blocks),
+            }),
+            other => Some(other.clone()),
+        })
+        .collect()
+}
+
+/// Lines of code in the blocks, including code nested in quotes and details
+fn code_line_count(blocks: &[Block]) -> usize {
+    blocks
+        .iter()
+        .map(|block| match block {
+            Block::CodeBlock(code_block) => code_block.code.lines().count(),
+            Block::Blockquote { blocks } | Block::Details { blocks, .. } => code_line_count(blocks),
+            _ => 0,
+        })
+        .sum()
+}
+
+/// Summary information about a converted page
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DocumentMeta {
+    pub title: String,
+    pub source_url: String,
+    /// UTC time of the conversion, e.g. `2024-05-01T12:00:00Z`
+    pub fetched_at: String,
+    /// Words of prose, as `Document::stats` counts them
+    pub word_count: usize,
+    pub link_count: usize,
+    pub image_count: usize,
+    /// From `<meta name="description">`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// From `<meta name="keywords">`, split on commas
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub keywords: Vec<String>,
+    /// From `<meta name="author">`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    /// From `<meta name="generator">`, the software that produced the page
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub generator: Option<String>,
+    /// When the page was published (RFC 3339), from article meta, `<time>`, JSON-LD or microdata
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub published_at: Option<String>,
+    /// When the page was last modified (RFC 3339)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modified_at: Option<String>,
+    /// Primary language subtag (`en`, `de`, ...) from `<html lang>`, the
+    /// `content-language` header meta or `og:locale`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    /// From `<link rel="canonical">`, the URL the page should be deduplicated under
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub canonical_url: Option<String>,
+    /// Language variants of the page from `hreflang` alternate links
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub alternates: Vec<LanguageAlternate>,
+    /// Where the page sits in the site, from breadcrumb navigation or JSON-LD
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub breadcrumbs: Vec<Crumb>,
+    /// Directives from robots meta tags; `noindex` pages should not be indexed
+    #[serde(default, skip_serializing_if = "RobotsDirectives::is_empty")]
+    pub robots: RobotsDirectives,
+    /// OpenGraph and Twitter Card tags, collected with `ConversionOptions::social_metadata`
+    #[serde(default, skip_serializing_if = "SocialMeta::is_empty")]
+    pub social: SocialMeta,
+}
+
+impl DocumentMeta {
+    /// The breadcrumb trail as one line, e.g. `Home > Docs > API`
+    pub fn breadcrumb_path(&self) -> Option<String> {
+        (!self.breadcrumbs.is_empty()).then(|| {
+            self.breadcrumbs
+                .iter()
+                .map(|crumb| crumb.text.as_str())
+                .collect::<Vec<_>>()
+                .join(" > ")
+        })
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// A language variant of the page, e.g. `de` at `https://example.com/de/`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LanguageAlternate {
+    /// The `hreflang` value as written, e.g. `de-AT` or `x-default`
+    pub hreflang: String,
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Heading {
     pub level: u8,
     pub text: String,
+    /// Anchor id from the heading or an anchor around/inside it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Link {
     pub text: String,
     pub url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Image {
     pub alt: String,
     pub src: String,
+    /// Caption from an enclosing `figure`'s `figcaption`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub caption: Option<String>,
+    /// The `src` attribute, when a larger `srcset` candidate was chosen instead
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fallback_src: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// Declared width in pixels
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub width: Option<u32>,
+    /// Declared height in pixels
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub height: Option<u32>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct List {
     pub ordered: bool,
-    pub items: Vec<String>,
+    pub items: Vec<ListItem>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListItem {
+    pub text: String,
+    /// Task list state when the item starts with a checkbox
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checked: Option<bool>,
+    /// Lists nested directly inside the item
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<List>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CodeBlock {
+    #[serde(default)]
     pub language: String,
     pub code: String,
 }
 
+/// An abbreviation and its expansion, e.g. `HTML` / `HyperText Markup Language`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Abbreviation {
+    pub abbr: String,
+    pub title: String,
+}
+
+/// A video, audio or iframe embed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Embed {
+    pub kind: EmbedKind,
+    pub src: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EmbedKind {
+    Video,
+    Audio,
+    /// An iframe that is not a known video player
+    Frame,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Table {
+    pub headers: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+    /// Per-column alignment, empty when no column declares one
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub alignments: Vec<ColumnAlignment>,
+}
+
+/// Horizontal alignment of a table column
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColumnAlignment {
+    #[default]
+    None,
+    Left,
+    Center,
+    Right,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DefinitionList {
+    pub entries: Vec<Definition>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Definition {
+    pub term: String,
+    pub definitions: Vec<String>,
+}
+
+/// A block-level element, kept in the order it appeared in the source HTML
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Block {
+    Heading(Heading),
+    Paragraph {
+        text: String,
+    },
+    List(List),
+    CodeBlock(CodeBlock),
+    /// Quoted content, which may itself contain paragraphs, lists, code or nested quotes
+    Blockquote {
+        blocks: Vec<Block>,
+    },
+    Table(Table),
+    Image(Image),
+    Embed(Embed),
+    DefinitionList(DefinitionList),
+    /// Thematic break from an `<hr>` element
+    Hr,
+    /// Collapsible `<details>` section with its `<summary>` text
+    Details {
+        summary: String,
+        blocks: Vec<Block>,
+    },
+}
+
 /// Parse HTML into our document structure
 pub fn parse_html_to_document(html: &str, base_url_str: &str) -> Result<Document, MarkdownError> {
+    parse_html_to_document_with_options(html, base_url_str, &ConversionOptions::default())
+}
+
+/// Parse HTML into our document structure using the given options
+pub fn parse_html_to_document_with_options(
+    html: &str,
+    base_url_str: &str,
+    options: &ConversionOptions,
+) -> Result<Document, MarkdownError> {
     // Parse HTML first to decode entities
-    let document_html = Html::parse_document(html);
+    let mut document_html = Html::parse_document(html);
+    if options.use_noscript_fallback {
+        html_parser::lift_noscript(&mut document_html);
+    }
     let base_url = Url::parse(base_url_str)?;
 
     // Get the HTML after parsing (with decoded entities) and clean it
     let parsed_html = document_html.root_element().html();
-    let cleaned_html = html_parser::clean_html(&parsed_html)
-        .map_err(|e| MarkdownError::Other(format!("HTML cleaning failed: {}", e)))?;
+    let cleaned_html = html_parser::clean_html_keeping_attributes(&parsed_html);
 
-    let cleaned_document = Html::parse_document(&cleaned_html);
+    let mut cleaned_document = Html::parse_document(&cleaned_html);
+    if !options.include_hidden {
+        html_parser::remove_hidden_elements(&mut cleaned_document);
+    }
+    let base_url = if options.ignore_base_tag {
+        base_url
+    } else {
+        html_parser::effective_base_url(&cleaned_document, &base_url)
+    };
 
-    let title = extract_document_title(&cleaned_document)?;
+    let social = options
+        .social_metadata
+        .then(|| html_parser::social_metadata(&cleaned_document, &base_url));
+    let og_title = social.as_ref().and_then(|social| social.og_title.clone());
+    let title = match (extract_document_title(&cleaned_document)?, og_title) {
+        (Some(title), _) if !title.is_empty() => title,
+        (_, Some(og_title)) => og_title,
+        (title, None) => title.unwrap_or_else(|| "No Title".to_string()),
+    };
     let mut document = create_document_structure(&title, base_url_str);
 
-    populate_document_content(&mut document, &cleaned_document, &base_url)?;
+    populate_document_content(&mut document, &cleaned_document, &base_url, options)?;
+    if let Some(social) = social {
+        document.meta.social = social;
+    }
+    // JSON-LD lives in scripts, which cleaning removes
+    let dates = html_parser::page_dates(&document_html);
+    document.meta.published_at = dates.published;
+    document.meta.modified_at = dates.modified;
+    if options.collect_frames {
+        document.frames = html_parser::iframe_sources(&document_html, &base_url);
+    }
+    // Breadcrumb navigation is boilerplate to the cleaner
+    document.meta.breadcrumbs = html_parser::breadcrumbs(&document_html, &base_url);
+    #[cfg(feature = "language_detection")]
+    if options.detect_language && document.meta.language.is_none() {
+        document.meta.language = detect_language(&document.paragraphs);
+    }
 
     Ok(document)
 }
 
-/// Extract the document title from HTML
-fn extract_document_title(document_html: &Html) -> Result<String, MarkdownError> {
+/// Parse HTML into our document structure, splicing in pre-fetched iframe content
+///
+/// `frames` maps iframe URLs (see `html_parser::extract_iframe_sources`) to their
+/// HTML; same-origin frames found there are replaced by their body before extraction
+/// (see `html_parser::splice_frames`), other frames are kept as embeds.
+pub fn parse_html_to_document_with_frames(
+    html: &str,
+    base_url_str: &str,
+    options: &ConversionOptions,
+    frames: &HashMap<String, String>,
+) -> Result<Document, MarkdownError> {
+    let html = html_parser::splice_frames(html, base_url_str, frames)
+        .map_err(|e| MarkdownError::Other(format!("Frame splicing failed: {}", e)))?;
+    parse_html_to_document_with_options(&html, base_url_str, options)
+}
+
+/// Extract the document title from HTML, `None` when there is no `<title>`
+fn extract_document_title(document_html: &Html) -> Result<Option<String>, MarkdownError> {
     let title_selector =
         Selector::parse("title").map_err(|e| MarkdownError::SelectorError(e.to_string()))?;
-    let title = document_html
-        .select(&title_selector)
-        .next()
-        .map(|element| element.text().collect::<String>())
-        .unwrap_or_else(|| "No Title".to_string());
-    Ok(title.trim().to_string())
+    Ok(document_html.select(&title_selector).next().map(|element| {
+        let title = element.text().collect::<String>();
+        title.split_whitespace().collect::<Vec<_>>().join(" ")
+    }))
 }
 
 /// Create the initial document structure
@@ -117,312 +793,3754 @@ fn create_document_structure(title: &str, base_url: &str) -> Document {
         lists: Vec::new(),
         code_blocks: Vec::new(),
         blockquotes: Vec::new(),
+        tables: Vec::new(),
+        definition_lists: Vec::new(),
+        embeds: Vec::new(),
+        abbreviations: Vec::new(),
+        blocks: Vec::new(),
+        meta: DocumentMeta::default(),
+        frames: Vec::new(),
     }
 }
 
+/// Settings shared by every step of the DOM walk
+struct ParseContext<'a> {
+    base_url: &'a Url,
+    options: &'a ConversionOptions,
+    /// Abbreviations already expanded in running text, so each is expanded once per
+    /// document
+    expanded_abbreviations: RefCell<HashSet<String>>,
+}
+
 /// Populate document with content from HTML
 fn populate_document_content(
     document: &mut Document,
     document_html: &Html,
     base_url: &Url,
+    options: &ConversionOptions,
 ) -> Result<(), MarkdownError> {
-    process_headings(document, document_html)?;
-    process_paragraphs(document, document_html)?;
-    process_links(document, document_html, base_url)?;
-    process_images(document, document_html, base_url)?;
-    process_lists(document, document_html)?;
-    process_code_blocks(document, document_html)?;
-    process_blockquotes(document, document_html)?;
+    let ctx = ParseContext {
+        base_url,
+        options,
+        expanded_abbreviations: RefCell::new(HashSet::new()),
+    };
+    process_blocks(document, document_html, &ctx)?;
+    process_links(document, document_html, &ctx)?;
+    process_images(document, document_html, &ctx)?;
+    process_abbreviations(document, document_html)?;
+    process_meta(document, document_html, base_url)?;
     Ok(())
 }
 
-/// Process heading elements (h1-h6)
-fn process_headings(document: &mut Document, document_html: &Html) -> Result<(), MarkdownError> {
-    for i in 1..=6 {
-        let heading_selector = Selector::parse(&format!("h{}", i))
-            .map_err(|e| MarkdownError::SelectorError(e.to_string()))?;
-
-        for element in document_html.select(&heading_selector) {
-            let text = element.text().collect::<String>().trim().to_string();
-            if !text.is_empty() {
-                document.headings.push(Heading {
-                    level: i as u8,
-                    text,
-                });
-            }
-        }
-    }
+/// Record the page metadata once the content has been collected
+fn process_meta(
+    document: &mut Document,
+    document_html: &Html,
+    base_url: &Url,
+) -> Result<(), MarkdownError> {
+    document.meta = DocumentMeta {
+        title: document.title.clone(),
+        source_url: document.base_url.clone(),
+        fetched_at: utc_timestamp(SystemTime::now()),
+        word_count: document.stats().word_count,
+        link_count: document.links.len(),
+        image_count: document.images.len(),
+        description: meta_tag(document_html, "description"),
+        keywords: meta_tag(document_html, "keywords")
+            .map(|keywords| {
+                keywords
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|keyword| !keyword.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default(),
+        author: meta_tag(document_html, "author"),
+        generator: meta_tag(document_html, "generator"),
+        language: declared_language(document_html),
+        canonical_url: html_parser::canonical_url(document_html, base_url),
+        alternates: html_parser::alternates(document_html, base_url)
+            .into_iter()
+            .map(|(hreflang, url)| LanguageAlternate { hreflang, url })
+            .collect(),
+        robots: html_parser::robots_directives(document_html),
+        ..DocumentMeta::default()
+    };
     Ok(())
 }
 
-/// Process paragraph elements
-fn process_paragraphs(document: &mut Document, document_html: &Html) -> Result<(), MarkdownError> {
-    let p_selector =
-        Selector::parse("p").map_err(|e| MarkdownError::SelectorError(e.to_string()))?;
-    for element in document_html.select(&p_selector) {
-        let text = element.text().collect::<String>().trim().to_string();
-        // Assume HTML cleaning has removed script content; just check for non-empty text
-        if !text.is_empty() {
-            document.paragraphs.push(text);
-        }
+/// The language the page declares, checking `<html lang>`, then
+/// `<meta http-equiv="content-language">`, then `og:locale`
+fn declared_language(document_html: &Html) -> Option<String> {
+    let root = document_html.root_element();
+    let http_equiv = Selector::parse("meta[http-equiv][content]")
+        .ok()
+        .and_then(|selector| {
+            document_html
+                .select(&selector)
+                .find(|meta| {
+                    meta.value()
+                        .attr("http-equiv")
+                        .is_some_and(|name| name.trim().eq_ignore_ascii_case("content-language"))
+                })
+                .and_then(|meta| meta.value().attr("content"))
+        });
+    let og_locale = Selector::parse("meta[property][content]")
+        .ok()
+        .and_then(|selector| {
+            document_html
+                .select(&selector)
+                .find(|meta| {
+                    meta.value()
+                        .attr("property")
+                        .is_some_and(|name| name.trim().eq_ignore_ascii_case("og:locale"))
+                })
+                .and_then(|meta| meta.value().attr("content"))
+        });
+
+    [root.value().attr("lang"), http_equiv, og_locale]
+        .into_iter()
+        .flatten()
+        .find_map(primary_language_tag)
+}
+
+/// Normalize a language tag or locale to its BCP 47 primary subtag: `en-US`, `EN`
+/// and `en_GB` all become `en`. The header form `de, en` uses its first language.
+fn primary_language_tag(tag: &str) -> Option<String> {
+    let primary = tag.split(',').next()?.trim().split(['-', '_']).next()?;
+    ((2..=3).contains(&primary.len()) && primary.chars().all(|c| c.is_ascii_alphabetic()))
+        .then(|| primary.to_ascii_lowercase())
+}
+
+/// Guess the language of the paragraphs, `None` unless the guess is reliable
+#[cfg(feature = "language_detection")]
+fn detect_language(paragraphs: &[String]) -> Option<String> {
+    use whatlang::Lang;
+
+    let info = whatlang::detect(&paragraphs.join("\n"))?;
+    if !info.is_reliable() {
+        return None;
     }
-    Ok(())
+    // whatlang reports ISO 639-3 codes; BCP 47 uses the two-letter ISO 639-1 ones
+    let code = match info.lang() {
+        Lang::Afr => "af",
+        Lang::Aka => "ak",
+        Lang::Amh => "am",
+        Lang::Ara => "ar",
+        Lang::Aze => "az",
+        Lang::Bel => "be",
+        Lang::Ben => "bn",
+        Lang::Bul => "bg",
+        Lang::Cat => "ca",
+        Lang::Ces => "cs",
+        Lang::Cmn => "zh",
+        Lang::Dan => "da",
+        Lang::Deu => "de",
+        Lang::Ell => "el",
+        Lang::Eng => "en",
+        Lang::Epo => "eo",
+        Lang::Est => "et",
+        Lang::Fin => "fi",
+        Lang::Fra => "fr",
+        Lang::Guj => "gu",
+        Lang::Heb => "he",
+        Lang::Hin => "hi",
+        Lang::Hrv => "hr",
+        Lang::Hun => "hu",
+        Lang::Hye => "hy",
+        Lang::Ind => "id",
+        Lang::Ita => "it",
+        Lang::Jav => "jv",
+        Lang::Jpn => "ja",
+        Lang::Kan => "kn",
+        Lang::Kat => "ka",
+        Lang::Khm => "km",
+        Lang::Kor => "ko",
+        Lang::Lat => "la",
+        Lang::Lav => "lv",
+        Lang::Lit => "lt",
+        Lang::Mal => "ml",
+        Lang::Mar => "mr",
+        Lang::Mkd => "mk",
+        Lang::Mya => "my",
+        Lang::Nep => "ne",
+        Lang::Nld => "nl",
+        Lang::Nob => "nb",
+        Lang::Ori => "or",
+        Lang::Pan => "pa",
+        Lang::Pes => "fa",
+        Lang::Pol => "pl",
+        Lang::Por => "pt",
+        Lang::Ron => "ro",
+        Lang::Rus => "ru",
+        Lang::Sin => "si",
+        Lang::Slk => "sk",
+        Lang::Slv => "sl",
+        Lang::Sna => "sn",
+        Lang::Spa => "es",
+        Lang::Srp => "sr",
+        Lang::Swe => "sv",
+        Lang::Tam => "ta",
+        Lang::Tel => "te",
+        Lang::Tgl => "tl",
+        Lang::Tha => "th",
+        Lang::Tuk => "tk",
+        Lang::Tur => "tr",
+        Lang::Ukr => "uk",
+        Lang::Urd => "ur",
+        Lang::Uzb => "uz",
+        Lang::Vie => "vi",
+        Lang::Yid => "yi",
+        Lang::Zul => "zu",
+    };
+    Some(code.to_string())
 }
 
-/// Process link elements
-fn process_links(
+/// Content of the first non-empty `<meta name="...">` with the given (case-insensitive) name
+fn meta_tag(document_html: &Html, name: &str) -> Option<String> {
+    let selector = Selector::parse("meta[name][content]").ok()?;
+    document_html
+        .select(&selector)
+        .filter(|meta| {
+            meta.value()
+                .attr("name")
+                .is_some_and(|n| n.trim().eq_ignore_ascii_case(name))
+        })
+        .filter_map(|meta| meta.value().attr("content"))
+        .map(|content| content.split_whitespace().collect::<Vec<_>>().join(" "))
+        .find(|content| !content.is_empty())
+}
+
+/// Format a time as an RFC 3339 UTC timestamp with second precision
+fn utc_timestamp(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let (days, secs_of_day) = ((secs / 86_400) as i64, secs % 86_400);
+
+    // Civil date from days since the epoch (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        secs_of_day / 3_600,
+        secs_of_day % 3_600 / 60,
+        secs_of_day % 60
+    )
+}
+
+/// Walk the document body in DOM order, recording block-level elements
+///
+/// The per-type collections (`headings`, `paragraphs`, ...) are derived from the
+/// ordered blocks so both views always agree.
+fn process_blocks(
     document: &mut Document,
     document_html: &Html,
-    base_url: &Url,
+    ctx: &ParseContext,
 ) -> Result<(), MarkdownError> {
-    let a_selector =
-        Selector::parse("a[href]").map_err(|e| MarkdownError::SelectorError(e.to_string()))?;
-    for element in document_html.select(&a_selector) {
-        if let Some(href) = element.value().attr("href") {
-            let text = element.text().collect::<String>().trim().to_string();
-            if !text.is_empty()
-                && let Some(absolute_url) = resolve_url_against_base(base_url, href)
-            {
-                document.links.push(Link {
-                    text,
-                    url: absolute_url,
-                });
-            }
+    let body_selector =
+        Selector::parse("body").map_err(|e| MarkdownError::SelectorError(e.to_string()))?;
+    let root = document_html
+        .select(&body_selector)
+        .next()
+        .unwrap_or_else(|| document_html.root_element());
+
+    document.blocks = collect_blocks(&root, ctx);
+    dedupe_heading_ids(&mut document.blocks, &mut HashSet::new());
+
+    for block in &document.blocks {
+        match block {
+            Block::Heading(heading) => document.headings.push(heading.clone()),
+            Block::Paragraph { text } => document.paragraphs.push(text.clone()),
+            Block::List(list) => document.lists.push(list.clone()),
+            Block::CodeBlock(code_block) => document.code_blocks.push(code_block.clone()),
+            Block::Blockquote { blocks } => document
+                .blockquotes
+                .push(blocks_to_markdown(blocks, &ConversionOptions::default())),
+            Block::Table(table) => document.tables.push(table.clone()),
+            Block::DefinitionList(list) => document.definition_lists.push(list.clone()),
+            Block::Embed(embed) => document.embeds.push(embed.clone()),
+            Block::Image(_) | Block::Hr | Block::Details { .. } => {}
         }
     }
     Ok(())
 }
 
-/// Process image elements
-fn process_images(
-    document: &mut Document,
-    document_html: &Html,
-    base_url: &Url,
-) -> Result<(), MarkdownError> {
-    let img_selector =
-        Selector::parse("img[src]").map_err(|e| MarkdownError::SelectorError(e.to_string()))?;
-    for element in document_html.select(&img_selector) {
-        if let Some(src) = element.value().attr("src") {
-            let alt = element.value().attr("alt").unwrap_or("image").to_string();
-            if let Some(absolute_url) = resolve_url_against_base(base_url, src) {
-                document.images.push(Image {
-                    alt,
-                    src: absolute_url,
-                });
+/// Make heading ids unique, suffixing repeats with `-1`, `-2`, ... like GitHub slugs
+fn dedupe_heading_ids(blocks: &mut [Block], seen: &mut HashSet<String>) {
+    for block in blocks {
+        match block {
+            Block::Heading(Heading { id: Some(id), .. }) if !seen.insert(id.clone()) => {
+                let unique = (1..)
+                    .map(|n| format!("{}-{}", id, n))
+                    .find(|candidate| !seen.contains(candidate))
+                    .unwrap_or_default();
+                seen.insert(unique.clone());
+                *id = unique;
             }
+            Block::Blockquote { blocks } | Block::Details { blocks, .. } => {
+                dedupe_heading_ids(blocks, seen)
+            }
+            _ => {}
         }
     }
-    Ok(())
 }
 
-/// Process list elements (both ordered and unordered)
-fn process_lists(document: &mut Document, document_html: &Html) -> Result<(), MarkdownError> {
-    let li_selector =
-        Selector::parse("li").map_err(|e| MarkdownError::SelectorError(e.to_string()))?;
+/// Collect the blocks contained in an element, grouping loose inline content into paragraphs
+fn collect_blocks(parent: &ElementRef, ctx: &ParseContext) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    let mut inline_run: Vec<NodeRef<Node>> = Vec::new();
 
-    // Process unordered lists
-    let ul_selector =
-        Selector::parse("ul").map_err(|e| MarkdownError::SelectorError(e.to_string()))?;
-    for ul in document_html.select(&ul_selector) {
-        if let Some(list) = extract_list_items(&ul, &li_selector, false) {
-            document.lists.push(list);
+    for child in parent.children() {
+        match child.value() {
+            Node::Text(_) => inline_run.push(child),
+            Node::Element(_) if is_mathjax_rendering(child) => {}
+            Node::Element(_) if let Some(math) = math_expression(child) => {
+                if math.display {
+                    flush_inline_run(&mut inline_run, &mut blocks, ctx);
+                    if let Some(text) = math.to_markdown() {
+                        blocks.push(Block::Paragraph { text });
+                    }
+                } else {
+                    inline_run.push(child);
+                }
+            }
+            Node::Element(element) if is_inline_element(element.name()) => inline_run.push(child),
+            Node::Element(_) => {
+                flush_inline_run(&mut inline_run, &mut blocks, ctx);
+                if let Some(child_element) = ElementRef::wrap(child) {
+                    collect_element_blocks(&child_element, ctx, &mut blocks);
+                }
+            }
+            _ => {}
         }
     }
+    flush_inline_run(&mut inline_run, &mut blocks, ctx);
+
+    blocks
+}
 
-    // Process ordered lists
-    let ol_selector =
-        Selector::parse("ol").map_err(|e| MarkdownError::SelectorError(e.to_string()))?;
-    for ol in document_html.select(&ol_selector) {
-        if let Some(list) = extract_list_items(&ol, &li_selector, true) {
-            document.lists.push(list);
+/// Convert a single block-level element, recursing into generic containers
+fn collect_element_blocks(element: &ElementRef, ctx: &ParseContext, blocks: &mut Vec<Block>) {
+    match element.value().name() {
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => process_heading(element, blocks),
+        "p" => process_paragraph(&element.children().collect::<Vec<_>>(), ctx, blocks),
+        "ul" => process_list(element, false, ctx, blocks),
+        "ol" => process_list(element, true, ctx, blocks),
+        "pre" => process_code_block(element, blocks),
+        "blockquote" => process_blockquote(element, ctx, blocks),
+        "table" => process_table(element, ctx.options.table_span_fill, blocks),
+        "dl" => process_definition_list(element, ctx, blocks),
+        "figcaption" => process_figcaption(element, ctx, blocks),
+        "details" => process_details(element, ctx, blocks),
+        // The summary of a details section is read by process_details
+        "summary" if is_child_of(element, "details") => {}
+        "hr" => {
+            // Consecutive rules carry no extra meaning
+            if !matches!(blocks.last(), Some(Block::Hr)) {
+                blocks.push(Block::Hr);
+            }
+        }
+        "img" => {
+            if let Some(image) = image_from_element(element, ctx) {
+                blocks.push(Block::Image(image));
+            }
+        }
+        "video" | "audio" | "iframe" => {
+            if let Some(embed) = embed_from_element(element, ctx) {
+                blocks.push(Block::Embed(embed));
+            }
         }
+        "head" | "script" | "style" | "template" | "noscript" => {}
+        _ => blocks.extend(collect_blocks(element, ctx)),
     }
+}
 
-    Ok(())
+/// Elements that flow inside a paragraph rather than starting a new block
+fn is_inline_element(name: &str) -> bool {
+    matches!(
+        name,
+        "a" | "abbr"
+            | "b"
+            | "bdi"
+            | "bdo"
+            | "big"
+            | "br"
+            | "cite"
+            | "code"
+            | "data"
+            | "del"
+            | "dfn"
+            | "em"
+            | "font"
+            | "i"
+            | "ins"
+            | "kbd"
+            | "label"
+            | "mark"
+            | "q"
+            | "s"
+            | "samp"
+            | "small"
+            | "span"
+            | "strike"
+            | "strong"
+            | "sub"
+            | "sup"
+            | "time"
+            | "tt"
+            | "u"
+            | "var"
+            | "wbr"
+    )
 }
 
-/// Process code block elements
-fn process_code_blocks(document: &mut Document, document_html: &Html) -> Result<(), MarkdownError> {
-    let pre_selector =
-        Selector::parse("pre, code").map_err(|e| MarkdownError::SelectorError(e.to_string()))?;
-    for element in document_html.select(&pre_selector) {
-        let text = element.text().collect::<String>().trim().to_string();
-        if !text.is_empty() {
-            let lang = element
-                .value()
-                .classes()
-                .find(|c| c.starts_with("language-"))
-                .map(|c| c.strip_prefix("language-").unwrap_or(""))
-                .unwrap_or("")
-                .to_string();
-
-            document.code_blocks.push(CodeBlock {
-                language: lang,
-                code: text,
-            });
-        }
+/// Turn pending inline nodes into a paragraph block
+fn flush_inline_run(
+    inline_run: &mut Vec<NodeRef<Node>>,
+    blocks: &mut Vec<Block>,
+    ctx: &ParseContext,
+) {
+    if !inline_run.is_empty() {
+        process_paragraph(inline_run, ctx, blocks);
+        inline_run.clear();
     }
-    Ok(())
 }
 
-/// Process blockquote elements
-fn process_blockquotes(document: &mut Document, document_html: &Html) -> Result<(), MarkdownError> {
-    let blockquote_selector =
-        Selector::parse("blockquote").map_err(|e| MarkdownError::SelectorError(e.to_string()))?;
-    for element in document_html.select(&blockquote_selector) {
-        let text = element.text().collect::<String>().trim().to_string();
-        if !text.is_empty() {
-            document.blockquotes.push(text);
-        }
+/// Process a heading element (h1-h6)
+fn process_heading(element: &ElementRef, blocks: &mut Vec<Block>) {
+    let level = element.value().name()[1..].parse::<u8>().unwrap_or(1);
+    let text = inline_text(element.children());
+    if !text.is_empty() {
+        blocks.push(Block::Heading(Heading {
+            level,
+            text,
+            id: html_parser::heading_anchor(element),
+        }));
     }
-    Ok(())
 }
 
-/// Helper function to resolve URLs against a base URL
-fn resolve_url_against_base(base_url: &Url, href: &str) -> Option<String> {
-    let href_trimmed = href.trim();
-    if href_trimmed.is_empty()
-        || href_trimmed.starts_with('#')
-        || href_trimmed.to_lowercase().starts_with("javascript:")
-        || href_trimmed.to_lowercase().starts_with("data:")
-        || href_trimmed.contains(' ')
-        || href_trimmed.starts_with(':')
-        || href_trimmed.contains(":::")
-    {
-        return None;
+/// Process paragraph content, followed by any images it contains
+fn process_paragraph(nodes: &[NodeRef<Node>], ctx: &ParseContext, blocks: &mut Vec<Block>) {
+    let text = InlineRenderer::render(nodes.iter().copied(), ctx);
+    // Assume HTML cleaning has removed script content; just check for non-empty text
+    if !text.is_empty() {
+        blocks.push(Block::Paragraph { text });
     }
 
-    if let Ok(u) = base_url.join(href_trimmed) {
-        return Some(u.to_string());
+    // Images and embeds can't live inside a markdown paragraph, so they follow it
+    for node in nodes {
+        if let Some(element) = ElementRef::wrap(*node) {
+            for media in element.descendent_elements() {
+                let block = match media.value().name() {
+                    "img" => image_from_element(&media, ctx).map(Block::Image),
+                    "video" | "audio" | "iframe" => {
+                        embed_from_element(&media, ctx).map(Block::Embed)
+                    }
+                    _ => None,
+                };
+                blocks.extend(block);
+            }
+        }
     }
-    if let Ok(u) = url::Url::parse(href_trimmed) {
-        return Some(u.to_string());
+}
+
+/// Collect the text of inline nodes, collapsing whitespace
+fn inline_text<'a>(nodes: impl Iterator<Item = NodeRef<'a, Node>>) -> String {
+    let mut text = String::new();
+    for node in nodes {
+        push_node_text(node, &mut text);
     }
-    None
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
 }
 
-/// Helper function to extract list items
-fn extract_list_items(
-    list_element: &scraper::ElementRef,
-    li_selector: &Selector,
-    ordered: bool,
-) -> Option<List> {
-    let mut items = Vec::new();
-    for li in list_element.select(li_selector) {
-        let text = li.text().collect::<String>().trim().to_string();
-        if !text.is_empty() {
-            items.push(text);
+/// Append the text below a node, skipping `javascript:` anchors
+fn push_node_text(node: NodeRef<Node>, text: &mut String) {
+    match node.value() {
+        Node::Text(t) => text.push_str(&escape_markdown(&t.text)),
+        Node::Element(element) if element.name() == "br" => text.push(' '),
+        Node::Element(element) if is_code_span_element(element.name()) => {
+            text.push_str(&code_span(node))
         }
+        Node::Element(_) if is_mathjax_rendering(node) => {}
+        Node::Element(_) if let Some(math) = math_expression(node) => {
+            text.push_str(&math.to_markdown().unwrap_or_default())
+        }
+        Node::Element(element) if !is_script_link(element) => {
+            for child in node.children() {
+                push_node_text(child, text);
+            }
+        }
+        _ => {}
     }
+}
 
-    if !items.is_empty() {
-        Some(List { ordered, items })
-    } else {
-        None
+/// Placeholder for `<br>` while whitespace is still being collapsed
+const HARD_BREAK: char = '\u{E000}';
+
+/// Placeholder keeping a non-breaking space from being collapsed with other whitespace
+const NBSP_PLACEHOLDER: char = '\u{E001}';
+
+/// Backslash-escape the markup in plain text, such as a text node for markdown
+///
+/// The parser has already decoded the node's references, so decoded `&lt;b&gt;` must
+/// not turn into real HTML: a `<` that would open a tag, comment or autolink is
+/// backslash-escaped, as is a `&` that would start a reference (`&amp;lt;` in the
+/// source). So are brackets, backticks and the `*`, `_` and `~~` that could delimit
+/// emphasis, leaving every markdown construct in the output one the renderer wrote;
+/// a `*` between spaces (`5 * 3`) or a `_` inside a word (`snake_case`) cannot, and
+/// stays as it is.
+fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    let mut previous = None;
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let next = chars.peek().map(|&(_, next)| next);
+        let spaced = |c: Option<char>| c.is_some_and(char::is_whitespace);
+        let alphanumeric = |c: Option<char>| c.is_some_and(char::is_alphanumeric);
+        let escape = match c {
+            '<' => next
+                .is_some_and(|next| next.is_ascii_alphabetic() || matches!(next, '/' | '!' | '?')),
+            '\\' => next.is_some_and(|next| next.is_ascii_punctuation()),
+            '&' => starts_reference(&text[i..]),
+            '[' | ']' | '`' => true,
+            '*' => !(spaced(previous) && spaced(next)),
+            '_' => {
+                !(spaced(previous) && spaced(next) || alphanumeric(previous) && alphanumeric(next))
+            }
+            '~' => previous == Some('~') || next == Some('~'),
+            _ => false,
+        };
+        if escape {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+        previous = Some(c);
     }
+    escaped
 }
 
-/// Convert document to markdown format
-pub fn document_to_markdown(document: &Document) -> String {
-    let mut markdown_content = format!("# {}\n\n", document.title);
-
-    // Add headings
-    for heading in &document.headings {
-        let heading_prefix = "#".repeat(heading.level as usize);
-        markdown_content.push_str(&format!("{} {}\n\n", heading_prefix, heading.text));
+/// Whether `text` starts with a character reference a markdown renderer would decode
+fn starts_reference(text: &str) -> bool {
+    let rest = &text[1..];
+    let end = rest
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '#'))
+        .unwrap_or(rest.len());
+    let name = &rest[..end];
+    if !rest[end..].starts_with(';') {
+        return false;
+    }
+    match name.strip_prefix('#') {
+        Some(number) => match number.strip_prefix(['x', 'X']) {
+            Some(hex) => (1..=6).contains(&hex.len()) && hex.chars().all(|c| c.is_ascii_hexdigit()),
+            None => (1..=7).contains(&number.len()) && number.chars().all(|c| c.is_ascii_digit()),
+        },
+        None => {
+            (1..=32).contains(&name.len())
+                && name.starts_with(|c: char| c.is_ascii_alphabetic())
+                && name.chars().all(|c| c.is_ascii_alphanumeric())
+        }
     }
+}
 
-    // Add paragraphs
-    for paragraph in &document.paragraphs {
-        markdown_content.push_str(&format!("{}\n\n", paragraph));
+/// Renders inline nodes as markdown, emitting emphasis markers and inline links
+///
+/// Hard line breaks come out as `\n` and doubled breaks as `\n\n`; the markdown
+/// renderer decides how a single break is encoded.
+struct InlineRenderer<'u> {
+    ctx: &'u ParseContext<'u>,
+    out: String,
+    /// Marker and output length right after the most recently closed emphasis run
+    last_closed: Option<(&'static str, usize)>,
+}
+
+impl<'u> InlineRenderer<'u> {
+    fn new(ctx: &'u ParseContext<'u>) -> Self {
+        Self {
+            ctx,
+            out: String::new(),
+            last_closed: None,
+        }
     }
 
-    // Add links
-    for link in &document.links {
-        markdown_content.push_str(&format!("[{}]({})\n\n", link.text, link.url));
+    fn render<'a>(nodes: impl Iterator<Item = NodeRef<'a, Node>>, ctx: &ParseContext) -> String {
+        let mut renderer = InlineRenderer::new(ctx);
+        for node in nodes {
+            renderer.render_node(node);
+        }
+        finish_inline_text(&renderer.out).replace(NBSP_PLACEHOLDER, "\u{a0}")
     }
 
-    // Add images
-    for image in &document.images {
-        markdown_content.push_str(&format!("![{}]({})\n\n", image.alt, image.src));
+    /// Render the children of `node` into a fresh buffer sharing this renderer's settings
+    fn render_nested(&self, node: NodeRef<Node>) -> String {
+        let mut inner = InlineRenderer::new(self.ctx);
+        inner.render_children(node);
+        inner.out
     }
 
-    // Add lists
-    for list in &document.lists {
-        if list.ordered {
-            for (i, item) in list.items.iter().enumerate() {
-                markdown_content.push_str(&format!("{}. {}\n", i + 1, item));
+    fn render_node(&mut self, node: NodeRef<Node>) {
+        match node.value() {
+            Node::Text(t) => {
+                let text = escape_markdown(&t.text);
+                if self.ctx.options.preserve_nbsp {
+                    self.out
+                        .push_str(&text.replace('\u{a0}', &NBSP_PLACEHOLDER.to_string()));
+                } else {
+                    self.out.push_str(&text);
+                }
             }
-        } else {
-            for item in &list.items {
-                markdown_content.push_str(&format!("- {}\n", item));
+            Node::Element(_) if is_mathjax_rendering(node) => {}
+            Node::Element(_) if let Some(math) = math_expression(node) => {
+                self.out.push_str(&math.to_markdown().unwrap_or_default())
             }
+            Node::Element(element) => match element.name() {
+                "strong" | "b" => self.render_emphasis(node, "**"),
+                "em" | "i" => self.render_emphasis(node, "*"),
+                "del" | "s" | "strike" if self.ctx.options.flavor.strikethrough() => {
+                    self.render_emphasis(node, "~~")
+                }
+                "a" if is_script_link(element) => {}
+                "a" => self.render_link(node, element),
+                name if is_code_span_element(name) => self.out.push_str(&code_span(node)),
+                "br" => self.out.push(HARD_BREAK),
+                "sub" => self.render_script(node, "sub"),
+                "sup" => self.render_script(node, "sup"),
+                "abbr" | "dfn" => self.render_abbreviation(node),
+                // Fallback content of media elements, which become separate blocks
+                "video" | "audio" | "iframe" => {}
+                _ => self.render_children(node),
+            },
+            _ => {}
         }
-        markdown_content.push('\n');
     }
 
-    // Add code blocks
-    for code_block in &document.code_blocks {
-        markdown_content.push_str(&format!(
-            "```{}\n{}\n```\n\n",
-            code_block.language, code_block.code
-        ));
+    fn render_children(&mut self, node: NodeRef<Node>) {
+        for child in node.children() {
+            self.render_node(child);
+        }
     }
 
-    // Add blockquotes
-    for blockquote in &document.blockquotes {
-        let quoted = blockquote
-            .lines()
-            .map(|line| format!("> {}", line))
-            .collect::<Vec<String>>()
-            .join("\n");
-        markdown_content.push_str(&format!("{}\n\n", quoted));
+    /// Render an `abbr` or `dfn`, following its first occurrence in running text with
+    /// the title in parentheses when abbreviations are expanded inline
+    fn render_abbreviation(&mut self, node: NodeRef<Node>) {
+        self.render_children(node);
+        if self.ctx.options.abbreviation_style != AbbreviationStyle::Inline {
+            return;
+        }
+        let Some(abbreviation) = ElementRef::wrap(node).and_then(|e| abbreviation_of(&e)) else {
+            return;
+        };
+        let first = self
+            .ctx
+            .expanded_abbreviations
+            .borrow_mut()
+            .insert(abbreviation.abbr);
+        if first {
+            self.out
+                .push_str(&format!(" ({})", escape_markdown(&abbreviation.title)));
+        }
     }
 
-    // Clean up extra newlines
-    markdown_content
-        .replace("\n\n\n\n", "\n\n")
-        .replace("\n\n\n", "\n\n")
-        .trim()
-        .to_string()
+    /// Wrap the rendered children in `marker`, keeping surrounding whitespace outside it
+    fn render_emphasis(&mut self, node: NodeRef<Node>, marker: &'static str) {
+        let content = self.render_nested(node);
+        // A break at the edge of an element carries no meaning
+        let trimmed = content.trim_matches(|c: char| c.is_whitespace() || c == HARD_BREAK);
+
+        // Empty or punctuation-only emphasis would only produce stray markers
+        if trimmed
+            .chars()
+            .all(|c| c.is_whitespace() || c.is_ascii_punctuation() || c == HARD_BREAK)
+        {
+            self.out.push_str(&content);
+            return;
+        }
+
+        if content.starts_with(char::is_whitespace) {
+            self.out.push(' ');
+        }
+
+        // Adjacent runs of the same emphasis merge into one: **a****b** -> **ab**
+        let merging = self.last_closed == Some((marker, self.out.len()));
+        let previous = self.out.chars().next_back();
+        let next = if content.ends_with(char::is_whitespace) {
+            Some(' ')
+        } else {
+            following_char(node)
+        };
+
+        // Delimiters only work when they flank the text: `**bold,**next` stays literal,
+        // so plain punctuation at an edge moves outside them, as in `**bold**,next`
+        let delimiter = marker.chars().next().unwrap_or('*');
+        let (mut lead, mut core, mut tail) = ("", trimmed, "");
+        if !merging && !opener_flanks(previous, core.trim_start_matches(delimiter)) {
+            (lead, core) = core.split_at(movable_prefix(core));
+        }
+        if !closer_flanks(core.trim_end_matches(delimiter), next) {
+            (core, tail) = core.split_at(core.len() - movable_suffix(core));
+        }
+        let flanks = (merging
+            || opener_flanks(
+                lead.chars().next_back().or(previous),
+                core.trim_start_matches(delimiter),
+            ))
+            && closer_flanks(
+                core.trim_end_matches(delimiter),
+                tail.chars().next().or(next),
+            );
+
+        if !flanks {
+            // Anything else at the edge may be markup of its own, so use the HTML tag
+            let tag = match marker {
+                "**" => "strong",
+                "*" => "em",
+                _ => "del",
+            };
+            self.out.push_str(&format!("<{tag}>{trimmed}</{tag}>"));
+        } else {
+            self.out.push_str(lead);
+            if merging {
+                self.out.truncate(self.out.len() - marker.len());
+            } else {
+                self.out.push_str(marker);
+            }
+            self.out.push_str(core);
+            self.out.push_str(marker);
+            self.last_closed = Some((marker, self.out.len()));
+            self.out.push_str(tail);
+        }
+
+        if content.ends_with(char::is_whitespace) {
+            self.out.push(' ');
+        }
+    }
+
+    /// Render `<sub>`/`<sup>` content according to the configured style
+    fn render_script(&mut self, node: NodeRef<Node>, tag: &'static str) {
+        let options = self.ctx.options;
+        if tag == "sup"
+            && options.footnote_references
+            && let Some(label) = footnote_reference_label(node)
+        {
+            self.out.push_str(&format!("[^{}]", label));
+            return;
+        }
+
+        let content = self.render_nested(node);
+        let unicode = match options.sub_sup_style {
+            SubSupStyle::Unicode => unicode_script(content.trim(), tag == "sup"),
+            _ => None,
+        };
+        match (options.sub_sup_style, unicode) {
+            (SubSupStyle::Plain, _) => self.out.push_str(&content),
+            (_, Some(mapped)) => self.out.push_str(&mapped),
+            _ => self
+                .out
+                .push_str(&format!("<{tag}>{}</{tag}>", content.trim())),
+        }
+    }
+
+    /// Render an anchor as `[text](url)`, falling back to the URL when the text is empty
+    fn render_link(&mut self, node: NodeRef<Node>, element: &scraper::node::Element) {
+        let Some(url) = element
+            .attr("href")
+            .and_then(|href| resolve_url_against_base(self.ctx.base_url, href))
+        else {
+            // Unresolvable targets (fragments, invalid URLs) keep their text only
+            self.render_children(node);
+            return;
+        };
+
+        let content = self.render_nested(node);
+        let text = content.split_whitespace().collect::<Vec<_>>().join(" ");
+        let text = if text.is_empty() { url.clone() } else { text };
+
+        if content.starts_with(char::is_whitespace) {
+            self.out.push(' ');
+        }
+        let title = title_attribute(element);
+        let flavor = self.ctx.options.flavor;
+        if text == url && title.is_none() {
+            // Only web URLs are picked up bare; anything else needs the `<...>` form
+            if flavor.bare_autolinks()
+                && (url.starts_with("http://") || url.starts_with("https://"))
+            {
+                self.out.push_str(&url);
+            } else {
+                self.out.push_str(&format!("<{}>", url));
+            }
+        } else {
+            self.out.push_str(&format!(
+                "[{}]({})",
+                text,
+                link_destination(&url, title.as_deref())
+            ));
+        }
+        if content.ends_with(char::is_whitespace) {
+            self.out.push(' ');
+        }
+    }
+}
+
+/// Collapse whitespace and turn break placeholders into newlines
+///
+/// Breaks at the start or end are dropped and consecutive breaks become a paragraph break.
+fn finish_inline_text(raw: &str) -> String {
+    let mut text = String::new();
+    let mut blank_segments = 0;
+    for segment in raw.split(HARD_BREAK) {
+        let segment = segment.split_whitespace().collect::<Vec<_>>().join(" ");
+        if segment.is_empty() {
+            blank_segments += 1;
+            continue;
+        }
+        if !text.is_empty() {
+            text.push_str(if blank_segments > 0 { "\n\n" } else { "\n" });
+        }
+        text.push_str(&segment);
+        blank_segments = 0;
+    }
+    text
+}
+
+/// Punctuation that can move outside emphasis delimiters without changing the markup
+const MOVABLE_PUNCTUATION: [char; 8] = [',', '.', ';', ':', '!', '?', '\'', '"'];
+
+/// The first character of the text after `node`, if it has a following sibling with text
+fn following_char(node: NodeRef<Node>) -> Option<char> {
+    node.next_siblings()
+        .flat_map(|sibling| sibling.descendants())
+        .find_map(|descendant| match descendant.value() {
+            Node::Text(text) => text.chars().next(),
+            _ => None,
+        })
+}
+
+/// Whether an opening delimiter between `previous` and `text` is left-flanking
+fn opener_flanks(previous: Option<char>, text: &str) -> bool {
+    let punctuation_after = text.starts_with(|c: char| c.is_ascii_punctuation());
+    !punctuation_after || previous.is_none_or(|c| c.is_whitespace() || c.is_ascii_punctuation())
+}
+
+/// Whether a closing delimiter between `text` and `next` is right-flanking
+fn closer_flanks(text: &str, next: Option<char>) -> bool {
+    let punctuation_before = text.ends_with(|c: char| c.is_ascii_punctuation());
+    !punctuation_before || next.is_none_or(|c| c.is_whitespace() || c.is_ascii_punctuation())
+}
+
+/// Bytes of `MOVABLE_PUNCTUATION` opening `text` right before a letter or digit
+fn movable_prefix(text: &str) -> usize {
+    let rest = text.trim_start_matches(MOVABLE_PUNCTUATION);
+    if rest.starts_with(char::is_alphanumeric) {
+        text.len() - rest.len()
+    } else {
+        0
+    }
+}
+
+/// Bytes of `MOVABLE_PUNCTUATION` closing `text` right after a letter or digit
+fn movable_suffix(text: &str) -> usize {
+    let rest = text.trim_end_matches(MOVABLE_PUNCTUATION);
+    if rest.ends_with(char::is_alphanumeric) {
+        text.len() - rest.len()
+    } else {
+        0
+    }
+}
+
+/// The label of a footnote reference: a superscript holding only an in-page link
+///
+/// `<sup><a href="#fn1">1</a></sup>` and `<sup><a href="#note-2">[2]</a></sup>` give `1` and `2`.
+fn footnote_reference_label(sup: NodeRef<Node>) -> Option<String> {
+    let sup = ElementRef::wrap(sup)?;
+    let mut children = sup.children().filter(|child| match child.value() {
+        Node::Text(t) => !t.trim().is_empty(),
+        _ => child.value().is_element(),
+    });
+    let link = children.next().and_then(ElementRef::wrap)?;
+    if children.next().is_some()
+        || link.value().name() != "a"
+        || !link.value().attr("href")?.trim().starts_with('#')
+    {
+        return None;
+    }
+
+    let label = collapse_whitespace(&link.text().collect::<String>());
+    let label = label.trim_matches(|c| c == '[' || c == ']').trim();
+    (!label.is_empty() && !label.contains(char::is_whitespace)).then(|| label.to_string())
+}
+
+/// Map short content to Unicode sub/superscript characters, if every character has one
+fn unicode_script(text: &str, superscript: bool) -> Option<String> {
+    const MAX_CHARS: usize = 8;
+    if text.is_empty() || text.chars().count() > MAX_CHARS {
+        return None;
+    }
+    text.chars()
+        .map(|c| {
+            if superscript {
+                superscript_char(c)
+            } else {
+                subscript_char(c)
+            }
+        })
+        .collect()
+}
+
+fn superscript_char(c: char) -> Option<char> {
+    Some(match c {
+        '0' => '⁰',
+        '1' => '¹',
+        '2' => '²',
+        '3' => '³',
+        '4' => '⁴',
+        '5' => '⁵',
+        '6' => '⁶',
+        '7' => '⁷',
+        '8' => '⁸',
+        '9' => '⁹',
+        '+' => '⁺',
+        '-' | '−' => '⁻',
+        '=' => '⁼',
+        '(' => '⁽',
+        ')' => '⁾',
+        'i' => 'ⁱ',
+        'n' => 'ⁿ',
+        _ => return None,
+    })
+}
+
+fn subscript_char(c: char) -> Option<char> {
+    Some(match c {
+        '0' => '₀',
+        '1' => '₁',
+        '2' => '₂',
+        '3' => '₃',
+        '4' => '₄',
+        '5' => '₅',
+        '6' => '₆',
+        '7' => '₇',
+        '8' => '₈',
+        '9' => '₉',
+        '+' => '₊',
+        '-' | '−' => '₋',
+        '=' => '₌',
+        '(' => '₍',
+        ')' => '₎',
+        'a' => 'ₐ',
+        'e' => 'ₑ',
+        'o' => 'ₒ',
+        'x' => 'ₓ',
+        'n' => 'ₙ',
+        _ => return None,
+    })
+}
+
+/// Inline elements whose content is code-like and rendered as a backtick span
+fn is_code_span_element(name: &str) -> bool {
+    matches!(name, "code" | "kbd" | "samp" | "var" | "tt")
+}
+
+/// Wrap a node's text in a backtick code span
+///
+/// The fence is one backtick longer than the longest run inside the content, and
+/// content starting or ending with a backtick is padded so the fence stays separate.
+fn code_span(node: NodeRef<Node>) -> String {
+    let text = node
+        .descendants()
+        .filter_map(|n| n.value().as_text().map(|t| t.to_string()))
+        .collect::<String>();
+    let content = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if content.is_empty() {
+        return String::new();
+    }
+
+    let longest_run = content.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat(longest_run + 1);
+    let padding = if content.starts_with('`') || content.ends_with('`') {
+        " "
+    } else {
+        ""
+    };
+    format!("{fence}{padding}{content}{padding}{fence}")
+}
+
+/// Check whether an element is a `javascript:` anchor, which is a UI control rather than a link
+fn is_script_link(element: &scraper::node::Element) -> bool {
+    element.name() == "a"
+        && element
+            .attr("href")
+            .is_some_and(|href| href.trim().to_lowercase().starts_with("javascript:"))
+}
+
+/// A math expression found in the page
+struct MathExpression {
+    /// LaTeX source, when the page carries one
+    tex: Option<String>,
+    /// Flattened text of the rendered math, used when there is no LaTeX source
+    text: String,
+    display: bool,
+}
+
+impl MathExpression {
+    /// `$tex$` or `$$tex$$`, falling back to the flattened text in backticks
+    fn to_markdown(&self) -> Option<String> {
+        match (&self.tex, self.display) {
+            (Some(tex), true) => Some(format!("$${}$$", tex)),
+            (Some(tex), false) => Some(format!("${}$", tex)),
+            (None, _) if !self.text.is_empty() => Some(format!("`{}`", self.text)),
+            (None, _) => None,
+        }
+    }
+}
+
+/// Recognise MathML, MathJax and KaTeX markup and TeX sources (`math/tex` scripts, `data-latex`)
+fn math_expression(node: NodeRef<Node>) -> Option<MathExpression> {
+    let element = ElementRef::wrap(node)?;
+    let value = element.value();
+
+    if value.name() == "script" {
+        return is_tex_script(value).then(|| MathExpression {
+            tex: Some(collapse_whitespace(&element.text().collect::<String>())),
+            text: String::new(),
+            display: value
+                .attr("type")
+                .is_some_and(|kind| kind.contains("mode=display")),
+        });
+    }
+
+    let has_class = |name: &str| value.classes().any(|class| class == name);
+    let display = match value.name() {
+        "math" => value.attr("display") == Some("block"),
+        "mjx-container" => value.attr("display") == Some("true"),
+        _ if has_class("katex-display") => true,
+        _ if has_class("katex") => false,
+        name if value.attr("data-latex").is_some() => name == "div",
+        _ => return None,
+    };
+
+    let tex = value
+        .attr("data-latex")
+        .map(str::to_string)
+        .or_else(|| tex_annotation(&element))
+        .map(|tex| collapse_whitespace(&tex))
+        .filter(|tex| !tex.is_empty());
+
+    Some(MathExpression {
+        tex,
+        text: flattened_math_text(&element),
+        display,
+    })
+}
+
+/// The TeX source MathML carries in `<annotation encoding="application/x-tex">`
+fn tex_annotation(element: &ElementRef) -> Option<String> {
+    element
+        .descendent_elements()
+        .find(|e| {
+            e.value().name() == "annotation"
+                && e.value()
+                    .attr("encoding")
+                    .is_some_and(|encoding| encoding.eq_ignore_ascii_case("application/x-tex"))
+        })
+        .map(|annotation| annotation.text().collect())
+}
+
+/// Text of the MathML inside an element, without its annotations
+///
+/// KaTeX and MathJax output repeat the formula as presentational HTML, so only the
+/// MathML copy is read when there is one.
+fn flattened_math_text(element: &ElementRef) -> String {
+    fn push_text(node: NodeRef<Node>, text: &mut String) {
+        match node.value() {
+            Node::Text(t) => text.push_str(&t.text),
+            Node::Element(e) if e.name().starts_with("annotation") => {}
+            Node::Element(_) => node.children().for_each(|child| push_text(child, text)),
+            _ => {}
+        }
+    }
+
+    let source = if element.value().name() == "math" {
+        Some(*element)
+    } else {
+        element
+            .descendent_elements()
+            .find(|e| e.value().name() == "math")
+    };
+    let mut text = String::new();
+    push_text(*source.unwrap_or(*element), &mut text);
+    collapse_whitespace(&text)
+}
+
+/// Check whether an element is MathJax 2 output rendered from a neighbouring `math/tex` script
+///
+/// MathJax 2 keeps the script and inserts preview and rendered copies before it; the
+/// copies are skipped so the formula is emitted once, from its TeX source.
+fn is_mathjax_rendering(node: NodeRef<Node>) -> bool {
+    let is_mathjax_output = |node: &NodeRef<Node>| {
+        node.value().as_element().is_some_and(|element| {
+            element.name() != "mjx-container"
+                && element.classes().any(|class| class.starts_with("MathJax"))
+        })
+    };
+
+    is_mathjax_output(&node)
+        && node
+            .next_siblings()
+            .filter(|sibling| sibling.value().is_element())
+            .find(|sibling| !is_mathjax_output(sibling))
+            .and_then(|sibling| sibling.value().as_element().map(is_tex_script))
+            .unwrap_or(false)
+}
+
+/// Check whether an element is a `<script type="math/tex">` holding TeX source
+fn is_tex_script(element: &scraper::node::Element) -> bool {
+    element.name() == "script"
+        && element
+            .attr("type")
+            .is_some_and(|kind| kind.trim().to_ascii_lowercase().starts_with("math/tex"))
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Process link elements
+///
+/// With `content_links_only`, links in navigation and other page furniture are
+/// skipped, as are links outside the main content region when the page has one.
+fn process_links(
+    document: &mut Document,
+    document_html: &Html,
+    ctx: &ParseContext,
+) -> Result<(), MarkdownError> {
+    let a_selector =
+        Selector::parse("a[href]").map_err(|e| MarkdownError::SelectorError(e.to_string()))?;
+    let main_content = ctx
+        .options
+        .content_links_only
+        .then(|| html_parser::find_main_content(document_html))
+        .flatten();
+
+    for element in document_html.select(&a_selector) {
+        if ctx.options.content_links_only
+            && (html_parser::is_in_boilerplate(&element)
+                || main_content.is_some_and(|main| {
+                    !element
+                        .ancestors()
+                        .any(|ancestor| ancestor.id() == main.id())
+                }))
+        {
+            continue;
+        }
+
+        if let Some(href) = element.value().attr("href") {
+            let text = html_parser::get_element_text(&element);
+            if !text.is_empty()
+                && let Some(absolute_url) = resolve_url_against_base(ctx.base_url, href)
+            {
+                document.links.push(Link {
+                    text,
+                    url: match &ctx.options.normalize_links {
+                        Some(normalize) => html_parser::normalize_url(&absolute_url, normalize),
+                        None => absolute_url,
+                    },
+                    title: title_attribute(element.value()),
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Record the expansions given by `abbr` and `dfn` title attributes
+///
+/// The first title seen for an abbreviation wins.
+fn process_abbreviations(
+    document: &mut Document,
+    document_html: &Html,
+) -> Result<(), MarkdownError> {
+    let abbr_selector = Selector::parse("abbr[title], dfn[title]")
+        .map_err(|e| MarkdownError::SelectorError(e.to_string()))?;
+    for element in document_html.select(&abbr_selector) {
+        let Some(abbreviation) = abbreviation_of(&element) else {
+            continue;
+        };
+        if !document
+            .abbreviations
+            .iter()
+            .any(|a| a.abbr == abbreviation.abbr)
+        {
+            document.abbreviations.push(abbreviation);
+        }
+    }
+    Ok(())
+}
+
+/// The abbreviation an `abbr` or `dfn` element expands, if its title gives one
+fn abbreviation_of(element: &ElementRef) -> Option<Abbreviation> {
+    let abbr = collapse_whitespace(&element.text().collect::<String>());
+    let title = collapse_whitespace(element.value().attr("title")?);
+    (!abbr.is_empty() && !title.is_empty() && abbr != title).then_some(Abbreviation { abbr, title })
+}
+
+/// Process image elements
+fn process_images(
+    document: &mut Document,
+    document_html: &Html,
+    ctx: &ParseContext,
+) -> Result<(), MarkdownError> {
+    let img_selector =
+        Selector::parse("img").map_err(|e| MarkdownError::SelectorError(e.to_string()))?;
+    for element in document_html.select(&img_selector) {
+        if let Some(image) = image_from_element(&element, ctx) {
+            document.images.push(image);
+        }
+    }
+    Ok(())
+}
+
+/// Build an image from an `img` element, resolving its source URL
+///
+/// The largest `srcset` candidate (including `<source>` siblings inside a `<picture>`)
+/// is preferred over `src`, which is often only a small placeholder.
+fn image_from_element(element: &ElementRef, ctx: &ParseContext) -> Option<Image> {
+    let original = html_parser::image_source(element.value(), &ctx.options.lazy_image_attributes)
+        .and_then(|src| resolve_url_against_base(ctx.base_url, src));
+    let best = html_parser::largest_srcset_candidate(element)
+        .and_then(|candidate| resolve_url_against_base(ctx.base_url, candidate));
+
+    let (src, fallback_src) = match (best, original) {
+        (Some(best), Some(original)) if best != original => (best, Some(original)),
+        (Some(src), _) | (None, Some(src)) => (src, None),
+        (None, None) => return None,
+    };
+
+    let caption = single_image_figure(element).and_then(|figure| figure_caption(&figure, ctx));
+    let alt = match element.value().attr("alt").map(str::trim) {
+        Some(alt) if !alt.is_empty() => alt.to_string(),
+        _ => match ctx.options.alt_fallback {
+            AltFallback::Placeholder => "image".to_string(),
+            AltFallback::Empty => String::new(),
+            AltFallback::FileName => alt_from_file_name(&src),
+            AltFallback::Caption => caption.clone().unwrap_or_default(),
+            AltFallback::Skip => return None,
+        },
+    };
+    Some(Image {
+        alt,
+        src,
+        caption,
+        fallback_src,
+        title: title_attribute(element.value()),
+        width: dimension_attribute(element, "width"),
+        height: dimension_attribute(element, "height"),
+    })
+}
+
+/// Readable alt text from an image URL's file name, without extension or separators
+fn alt_from_file_name(src: &str) -> String {
+    let path = src.split(['?', '#']).next().unwrap_or(src);
+    let file_name = path.rsplit('/').next().unwrap_or(path);
+    let stem = file_name
+        .rsplit_once('.')
+        .map_or(file_name, |(stem, _)| stem)
+        .replace("%20", " ");
+    collapse_whitespace(&stem.replace(['-', '_', '+'], " "))
+}
+
+/// An element's `title` attribute with whitespace collapsed, if not blank
+fn title_attribute(element: &scraper::node::Element) -> Option<String> {
+    element
+        .attr("title")
+        .map(collapse_whitespace)
+        .filter(|title| !title.is_empty())
+}
+
+/// A pixel dimension attribute such as `width="800"` or `height="600px"`
+fn dimension_attribute(element: &ElementRef, name: &str) -> Option<u32> {
+    let value = element.value().attr(name)?.trim();
+    value
+        .strip_suffix("px")
+        .unwrap_or(value)
+        .trim()
+        .parse()
+        .ok()
+        .filter(|&pixels| pixels > 0)
+}
+
+/// Build an embed from a `video`, `audio` or `iframe` element
+///
+/// Media elements without a `src` use their first `<source>` child.
+fn embed_from_element(element: &ElementRef, ctx: &ParseContext) -> Option<Embed> {
+    let value = element.value();
+    let src = value.attr("src").or_else(|| {
+        element
+            .child_elements()
+            .filter(|e| e.value().name() == "source")
+            .find_map(|source| source.value().attr("src"))
+    })?;
+    let src = resolve_url_against_base(ctx.base_url, src)?;
+
+    let (kind, src) = match value.name() {
+        "video" => (EmbedKind::Video, src),
+        "audio" => (EmbedKind::Audio, src),
+        _ => match canonical_video_url(&src) {
+            Some(watch_url) => (EmbedKind::Video, watch_url),
+            None => (EmbedKind::Frame, src),
+        },
+    };
+
+    let title = value
+        .attr("title")
+        .or_else(|| value.attr("aria-label"))
+        .map(|title| title.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|title| !title.is_empty());
+
+    Some(Embed { kind, src, title })
+}
+
+/// Map YouTube and Vimeo player URLs to the page a reader would open
+fn canonical_video_url(src: &str) -> Option<String> {
+    let url = Url::parse(src).ok()?;
+    let host = url.host_str()?.trim_start_matches("www.");
+    let mut segments = url.path_segments()?;
+
+    match (host, segments.next(), segments.next()) {
+        ("youtube.com" | "youtube-nocookie.com", Some("embed"), Some(id)) if !id.is_empty() => {
+            Some(format!("https://www.youtube.com/watch?v={}", id))
+        }
+        ("player.vimeo.com", Some("video"), Some(id)) if !id.is_empty() => {
+            Some(format!("https://vimeo.com/{}", id))
+        }
+        _ => None,
+    }
+}
+
+/// Find the enclosing `figure` of an element, if that figure holds exactly one image
+fn single_image_figure<'a>(element: &ElementRef<'a>) -> Option<ElementRef<'a>> {
+    element
+        .ancestors()
+        .filter_map(ElementRef::wrap)
+        .find(|e| e.value().name() == "figure")
+        .filter(has_single_image)
+}
+
+fn has_single_image(figure: &ElementRef) -> bool {
+    figure
+        .descendent_elements()
+        .filter(|e| e.value().name() == "img")
+        .count()
+        == 1
+}
+
+/// Text of a figure's `figcaption`
+fn figure_caption(figure: &ElementRef, ctx: &ParseContext) -> Option<String> {
+    figure
+        .child_elements()
+        .find(|e| e.value().name() == "figcaption")
+        .map(|caption| InlineRenderer::render(caption.children(), ctx))
+        .filter(|caption| !caption.is_empty())
+}
+
+/// Process a `figcaption` that could not be attached to a single image
+///
+/// Figures with several images, code or other content keep their caption as an
+/// italic paragraph after the content instead.
+fn process_figcaption(element: &ElementRef, ctx: &ParseContext, blocks: &mut Vec<Block>) {
+    let attached = element
+        .parent()
+        .and_then(ElementRef::wrap)
+        .is_some_and(|parent| parent.value().name() == "figure" && has_single_image(&parent));
+    if attached {
+        return;
+    }
+
+    let text = InlineRenderer::render(element.children(), ctx);
+    if !text.is_empty() {
+        blocks.push(Block::Paragraph {
+            text: format!("*{}*", text),
+        });
+    }
+}
+
+/// Process a `details` section, keeping its summary apart from the body blocks
+fn process_details(element: &ElementRef, ctx: &ParseContext, blocks: &mut Vec<Block>) {
+    let summary = element
+        .child_elements()
+        .find(|e| e.value().name() == "summary")
+        .map(|summary| InlineRenderer::render(summary.children(), ctx))
+        .unwrap_or_default();
+    let body = collect_blocks(element, ctx);
+
+    if !summary.is_empty() || !body.is_empty() {
+        blocks.push(Block::Details {
+            summary,
+            blocks: body,
+        });
+    }
+}
+
+/// Check whether an element's parent has the given tag name
+fn is_child_of(element: &ElementRef, name: &str) -> bool {
+    element
+        .parent()
+        .and_then(|parent| parent.value().as_element().map(|e| e.name() == name))
+        .unwrap_or(false)
+}
+
+/// Process a list element (ordered or unordered)
+fn process_list(element: &ElementRef, ordered: bool, ctx: &ParseContext, blocks: &mut Vec<Block>) {
+    if let Some(list) = extract_list_items(element, ordered, ctx) {
+        blocks.push(Block::List(list));
+    }
+}
+
+/// Process a `pre` code block element
+fn process_code_block(element: &ElementRef, blocks: &mut Vec<Block>) {
+    let text = preformatted_text(element);
+    if !text.trim().is_empty() {
+        // The language class may sit on the pre itself or on its inner code element
+        let lang = std::iter::once(*element)
+            .chain(
+                element
+                    .child_elements()
+                    .filter(|e| e.value().name() == "code"),
+            )
+            .flat_map(|e| e.value().classes())
+            .find_map(|c| c.strip_prefix("language-"))
+            .unwrap_or("")
+            .to_string();
+
+        blocks.push(Block::CodeBlock(CodeBlock {
+            language: lang,
+            code: text,
+        }));
+    }
+}
+
+/// The exact text of a `pre` element, with `<br>` as a newline
+///
+/// Indentation and blank lines are kept; only a single leading and trailing newline
+/// (the ones around the code in `<pre>\ncode\n</pre>`) are dropped.
+fn preformatted_text(element: &ElementRef) -> String {
+    let mut text = String::new();
+    for node in element.descendants() {
+        match node.value() {
+            Node::Text(t) => text.push_str(t),
+            Node::Element(e) if e.name() == "br" => text.push('\n'),
+            _ => {}
+        }
+    }
+
+    let text = text.replace("\r\n", "\n");
+    let text = text.strip_prefix('\n').unwrap_or(&text);
+    text.strip_suffix('\n').unwrap_or(text).to_string()
+}
+
+/// Process a blockquote element, keeping the block structure of its content
+fn process_blockquote(element: &ElementRef, ctx: &ParseContext, blocks: &mut Vec<Block>) {
+    let quoted = collect_blocks(element, ctx);
+    if !quoted.is_empty() {
+        blocks.push(Block::Blockquote { blocks: quoted });
+    }
+}
+
+/// Process a table element
+fn process_table(element: &ElementRef, span_fill: TableSpanFill, blocks: &mut Vec<Block>) {
+    if let Some(table) = extract_table(element, span_fill, html_parser::get_element_text) {
+        blocks.push(Block::Table(table));
+    }
+}
+
+/// Process a definition list (`dl` with `dt` terms and `dd` definitions)
+fn process_definition_list(element: &ElementRef, ctx: &ParseContext, blocks: &mut Vec<Block>) {
+    let mut entries: Vec<Definition> = Vec::new();
+    collect_definitions(element, ctx, &mut entries);
+
+    entries.retain(|entry| !entry.term.is_empty() || !entry.definitions.is_empty());
+    if !entries.is_empty() {
+        blocks.push(Block::DefinitionList(DefinitionList { entries }));
+    }
+}
+
+/// Gather terms and definitions, descending into the `div` wrappers HTML allows inside `dl`
+fn collect_definitions(element: &ElementRef, ctx: &ParseContext, entries: &mut Vec<Definition>) {
+    for child in element.child_elements() {
+        match child.value().name() {
+            "dt" => entries.push(Definition {
+                term: InlineRenderer::render(child.children(), ctx),
+                definitions: Vec::new(),
+            }),
+            "dd" => {
+                let text = InlineRenderer::render(child.children(), ctx);
+                if text.is_empty() {
+                    continue;
+                }
+                match entries.last_mut() {
+                    Some(entry) => entry.definitions.push(text),
+                    None => entries.push(Definition {
+                        term: String::new(),
+                        definitions: vec![text],
+                    }),
+                }
+            }
+            "div" => collect_definitions(&child, ctx, entries),
+            _ => {}
+        }
+    }
+}
+
+/// Helper function to extract the header and body rows of a table
+///
+/// Only rows that belong directly to this table are considered, so nested tables
+/// are extracted on their own rather than being flattened into the parent.
+/// Cells spanning several columns or rows are expanded so every row ends up with
+/// the same number of columns. `cell_text` reads the text of each cell.
+pub(crate) fn extract_table(
+    table_element: &ElementRef,
+    span_fill: TableSpanFill,
+    cell_text: fn(&ElementRef) -> String,
+) -> Option<Table> {
+    let mut headers = Vec::new();
+    let mut rows = Vec::new();
+    let mut header_alignments = Vec::new();
+    let mut row_alignments: Vec<Vec<ColumnAlignment>> = Vec::new();
+    // Cells from earlier rows that still cover a column: (rows remaining, text)
+    let mut pending: Vec<Option<(usize, String)>> = Vec::new();
+
+    let table_rows = table_element
+        .descendent_elements()
+        .filter(|e| e.value().name() == "tr" && is_own_table_row(table_element, e));
+
+    for tr in table_rows {
+        let mut cells = Vec::new();
+        let mut alignments = Vec::new();
+        let mut all_header_cells = true;
+        for cell in tr.child_elements() {
+            match cell.value().name() {
+                "th" => {}
+                "td" => all_header_cells = false,
+                _ => continue,
+            }
+
+            fill_spanned_cells(&mut cells, &mut pending);
+            alignments.resize(cells.len(), ColumnAlignment::None);
+            let alignment = cell_alignment(&cell);
+            let text = cell_text(&cell);
+            let colspan = span_attribute(&cell, "colspan", 1000);
+            let rowspan = span_attribute(&cell, "rowspan", 65534);
+            let covered = match span_fill {
+                TableSpanFill::Duplicate => text.clone(),
+                TableSpanFill::Empty => String::new(),
+            };
+
+            for offset in 0..colspan {
+                let column = cells.len();
+                if rowspan > 1 {
+                    if pending.len() <= column {
+                        pending.resize(column + 1, None);
+                    }
+                    pending[column] = Some((rowspan - 1, covered.clone()));
+                }
+                cells.push(if offset == 0 {
+                    text.clone()
+                } else {
+                    covered.clone()
+                });
+                alignments.push(alignment);
+            }
+        }
+        // Rowspans from earlier rows may also cover columns after the last cell
+        if !cells.is_empty() {
+            fill_spanned_cells(&mut cells, &mut pending);
+        }
+
+        if cells.is_empty() {
+            continue;
+        }
+
+        let in_thead = tr
+            .parent()
+            .and_then(|parent| parent.value().as_element().map(|e| e.name() == "thead"))
+            .unwrap_or(false);
+
+        // Header comes from thead, or is inferred from a leading row of th cells
+        if headers.is_empty() && rows.is_empty() && (in_thead || all_header_cells) {
+            headers = cells;
+            header_alignments = alignments;
+        } else {
+            rows.push(cells);
+            row_alignments.push(alignments);
+        }
+    }
+
+    if headers.is_empty() && rows.is_empty() {
+        return None;
+    }
+
+    let column_count = rows
+        .iter()
+        .map(Vec::len)
+        .chain(std::iter::once(headers.len()))
+        .max()
+        .unwrap_or(0);
+    if !headers.is_empty() {
+        headers.resize(column_count, String::new());
+    }
+    for row in &mut rows {
+        row.resize(column_count, String::new());
+    }
+
+    let alignments = column_alignments(&header_alignments, &row_alignments, column_count);
+
+    Some(Table {
+        headers,
+        rows,
+        alignments,
+    })
+}
+
+/// Decide each column's alignment
+///
+/// The header cell's alignment wins; otherwise body cells must agree, and a column
+/// with conflicting body alignments is left unaligned.
+fn column_alignments(
+    header: &[ColumnAlignment],
+    rows: &[Vec<ColumnAlignment>],
+    column_count: usize,
+) -> Vec<ColumnAlignment> {
+    let alignments: Vec<ColumnAlignment> = (0..column_count)
+        .map(|column| {
+            let from_header = header.get(column).copied().unwrap_or_default();
+            if from_header != ColumnAlignment::None {
+                return from_header;
+            }
+
+            let mut body = rows
+                .iter()
+                .filter_map(|row| row.get(column).copied())
+                .filter(|&alignment| alignment != ColumnAlignment::None);
+            let first = body.next().unwrap_or_default();
+            if body.all(|alignment| alignment == first) {
+                first
+            } else {
+                ColumnAlignment::None
+            }
+        })
+        .collect();
+
+    if alignments.iter().all(|&a| a == ColumnAlignment::None) {
+        Vec::new()
+    } else {
+        alignments
+    }
+}
+
+/// Read a cell's alignment from `align`, an inline `text-align` style or a framework class
+fn cell_alignment(cell: &ElementRef) -> ColumnAlignment {
+    let parse = |value: &str| match value.trim().to_ascii_lowercase().as_str() {
+        "left" | "start" => ColumnAlignment::Left,
+        "center" | "middle" => ColumnAlignment::Center,
+        "right" | "end" => ColumnAlignment::Right,
+        _ => ColumnAlignment::None,
+    };
+
+    if let Some(align) = cell.value().attr("align") {
+        return parse(align);
+    }
+
+    let from_style = cell.value().attr("style").and_then(|style| {
+        style.split(';').find_map(|declaration| {
+            let (property, value) = declaration.split_once(':')?;
+            (property.trim().eq_ignore_ascii_case("text-align")).then(|| parse(value))
+        })
+    });
+    if let Some(alignment) = from_style {
+        return alignment;
+    }
+
+    // Bootstrap (`text-right`, `text-end`), Bulma (`has-text-right`) and Tailwind style classes
+    cell.value()
+        .classes()
+        .find_map(|class| {
+            let class = class.strip_prefix("has-").unwrap_or(class);
+            class.strip_prefix("text-").map(parse)
+        })
+        .filter(|&alignment| alignment != ColumnAlignment::None)
+        .unwrap_or_default()
+}
+
+/// Append the cells still covered by rowspans from earlier rows at the current position
+fn fill_spanned_cells(cells: &mut Vec<String>, pending: &mut [Option<(usize, String)>]) {
+    loop {
+        let Some(slot) = pending.get_mut(cells.len()) else {
+            return;
+        };
+        let Some((remaining, text)) = slot else {
+            return;
+        };
+        cells.push(text.clone());
+        *remaining -= 1;
+        if *remaining == 0 {
+            *slot = None;
+        }
+    }
+}
+
+/// Read a `colspan`/`rowspan` attribute, treating missing or invalid values as 1
+fn span_attribute(cell: &ElementRef, name: &str, max: usize) -> usize {
+    cell.value()
+        .attr(name)
+        .and_then(|value| value.trim().parse::<usize>().ok())
+        .filter(|&span| span > 0)
+        .map_or(1, |span| span.min(max))
+}
+
+/// Check whether a row's nearest enclosing table is the given table element
+fn is_own_table_row(table_element: &ElementRef, tr: &ElementRef) -> bool {
+    tr.ancestors()
+        .find(|node| {
+            node.value()
+                .as_element()
+                .is_some_and(|element| element.name() == "table")
+        })
+        .is_some_and(|node| node.id() == table_element.id())
+}
+
+/// Helper function to resolve URLs against a base URL
+///
+/// Fragments, `javascript:` and `data:` URLs and hrefs that cannot be resolved give
+/// `None`, so callers skip them rather than pointing at the page itself.
+fn resolve_url_against_base(base_url: &Url, href: &str) -> Option<String> {
+    let href_trimmed = href.trim();
+    if href_trimmed.is_empty()
+        || href_trimmed.starts_with('#')
+        || href_trimmed.to_lowercase().starts_with("javascript:")
+        || href_trimmed.to_lowercase().starts_with("data:")
+        || href_trimmed.contains(' ')
+        || href_trimmed.starts_with(':')
+        || href_trimmed.contains(":::")
+    {
+        return None;
+    }
+
+    html_parser::resolve_against(base_url, href_trimmed).ok()
+}
+
+/// Helper function to extract the direct items of a list
+fn extract_list_items(
+    list_element: &ElementRef,
+    ordered: bool,
+    ctx: &ParseContext,
+) -> Option<List> {
+    let mut items = Vec::new();
+    for li in list_element
+        .child_elements()
+        .filter(|e| e.value().name() == "li")
+    {
+        let is_nested_list = |node: &NodeRef<Node>| {
+            node.value()
+                .as_element()
+                .is_some_and(|e| matches!(e.name(), "ul" | "ol"))
+        };
+        let text = InlineRenderer::render(li.children().filter(|c| !is_nested_list(c)), ctx);
+        let children: Vec<List> = li
+            .child_elements()
+            .filter_map(|nested| match nested.value().name() {
+                "ul" => extract_list_items(&nested, false, ctx),
+                "ol" => extract_list_items(&nested, true, ctx),
+                _ => None,
+            })
+            .collect();
+        if !text.is_empty() || !children.is_empty() {
+            items.push(ListItem {
+                checked: leading_checkbox_state(&li),
+                text,
+                children,
+            });
+        }
+    }
+
+    if !items.is_empty() {
+        Some(List { ordered, items })
+    } else {
+        None
+    }
+}
+
+/// Read the state of a checkbox input that precedes any text in a list item
+fn leading_checkbox_state(li: &ElementRef) -> Option<bool> {
+    for node in li.descendants().skip(1) {
+        match node.value() {
+            Node::Text(t) if !t.trim().is_empty() => return None,
+            Node::Element(element) if element.name() == "input" => {
+                let is_checkbox = element
+                    .attr("type")
+                    .is_some_and(|t| t.eq_ignore_ascii_case("checkbox"));
+                return is_checkbox.then(|| element.attr("checked").is_some());
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Convert document to plain text, e.g. for embedding models
+///
+/// Headings become bare lines, list items dashed lines, links their anchor text and code
+/// blocks stay verbatim; images and embeds are dropped. Blocks are separated by a blank
+/// line so paragraph boundaries survive chunking.
+pub fn document_to_text(document: &Document) -> String {
+    let mut parts = vec![document.title.clone()];
+    parts.extend(blocks_to_text(&document.blocks));
+    collapse_blank_lines(&parts.join("\n\n")).trim().to_string()
+}
+
+fn blocks_to_text(blocks: &[Block]) -> Vec<String> {
+    blocks
+        .iter()
+        .filter_map(|block| match block {
+            Block::Heading(heading) => Some(plain_text(&heading.text)),
+            Block::Paragraph { text } => Some(plain_text(text)),
+            Block::List(list) => Some(list_to_text(list, 0)),
+            Block::CodeBlock(code_block) => Some(code_block.code.clone()),
+            Block::Blockquote { blocks } => Some(blocks_to_text(blocks).join("\n\n")),
+            Block::Table(table) => Some(
+                std::iter::once(&table.headers)
+                    .filter(|headers| !headers.is_empty())
+                    .chain(&table.rows)
+                    .map(|row| {
+                        row.iter()
+                            .map(|cell| plain_text(cell))
+                            .collect::<Vec<_>>()
+                            .join(" | ")
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            ),
+            Block::DefinitionList(list) => Some(
+                list.entries
+                    .iter()
+                    .map(|entry| {
+                        std::iter::once(plain_text(&entry.term))
+                            .chain(
+                                entry
+                                    .definitions
+                                    .iter()
+                                    .map(|d| format!("  {}", plain_text(d))),
+                            )
+                            .collect::<Vec<_>>()
+                            .join("\n")
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n\n"),
+            ),
+            Block::Details { summary, blocks } => Some(
+                std::iter::once(plain_text(summary))
+                    .chain(blocks_to_text(blocks))
+                    .collect::<Vec<_>>()
+                    .join("\n\n"),
+            ),
+            Block::Image(_) | Block::Embed(_) | Block::Hr => None,
+        })
+        .filter(|text| !text.trim().is_empty())
+        .collect()
+}
+
+fn list_to_text(list: &List, depth: usize) -> String {
+    let indent = "  ".repeat(depth);
+    let mut lines = Vec::new();
+    for item in &list.items {
+        let text = plain_text(&item.text).replace('\n', " ");
+        if !text.is_empty() {
+            lines.push(format!("{}- {}", indent, text));
+        }
+        lines.extend(
+            item.children
+                .iter()
+                .map(|child| list_to_text(child, depth + 1)),
+        );
+    }
+    lines.join("\n")
+}
+
+/// Strip inline markdown from rendered text: emphasis markers, link and image syntax,
+/// code span backticks and backslash escapes
+fn plain_text(markdown: &str) -> String {
+    convert_inline(markdown, InlineTarget::Plain)
+}
+
+/// Rewrite inline markdown as reStructuredText inline markup
+fn rst_inline(markdown: &str) -> String {
+    convert_inline(markdown, InlineTarget::Rst)
+}
+
+/// What inline markdown is converted into
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum InlineTarget {
+    Plain,
+    Rst,
+}
+
+/// Tags the markdown renderer writes emphasis as when its delimiters would not flank
+const EMPHASIS_TAGS: [&str; 3] = ["strong", "em", "del"];
+
+/// Scans the inline markdown the renderer wrote, which escapes markup in source text,
+/// so every construct it finds is one the renderer emitted
+fn convert_inline(markdown: &str, target: InlineTarget) -> String {
+    let mut out = String::with_capacity(markdown.len());
+    // Backtick runs with no closing run later on, so each length is searched for once
+    let mut unclosed: Vec<usize> = Vec::new();
+    let mut i = 0;
+    while let Some(c) = markdown[i..].chars().next() {
+        let rest = &markdown[i..];
+        match c {
+            '\\' if rest[1..].starts_with(|next: char| next.is_ascii_punctuation()) => {
+                push_literal(&mut out, &rest[1..2], target);
+                i += 2;
+            }
+            '`' => {
+                let run = rest.bytes().take_while(|&b| b == b'`').count();
+                let fence = &rest[..run];
+                let end = if unclosed.contains(&run) {
+                    None
+                } else {
+                    rest[run..].find(fence)
+                };
+                match end {
+                    Some(end) => {
+                        let code = &rest[run..run + end];
+                        let code = code
+                            .strip_prefix(' ')
+                            .and_then(|code| code.strip_suffix(' '))
+                            .unwrap_or(code);
+                        match target {
+                            InlineTarget::Plain => out.push_str(code),
+                            InlineTarget::Rst => out.push_str(&format!("``{}``", code)),
+                        }
+                        i += run + end + run;
+                    }
+                    None => {
+                        if !unclosed.contains(&run) {
+                            unclosed.push(run);
+                        }
+                        out.push_str(fence);
+                        i += run;
+                    }
+                }
+            }
+            '!' | '[' => {
+                let image = c == '!';
+                let start = if image { i + 1 } else { i };
+                let link = markdown[start..]
+                    .starts_with('[')
+                    .then(|| inline_link(markdown, start))
+                    .flatten();
+                match link {
+                    Some((label, destination, end)) => {
+                        let label = plain_text(label);
+                        let url = destination.split_whitespace().next().unwrap_or_default();
+                        match (target, image) {
+                            (InlineTarget::Plain, true) => {}
+                            (InlineTarget::Plain, false) => out.push_str(&label),
+                            (InlineTarget::Rst, true) => push_literal(&mut out, &label, target),
+                            (InlineTarget::Rst, false) if label.is_empty() || label == url => {
+                                out.push_str(url)
+                            }
+                            (InlineTarget::Rst, false) => out.push_str(&rst_link(&label, url)),
+                        }
+                        i = end;
+                    }
+                    None => {
+                        push_literal(&mut out, &rest[..1], target);
+                        i += 1;
+                    }
+                }
+            }
+            // Autolinks and emphasis tags hold no whitespace, which bounds the search
+            '<' if let Some(len) = rest[1..].find(['>', '<', ' ', '\n'])
+                && rest[1 + len..].starts_with('>') =>
+            {
+                let inner = &rest[1..1 + len];
+                if inner.contains("://") || inner.starts_with("mailto:") {
+                    out.push_str(inner.trim_start_matches("mailto:"));
+                    i += len + 2;
+                } else if EMPHASIS_TAGS.contains(&inner.trim_start_matches('/')) {
+                    // Emphasis written as HTML where its delimiters would not flank
+                    i += len + 2;
+                } else {
+                    push_literal(&mut out, "<", target);
+                    i += 1;
+                }
+            }
+            '*' | '~' => {
+                let run = rest.chars().take_while(|&x| x == c).count();
+                let before = markdown[..i].chars().next_back();
+                let after = rest[run..].chars().next();
+                let spaced = |c: Option<char>| c.is_none_or(char::is_whitespace);
+                // A run between spaces is a literal, as in `5 * 3`; a single `~` is never
+                // strikethrough. RST shares markdown's `*` and `**` emphasis.
+                if (spaced(before) && spaced(after)) || (c == '~' && run == 1) {
+                    push_literal(&mut out, &rest[..run], target);
+                } else if c == '*' && target == InlineTarget::Rst {
+                    out.push_str(&rest[..run]);
+                }
+                i += run;
+            }
+            _ => {
+                push_literal(&mut out, &rest[..c.len_utf8()], target);
+                i += c.len_utf8();
+            }
+        }
+    }
+    out
+}
+
+/// Source text on a single line, escaped for RST the way rendered paragraph text is
+fn rst_source_text(text: &str) -> String {
+    rst_inline(&escape_markdown(&collapse_whitespace(text)))
+}
+
+/// An anonymous RST hyperlink, so links sharing a label do not clash as targets
+fn rst_link(label: &str, url: &str) -> String {
+    let mut escaped = String::with_capacity(label.len());
+    push_literal(&mut escaped, label, InlineTarget::Rst);
+    // `<` would start the target
+    format!("`{} <{}>`__", escaped.replace('<', "\\<"), url)
+}
+
+/// Characters RST reads as inline markup: emphasis, literals, references, substitutions
+const RST_MARKUP: [char; 5] = ['\\', '*', '`', '_', '|'];
+
+/// Append source text, backslash-escaping whatever the target would read as markup
+fn push_literal(out: &mut String, text: &str, target: InlineTarget) {
+    match target {
+        InlineTarget::Plain => out.push_str(text),
+        InlineTarget::Rst => {
+            for c in text.chars() {
+                if RST_MARKUP.contains(&c) {
+                    out.push('\\');
+                }
+                out.push(c);
+            }
+        }
+    }
+}
+
+/// Underline characters for RST section titles, from `h1` to `h6`
+///
+/// The document title uses `=` above and below, which RST treats as a separate style.
+/// Adornments span the title's display width, with wide (e.g. CJK) characters taking
+/// two columns, as docutils measures them.
+const RST_ADORNMENTS: [char; 6] = ['=', '-', '~', '^', '"', '\''];
+
+/// Convert document to reStructuredText, e.g. for Sphinx
+pub fn document_to_rst(document: &Document) -> String {
+    let title = rst_source_text(&document.title);
+    let rule = "=".repeat(title.width().max(1));
+    let mut parts = vec![format!("{}\n{}\n{}", rule, title, rule)];
+    parts.extend(blocks_to_rst(&document.blocks));
+    let mut rst = parts.join("\n\n");
+    rst.push('\n');
+    rst
+}
+
+fn blocks_to_rst(blocks: &[Block]) -> Vec<String> {
+    blocks
+        .iter()
+        .map(|block| match block {
+            Block::Heading(heading) => {
+                let text = rst_inline(&heading.text);
+                let adornment = RST_ADORNMENTS[(heading.level.clamp(1, 6) - 1) as usize];
+                let underline = adornment.to_string().repeat(text.width().max(1));
+                format!("{}\n{}", text, underline)
+            }
+            Block::Paragraph { text } => text
+                .split("\n\n")
+                .map(|paragraph| {
+                    let paragraph = rst_inline(paragraph);
+                    // Hard breaks need a line block to survive
+                    if paragraph.contains('\n') {
+                        paragraph
+                            .lines()
+                            .map(|line| format!("| {}", line))
+                            .collect::<Vec<_>>()
+                            .join("\n")
+                    } else {
+                        paragraph
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join("\n\n"),
+            Block::List(list) => list_to_rst(list),
+            Block::CodeBlock(code_block) => {
+                let directive = match code_block.language.as_str() {
+                    "" => "::".to_string(),
+                    language => format!(".. code-block:: {}", language),
+                };
+                format!("{}\n\n{}", directive, indent_rst(&code_block.code, 3))
+            }
+            Block::Blockquote { blocks } => indent_rst(&blocks_to_rst(blocks).join("\n\n"), 4),
+            Block::Table(table) => table_to_rst(table),
+            Block::Image(image) => image_to_rst(image),
+            Block::Embed(embed) => {
+                let label = match (embed.kind, &embed.title) {
+                    (EmbedKind::Video, Some(title)) => format!("Video: {}", title),
+                    (EmbedKind::Audio, Some(title)) => format!("Audio: {}", title),
+                    (EmbedKind::Frame, Some(title)) => title.clone(),
+                    (EmbedKind::Video, None) => "Video".to_string(),
+                    (EmbedKind::Audio, None) => "Audio".to_string(),
+                    (EmbedKind::Frame, None) => "Embedded content".to_string(),
+                };
+                rst_link(&label, &embed.src)
+            }
+            Block::DefinitionList(list) => list
+                .entries
+                .iter()
+                .map(|entry| {
+                    let definitions = entry
+                        .definitions
+                        .iter()
+                        .map(|d| rst_inline(d))
+                        .collect::<Vec<_>>()
+                        .join("\n\n");
+                    format!(
+                        "{}\n{}",
+                        rst_inline(&entry.term),
+                        indent_rst(&definitions, 3)
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n\n"),
+            Block::Hr => "----".to_string(),
+            Block::Details { summary, blocks } => {
+                let mut parts = Vec::new();
+                if !summary.is_empty() {
+                    parts.push(format!(".. rubric:: {}", rst_inline(summary)));
+                }
+                parts.extend(blocks_to_rst(blocks));
+                parts.join("\n\n")
+            }
+        })
+        .filter(|rst| !rst.trim().is_empty())
+        .collect()
+}
+
+/// Indent every non-empty line by `width` spaces
+fn indent_rst(text: &str, width: usize) -> String {
+    let indent = " ".repeat(width);
+    text.lines()
+        .map(|line| match line {
+            "" => String::new(),
+            _ => format!("{}{}", indent, line),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Bullet (`-`) or enumerated (`1.`) list; nested lists sit under the item text after a blank line
+fn list_to_rst(list: &List) -> String {
+    let nested = list.items.iter().any(|item| !item.children.is_empty());
+    list.items
+        .iter()
+        .enumerate()
+        .map(|(i, item)| {
+            let marker = if list.ordered {
+                format!("{}.", i + 1)
+            } else {
+                "-".to_string()
+            };
+            let task = match item.checked {
+                Some(true) => "[x] ",
+                Some(false) => "[ ] ",
+                None => "",
+            };
+            let width = marker.len() + 1;
+            let text = rst_inline(&item.text);
+            let mut entry = format!(
+                "{} {}{}",
+                marker,
+                task,
+                indent_rst(&text, width).trim_start()
+            );
+            for child in &item.children {
+                entry.push_str("\n\n");
+                entry.push_str(&indent_rst(&list_to_rst(child), width));
+            }
+            entry
+        })
+        .collect::<Vec<_>>()
+        .join(if nested { "\n\n" } else { "\n" })
+}
+
+/// Render a table with the `list-table` directive, which handles any cell content
+fn table_to_rst(table: &Table) -> String {
+    let column_count = table
+        .rows
+        .iter()
+        .map(Vec::len)
+        .chain(std::iter::once(table.headers.len()))
+        .max()
+        .unwrap_or(0);
+
+    let mut lines = vec![".. list-table::".to_string()];
+    if !table.headers.is_empty() {
+        lines.push("   :header-rows: 1".to_string());
+    }
+    lines.push(String::new());
+    for row in std::iter::once(&table.headers)
+        .filter(|headers| !headers.is_empty())
+        .chain(&table.rows)
+    {
+        for i in 0..column_count {
+            let cell = rst_inline(row.get(i).map(String::as_str).unwrap_or(""));
+            let prefix = if i == 0 { "   * -" } else { "     -" };
+            lines.push(format!("{} {}", prefix, cell).trim_end().to_string());
+        }
+    }
+    lines.join("\n")
+}
+
+/// An `image` directive, or a `figure` when the image has a caption
+fn image_to_rst(image: &Image) -> String {
+    let directive = if image.caption.is_some() {
+        "figure"
+    } else {
+        "image"
+    };
+    let mut lines = vec![format!(".. {}:: {}", directive, image.src)];
+    if !image.alt.is_empty() {
+        // A line break in the alt text would start another option
+        lines.push(format!("   :alt: {}", rst_source_text(&image.alt)));
+    }
+    if let Some(width) = image.width {
+        lines.push(format!("   :width: {}px", width));
+    }
+    if let Some(height) = image.height {
+        lines.push(format!("   :height: {}px", height));
+    }
+    if let Some(caption) = &image.caption {
+        lines.push(String::new());
+        lines.push(indent_rst(&rst_inline(caption), 3));
+    }
+    lines.join("\n")
+}
+
+/// Convert document to markdown format
+pub fn document_to_markdown(document: &Document) -> String {
+    document_to_markdown_with_options(document, &ConversionOptions::default())
+}
+
+/// Convert document to markdown format using the given options
+pub fn document_to_markdown_with_options(
+    document: &Document,
+    options: &ConversionOptions,
+) -> String {
+    let mut markdown_content = match options.layout {
+        MarkdownLayout::Ordered => ordered_markdown(document, options),
+        MarkdownLayout::Grouped => grouped_markdown(document, options),
+    };
+
+    // Inline expansions are written while rendering the `abbr` elements
+    if options.abbreviation_style == AbbreviationStyle::Definitions {
+        for abbreviation in &document.abbreviations {
+            markdown_content.push_str(&format!(
+                "\n*[{}]: {}",
+                escape_markdown(&abbreviation.abbr),
+                escape_markdown(&abbreviation.title)
+            ));
+        }
+    }
+
+    if options.link_style == LinkStyle::Reference {
+        markdown_content = reference_style_links(&markdown_content);
+    }
+
+    // Clean up extra newlines
+    let markdown_content = collapse_blank_lines(&markdown_content).trim().to_string();
+
+    if options.front_matter {
+        format!("{}\n\n{}", front_matter(&document.meta), markdown_content)
+    } else {
+        markdown_content
+    }
+}
+
+/// A `---`-delimited YAML block with the document metadata
+///
+/// Strings are written as JSON strings, which are valid double-quoted YAML scalars,
+/// so colons, quotes and `#` in titles need no further escaping.
+fn front_matter(meta: &DocumentMeta) -> String {
+    let quoted = |value: &str| serde_json::to_string(value).unwrap_or_else(|_| "\"\"".to_string());
+    let mut yaml = format!(
+        "---\ntitle: {}\nsource_url: {}\nfetched_at: {}\nword_count: {}\nlink_count: {}\nimage_count: {}\n",
+        quoted(&meta.title),
+        quoted(&meta.source_url),
+        quoted(&meta.fetched_at),
+        meta.word_count,
+        meta.link_count,
+        meta.image_count
+    );
+    // Page-supplied fields only appear when the page declares them
+    if let Some(description) = &meta.description {
+        yaml.push_str(&format!("description: {}\n", quoted(description)));
+    }
+    if !meta.keywords.is_empty() {
+        let keywords: Vec<String> = meta.keywords.iter().map(|k| quoted(k)).collect();
+        yaml.push_str(&format!("keywords: [{}]\n", keywords.join(", ")));
+    }
+    if let Some(author) = &meta.author {
+        yaml.push_str(&format!("author: {}\n", quoted(author)));
+    }
+    if let Some(generator) = &meta.generator {
+        yaml.push_str(&format!("generator: {}\n", quoted(generator)));
+    }
+    if let Some(published_at) = &meta.published_at {
+        yaml.push_str(&format!("published_at: {}\n", quoted(published_at)));
+    }
+    if let Some(modified_at) = &meta.modified_at {
+        yaml.push_str(&format!("modified_at: {}\n", quoted(modified_at)));
+    }
+    if let Some(language) = &meta.language {
+        yaml.push_str(&format!("language: {}\n", quoted(language)));
+    }
+    if let Some(canonical_url) = &meta.canonical_url {
+        yaml.push_str(&format!("canonical_url: {}\n", quoted(canonical_url)));
+    }
+    if let Some(path) = meta.breadcrumb_path() {
+        yaml.push_str(&format!("path: {}\n", quoted(&path)));
+    }
+    yaml.push_str("---");
+    yaml
+}
+
+/// Collapse runs of blank lines into one, leaving fenced code untouched
+fn collapse_blank_lines(markdown: &str) -> String {
+    let mut lines = Vec::new();
+    let mut fences = FenceTracker::default();
+    let mut previous_blank = false;
+    for line in markdown.lines() {
+        fences.update(line);
+        let blank = line.is_empty() && !fences.in_fence();
+        if !(blank && previous_blank) {
+            lines.push(line);
+        }
+        previous_blank = blank;
+    }
+    lines.join("\n")
+}
+
+/// Follows fenced code blocks line by line using CommonMark's fence rules
+///
+/// A fence is closed only by the same character repeated at least as many times,
+/// so code containing shorter fences stays inside its block.
+#[derive(Default)]
+struct FenceTracker {
+    open: Option<(char, usize)>,
+}
+
+impl FenceTracker {
+    /// Feed the next line, returning whether it opens or closes a fence
+    fn update(&mut self, line: &str) -> bool {
+        let line = line.trim_end_matches(['\n', '\r']);
+        let rest = line.trim_start_matches(' ');
+        if line.len() - rest.len() > 3 {
+            return false;
+        }
+        let Some(fence_char) = rest.chars().next().filter(|c| matches!(c, '`' | '~')) else {
+            return false;
+        };
+        let run = rest.len() - rest.trim_start_matches(fence_char).len();
+        if run < 3 {
+            return false;
+        }
+        let after = &rest[run..];
+
+        match self.open {
+            Some((open_char, open_len)) => {
+                let closes = fence_char == open_char && run >= open_len && after.trim().is_empty();
+                if closes {
+                    self.open = None;
+                }
+                closes
+            }
+            // A backtick fence's info string cannot itself contain backticks
+            None if fence_char == '`' && after.contains('`') => false,
+            None => {
+                self.open = Some((fence_char, run));
+                true
+            }
+        }
+    }
+
+    fn in_fence(&self) -> bool {
+        self.open.is_some()
+    }
+}
+
+/// Rewrite inline links and images as reference links, listing the targets at the end
+///
+/// Targets are numbered in order of first appearance (`[1]`, `[2]`, ... for links and
+/// `[img1]`, ... for images) and a repeated target reuses its number, so the output
+/// is the same on every run. Fenced code and code spans are left untouched, and as
+/// brackets in text are always escaped, only links the renderer wrote are rewritten.
+fn reference_style_links(markdown: &str) -> String {
+    let mut references = References::default();
+    let mut fences = FenceTracker::default();
+    let mut out = String::with_capacity(markdown.len());
+    for line in markdown.split_inclusive('\n') {
+        if fences.update(line) || fences.in_fence() {
+            out.push_str(line);
+        } else {
+            out.push_str(&references.rewrite(line));
+        }
+    }
+
+    if !references.links.is_empty() || !references.images.is_empty() {
+        out.push_str("\n\n");
+        for (i, destination) in references.links.iter().enumerate() {
+            out.push_str(&format!("[{}]: {}\n", i + 1, destination));
+        }
+        for (i, destination) in references.images.iter().enumerate() {
+            out.push_str(&format!("[img{}]: {}\n", i + 1, destination));
+        }
+    }
+    out
+}
+
+/// Link and image destinations collected while rewriting, in order of first use
+#[derive(Default)]
+struct References {
+    links: Vec<String>,
+    images: Vec<String>,
+}
+
+impl References {
+    fn label(&mut self, destination: &str, image: bool) -> String {
+        let targets = if image {
+            &mut self.images
+        } else {
+            &mut self.links
+        };
+        let index = match targets.iter().position(|d| d == destination) {
+            Some(index) => index,
+            None => {
+                targets.push(destination.to_string());
+                targets.len() - 1
+            }
+        };
+        if image {
+            format!("img{}", index + 1)
+        } else {
+            (index + 1).to_string()
+        }
+    }
+
+    /// Replace every inline link and image in a line of text
+    fn rewrite(&mut self, text: &str) -> String {
+        let bytes = text.as_bytes();
+        let mut out = String::with_capacity(text.len());
+        let mut copied = 0;
+        let mut i = 0;
+        while i < bytes.len() {
+            match bytes[i] {
+                b'\\' => i += 2,
+                b'`' => {
+                    let run = bytes[i..].iter().take_while(|&&b| b == b'`').count();
+                    let fence = &text[i..i + run];
+                    i = text[i + run..]
+                        .find(fence)
+                        .map_or(i + run, |end| i + run + end + run);
+                }
+                b'[' if let Some((label, destination, end)) = inline_link(text, i) => {
+                    let image = i > 0 && bytes[i - 1] == b'!';
+                    out.push_str(&text[copied..i]);
+                    let label = self.rewrite(label);
+                    let reference = self.label(destination, image);
+                    out.push_str(&format!("[{}][{}]", label, reference));
+                    copied = end;
+                    i = end;
+                }
+                _ => i += 1,
+            }
+        }
+        out.push_str(&text[copied.min(text.len())..]);
+        out
+    }
+}
+
+/// Parse `[label](destination)` starting at the `[` at `start`
+///
+/// Returns the label, the destination (URL and optional title) and the end offset.
+/// Brackets in the label and parentheses in the URL may nest.
+fn inline_link(text: &str, start: usize) -> Option<(&str, &str, usize)> {
+    let bytes = text.as_bytes();
+    let mut depth = 0;
+    let mut i = start;
+    let label_end = loop {
+        match bytes.get(i)? {
+            b'\\' => i += 1,
+            b'[' => depth += 1,
+            b']' => {
+                depth -= 1;
+                if depth == 0 {
+                    break i;
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    };
+    if bytes.get(label_end + 1) != Some(&b'(') {
+        return None;
+    }
+
+    let mut depth = 0;
+    let mut in_title = false;
+    let mut i = label_end + 2;
+    loop {
+        match bytes.get(i)? {
+            b'\\' => i += 1,
+            b'"' => in_title = !in_title,
+            b'(' if !in_title => depth += 1,
+            b')' if !in_title && depth == 0 => break,
+            b')' if !in_title => depth -= 1,
+            _ => {}
+        }
+        i += 1;
+    }
+    let destination = text[label_end + 2..i].trim();
+    (!destination.is_empty()).then(|| (&text[start + 1..label_end], destination, i + 1))
+}
+
+/// The leading `# Title` line, if the title mode calls for one
+fn title_markdown(document: &Document, options: &ConversionOptions) -> String {
+    let include = match options.title_mode {
+        TitleMode::Always => true,
+        TitleMode::Never => false,
+        TitleMode::IfNoH1 => !document
+            .headings
+            .iter()
+            .find(|heading| heading.level == 1)
+            .is_some_and(|h1| title_matches_heading(&document.title, &h1.text)),
+    };
+
+    if include {
+        format!(
+            "{}\n\n",
+            heading_line(1, &escape_markdown(&document.title), options)
+        )
+    } else {
+        String::new()
+    }
+}
+
+/// A nested list linking to each heading, or nothing when `options.toc` is off
+///
+/// Anchors follow GitHub's slugs, with repeats suffixed `-1`, `-2`, ...; headings
+/// whose id is written into the markdown link to that id instead. The page title
+/// and an `h1` repeating it are not listed, but still take their slug.
+fn toc_markdown(document: &Document, options: &ConversionOptions) -> String {
+    if !options.toc {
+        return String::new();
+    }
+
+    let mut seen = HashSet::new();
+    let mut unique_slug = |slug: String| {
+        let slug = if seen.contains(&slug) {
+            (1..)
+                .map(|n| format!("{}-{}", slug, n))
+                .find(|candidate| !seen.contains(candidate))
+                .unwrap_or_default()
+        } else {
+            slug
+        };
+        seen.insert(slug.clone());
+        slug
+    };
+    if !title_markdown(document, options).is_empty() {
+        unique_slug(github_slug(&document.title));
+    }
+    let title_h1 = document.headings.iter().position(|heading| {
+        heading.level == 1 && title_matches_heading(&document.title, &heading.text)
+    });
+
+    let mut entries = Vec::new();
+    for (i, heading) in document.headings.iter().enumerate() {
+        let anchor = match (&heading.id, options.anchor_style) {
+            (Some(id), AnchorStyle::Attribute) => {
+                unique_slug(attribute_anchor_id(id).unwrap_or_else(|| github_slug(&heading.text)))
+            }
+            (Some(id), AnchorStyle::Html) => unique_slug(id.clone()),
+            _ => unique_slug(github_slug(&heading.text)),
+        };
+        let listed = Some(i) != title_h1
+            && (options.toc_max_level == 0 || heading.level <= options.toc_max_level);
+        if listed {
+            entries.push((
+                heading.level,
+                format!(
+                    "[{}](#{})",
+                    escape_markdown(&plain_text(&heading.text)),
+                    anchor
+                ),
+            ));
+        }
+    }
+    if entries.is_empty() {
+        return String::new();
+    }
+
+    let toc = List {
+        ordered: false,
+        items: toc_items(&entries),
+    };
+    format!("{}\n\n", list_to_markdown(&toc, options))
+}
+
+/// Nest TOC entries under the closest preceding entry of a higher level
+fn toc_items(entries: &[(u8, String)]) -> Vec<ListItem> {
+    let mut items = Vec::new();
+    let mut i = 0;
+    while i < entries.len() {
+        let (level, text) = &entries[i];
+        let end = entries[i + 1..]
+            .iter()
+            .position(|(next, _)| next <= level)
+            .map_or(entries.len(), |offset| i + 1 + offset);
+        let children = toc_items(&entries[i + 1..end]);
+        items.push(ListItem {
+            text: text.clone(),
+            checked: None,
+            children: match children.is_empty() {
+                true => Vec::new(),
+                false => vec![List {
+                    ordered: false,
+                    items: children,
+                }],
+            },
+        });
+        i = end;
+    }
+    items
+}
+
+/// GitHub's heading anchor: lowercase text with spaces as dashes and punctuation removed
+fn github_slug(text: &str) -> String {
+    plain_text(text)
+        .to_lowercase()
+        .chars()
+        .filter_map(|c| match c {
+            c if c.is_whitespace() => Some('-'),
+            '-' | '_' => Some(c),
+            c if c.is_alphanumeric() => Some(c),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Compare a page title with a heading, ignoring case and whitespace
+///
+/// Titles often append the site name (`Post title | Site`), so a title that starts
+/// with the heading followed by a separator also matches.
+fn title_matches_heading(title: &str, heading: &str) -> bool {
+    let normalize = |text: &str| {
+        text.split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .to_lowercase()
+    };
+    let title = normalize(title);
+    let heading = normalize(heading);
+    if heading.is_empty() {
+        return false;
+    }
+
+    title == heading
+        || title.strip_prefix(&heading).is_some_and(|rest| {
+            rest.trim_start()
+                .starts_with(['|', '-', '–', '—', '·', ':', '•'])
+        })
+}
+
+/// Render blocks in document order
+fn ordered_markdown(document: &Document, options: &ConversionOptions) -> String {
+    let mut markdown_content = title_markdown(document, options);
+    markdown_content.push_str(&toc_markdown(document, options));
+
+    markdown_content.push_str(&blocks_to_markdown(&document.blocks, options));
+    markdown_content.push_str("\n\n");
+
+    // Links are rendered inline; the index is only kept for callers relying on it
+    if options.include_link_index && !document.links.is_empty() {
+        markdown_content.push_str("## Links\n\n");
+        for link in &document.links {
+            markdown_content.push_str(&format!(
+                "- [{}]({})\n",
+                escape_markdown(&link.text),
+                link_destination(&link.url, link.title.as_deref())
+            ));
+        }
+    }
+
+    markdown_content
+}
+
+/// Render a sequence of blocks separated by blank lines
+fn blocks_to_markdown(blocks: &[Block], options: &ConversionOptions) -> String {
+    blocks
+        .iter()
+        .map(|block| block_to_markdown(block, options))
+        .collect::<Vec<String>>()
+        .join("\n\n")
+}
+
+/// Render a single block as markdown
+fn block_to_markdown(block: &Block, options: &ConversionOptions) -> String {
+    match block {
+        Block::Heading(heading) => heading_to_markdown(heading, options),
+        Block::Paragraph { text } => hard_breaks(text, options),
+        Block::List(list) => list_to_markdown(list, options),
+        Block::CodeBlock(code_block) => code_block_to_markdown(code_block, options),
+        Block::Blockquote { blocks } => blockquote_to_markdown(blocks, options),
+        Block::Table(table) => table_to_markdown(table, options),
+        Block::Image(image) => image_to_markdown(image, options),
+        Block::Embed(embed) => embed_to_markdown(embed),
+        Block::DefinitionList(list) => {
+            definition_list_to_markdown(list, options.definition_list_style)
+        }
+        // Blocks are always separated by a blank line, so the rule can't be read
+        // as a setext underline of the preceding paragraph or heading
+        Block::Hr => "---".to_string(),
+        Block::Details { summary, blocks } => details_to_markdown(summary, blocks, options),
+    }
+}
+
+/// Legacy layout: each kind of content rendered as its own group
+fn grouped_markdown(document: &Document, options: &ConversionOptions) -> String {
+    let mut markdown_content = title_markdown(document, options);
+    markdown_content.push_str(&toc_markdown(document, options));
+
+    // Add headings
+    for heading in &document.headings {
+        markdown_content.push_str(&format!("{}\n\n", heading_to_markdown(heading, options)));
+    }
+
+    // Add paragraphs
+    for paragraph in &document.paragraphs {
+        markdown_content.push_str(&format!("{}\n\n", hard_breaks(paragraph, options)));
+    }
+
+    // Add links
+    for link in &document.links {
+        markdown_content.push_str(&format!(
+            "[{}]({})\n\n",
+            escape_markdown(&link.text),
+            link_destination(&link.url, link.title.as_deref())
+        ));
+    }
+
+    // Add images
+    for image in &document.images {
+        markdown_content.push_str(&format!("{}\n\n", image_to_markdown(image, options)));
+    }
+
+    // Add embeds
+    for embed in &document.embeds {
+        markdown_content.push_str(&format!("{}\n\n", embed_to_markdown(embed)));
+    }
+
+    // Add lists
+    for list in &document.lists {
+        markdown_content.push_str(&format!("{}\n\n", list_to_markdown(list, options)));
+    }
+
+    // Add code blocks
+    for code_block in &document.code_blocks {
+        markdown_content.push_str(&format!(
+            "{}\n\n",
+            code_block_to_markdown(code_block, options)
+        ));
+    }
+
+    // Add tables
+    for table in &document.tables {
+        markdown_content.push_str(&format!("{}\n\n", table_to_markdown(table, options)));
+    }
+
+    // Add blockquotes
+    for block in &document.blocks {
+        if let Block::Blockquote { blocks } = block {
+            markdown_content.push_str(&format!("{}\n\n", blockquote_to_markdown(blocks, options)));
+        }
+    }
+
+    // Add definition lists
+    for list in &document.definition_lists {
+        markdown_content.push_str(&format!(
+            "{}\n\n",
+            definition_list_to_markdown(list, options.definition_list_style)
+        ));
+    }
+
+    markdown_content
+}
+
+/// Render an embed as a labelled link, e.g. `[▶ Video: Launch talk](url)`
+fn embed_to_markdown(embed: &Embed) -> String {
+    let label = match embed.kind {
+        EmbedKind::Video => "▶ Video",
+        EmbedKind::Audio => "♪ Audio",
+        EmbedKind::Frame => "Embedded content",
+    };
+    match &embed.title {
+        Some(title) => format!("[{}: {}]({})", label, escape_markdown(title), embed.src),
+        None => format!("[{}]({})", label, embed.src),
+    }
+}
+
+fn heading_to_markdown(heading: &Heading, options: &ConversionOptions) -> String {
+    let text = match (&heading.id, options.anchor_style) {
+        (Some(id), AnchorStyle::Attribute) => match attribute_anchor_id(id) {
+            Some(id) => format!("{} {{#{}}}", heading.text, id),
+            None => heading.text.clone(),
+        },
+        (Some(id), AnchorStyle::Html) => format!(
+            "<a id=\"{}\"></a>{}",
+            html_parser::escape_html_attribute(id),
+            heading.text
+        ),
+        _ => heading.text.clone(),
+    };
+    heading_line(heading.level, &text, options)
+}
+
+/// The id written in a `{#id}` attribute; ids with whitespace or braces, which would
+/// break the attribute, are slugified, and None is returned when nothing is left
+fn attribute_anchor_id(id: &str) -> Option<String> {
+    let id = if id.contains(|c: char| c.is_whitespace() || c == '{' || c == '}') {
+        github_slug(id)
+    } else {
+        id.to_string()
+    };
+    (!id.is_empty()).then_some(id)
+}
+
+/// Write a heading line at `level` shifted by the configured offset, in the configured style
+fn heading_line(level: u8, text: &str, options: &ConversionOptions) -> String {
+    let level = (i16::from(level) + i16::from(options.heading_offset)).clamp(1, 6);
+    match (options.heading_style, level) {
+        (HeadingStyle::Setext, 1 | 2) if !text.is_empty() => {
+            let underline = if level == 1 { "=" } else { "-" };
+            let width = text
+                .lines()
+                .map(|line| line.chars().count())
+                .max()
+                .unwrap_or(0);
+            format!("{}\n{}", text, underline.repeat(width.max(3)))
+        }
+        _ => format!("{} {}", "#".repeat(level as usize), text),
+    }
+}
+
+fn image_to_markdown(image: &Image, options: &ConversionOptions) -> String {
+    let title = image.title.as_deref();
+    let alt = escape_markdown(&image.alt);
+    match (&image.caption, options.caption_style) {
+        (Some(caption), CaptionStyle::Italic) => format!(
+            "![{}]({})\n\n*{}*",
+            alt,
+            link_destination(&image.src, title),
+            caption
+        ),
+        (Some(caption), CaptionStyle::Title) => format!(
+            "![{}]({})",
+            alt,
+            link_destination(&image.src, Some(caption))
+        ),
+        (None, _) => format!("![{}]({})", alt, link_destination(&image.src, title)),
+    }
+}
+
+/// A link or image destination with an optional `"title"`, escaping quotes in the title
+fn link_destination(url: &str, title: Option<&str>) -> String {
+    match title {
+        Some(title) => format!("{} \"{}\"", url, title.replace('"', "\\\"")),
+        None => url.to_string(),
+    }
+}
+
+fn list_to_markdown(list: &List, options: &ConversionOptions) -> String {
+    list.items
+        .iter()
+        .enumerate()
+        .map(|(i, item)| {
+            let marker = match (list.ordered, options.ordered_style) {
+                (true, OrderedListStyle::Sequential) => format!("{}.", i + 1),
+                (true, OrderedListStyle::AllOnes) => "1.".to_string(),
+                (false, _) => options.bullet_char.as_char().to_string(),
+            };
+            let task = match item.checked {
+                Some(true) => "[x] ",
+                Some(false) => "[ ] ",
+                None => "",
+            };
+            // Continuation lines are indented to the item's content column
+            let text = hard_breaks(&item.text, options);
+            let indent = " ".repeat(marker.len() + 1);
+            let text = text
+                .split('\n')
+                .enumerate()
+                .map(|(n, line)| match n {
+                    0 => line.to_string(),
+                    _ if line.is_empty() => String::new(),
+                    _ => format!("{}{}", indent, line),
+                })
+                .collect::<Vec<String>>()
+                .join("\n");
+            let mut entry = format!("{} {}{}", marker, task, text);
+
+            let nested_indent = match options.indent_width {
+                0 => indent.clone(),
+                width => " ".repeat(width),
+            };
+            for child in &item.children {
+                for line in list_to_markdown(child, options).split('\n') {
+                    entry.push('\n');
+                    if !line.is_empty() {
+                        entry.push_str(&nested_indent);
+                        entry.push_str(line);
+                    }
+                }
+            }
+            entry
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Fence a code block, making the fence longer than any run of its character in the code
+fn code_block_to_markdown(code_block: &CodeBlock, options: &ConversionOptions) -> String {
+    let fence_char = match options.code_fence {
+        FenceChar::Backtick => '`',
+        FenceChar::Tilde => '~',
+    };
+    let longest_run = code_block
+        .code
+        .split(|c| c != fence_char)
+        .map(str::len)
+        .max()
+        .unwrap_or(0);
+    let fence = fence_char.to_string().repeat((longest_run + 1).max(3));
+    // A backtick or whitespace would end the info string, or the fence line itself
+    let language = if code_block
+        .language
+        .contains(|c: char| c == '`' || c.is_whitespace())
+    {
+        ""
+    } else {
+        code_block.language.as_str()
+    };
+    format!("{}{}\n{}\n{}", fence, language, code_block.code, fence)
+}
+
+/// Render quoted blocks with a `>` prefix on every line; nested quotes stack prefixes
+fn blockquote_to_markdown(blocks: &[Block], options: &ConversionOptions) -> String {
+    blocks_to_markdown(blocks, options)
+        .lines()
+        .map(|line| match line {
+            "" => ">".to_string(),
+            _ => format!("> {}", line),
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn definition_list_to_markdown(list: &DefinitionList, style: DefinitionListStyle) -> String {
+    list.entries
+        .iter()
+        .map(|entry| {
+            let mut lines = Vec::new();
+            match style {
+                DefinitionListStyle::BoldTerm => {
+                    if !entry.term.is_empty() {
+                        lines.push(format!("**{}**", entry.term));
+                    }
+                    lines.extend(entry.definitions.iter().map(|d| format!("  {}", d)));
+                }
+                DefinitionListStyle::Colon => {
+                    lines.push(entry.term.clone());
+                    lines.extend(entry.definitions.iter().map(|d| format!(": {}", d)));
+                }
+            }
+            lines.join("\n")
+        })
+        .collect::<Vec<String>>()
+        .join("\n\n")
+}
+
+fn details_to_markdown(summary: &str, blocks: &[Block], options: &ConversionOptions) -> String {
+    let body = blocks_to_markdown(blocks, options);
+    match options.details_style {
+        DetailsStyle::BoldSummary => {
+            let mut parts = Vec::new();
+            if !summary.is_empty() {
+                parts.push(format!("**{}**", summary));
+            }
+            if !body.is_empty() {
+                parts.push(body);
+            }
+            parts.join("\n\n")
+        }
+        // Blank lines around the body let markdown inside the HTML block be parsed
+        DetailsStyle::Html => format!(
+            "<details>\n<summary>{}</summary>\n\n{}\n\n</details>",
+            summary, body
+        ),
+    }
+}
+
+/// Encode single newlines in block text as markdown hard breaks
+fn hard_breaks(text: &str, options: &ConversionOptions) -> String {
+    let marker = match options.flavor.line_break_style(options.line_break_style) {
+        LineBreakStyle::Spaces => "  \n",
+        LineBreakStyle::Backslash => "\\\n",
+    };
+    text.split("\n\n")
+        .map(|paragraph| paragraph.replace('\n', marker))
+        .collect::<Vec<String>>()
+        .join("\n\n")
+}
+
+/// Render a table as a GitHub-style pipe table, or as a definition list for
+/// flavors without tables
+fn table_to_markdown(table: &Table, options: &ConversionOptions) -> String {
+    if !options.flavor.pipe_tables() {
+        return definition_list_to_markdown(
+            &table_to_definition_list(table),
+            options.definition_list_style,
+        );
+    }
+
+    let column_count = table
+        .rows
+        .iter()
+        .map(Vec::len)
+        .chain(std::iter::once(table.headers.len()))
+        .max()
+        .unwrap_or(0);
+
+    let format_row = |cells: &[String]| {
+        let mut line = String::from("|");
+        for i in 0..column_count {
+            let cell = cells.get(i).map(String::as_str).unwrap_or("");
+            line.push(' ');
+            line.push_str(&escape_table_cell(cell));
+            line.push_str(" |");
+        }
+        line
+    };
+
+    let mut lines = vec![format_row(&table.headers)];
+    let separator: String = (0..column_count)
+        .map(
+            |i| match table.alignments.get(i).copied().unwrap_or_default() {
+                ColumnAlignment::None => " --- |",
+                ColumnAlignment::Left => " :--- |",
+                ColumnAlignment::Center => " :---: |",
+                ColumnAlignment::Right => " ---: |",
+            },
+        )
+        .collect();
+    lines.push(format!("|{}", separator));
+    for row in &table.rows {
+        lines.push(format_row(row));
+    }
+    lines.join("\n")
+}
+
+/// One entry per row: the first cell is the term, the others become `Header: value` lines
+///
+/// Cells hold plain text, so they are escaped into the markdown definition lists carry.
+fn table_to_definition_list(table: &Table) -> DefinitionList {
+    let entries = table
+        .rows
+        .iter()
+        .filter_map(|row| {
+            let (term, cells) = row.split_first()?;
+            let definitions = cells
+                .iter()
+                .enumerate()
+                .filter(|(_, cell)| !cell.is_empty())
+                .map(|(i, cell)| match table.headers.get(i + 1) {
+                    Some(header) if !header.is_empty() => {
+                        format!("{}: {}", escape_markdown(header), escape_markdown(cell))
+                    }
+                    _ => escape_markdown(cell),
+                })
+                .collect();
+            Some(Definition {
+                term: escape_markdown(term),
+                definitions,
+            })
+        })
+        .collect();
+    DefinitionList { entries }
 }
 
-/// Convert document to JSON format
+/// Escape markup and pipes and flatten line breaks so cell text stays inside its column
+fn escape_table_cell(text: &str) -> String {
+    escape_markdown(text)
+        .replace('|', "\\|")
+        .replace(['\n', '\r'], " ")
+}
+
+/// Convert document to pretty-printed JSON
 pub fn document_to_json(document: &Document) -> Result<String, MarkdownError> {
-    serde_json::to_string_pretty(document).map_err(|e| {
+    document_to_json_with_style(document, JsonStyle::Pretty)
+}
+
+/// Version of the JSON layout, bumped whenever fields are renamed, removed or change meaning
+pub const JSON_SCHEMA_VERSION: u32 = 1;
+
+/// The JSON top level: schema information followed by the document's own fields
+#[derive(Serialize)]
+struct JsonEnvelope<'a> {
+    schema_version: u32,
+    generator: &'static str,
+    #[serde(flatten)]
+    document: &'a Document,
+    stats: DocumentStats,
+}
+
+/// Convert document to JSON, pretty-printed or compact
+///
+/// The top-level object starts with `schema_version` and `generator`
+/// (`markdown_lab_rs/<version>`) so consumers can detect format changes, and
+/// includes the document's `stats`.
+pub fn document_to_json_with_style(
+    document: &Document,
+    style: JsonStyle,
+) -> Result<String, MarkdownError> {
+    let envelope = JsonEnvelope {
+        schema_version: JSON_SCHEMA_VERSION,
+        generator: concat!("markdown_lab_rs/", env!("CARGO_PKG_VERSION")),
+        document,
+        stats: document.stats(),
+    };
+    let json = match style {
+        JsonStyle::Pretty => serde_json::to_string_pretty(&envelope),
+        JsonStyle::Compact => serde_json::to_string(&envelope),
+    };
+    json.map_err(|e| {
         MarkdownError::SerializationError(format!("Failed to serialize to JSON: {}", e))
     })
 }
 
+/// Version byte leading every `document_to_bytes` payload
+#[cfg(feature = "cache")]
+pub const BINARY_FORMAT_VERSION: u8 = 1;
+
+/// Serialize a document to a compact binary form for caching
+///
+/// The payload is a version byte followed by the document as MessagePack with named
+/// fields, so it loads back with the same defaults as `Document::from_json`.
+#[cfg(feature = "cache")]
+pub fn document_to_bytes(document: &Document) -> Result<Vec<u8>, MarkdownError> {
+    let mut bytes = vec![BINARY_FORMAT_VERSION];
+    rmp_serde::encode::write_named(&mut bytes, document).map_err(|e| {
+        MarkdownError::SerializationError(format!("Failed to serialize to MessagePack: {}", e))
+    })?;
+    Ok(bytes)
+}
+
+/// Load a document written by `document_to_bytes`
+///
+/// Empty, truncated or corrupted input and payloads from another format version are
+/// reported as `SerializationError`.
+#[cfg(feature = "cache")]
+pub fn document_from_bytes(bytes: &[u8]) -> Result<Document, MarkdownError> {
+    match bytes.split_first() {
+        Some((&BINARY_FORMAT_VERSION, payload)) => rmp_serde::from_slice(payload).map_err(|e| {
+            MarkdownError::SerializationError(format!("Failed to parse cached document: {}", e))
+        }),
+        Some((version, _)) => Err(MarkdownError::SerializationError(format!(
+            "Unsupported cached document version {} (expected {})",
+            version, BINARY_FORMAT_VERSION
+        ))),
+        None => Err(MarkdownError::SerializationError(
+            "Cached document is empty".to_string(),
+        )),
+    }
+}
+
 /// Convert document to XML format
+///
+/// The schema is fixed: `<document>` holds `<title>`, `<base_url>`, `<meta>`, one
+/// wrapper per collection (`<headings>`, `<links>`, ...; empty ones are self-closing)
+/// and `<blocks>` with the content in document order. Code is wrapped in CDATA.
 pub fn document_to_xml(document: &Document) -> Result<String, MarkdownError> {
-    use quick_xml::se::to_string;
+    let mut writer = XmlWriter::new(Vec::new());
+    write_document_xml(&mut writer, document).map_err(|e| {
+        MarkdownError::SerializationError(format!("Failed to serialize to XML: {}", e))
+    })?;
+    String::from_utf8(writer.into_inner()).map_err(|e| {
+        MarkdownError::SerializationError(format!("Failed to serialize to XML: {}", e))
+    })
+}
+
+type XmlWriter = quick_xml::Writer<Vec<u8>>;
+
+fn write_document_xml(w: &mut XmlWriter, document: &Document) -> std::io::Result<()> {
+    use quick_xml::events::{BytesDecl, Event};
+
+    w.write_event(Event::Decl(BytesDecl::new("1.0", Some("UTF-8"), None)))?;
+    w.create_element("document").write_inner_content(|w| {
+        xml_text_element(w, "title", &document.title)?;
+        xml_text_element(w, "base_url", &document.base_url)?;
+        w.create_element("meta").write_inner_content(|w| {
+            let meta = &document.meta;
+            xml_text_element(w, "title", &meta.title)?;
+            xml_text_element(w, "source_url", &meta.source_url)?;
+            xml_text_element(w, "fetched_at", &meta.fetched_at)?;
+            xml_text_element(w, "word_count", &meta.word_count.to_string())?;
+            xml_text_element(w, "link_count", &meta.link_count.to_string())?;
+            xml_text_element(w, "image_count", &meta.image_count.to_string())?;
+            if let Some(description) = &meta.description {
+                xml_text_element(w, "description", description)?;
+            }
+            if !meta.keywords.is_empty() {
+                xml_collection(w, "keywords", &meta.keywords, |w, keyword| {
+                    xml_text_element(w, "keyword", keyword)
+                })?;
+            }
+            if let Some(author) = &meta.author {
+                xml_text_element(w, "author", author)?;
+            }
+            if let Some(generator) = &meta.generator {
+                xml_text_element(w, "generator", generator)?;
+            }
+            if let Some(published_at) = &meta.published_at {
+                xml_text_element(w, "published_at", published_at)?;
+            }
+            if let Some(modified_at) = &meta.modified_at {
+                xml_text_element(w, "modified_at", modified_at)?;
+            }
+            if let Some(language) = &meta.language {
+                xml_text_element(w, "language", language)?;
+            }
+            if let Some(canonical_url) = &meta.canonical_url {
+                xml_text_element(w, "canonical_url", canonical_url)?;
+            }
+            if !meta.alternates.is_empty() {
+                xml_collection(w, "alternates", &meta.alternates, |w, alternate| {
+                    xml_element(
+                        w,
+                        "alternate",
+                        &[
+                            ("hreflang", alternate.hreflang.clone()),
+                            ("url", alternate.url.clone()),
+                        ],
+                    )
+                    .write_empty()?;
+                    Ok(())
+                })?;
+            }
+            if !meta.breadcrumbs.is_empty() {
+                xml_collection(w, "breadcrumbs", &meta.breadcrumbs, |w, crumb| {
+                    let attributes: Vec<(&str, String)> =
+                        crumb.url.iter().map(|url| ("url", url.clone())).collect();
+                    xml_element(w, "crumb", &attributes)
+                        .write_text_content(xml_text(&crumb.text))?;
+                    Ok(())
+                })?;
+            }
+            if !meta.robots.is_empty() {
+                let robots = meta.robots;
+                xml_element(
+                    w,
+                    "robots",
+                    &[
+                        ("noindex", robots.noindex.to_string()),
+                        ("nofollow", robots.nofollow.to_string()),
+                        ("noarchive", robots.noarchive.to_string()),
+                    ],
+                )
+                .write_empty()?;
+            }
+            if !meta.social.is_empty() {
+                w.create_element("social").write_inner_content(|w| {
+                    let social = &meta.social;
+                    for (name, value) in [
+                        ("og_title", &social.og_title),
+                        ("og_description", &social.og_description),
+                        ("og_image", &social.og_image),
+                        ("og_type", &social.og_type),
+                        ("og_site_name", &social.og_site_name),
+                        ("twitter_card", &social.twitter_card),
+                        ("twitter_image", &social.twitter_image),
+                    ] {
+                        if let Some(value) = value {
+                            xml_text_element(w, name, value)?;
+                        }
+                    }
+                    Ok(())
+                })?;
+            }
+            Ok(())
+        })?;
+        xml_collection(w, "headings", &document.headings, write_heading_xml)?;
+        xml_collection(w, "paragraphs", &document.paragraphs, |w, p| {
+            xml_text_element(w, "paragraph", p)
+        })?;
+        xml_collection(w, "links", &document.links, |w, link| {
+            let mut attributes = vec![("url", link.url.clone())];
+            attributes.extend(link.title.clone().map(|title| ("title", title)));
+            xml_element(w, "link", &attributes).write_text_content(xml_text(&link.text))?;
+            Ok(())
+        })?;
+        xml_collection(w, "images", &document.images, write_image_xml)?;
+        xml_collection(w, "lists", &document.lists, write_list_xml)?;
+        xml_collection(
+            w,
+            "code_blocks",
+            &document.code_blocks,
+            write_code_block_xml,
+        )?;
+        xml_collection(w, "blockquotes", &document.blockquotes, |w, quote| {
+            xml_text_element(w, "blockquote", quote)
+        })?;
+        xml_collection(w, "tables", &document.tables, write_table_xml)?;
+        xml_collection(
+            w,
+            "definition_lists",
+            &document.definition_lists,
+            write_definition_list_xml,
+        )?;
+        xml_collection(w, "embeds", &document.embeds, write_embed_xml)?;
+        xml_collection(w, "abbreviations", &document.abbreviations, |w, abbr| {
+            let attributes = [("abbr", abbr.abbr.clone()), ("title", abbr.title.clone())];
+            xml_element(w, "abbreviation", &attributes).write_empty()?;
+            Ok(())
+        })?;
+        xml_collection(w, "blocks", &document.blocks, write_block_xml)
+    })?;
+    Ok(())
+}
+
+/// Write `<name>` around the items, or `<name/>` when there are none
+fn xml_collection<T>(
+    w: &mut XmlWriter,
+    name: &str,
+    items: &[T],
+    write_item: impl Fn(&mut XmlWriter, &T) -> std::io::Result<()>,
+) -> std::io::Result<()> {
+    if items.is_empty() {
+        w.create_element(name).write_empty()?;
+    } else {
+        w.create_element(name)
+            .write_inner_content(|w| items.iter().try_for_each(|item| write_item(w, item)))?;
+    }
+    Ok(())
+}
+
+/// Start an element with the given attributes; values are escaped by the writer
+fn xml_element<'w>(
+    w: &'w mut XmlWriter,
+    name: &'w str,
+    attributes: &[(&str, String)],
+) -> quick_xml::writer::ElementWriter<'w, Vec<u8>> {
+    w.create_element(name).with_attributes(
+        attributes
+            .iter()
+            .map(|(key, value)| (*key, xml_safe(value)))
+            .collect::<Vec<_>>()
+            .iter()
+            .map(|(key, value)| (*key, value.as_str())),
+    )
+}
+
+fn xml_text_element(w: &mut XmlWriter, name: &str, text: &str) -> std::io::Result<()> {
+    w.create_element(name).write_text_content(xml_text(text))?;
+    Ok(())
+}
+
+fn xml_text(text: &str) -> quick_xml::events::BytesText<'static> {
+    quick_xml::events::BytesText::new(&xml_safe(text)).into_owned()
+}
+
+/// Drop characters XML 1.0 cannot represent, even escaped (most C0 controls)
+fn xml_safe(text: &str) -> String {
+    text.chars()
+        .filter(|&c| {
+            matches!(c, '\t' | '\n' | '\r') || (c >= ' ' && c != '\u{FFFE}' && c != '\u{FFFF}')
+        })
+        .collect()
+}
+
+fn write_heading_xml(w: &mut XmlWriter, heading: &Heading) -> std::io::Result<()> {
+    let mut attributes = vec![("level", heading.level.to_string())];
+    attributes.extend(heading.id.clone().map(|id| ("id", id)));
+    xml_element(w, "heading", &attributes).write_text_content(xml_text(&heading.text))?;
+    Ok(())
+}
+
+fn write_image_xml(w: &mut XmlWriter, image: &Image) -> std::io::Result<()> {
+    let mut attributes = vec![("src", image.src.clone()), ("alt", image.alt.clone())];
+    attributes.extend(image.title.clone().map(|title| ("title", title)));
+    attributes.extend(image.width.map(|width| ("width", width.to_string())));
+    attributes.extend(image.height.map(|height| ("height", height.to_string())));
+    attributes.extend(image.fallback_src.clone().map(|src| ("fallback_src", src)));
+    let element = xml_element(w, "image", &attributes);
+    match &image.caption {
+        Some(caption) => {
+            element.write_inner_content(|w| xml_text_element(w, "caption", caption))?;
+        }
+        None => {
+            element.write_empty()?;
+        }
+    }
+    Ok(())
+}
+
+fn write_list_xml(w: &mut XmlWriter, list: &List) -> std::io::Result<()> {
+    let attributes = [("ordered", list.ordered.to_string())];
+    xml_element(w, "list", &attributes).write_inner_content(|w| {
+        for item in &list.items {
+            let attributes: Vec<_> = item
+                .checked
+                .map(|checked| ("checked", checked.to_string()))
+                .into_iter()
+                .collect();
+            xml_element(w, "item", &attributes).write_inner_content(|w| {
+                xml_text_element(w, "text", &item.text)?;
+                item.children
+                    .iter()
+                    .try_for_each(|child| write_list_xml(w, child))
+            })?;
+        }
+        Ok(())
+    })?;
+    Ok(())
+}
 
-    match to_string(document) {
-        Ok(xml) => Ok(xml),
-        Err(e) => {
-            eprintln!("Error serializing document to XML: {:?}", e);
-            Err(MarkdownError::SerializationError(format!(
-                "Failed to serialize to XML: {}",
-                e
-            )))
+/// Code goes in CDATA so it reads verbatim; `]]>` inside it is split across sections
+fn write_code_block_xml(w: &mut XmlWriter, code_block: &CodeBlock) -> std::io::Result<()> {
+    use quick_xml::events::{BytesCData, Event};
+
+    let attributes: Vec<_> = Some(code_block.language.clone())
+        .filter(|language| !language.is_empty())
+        .map(|language| ("language", language))
+        .into_iter()
+        .collect();
+    let code = xml_safe(&code_block.code);
+    xml_element(w, "code_block", &attributes).write_inner_content(|w| {
+        BytesCData::escaped(&code).try_for_each(|section| w.write_event(Event::CData(section)))
+    })?;
+    Ok(())
+}
+
+fn write_table_xml(w: &mut XmlWriter, table: &Table) -> std::io::Result<()> {
+    let attributes: Vec<_> = (!table.alignments.is_empty())
+        .then(|| {
+            let alignments: Vec<&str> = table
+                .alignments
+                .iter()
+                .map(|alignment| match alignment {
+                    ColumnAlignment::None => "none",
+                    ColumnAlignment::Left => "left",
+                    ColumnAlignment::Center => "center",
+                    ColumnAlignment::Right => "right",
+                })
+                .collect();
+            ("align", alignments.join(","))
+        })
+        .into_iter()
+        .collect();
+    let write_cells = |w: &mut XmlWriter, name: &str, cells: &[String]| -> std::io::Result<()> {
+        w.create_element(name).write_inner_content(|w| {
+            cells
+                .iter()
+                .try_for_each(|cell| xml_text_element(w, "cell", cell))
+        })?;
+        Ok(())
+    };
+    xml_element(w, "table", &attributes).write_inner_content(|w| {
+        if !table.headers.is_empty() {
+            write_cells(w, "header", &table.headers)?;
+        }
+        table
+            .rows
+            .iter()
+            .try_for_each(|row| write_cells(w, "row", row))
+    })?;
+    Ok(())
+}
+
+fn write_definition_list_xml(w: &mut XmlWriter, list: &DefinitionList) -> std::io::Result<()> {
+    w.create_element("definition_list")
+        .write_inner_content(|w| {
+            for entry in &list.entries {
+                w.create_element("entry").write_inner_content(|w| {
+                    xml_text_element(w, "term", &entry.term)?;
+                    entry
+                        .definitions
+                        .iter()
+                        .try_for_each(|definition| xml_text_element(w, "definition", definition))
+                })?;
+            }
+            Ok(())
+        })?;
+    Ok(())
+}
+
+fn write_embed_xml(w: &mut XmlWriter, embed: &Embed) -> std::io::Result<()> {
+    let kind = match embed.kind {
+        EmbedKind::Video => "video",
+        EmbedKind::Audio => "audio",
+        EmbedKind::Frame => "frame",
+    };
+    let mut attributes = vec![("kind", kind.to_string()), ("src", embed.src.clone())];
+    attributes.extend(embed.title.clone().map(|title| ("title", title)));
+    xml_element(w, "embed", &attributes).write_empty()?;
+    Ok(())
+}
+
+fn write_block_xml(w: &mut XmlWriter, block: &Block) -> std::io::Result<()> {
+    match block {
+        Block::Heading(heading) => write_heading_xml(w, heading),
+        Block::Paragraph { text } => xml_text_element(w, "paragraph", text),
+        Block::List(list) => write_list_xml(w, list),
+        Block::CodeBlock(code_block) => write_code_block_xml(w, code_block),
+        Block::Blockquote { blocks } => {
+            w.create_element("blockquote")
+                .write_inner_content(|w| blocks.iter().try_for_each(|b| write_block_xml(w, b)))?;
+            Ok(())
+        }
+        Block::Table(table) => write_table_xml(w, table),
+        Block::Image(image) => write_image_xml(w, image),
+        Block::Embed(embed) => write_embed_xml(w, embed),
+        Block::DefinitionList(list) => write_definition_list_xml(w, list),
+        Block::Hr => {
+            w.create_element("hr").write_empty()?;
+            Ok(())
         }
+        Block::Details { summary, blocks } => {
+            w.create_element("details").write_inner_content(|w| {
+                xml_text_element(w, "summary", summary)?;
+                blocks.iter().try_for_each(|b| write_block_xml(w, b))
+            })?;
+            Ok(())
+        }
+    }
+}
+
+/// Export every link and image as CSV (RFC 4180), one row each
+///
+/// Columns are `kind` (`link`/`image`), `text` (link text or alt text), `url` and
+/// `scope`: `internal` when the URL's host matches the document's base URL host,
+/// otherwise `external`. Pass `b'\t'` as the delimiter for TSV.
+pub fn document_to_links_csv(document: &Document, delimiter: u8) -> String {
+    let base_host = Url::parse(&document.base_url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string));
+    let scope = |url: &str| {
+        let host = Url::parse(url)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_string));
+        if host.is_some() && host == base_host {
+            "internal"
+        } else {
+            "external"
+        }
+    };
+
+    let links = document.links.iter().map(|link| {
+        [
+            "link",
+            link.text.as_str(),
+            link.url.as_str(),
+            scope(&link.url),
+        ]
+    });
+    let images = document.images.iter().map(|image| {
+        [
+            "image",
+            image.alt.as_str(),
+            image.src.as_str(),
+            scope(&image.src),
+        ]
+    });
+
+    std::iter::once(["kind", "text", "url", "scope"])
+        .chain(links)
+        .chain(images)
+        .map(|row| {
+            let mut line = row
+                .iter()
+                .map(|field| csv_field(field, delimiter as char))
+                .collect::<Vec<_>>()
+                .join(&(delimiter as char).to_string());
+            line.push_str("\r\n");
+            line
+        })
+        .collect()
+}
+
+/// Quote a CSV field when it holds the delimiter, a quote or a line break
+pub(crate) fn csv_field(field: &str, delimiter: char) -> String {
+    if field.contains([delimiter, '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
     }
 }
 
+/// Convert document to YAML format
+///
+/// Multi-line strings such as code blocks are written as `|` block scalars.
+pub fn document_to_yaml(document: &Document) -> Result<String, MarkdownError> {
+    serde_yaml::to_string(document).map_err(|e| {
+        MarkdownError::SerializationError(format!("Failed to serialize to YAML: {}", e))
+    })
+}
+
 /// Convert HTML to the specified output format
 pub fn convert_html(
     html: &str,
     base_url: &str,
     format: OutputFormat,
 ) -> Result<String, MarkdownError> {
-    let document = parse_html_to_document(html, base_url)?;
+    convert_html_with_options(html, base_url, format, &ConversionOptions::default())
+}
 
+/// Convert a page's raw bytes to the specified output format
+///
+/// The bytes are decoded with the charset from a byte order mark, the page's
+/// `<meta charset>` or `encoding_hint`, see `html_parser::decode_html_bytes`.
+pub fn convert_html_bytes(
+    html: &[u8],
+    base_url: &str,
+    format: OutputFormat,
+    encoding_hint: Option<&str>,
+) -> Result<String, MarkdownError> {
+    convert_html_bytes_with_options(
+        html,
+        base_url,
+        format,
+        encoding_hint,
+        &ConversionOptions::default(),
+    )
+}
+
+/// Convert a page's raw bytes to the specified output format using the given options
+pub fn convert_html_bytes_with_options(
+    html: &[u8],
+    base_url: &str,
+    format: OutputFormat,
+    encoding_hint: Option<&str>,
+    options: &ConversionOptions,
+) -> Result<String, MarkdownError> {
+    let (html, _) = html_parser::decode_html_bytes(html, encoding_hint);
+    convert_html_with_options(&html, base_url, format, options)
+}
+
+/// Convert HTML to the specified output format using the given options
+pub fn convert_html_with_options(
+    html: &str,
+    base_url: &str,
+    format: OutputFormat,
+    options: &ConversionOptions,
+) -> Result<String, MarkdownError> {
+    // Sanitized HTML is re-serialized from the DOM and needs no document model
+    if format == OutputFormat::Html {
+        return html_parser::standalone_html(html, base_url)
+            .map_err(|e| MarkdownError::Other(format!("HTML sanitizing failed: {}", e)));
+    }
+
+    let document = parse_html_to_document_with_options(html, base_url, options)?;
+    render_document(&document, format, options)
+}
+
+/// Render an already parsed document to the specified output format
+///
+/// Sanitized HTML is produced from the source markup, so `OutputFormat::Html` is
+/// not available here.
+pub fn render_document(
+    document: &Document,
+    format: OutputFormat,
+    options: &ConversionOptions,
+) -> Result<String, MarkdownError> {
     match format {
-        OutputFormat::Markdown => Ok(document_to_markdown(&document)),
-        OutputFormat::Json => document_to_json(&document),
-        OutputFormat::Xml => document_to_xml(&document),
+        OutputFormat::Markdown => Ok(document_to_markdown_with_options(document, options)),
+        OutputFormat::Json => document_to_json_with_style(document, options.json_style),
+        OutputFormat::Xml => document_to_xml(document),
+        OutputFormat::Yaml => document_to_yaml(document),
+        OutputFormat::Text => Ok(document_to_text(document)),
+        OutputFormat::Rst => Ok(document_to_rst(document)),
+        OutputFormat::Html => Err(MarkdownError::Other(
+            "HTML output needs the source HTML and cannot be rendered from a document".to_string(),
+        )),
+    }
+}
+
+/// Built-in templates for `convert_html_with_template`, by name
+const BUILTIN_TEMPLATES: [(&str, &str); 2] = [
+    ("default", include_str!("templates/default.md.tera")),
+    ("compact", include_str!("templates/compact.md.tera")),
+];
+
+/// Convert HTML by rendering the document through a Tera template
+///
+/// `template` is either the name of a built-in template (`"default"`: every
+/// collection grouped by kind with Links and Images sections; `"compact"`: headings,
+/// paragraphs, lists and code in document order) or the template source itself.
+/// Every `Document` field is available, e.g. `title`, `headings`, `paragraphs`,
+/// `links`, `images`, `lists`, `code_blocks`, `blockquotes`, `blocks` and `meta`.
+pub fn convert_html_with_template(
+    html: &str,
+    base_url: &str,
+    template: &str,
+) -> Result<String, MarkdownError> {
+    let document = parse_html_to_document(html, base_url)?;
+    let source = BUILTIN_TEMPLATES
+        .iter()
+        .find(|(name, _)| *name == template)
+        .map_or(template, |(_, source)| source);
+
+    let context = tera::Context::from_serialize(&document)
+        .map_err(|e| MarkdownError::TemplateError(template_error_message(&e)))?;
+    let rendered = tera::Tera::one_off(source, &context, false)
+        .map_err(|e| MarkdownError::TemplateError(template_error_message(&e)))?;
+
+    Ok(collapse_blank_lines(&rendered).trim().to_string())
+}
+
+/// Tera reports the useful detail (line, unknown variable, ...) in the error's sources
+fn template_error_message(error: &tera::Error) -> String {
+    let mut message = error.to_string();
+    let mut source = std::error::Error::source(error);
+    while let Some(cause) = source {
+        message.push_str(": ");
+        message.push_str(&cause.to_string());
+        source = cause.source();
     }
+    message
 }
 
 /// Backward compatibility function for convert_to_markdown
@@ -1,6 +1,15 @@
 #[cfg(test)]
 mod html_parser_tests {
-    use crate::html_parser::{clean_html, extract_links, extract_main_content};
+    use crate::html_parser::{
+        CleanOptions, ImageSourceKind, LinkExtractionOptions, LinkFilter, LinkKind,
+        MainContentOptions, NormalizeOptions, ParserError, SanitizePolicy, classify_links,
+        clean_html, clean_html_with_options, clean_parsed_html, extract_forms, extract_headings,
+        extract_headings_flat, extract_images_detailed, extract_links, extract_links_detailed,
+        extract_links_filtered, extract_links_with_options, extract_main_content,
+        extract_main_content_with_options, extract_normalized_links, extract_tables,
+        extract_tables_csv, extract_text_content, get_element_text, normalize_url, resolve_url,
+        sanitize_html, standalone_html,
+    };
 
     #[test]
     fn test_extract_main_content() {
@@ -13,6 +22,746 @@ mod html_parser_tests {
         assert!(!content.contains("Footer content"));
     }
 
+    #[test]
+    fn test_extract_main_content_skips_hidden_in_misnested_markup() {
+        for html in [
+            "<a href=x><div>1<span hidden>a</span><span hidden>b</span>;</a>",
+            "<code><div>1<span hidden>a</span><span hidden>b</span>;</code>",
+        ] {
+            let content = extract_main_content(html).unwrap().root_element().html();
+            assert!(content.contains("1;"), "{content}");
+            assert!(!content.contains("hidden"), "{content}");
+
+            let options = MainContentOptions {
+                include_hidden: true,
+            };
+            let content = extract_main_content_with_options(html, &options)
+                .unwrap()
+                .root_element()
+                .html();
+            assert!(content.contains("1<span hidden=\"\">a</span><span hidden=\"\">b</span>;"));
+        }
+    }
+
+    #[test]
+    fn test_extract_main_content_skips_hidden() {
+        let html = r#"<body><main>
+            <h1>Title<span class="sr-only"> (opens menu)</span></h1>
+            <p>Visible <span aria-hidden="true">&#9733;</span>text</p>
+            <div style="color: red; DISPLAY : None !important">Styled away</div>
+            <div style="visibility:hidden">Invisible</div>
+            <div hidden><p>Nested <b>deeply</b></p></div>
+            <div hidden="until-found">Findable</div>
+            <div aria-hidden="false">Not hidden</div>
+        </main></body>"#;
+
+        let content = extract_main_content(html).unwrap().root_element().html();
+        for hidden in [
+            "opens menu",
+            "&#9733;",
+            "\u{2605}",
+            "Styled away",
+            "Invisible",
+            "deeply",
+        ] {
+            assert!(!content.contains(hidden), "{hidden} survived");
+        }
+        for visible in ["Title", "Visible", "text", "Findable", "Not hidden"] {
+            assert!(content.contains(visible), "{visible} missing");
+        }
+
+        let options = MainContentOptions {
+            include_hidden: true,
+        };
+        let all = extract_main_content_with_options(html, &options).unwrap();
+        assert!(all.root_element().html().contains("deeply"));
+    }
+
+    #[test]
+    fn test_extract_links_detailed() {
+        let html = r##"<html><head><base href="/docs/"></head><body>
+            <nav><a href="intro" rel="Next">Intro</a></nav>
+            <main><p><a href="intro" title=" Start here " rel="bookmark next">Getting started</a>
+            <a href="./intro"><img src="i.png" alt="Intro  icon"></a>
+            <a href="intro">Intro</a>
+            <a href="https://cdn.example.com/file" rel="nofollow noopener">CDN</a>
+            <a href="javascript:void(0)">JS</a><a href="">Empty</a><a href="#top">Top</a></p></main>
+            </body></html>"##;
+
+        let links = extract_links_detailed(html, "https://example.com/page").unwrap();
+        assert_eq!(links.len(), 2);
+
+        let intro = &links[0];
+        assert_eq!(intro.url, "https://example.com/docs/intro");
+        assert_eq!(intro.text, "Intro | Getting started | Intro icon");
+        assert_eq!(intro.title.as_deref(), Some("Start here"));
+        assert_eq!(intro.rel, ["next", "bookmark"]);
+        assert!(!intro.is_external);
+        assert_eq!(intro.dom_path_hint, "body > nav > a");
+
+        let cdn = &links[1];
+        assert!(cdn.is_external && cdn.title.is_none());
+        assert_eq!(cdn.rel, ["nofollow", "noopener"]);
+        assert_eq!(cdn.dom_path_hint, "body > main > p > a");
+
+        // the simple list resolves the same URLs
+        let mut urls: Vec<String> = links.into_iter().map(|link| link.url).collect();
+        urls.sort();
+        assert_eq!(
+            extract_links(html, "https://example.com/page").unwrap(),
+            urls
+        );
+
+        // `www.` is the same host for both the flag and the kind
+        let links = extract_links_detailed(
+            r#"<a href="https://www.example.com/a">A</a><a href="https://EXAMPLE.com./b">B</a>"#,
+            "https://example.com/",
+        )
+        .unwrap();
+        for link in &links {
+            assert!(!link.is_external);
+            assert_eq!(link.kind, LinkKind::Internal);
+        }
+    }
+
+    #[test]
+    fn test_extract_links_filtered() {
+        let html = r#"<a href="/docs/intro">Intro</a>
+            <a href="/docs/draft/intro">Draft</a>
+            <a href="/files/report.PDF">Report</a>
+            <a href="/blog/post">Post</a>
+            <a href="https://blog.example.co.uk/post">Blog</a>
+            <a href="https://example.com/">Elsewhere</a>
+            <a href="ftp://example.co.uk/file">FTP</a>"#;
+        let base = "https://www.example.co.uk/";
+        let regex = |pattern: &str| regex::Regex::new(pattern).unwrap();
+
+        let same_site = LinkFilter {
+            same_domain_only: true,
+            exclude_extensions: vec![".pdf".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            extract_links_filtered(html, base, &same_site).unwrap(),
+            [
+                "https://blog.example.co.uk/post",
+                "https://www.example.co.uk/blog/post",
+                "https://www.example.co.uk/docs/draft/intro",
+                "https://www.example.co.uk/docs/intro",
+            ]
+        );
+
+        let same_host = LinkFilter {
+            include_subdomains: false,
+            allowed_schemes: Vec::new(),
+            ..same_site.clone()
+        };
+        let links = extract_links_filtered(html, base, &same_host).unwrap();
+        assert_eq!(links.len(), 3);
+        assert!(!links.iter().any(|link| link.contains("blog.example")));
+
+        // an exclude pattern wins over a matching include pattern
+        let docs = LinkFilter {
+            include_patterns: vec![regex("/docs/"), regex("/blog/")],
+            exclude_patterns: vec![regex("/draft/"), regex("^https://blog\\.")],
+            ..same_site
+        };
+        assert_eq!(
+            extract_links_filtered(html, base, &docs).unwrap(),
+            [
+                "https://www.example.co.uk/blog/post",
+                "https://www.example.co.uk/docs/intro",
+            ]
+        );
+
+        let everything = extract_links_filtered(html, base, &LinkFilter::default()).unwrap();
+        assert_eq!(everything.len(), 6);
+        assert!(!everything.iter().any(|link| link.starts_with("ftp:")));
+    }
+
+    #[test]
+    fn test_normalize_url() {
+        let defaults = NormalizeOptions::default();
+        let cases = [
+            (
+                "HTTP://WWW.Example.COM:80/Path",
+                "http://www.example.com/Path",
+            ),
+            ("https://example.com:443/a", "https://example.com/a"),
+            ("https://example.com:8443/a", "https://example.com:8443/a"),
+            (
+                "https://example.com//docs///api/",
+                "https://example.com/docs/api/",
+            ),
+            ("https://example.com/a#section", "https://example.com/a"),
+            (
+                "https://example.com/?utm_source=x&UTM_Medium=y&fbclid=1&gclid=2",
+                "https://example.com/",
+            ),
+            (
+                "https://example.com/?b=2&a=1&b=1",
+                "https://example.com/?a=1&b=2&b=1",
+            ),
+            // unreserved escapes are decoded, others uppercased, encoded spaces kept
+            (
+                "https://example.com/%7euser/%e4%b8%ad?q=a%2fb+c&r=%41",
+                "https://example.com/~user/%E4%B8%AD?q=a%2Fb+c&r=A",
+            ),
+            (
+                "https://example.com/caf\u{e9}",
+                "https://example.com/caf%C3%A9",
+            ),
+            (
+                "https://B\u{fc}cher.Example/",
+                "https://xn--bcher-kva.example/",
+            ),
+            ("mailto:Someone@Example.com#x", "mailto:Someone@Example.com"),
+            ("not a url", "not a url"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(normalize_url(input, &defaults), expected, "{input}");
+        }
+
+        let keep_fragment = NormalizeOptions {
+            strip_fragment: false,
+            removed_params: vec!["ref".to_string()],
+        };
+        assert_eq!(
+            normalize_url(
+                "https://example.com/?ref=a&utm_source=b#top",
+                &keep_fragment
+            ),
+            "https://example.com/?utm_source=b#top"
+        );
+
+        let html = r#"<a href="/a#one">1</a><a href="/a#two">2</a><a href="/a?utm_id=3">3</a>"#;
+        assert_eq!(
+            extract_normalized_links(html, "https://example.com", &defaults).unwrap(),
+            ["https://example.com/a"]
+        );
+        assert_eq!(extract_links(html, "https://example.com").unwrap().len(), 3);
+
+        let options = crate::markdown_converter::ConversionOptions {
+            normalize_links: Some(defaults),
+            ..Default::default()
+        };
+        let document = crate::markdown_converter::parse_html_to_document_with_options(
+            html,
+            "https://example.com",
+            &options,
+        )
+        .unwrap();
+        assert_eq!(document.links.len(), 3);
+        assert!(
+            document
+                .links
+                .iter()
+                .all(|link| link.url == "https://example.com/a")
+        );
+    }
+
+    #[test]
+    fn test_resolve_url_schemes() {
+        let base = "https://example.com/docs/page";
+        let cases = [
+            ("//cdn.example.com/app.js", "https://cdn.example.com/app.js"),
+            ("mailto:team@example.com", "mailto:team@example.com"),
+            ("tel:+1-555-0100", "tel:+1-555-0100"),
+            ("data:text/plain;base64,SGk=", "data:text/plain;base64,SGk="),
+            (
+                "FTP://files.example.com/a.zip",
+                "FTP://files.example.com/a.zip",
+            ),
+            ("../img/a.png", "https://example.com/img/a.png"),
+            ("?page=2", "https://example.com/docs/page?page=2"),
+        ];
+        for (href, expected) in cases {
+            assert_eq!(resolve_url(base, href).unwrap(), expected, "{href}");
+        }
+        assert_eq!(
+            resolve_url("http://example.com", "//cdn.example.com/x").unwrap(),
+            "http://cdn.example.com/x"
+        );
+        // absolute URLs do not depend on the base
+        assert_eq!(resolve_url("not a base", "tel:123").unwrap(), "tel:123");
+        assert!(resolve_url(base, "http://[::1").is_err());
+        assert!(resolve_url(base, "//[bad host/").is_err());
+
+        let html = r#"<a href="//cdn.example.com/file">CDN</a><a href="mailto:a@example.com">Mail</a>
+            <a href="http://[::1">Broken</a>"#;
+        assert_eq!(
+            extract_links(html, base).unwrap(),
+            ["https://cdn.example.com/file", "mailto:a@example.com"]
+        );
+
+        let markdown = crate::markdown_converter::convert_to_markdown(
+            &format!("{}<img src=\"//cdn.example.com/a.png\" alt=\"A\">", html),
+            base,
+        )
+        .unwrap();
+        assert!(markdown.contains("[CDN](https://cdn.example.com/file)"));
+        assert!(markdown.contains("[Mail](mailto:a@example.com)"));
+        assert!(markdown.contains("![A](https://cdn.example.com/a.png)"));
+        // an href that cannot be resolved keeps its text but does not link anywhere
+        assert!(
+            markdown.contains("Broken") && !markdown.contains("](https://example.com/docs/page)")
+        );
+    }
+
+    #[test]
+    fn test_link_extraction_options() {
+        let html = r##"<head><base href="https://example.com/docs/"></head>
+            <a href="MAILTO:Team@Example.com">Mail</a><a href="tel:+1-555-0100">Call</a>
+            <a href="#faq">FAQ</a><a href=" JavaScript:void(0)">JS</a><a href="guide">Guide</a>"##;
+        let base = "https://example.com/page";
+        let extract = |options: LinkExtractionOptions| {
+            extract_links_with_options(html, base, &options).unwrap()
+        };
+
+        let defaults = extract(LinkExtractionOptions::default());
+        assert_eq!(
+            defaults,
+            [
+                "MAILTO:Team@Example.com",
+                "https://example.com/docs/guide",
+                "tel:+1-555-0100"
+            ]
+        );
+        assert_eq!(extract_links(html, base).unwrap(), defaults);
+
+        let no_contacts = extract(LinkExtractionOptions {
+            include_mailto: false,
+            include_tel: false,
+            ..Default::default()
+        });
+        assert_eq!(no_contacts, ["https://example.com/docs/guide"]);
+
+        let resolved_fragments = extract(LinkExtractionOptions {
+            include_mailto: false,
+            include_tel: false,
+            include_fragments: true,
+            ..Default::default()
+        });
+        assert_eq!(
+            resolved_fragments,
+            [
+                "https://example.com/docs/#faq",
+                "https://example.com/docs/guide"
+            ]
+        );
+
+        let raw_fragments = extract(LinkExtractionOptions {
+            include_tel: false,
+            include_fragments: true,
+            resolve_fragments_against_base: false,
+            ..Default::default()
+        });
+        assert_eq!(
+            raw_fragments,
+            [
+                "#faq",
+                "MAILTO:Team@Example.com",
+                "https://example.com/docs/guide"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_extract_images_detailed() {
+        let inline = format!("data:image/png;base64,{}", "A".repeat(300));
+        let html = format!(
+            r#"<main>
+            <picture>
+                <source srcset="hero-800.webp 800w, hero-1600.webp 1600w" type="image/webp">
+                <img src="hero-400.jpg" alt="Hero" width="400">
+            </picture>
+            <img src="thumb.jpg" srcset="thumb.jpg 1x, thumb@2x.jpg 2x" alt="Thumb">
+            <img class="lazy" src="spacer.gif" data-original="/photos/cat.jpg" width="300px" height="200">
+            <img src="/photos/cat.jpg" alt="  A   cat ">
+            <img src="plain.png">
+            <img src="{inline}" alt="Inline">
+            <img src="data:image/gif;base64,R0lGOD">
+            <img alt="No source">
+            </main>"#
+        );
+        let base = "https://example.com/blog/";
+
+        let images = extract_images_detailed(&html, base, None).unwrap();
+        let summary: Vec<(&str, &str, ImageSourceKind)> = images
+            .iter()
+            .map(|image| (image.url.as_str(), image.alt.as_str(), image.source))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (
+                    "https://example.com/blog/hero-1600.webp",
+                    "Hero",
+                    ImageSourceKind::Srcset
+                ),
+                (
+                    "https://example.com/blog/thumb@2x.jpg",
+                    "Thumb",
+                    ImageSourceKind::Srcset
+                ),
+                (
+                    "https://example.com/photos/cat.jpg",
+                    "A cat",
+                    ImageSourceKind::DataSrc
+                ),
+                (
+                    "https://example.com/blog/plain.png",
+                    "",
+                    ImageSourceKind::Src
+                ),
+            ]
+        );
+        assert_eq!((images[0].width, images[0].height), (Some(400), None));
+        assert_eq!((images[2].width, images[2].height), (Some(300), Some(200)));
+
+        let with_inline = extract_images_detailed(&html, base, Some(256)).unwrap();
+        assert_eq!(with_inline.len(), 5);
+        assert_eq!(with_inline[4].url, inline);
+        assert_eq!(with_inline[4].source, ImageSourceKind::Src);
+        assert_eq!(
+            extract_images_detailed(&html, base, Some(1024))
+                .unwrap()
+                .len(),
+            4
+        );
+        // the caller's threshold replaces the placeholder length rule
+        let tiny = extract_images_detailed(&html, base, Some(20)).unwrap();
+        assert_eq!(tiny.len(), 6);
+        assert_eq!(tiny[5].url, "data:image/gif;base64,R0lGOD");
+    }
+
+    #[test]
+    fn test_extract_tables() {
+        let html = r#"<body>
+            <table>
+                <caption> Quarterly  results </caption>
+                <thead><tr><th>Region</th><th colspan="2">Sales</th></tr></thead>
+                <tbody>
+                    <tr><th>North</th><td>10</td><td>12</td></tr>
+                    <tr><th rowspan="2">South</th><td>"7"</td><td>8, est.</td></tr>
+                    <tr><td>9</td><td>
+                        <table><tr><th>Note</th></tr><tr><td>revised</td></tr></table>
+                    </td></tr>
+                </tbody>
+            </table>
+            <table><tr><td>solo</td></tr></table>
+            <table></table>
+        </body>"#;
+
+        let tables = extract_tables(html);
+        assert_eq!(tables.len(), 3);
+
+        let outer = &tables[0];
+        assert_eq!(outer.caption.as_deref(), Some("Quarterly results"));
+        assert_eq!(outer.headers, ["Region", "Sales", "Sales"]);
+        assert_eq!(
+            outer.rows,
+            [
+                ["North", "10", "12"],
+                ["South", "\"7\"", "8, est."],
+                ["South", "9", ""],
+            ]
+        );
+
+        // the nested table stands on its own
+        let nested = &tables[1];
+        assert_eq!(nested.caption, None);
+        assert_eq!(nested.headers, ["Note"]);
+        assert_eq!(nested.rows, [["revised"]]);
+
+        assert!(tables[2].headers.is_empty());
+        assert_eq!(tables[2].rows, [["solo"]]);
+
+        let csv = extract_tables_csv(html);
+        assert_eq!(
+            csv[0],
+            "Region,Sales,Sales\r\nNorth,10,12\r\nSouth,\"\"\"7\"\"\",\"8, est.\"\r\nSouth,9,\r\n"
+        );
+        assert_eq!(csv[2], "solo\r\n");
+    }
+
+    #[test]
+    fn test_extract_headings_outline() {
+        let html = r##"<body>
+            <h3>Preface</h3>
+            <h1 id="guide">The <a href="/guide">Guide</a></h1>
+            <h2><a name="install"></a>Install  <code>lab</code></h2>
+            <h4>From <a href="https://github.com/x/y">source</a></h4>
+            <h3>Verify</h3>
+            <h2>Usage &amp; tips</h2>
+            <h5>Deep &amp;lt;dive&amp;gt;</h5>
+            <h2></h2>
+            <h6 class="sr-only">Skip links</h6>
+            <h1>Appendix</h1>
+        </body>"##;
+
+        let outline = extract_headings(html);
+        let texts = |nodes: &[crate::html_parser::HeadingNode]| {
+            nodes.iter().map(|n| n.text.clone()).collect::<Vec<_>>()
+        };
+        assert_eq!(texts(&outline), ["Preface", "The Guide", "Appendix"]);
+        assert!(outline[0].children.is_empty());
+
+        let guide = &outline[1];
+        assert_eq!(guide.id.as_deref(), Some("guide"));
+        assert_eq!(texts(&guide.children), ["Install lab", "Usage & tips"]);
+        let install = &guide.children[0];
+        assert_eq!(install.id.as_deref(), Some("install"));
+        assert_eq!(texts(&install.children), ["From source", "Verify"]);
+        assert_eq!(install.children[0].level, 4);
+        assert_eq!(texts(&guide.children[1].children), ["Deep &lt;dive&gt;"]);
+
+        let flat = extract_headings_flat(html);
+        assert_eq!(flat.len(), 8);
+        assert!(flat.iter().all(|heading| heading.children.is_empty()));
+        assert_eq!(
+            flat.iter().map(|h| h.level).collect::<Vec<_>>(),
+            [3, 1, 2, 4, 3, 2, 5, 1]
+        );
+    }
+
+    #[test]
+    fn test_get_element_text_spacing() {
+        let text_of = |html: &str| {
+            let fragment = scraper::Html::parse_fragment(html);
+            let selector = scraper::Selector::parse("#t").unwrap();
+            get_element_text(&fragment.select(&selector).next().unwrap())
+        };
+
+        assert_eq!(
+            text_of(r#"<div id="t">Hello <b>world</b>!</div>"#),
+            "Hello world!"
+        );
+        assert_eq!(
+            text_of(r#"<div id="t">See <a href="/x">docs</a>, then (<i>maybe</i>) ask.</div>"#),
+            "See docs, then (maybe) ask."
+        );
+        assert_eq!(
+            text_of(r#"<p id="t"><span>foo</span><span>bar</span></p>"#),
+            "foobar"
+        );
+        assert_eq!(
+            text_of(r#"<p id="t"><span>foo</span> <span>bar</span></p>"#),
+            "foo bar"
+        );
+        assert_eq!(
+            text_of(r#"<p id="t">H<sub>2</sub>O &amp; <em>co</em>.</p>"#),
+            "H2O & co."
+        );
+        assert_eq!(
+            text_of(r#"<p id="t">Write &amp;amp; and &amp;lt;</p>"#),
+            "Write &amp; and &lt;"
+        );
+        assert_eq!(text_of(r#"<p id="t">line<br>break</p>"#), "line break");
+        assert_eq!(
+            text_of(r#"<div id="t"><p>One</p><p>Two</p><ul><li>a</li><li>b</li></ul>end</div>"#),
+            "One Two a b end"
+        );
+        assert_eq!(
+            text_of("<div id=\"t\">\n  spaced \t\n <b> out </b>\n</div>"),
+            "spaced out"
+        );
+    }
+
+    #[test]
+    fn test_extract_text_content() {
+        let html = r#"<html><head><title>Ignored</title><style>p { color: red }</style></head>
+        <body>
+            <script>var x = "<p>no</p>";</script>
+            <div class="outer">
+                <div>First <b>bold</b>
+                    <div>nested   block</div>
+                    tail</div>
+                <div></div><div>   </div>
+                <p>Para one<br>second line</p>
+                <p></p><p><br><br><br></p>
+                <h2>Heading</h2>
+                <ul><li>one</li><li>two <a href="/x">link</a>.</li></ul>
+            </div>
+            <table>
+                <tr><th>Name</th><th> Qty </th></tr>
+                <tr><td>Apple</td><td></td></tr>
+                <tr><td>Pear</td><td>2</td></tr>
+            </table>
+            <pre>fn main() {
+    body();
+}</pre>
+        </body></html>"#;
+
+        assert_eq!(
+            extract_text_content(html),
+            "First bold\nnested block\ntail\n\nPara one\nsecond line\n\nHeading\n\n\
+             one\ntwo link.\n\nName\tQty\nApple\t\nPear\t2\n\nfn main() {\n    body();\n}"
+        );
+        assert_eq!(extract_text_content("<p>  </p>"), "");
+        assert_eq!(
+            extract_text_content("<p>Search for &amp;lt; and &amp;amp;</p>"),
+            "Search for &lt; and &amp;"
+        );
+    }
+
+    #[test]
+    fn test_link_kinds() {
+        let html = r#"<main>
+            <a href="/about">About</a>
+            <a href="https://example.co.uk/contact">Contact</a>
+            <a href="https://shop.example.co.uk/">Shop</a>
+            <a href="https://example.uk.com/">Lookalike</a>
+            <a href="https://other.co.uk/">Other</a>
+            <a href="https://bbc.co.uk/news">BBC</a>
+            <a href="/files/report.pdf?v=2">Report</a>
+            <a href="https://cdn.net/video/intro.MP4">Video</a>
+            <a href="https://www.example.co.uk/photos/">Photos</a>
+            <a href="/releases/v1.2">Release</a>
+            <a href="mailto:hi@example.co.uk">Mail</a>
+        </main>"#;
+        let links = extract_links_detailed(html, "https://www.example.co.uk/").unwrap();
+        let kinds: Vec<_> = links.iter().map(|link| link.kind.as_str()).collect();
+        assert_eq!(
+            kinds,
+            [
+                "internal",
+                "internal",
+                "subdomain",
+                "external",
+                "external",
+                "external",
+                "asset",
+                "asset",
+                "internal",
+                "internal",
+                "external",
+            ]
+        );
+        assert!(!links[1].is_external && links[1].kind == LinkKind::Internal);
+
+        let urls = vec![
+            "https://a.example.com/".to_string(),
+            "https://example.com.au/".to_string(),
+            "https://x.example.com.au/".to_string(),
+            "not a url".to_string(),
+        ];
+        assert_eq!(
+            classify_links(&urls, "https://example.com.au/").unwrap(),
+            [
+                LinkKind::External,
+                LinkKind::Internal,
+                LinkKind::Subdomain,
+                LinkKind::External
+            ]
+        );
+        assert!(classify_links(&urls, "relative/page").is_err());
+    }
+
+    #[test]
+    fn test_clean_html_removes_forms() {
+        let article = "Tides rise and fall twice a day because the moon pulls on the oceans. \
+            The sun adds a smaller pull of its own, so the highest spring tides come when \
+            both line up at new and full moon, and the weakest neap tides come between them.";
+        let html = format!(
+            r#"<body><main>
+            <form role="search" action="/search"><label for="q">Search</label>
+                <input id="q" name="q"><button>Go</button></form>
+            <article><p>{article}</p>
+                <ul><li><input type="checkbox" checked> Read</li></ul></article>
+            <form class="newsletter"><p>Get the weekly digest</p>
+                <input type="email" placeholder="Email"><button>Subscribe</button></form>
+            <div class="consent"><button>Accept all cookies</button></div>
+            </main></body>"#
+        );
+
+        let cleaned = clean_html(&html).unwrap();
+        for removed in [
+            "Search",
+            "Go",
+            "weekly digest",
+            "Subscribe",
+            "Accept all cookies",
+        ] {
+            assert!(!cleaned.contains(removed), "{removed} survived");
+        }
+        assert!(cleaned.contains(article), "{cleaned}");
+        assert!(cleaned.contains(r#"type="checkbox""#), "{cleaned}");
+
+        let kept = clean_html_with_options(
+            &html,
+            &CleanOptions {
+                keep_forms: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert!(kept.contains("Subscribe") && kept.contains("Accept all cookies"));
+
+        // ASP.NET pages wrap everything in one form; only its controls go
+        let wrapped = format!(
+            r#"<body><form id="aspnetForm" action="default.aspx" method="post">
+            <input type="hidden" name="__VIEWSTATE" value="abc">
+            <div><p>{article}</p></div><button>Sign up</button></form></body>"#
+        );
+        let cleaned = clean_html(&wrapped).unwrap();
+        assert!(cleaned.contains(article));
+        assert!(!cleaned.contains("Sign up") && !cleaned.contains("VIEWSTATE"));
+        let with_main =
+            r#"<form><main><p>Short story.</p></main><select><option>A</option></select></form>"#;
+        let cleaned = clean_html(with_main).unwrap();
+        assert!(cleaned.contains("Short story.") && !cleaned.contains("<option>"));
+    }
+
+    #[test]
+    fn test_extract_forms() {
+        let html = r#"<body><main><article><p>Story</p></article>
+            <form action="/subscribe" method="POST">
+                <label>Email <input type="email" name="email" required></label>
+                <label for="freq">How often</label>
+                <select id="freq" name="frequency">
+                    <option value="d">Daily</option><option selected>Weekly</option>
+                </select>
+                <textarea name="note" aria-label="Note">hi</textarea>
+                <input type="hidden" name="token" value="xyz">
+                <input type="submit" value="Subscribe"><button>Later</button>
+            </form>
+            <form method="fetch"><input name="q" placeholder="Search"></form>
+        </main></body>"#;
+
+        let forms = extract_forms(html);
+        assert_eq!(forms.len(), 2);
+        assert_eq!(forms[0].action.as_deref(), Some("/subscribe"));
+        assert_eq!(forms[0].method, "POST");
+        let fields: Vec<_> = forms[0]
+            .fields
+            .iter()
+            .map(|field| {
+                (
+                    field.name.as_deref().unwrap_or_default(),
+                    field.kind.as_str(),
+                    field.label.as_deref(),
+                    field.value.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            fields,
+            [
+                ("email", "email", Some("Email"), None),
+                ("frequency", "select", Some("How often"), Some("Weekly")),
+                ("note", "textarea", Some("Note"), Some("hi")),
+                ("token", "hidden", None, Some("xyz")),
+            ]
+        );
+        assert!(forms[0].fields[0].required && !forms[0].fields[1].required);
+        assert_eq!(forms[0].fields[1].options, ["d", "Weekly"]);
+
+        assert_eq!(forms[1].action, None);
+        assert_eq!(forms[1].method, "GET");
+        assert_eq!(forms[1].fields[0].label.as_deref(), Some("Search"));
+    }
+
     #[test]
     fn test_clean_html() {
         let html = "<div><script>alert('test');</script><p>Keep this content</p><style>.test{color:red;}</style><div class=\"ad\">Remove this ad</div></div>";
@@ -24,6 +773,341 @@ mod html_parser_tests {
         assert!(!result.contains(".test{color:red;}"));
     }
 
+    #[test]
+    fn test_misnested_markup_is_edited_safely() {
+        // the adoption agency leaves these trees with parent links that disagree
+        // with the child lists
+        let html = "<code><div>1<input><nav></nav>;</code><p";
+        let cleaned = clean_html(html).unwrap();
+        assert!(cleaned.contains("<div><code>1;</code></div>"), "{cleaned}");
+        let options = CleanOptions {
+            keep_comments: true,
+            ..Default::default()
+        };
+        assert_eq!(clean_html_with_options(html, &options).unwrap(), cleaned);
+
+        let mut document = scraper::Html::parse_document(
+            "<code><div>1<noscript><code><div>2<img src=b.png>;</code></noscript>;</code>",
+        );
+        crate::html_parser::lift_noscript(&mut document);
+        let lifted = document.root_element().html();
+        assert!(
+            lifted.contains(
+                "<code>1<code></code><div><code>2<img src=\"b.png\">;</code></div>;</code>"
+            ),
+            "{lifted}"
+        );
+
+        let frames = std::collections::HashMap::from([(
+            "https://example.com/frame.html".to_string(),
+            "<code><div>2<span>x</span>;</code>".to_string(),
+        )]);
+        let spliced = crate::html_parser::splice_frames(
+            r#"<code><div>1<iframe src="/frame.html"></iframe>;</code>"#,
+            "https://example.com/",
+            &frames,
+        )
+        .unwrap();
+        assert!(
+            spliced
+                .contains("<code>1<code></code><div><code>2<span>x</span>;</code></div>;</code>"),
+            "{spliced}"
+        );
+    }
+
+    #[test]
+    fn test_clean_html_removes_iframes() {
+        let html = r#"<body><main><p>Story</p>
+            <iframe src="https://www.youtube.com/embed/dQw4w9WgXcQ" title="Talk"></iframe>
+            </main></body>"#;
+
+        let cleaned = clean_html(html).unwrap();
+        assert!(cleaned.contains("Story"));
+        assert!(!cleaned.contains("<iframe") && !cleaned.contains("youtube"));
+        let standalone = standalone_html(html, "https://example.com").unwrap();
+        assert!(!standalone.contains("<iframe") && !standalone.contains("youtube"));
+    }
+
+    #[test]
+    fn test_clean_html_removes_math_scripts() {
+        let html =
+            r#"<body><p>Euler: <script type="math/tex">e^{i\pi} + 1 = 0</script></p></body>"#;
+
+        let cleaned = clean_html(html).unwrap();
+        assert!(cleaned.contains("Euler:"));
+        assert!(!cleaned.contains("<script") && !cleaned.contains("e^{i"));
+    }
+
+    #[test]
+    fn test_clean_html_keeps_text_repeated_from_removed_elements() {
+        let html = r#"<body><script>var label = "<p>Keep this content</p>";</script>
+            <p>Keep this content</p><div class="ad">Keep this content</div>
+            <nav><div class="ad">Nested ad</div><a href="/">Home</a></nav></body>"#;
+
+        let result = clean_html(html).unwrap();
+        assert_eq!(result.matches("Keep this content").count(), 1);
+        assert!(result.contains("<p>Keep this content</p>"));
+        assert!(!result.contains("<script") && !result.contains("var label"));
+        assert!(!result.contains("Nested ad") && !result.contains("Home"));
+    }
+
+    #[test]
+    fn test_clean_html_removes_elements_regardless_of_markup() {
+        let html = "<body><DIV data-z=1 CLASS='banner' data-a=\"x\">Promo</DIV>\
+            <img src=a.png alt=pic/><noscript><img src=\"pixel.gif\"/></noscript>\
+            <p title='a &amp; b'>Body &amp; text</p></body>";
+
+        let result = clean_html(html).unwrap();
+        assert!(!result.contains("Promo") && !result.contains("pixel.gif"));
+        assert!(result.contains("Body &amp; text") && result.contains("a.png"));
+
+        let document = scraper::Html::parse_document(html);
+        let cleaned = clean_parsed_html(&document).unwrap().root_element().html();
+        assert_eq!(cleaned, result);
+    }
+
+    #[test]
+    fn test_clean_html_removes_comments() {
+        let html = r#"<body><!-- tracking: <img src="https://t.example/p.gif"> -->
+            <p>Before<!-- inline -->After</p>
+            <!--[if lt IE 9]><script src="html5shiv.js"></script><p>Upgrade</p><![endif]-->
+            <![if !IE]><p>Modern</p><![endif]>
+            <pre>let a = 1;<!-- hidden -->
+let b = 2;</pre></body>"#;
+
+        let result = clean_html(html).unwrap();
+        for removed in [
+            "<!--",
+            "tracking",
+            "t.example",
+            "Upgrade",
+            "html5shiv",
+            "hidden",
+        ] {
+            assert!(!result.contains(removed), "{removed} survived");
+        }
+        assert!(result.contains("<p>BeforeAfter</p>"));
+        assert!(result.contains("<p>Modern</p>"));
+        assert!(result.contains("<pre>let a = 1;\nlet b = 2;</pre>"));
+
+        let document = scraper::Html::parse_document(html);
+        assert_eq!(
+            clean_parsed_html(&document).unwrap().root_element().html(),
+            result
+        );
+
+        let keep = CleanOptions {
+            keep_comments: true,
+            ..Default::default()
+        };
+        let kept = clean_html_with_options(html, &keep).unwrap();
+        assert!(kept.contains("<!-- inline -->") && kept.contains("<!-- hidden -->"));
+        assert_eq!(
+            clean_html_with_options(html, &CleanOptions::default()).unwrap(),
+            result
+        );
+    }
+
+    #[test]
+    fn test_clean_html_sanitizes_attributes() {
+        let html = r#"<body>
+            <p OnClick="steal()" onMouseOver='x()' style="color:red" class="lead" id="intro">Hi</p>
+            <a href="java&#115;cript:alert(1)">one</a>
+            <a href=" JaVa&#x09;Script:alert(2)">two</a>
+            <a href="vbscript:msgbox">three</a>
+            <a href="data:text/html;base64,PHNjcmlwdD4=">four</a>
+            <a href="/docs" title="on call">five</a>
+            <img src="data:image/png;base64,iVBORw0KGgo=" alt="dot">
+        </body>"#;
+
+        let result = clean_html(html).unwrap();
+        for removed in [
+            "onclick",
+            "onmouseover",
+            "steal",
+            "script:",
+            "data:text/html",
+        ] {
+            assert!(
+                !result.to_ascii_lowercase().contains(removed),
+                "{removed} survived"
+            );
+        }
+        assert!(result.contains(r#"style="color:red""#) && result.contains(r#"id="intro""#));
+        assert!(result.contains("<a>one</a>") && result.contains("<a>four</a>"));
+        assert!(result.contains(r#"<a href="/docs" title="on call">five</a>"#));
+        assert!(result.contains("data:image/png"));
+
+        let document = scraper::Html::parse_document(html);
+        assert_eq!(
+            clean_parsed_html(&document).unwrap().root_element().html(),
+            result
+        );
+
+        let options = CleanOptions {
+            strip_style: true,
+            strip_class: true,
+            strip_id: true,
+            ..Default::default()
+        };
+        let stripped = clean_html_with_options(html, &options).unwrap();
+        assert!(stripped.contains("<p>Hi</p>"));
+    }
+
+    #[test]
+    fn test_clean_html_with_options() {
+        let html = r#"<body><header><h1>Site</h1></header>
+            <div class="newsletter-signup">Sign up</div><div id="paywall">Subscribe</div>
+            <nav class="keep"><a href="/">Home</a></nav>
+            <footer><span class="keep">Fine print</span></footer>
+            <p>Story</p></body>"#;
+        let options = CleanOptions {
+            extra_remove_selectors: vec![".newsletter-signup".to_string(), "#paywall".to_string()],
+            keep_selectors: vec![
+                "header".to_string(),
+                "nav.keep".to_string(),
+                ".keep".to_string(),
+            ],
+            ..Default::default()
+        };
+
+        let result = clean_html_with_options(html, &options).unwrap();
+        assert!(result.contains("Site") && result.contains("Home") && result.contains("Story"));
+        assert!(!result.contains("Sign up") && !result.contains("Subscribe"));
+        // keeping an element does not rescue it from a removed ancestor
+        assert!(!result.contains("Fine print"));
+
+        assert_eq!(
+            clean_html_with_options(html, &CleanOptions::default()).unwrap(),
+            clean_html(html).unwrap()
+        );
+
+        let invalid = CleanOptions {
+            keep_selectors: vec!["header".to_string(), "div[".to_string()],
+            ..Default::default()
+        };
+        match clean_html_with_options(html, &invalid) {
+            Err(ParserError::SelectorError(message)) => assert!(message.contains("\"div[\"")),
+            other => panic!("expected a selector error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_standalone_html_rejects_script_urls() {
+        let html = "<body><main>\
+            <a href=\"java\tscript:alert(1)\">tab</a>\
+            <a href=\"java&#x09;script:alert(2)\">entity tab</a>\
+            <a href=\"java&#115;cript:alert(3)\">entity</a>\
+            <a href=\"java&amp;#115;cript:alert(3)\">double</a>\
+            <a href=\" VBScript:msgbox\">vb</a>\
+            <a href=\"data:text/html,<script>alert(4)</script>\">data</a>\
+            <iframe src=\"data:text/html;base64,PHNjcmlwdD4=\"></iframe>\
+            <form action=\"javascript:alert(5)\"><button formaction=\"javascript:alert(6)\">Go</button></form>\
+            <form action=\"/search\"><button formaction=\"submit\">Find</button></form>\
+            <img src=\"data:image/png;base64,iVBORw0KGgo=\" alt=\"dot\">\
+            </main></body>";
+        let sanitized = standalone_html(html, "https://example.com/docs/").unwrap();
+
+        for removed in ["script:", "alert", "data:text/html"] {
+            assert!(
+                !sanitized.to_ascii_lowercase().contains(removed),
+                "{removed} survived in {sanitized}"
+            );
+        }
+        assert!(
+            sanitized.contains("<a>tab</a><a>entity tab</a><a>entity</a><a>double</a><a>vb</a>")
+        );
+        assert!(sanitized.contains("<form><button>Go</button></form>"));
+        assert!(sanitized.contains(
+            r#"<form action="https://example.com/search"><button formaction="https://example.com/docs/submit">"#
+        ));
+        assert!(sanitized.contains(r#"src="data:image/png;base64,iVBORw0KGgo=""#));
+    }
+
+    #[test]
+    fn test_standalone_html_drops_srcdoc_and_script_data_urls() {
+        let html = "<body><main>\
+            <iframe srcdoc=\"<script>alert(1)</script>\"></iframe>\
+            <object data=\"javascript:alert(2)\"></object>\
+            <object data=\"/chart.svg\" codebase=\"java&#x09;script:alert(3)\"></object>\
+            </main></body>";
+        let sanitized = standalone_html(html, "https://example.com").unwrap();
+        assert!(
+            !sanitized.contains("alert") && !sanitized.contains("srcdoc"),
+            "{sanitized}"
+        );
+        assert!(!sanitized.contains("<iframe") && !sanitized.contains("<object"));
+
+        let cleaned = clean_html(html).unwrap();
+        assert!(
+            !cleaned.contains("alert") && !cleaned.contains("srcdoc"),
+            "{cleaned}"
+        );
+        assert!(cleaned.contains(r#"<object data="/chart.svg"></object>"#));
+    }
+
+    #[test]
+    fn test_script_styles_are_removed() {
+        let html = "<body><main>\
+            <p style=\"background: url(javascript:alert(1))\">url</p>\
+            <p style=\"background: URL( 'java&#x09;script:alert(2)' )\">quoted</p>\
+            <p style=\"background: url(\\6a avascript:alert(3))\">escaped</p>\
+            <p style=\"width: expression(alert(4))\">expression</p>\
+            <p style=\"background: url(/bg.png); color: red\" class=\"note\" id=\"n\">kept</p>\
+            </main></body>";
+        let sanitized = standalone_html(html, "https://example.com").unwrap();
+        assert!(!sanitized.contains("alert"), "{sanitized}");
+        assert!(sanitized.contains("<p>url</p><p>quoted</p><p>escaped</p><p>expression</p>"));
+        assert!(sanitized.contains(r#"style="background: url(/bg.png); color: red""#));
+        assert!(sanitized.contains(r#"class="note""#) && sanitized.contains(r#"id="n""#));
+
+        let cleaned = clean_html(html).unwrap();
+        assert!(!cleaned.contains("alert"), "{cleaned}");
+        assert!(cleaned.contains("background: url(/bg.png)"));
+    }
+
+    #[test]
+    fn test_sanitize_html_with_policy() {
+        let html = r#"<html><head><title>Hidden</title><style>p{}</style></head><body>
+            <section><div><p>Outer <font color="red">styled <b>bold <u>deep</u></b></font> end</p></div></section>
+            <h2 id="x" style="color:red" onmouseover="steal()">Title</h2>
+            <a href=" JaVa&#x09;Script:alert(1)" title="t">bad</a>
+            <a href="data:text/html,hi">data</a>
+            <a href="/docs?q=a:b" onclick="x()">relative</a>
+            <a href="mailto:me@example.com">mail</a>
+            <img src="https://example.com/a.png" alt="a &quot;b&quot;" onerror="x()">
+            <table><tr><td colspan="2" class="c">1 &lt; 2</td></tr></table>
+            <iframe src="https://evil.example">framed text</iframe>
+            <!-- comment --><script>alert(1)</script></body></html>"#;
+
+        let clean = sanitize_html(html, &SanitizePolicy::default());
+        assert!(clean.contains("<p>Outer styled bold deep end</p>"));
+        assert!(clean.contains("<h2>Title</h2>"));
+        assert!(clean.contains(r#"<a title="t">bad</a>"#));
+        assert!(clean.contains("<a>data</a>"));
+        assert!(clean.contains(r#"<a href="/docs?q=a:b">relative</a>"#));
+        assert!(clean.contains(r#"<a href="mailto:me@example.com">mail</a>"#));
+        assert!(clean.contains(r#"<img alt="a &quot;b&quot;" src="https://example.com/a.png">"#));
+        assert!(clean.contains(r#"<td colspan="2">1 &lt; 2</td>"#));
+        for removed in [
+            "Hidden", "p{}", "<section", "<div", "<font", "<b>", "style=", " on", "script",
+            "framed", "comment",
+        ] {
+            assert!(!clean.contains(removed), "{removed} survived");
+        }
+
+        let mut links_only = SanitizePolicy::default();
+        links_only.allowed_tags.retain(|tag, _| tag == "a");
+        links_only.allowed_schemes = vec!["https".to_string()];
+        assert_eq!(
+            sanitize_html(
+                r#"<p><a href="http://a.example">a</a> <a href="https://b.example">b</a></p>"#,
+                &links_only
+            ),
+            r#"<a>a</a> <a href="https://b.example">b</a>"#
+        );
+    }
+
     #[test]
     fn test_extract_links() {
         let html = "<div><a href=\"https://example.com\">Example</a><a href=\"/relative/path\">Relative</a><a href=\"javascript:void(0)\">JS Link</a><a href=\"#section\">Hash Link</a></div>";
@@ -39,7 +1123,22 @@ mod html_parser_tests {
 
 #[cfg(test)]
 mod markdown_converter_tests {
-    use crate::markdown_converter::convert_to_markdown;
+    use crate::html_parser::{
+        Crumb, FeedKind, RobotsDirectives, SocialMeta, decode_html_bytes, discover_feeds,
+        extract_alternates, extract_breadcrumbs, extract_follow_links, extract_iframe_sources,
+        extract_json_ld, extract_links, extract_modified_date, extract_publication_date,
+        extract_robots_directives, extract_social_metadata, json_ld_types,
+    };
+    use crate::markdown_converter::{
+        AbbreviationStyle, AltFallback, AnchorStyle, BulletChar, CaptionStyle, ConversionOptions,
+        DefinitionListStyle, DetailsStyle, Document, DocumentStats, FenceChar, HeadingStyle,
+        JsonStyle, LineBreakStyle, LinkStyle, MarkdownError, MarkdownFlavor, MarkdownLayout,
+        OrderedListStyle, OutputFormat, SubSupStyle, TableSpanFill, TitleMode, convert_html,
+        convert_html_bytes, convert_html_with_options, convert_html_with_template,
+        convert_to_markdown, document_to_json, document_to_links_csv, parse_html_to_document,
+        parse_html_to_document_with_frames, parse_html_to_document_with_options, render_document,
+    };
+    use std::collections::HashMap;
 
     #[test]
     fn test_convert_basic_html() {
@@ -79,6 +1178,39 @@ mod markdown_converter_tests {
         assert!(markdown.contains("```"));
     }
 
+    #[test]
+    fn test_code_block_preserves_indentation() {
+        let html = "<pre><code class=\"language-python\">\ndef greet(name):\n    if name:\n        \
+            return f\"Hi {name}\"\n\n\n    return None\n</code></pre>";
+
+        let document = parse_html_to_document(html, "https://example.com").unwrap();
+        let expected =
+            "def greet(name):\n    if name:\n        return f\"Hi {name}\"\n\n\n    return None";
+        assert_eq!(document.code_blocks[0].code, expected);
+
+        let markdown = convert_to_markdown(html, "https://example.com").unwrap();
+        assert!(markdown.contains(&format!("```python\n{}\n```", expected)));
+    }
+
+    #[test]
+    fn test_pre_code_is_one_code_block() {
+        let html = "<pre><code class=\"language-python\">print(1)</code></pre>\
+            <pre class=\"language-sh\">ls -la</pre>\
+            <p>Inline <code>x = 1</code> stays inline.</p><div><code>standalone()</code></div>";
+
+        let document = parse_html_to_document(html, "https://example.com").unwrap();
+
+        assert_eq!(document.code_blocks.len(), 2);
+        assert_eq!(document.code_blocks[0].language, "python");
+        assert_eq!(document.code_blocks[0].code, "print(1)");
+        assert_eq!(document.code_blocks[1].language, "sh");
+
+        let markdown = convert_to_markdown(html, "https://example.com").unwrap();
+        assert_eq!(markdown.matches("```").count(), 4);
+        assert!(markdown.contains("Inline `x = 1` stays inline."));
+        assert!(markdown.contains("`standalone()`"));
+    }
+
     #[test]
     fn test_skip_unresolvable_links() {
         // Links like javascript: and invalid schemes should be skipped
@@ -94,35 +1226,3864 @@ mod markdown_converter_tests {
         assert!(!markdown.contains("::::bad::::"));
         assert!(markdown.contains("[OK](https://example.com/ok)"));
     }
-}
 
-#[cfg(test)]
-mod chunker_tests {
-    use crate::chunker::create_semantic_chunks;
+    #[test]
+    fn test_convert_table_with_thead() {
+        let html = "<table><thead><tr><th>Name</th><th>Value</th></tr></thead>\
+            <tbody><tr><td>a|b</td><td>1</td></tr><tr><td>c</td><td>2</td></tr></tbody></table>";
+
+        let markdown = convert_to_markdown(html, "https://example.com").unwrap();
+
+        assert!(markdown.contains("| Name | Value |\n| --- | --- |"));
+        assert!(markdown.contains("| a\\|b | 1 |"));
+        assert!(markdown.contains("| c | 2 |"));
+    }
 
     #[test]
-    fn test_basic_chunking() {
-        let markdown = "# Title\n\n## Section 1\n\nThis is a test paragraph.\n\n## Section 2\n\n* List item 1\n* List item 2";
+    fn test_table_cells_escape_markup() {
+        let html = "<table><tr><th>&lt;script&gt;</th><th>*emphasis*</th></tr>\
+            <tr><td>&lt;img src=x onerror=alert(1)&gt;</td><td>[text](url)</td></tr></table>";
 
-        let chunks = create_semantic_chunks(markdown, 500, 50).unwrap();
-        assert!(!chunks.is_empty());
-        assert!(chunks[0].contains("# Title"));
+        let markdown = convert_to_markdown(html, "https://example.com").unwrap();
+        assert!(markdown.contains(
+            "| \\<script> | \\*emphasis\\* |\n| --- | --- |\n\
+             | \\<img src=x onerror=alert(1)> | \\[text\\](url) |"
+        ));
+
+        let options = ConversionOptions {
+            flavor: MarkdownFlavor::CommonMark,
+            ..Default::default()
+        };
+        let markdown = convert_html_with_options(
+            html,
+            "https://example.com",
+            OutputFormat::Markdown,
+            &options,
+        )
+        .unwrap();
+        assert!(markdown.contains("\\<img src=x onerror=alert(1)>"));
+        assert!(markdown.contains("\\*emphasis\\*: \\[text\\](url)"));
+        assert!(!markdown.replace("\\<", "").contains('<'));
     }
 
     #[test]
-    fn test_chunk_overlap() {
-        let markdown = "# First\n\nContent 1\n\n# Second\n\nContent 2\n\n# Third\n\nContent 3";
+    fn test_convert_table_infers_header_row() {
+        let html = "<table><tr><th>Year</th><th>Event</th></tr>\
+            <tr><td>1991</td><td>Release</td></tr></table>";
 
-        let chunks = create_semantic_chunks(markdown, 20, 10).unwrap();
-        assert!(chunks.len() > 1);
+        let markdown = convert_to_markdown(html, "https://example.com").unwrap();
 
-        // Check for overlap
-        if chunks.len() >= 2 {
-            let first_chunk = &chunks[0];
-            let second_chunk = &chunks[1];
+        assert!(markdown.contains("| Year | Event |\n| --- | --- |\n| 1991 | Release |"));
+    }
 
-            assert!(first_chunk.contains("First"));
-            assert!(second_chunk.contains("Second"));
+    #[test]
+    fn test_convert_table_with_spans() {
+        let html = "<table><tr><th colspan=\"2\">Plan</th><th>Price</th></tr>\
+            <tr><td rowspan=\"3\">Basic</td><td>Monthly</td><td>5</td></tr>\
+            <tr><td>Yearly</td><td>50</td></tr>\
+            <tr><td>Lifetime</td><td>200</td></tr></table>";
+
+        let document = parse_html_to_document(html, "https://example.com").unwrap();
+        let table = &document.tables[0];
+        assert_eq!(table.headers, vec!["Plan", "Plan", "Price"]);
+        assert_eq!(table.rows[2], vec!["Basic", "Lifetime", "200"]);
+        assert!(table.rows.iter().all(|row| row.len() == 3));
+
+        let markdown = convert_to_markdown(html, "https://example.com").unwrap();
+        assert!(markdown.contains(
+            "| Plan | Plan | Price |\n| --- | --- | --- |\n| Basic | Monthly | 5 |\n| Basic | Yearly | 50 |"
+        ));
+    }
+
+    #[test]
+    fn test_convert_table_with_empty_span_fill() {
+        let html = "<table><tr><th colspan=\"2\">Plan</th><th>Price</th></tr>\
+            <tr><td rowspan=\"3\">Basic</td><td>Monthly</td><td>5</td></tr>\
+            <tr><td>Yearly</td><td>50</td></tr>\
+            <tr><td>Lifetime</td><td>200</td></tr></table>";
+        let options = ConversionOptions {
+            table_span_fill: TableSpanFill::Empty,
+            ..Default::default()
+        };
+
+        let markdown = convert_html_with_options(
+            html,
+            "https://example.com",
+            OutputFormat::Markdown,
+            &options,
+        )
+        .unwrap();
+
+        assert!(markdown.contains("| Plan |  | Price |"));
+        assert!(
+            markdown.contains("| Basic | Monthly | 5 |\n|  | Yearly | 50 |\n|  | Lifetime | 200 |")
+        );
+    }
+
+    #[test]
+    fn test_convert_table_alignment() {
+        let html = "<table><thead><tr><th>Item</th><th align=\"right\">Price</th>\
+            <th style=\"text-align: center\">Stock</th><th class=\"text-left\">Note</th></tr></thead>\
+            <tbody><tr><td class=\"text-right\">Pen</td><td class=\"text-center\">1.50</td>\
+            <td class=\"text-center\">4</td><td>Blue</td></tr>\
+            <tr><td class=\"text-right\">Ink</td><td>3.00</td><td>9</td><td>Black</td></tr></tbody></table>";
+
+        let markdown = convert_to_markdown(html, "https://example.com").unwrap();
+
+        assert!(markdown.contains(
+            "| Item | Price | Stock | Note |\n| ---: | ---: | :---: | :--- |\n| Pen | 1.50 | 4 | Blue |"
+        ));
+    }
+
+    /// Contents of the fenced code blocks in `markdown`, closing fences per CommonMark
+    fn fenced_blocks(markdown: &str) -> Vec<String> {
+        let mut blocks = Vec::new();
+        let mut open: Option<(char, usize, Vec<&str>)> = None;
+        for line in markdown.lines() {
+            let fence_char = line.chars().next().filter(|c| matches!(c, '`' | '~'));
+            let run = fence_char.map_or(0, |c| line.len() - line.trim_start_matches(c).len());
+            match (&mut open, fence_char) {
+                (Some((c, len, _)), Some(f))
+                    if f == *c && run >= *len && line[run..].trim().is_empty() =>
+                {
+                    blocks.push(open.take().unwrap().2.join("\n"));
+                }
+                (Some((_, _, body)), _) => body.push(line),
+                (None, Some(c)) if run >= 3 => open = Some((c, run, Vec::new())),
+                (None, _) => {}
+            }
         }
+        blocks
+    }
+
+    #[test]
+    fn test_code_containing_fences() {
+        let three = "Use a fence:\n```rust\nfn main() {}\n```";
+        let four = "````\n```\nnested\n```\n````";
+        let html = format!(
+            "<pre><code>{}</code></pre><p>Between</p><pre><code>{}</code></pre>",
+            three, four
+        );
+        let markdown = convert_to_markdown(&html, "https://example.com").unwrap();
+
+        assert!(markdown.contains("````\nUse a fence:"));
+        assert!(markdown.contains("`````\n````\n```"));
+        assert_eq!(fenced_blocks(&markdown), vec![three, four]);
+    }
+
+    #[test]
+    fn test_code_language_breaking_the_fence_is_dropped() {
+        use crate::markdown_converter::{Block, CodeBlock, document_to_markdown};
+
+        let html = "<pre><code class=\"language-a`b\">x = 1</code></pre>";
+        let markdown = convert_to_markdown(html, "https://example.com").unwrap();
+        assert!(markdown.ends_with("```\nx = 1\n```"));
+
+        let mut document = parse_html_to_document(html, "https://example.com").unwrap();
+        for language in ["rust\n# Injected", "rust extra"] {
+            document.blocks = vec![Block::CodeBlock(CodeBlock {
+                language: language.to_string(),
+                code: "x = 1".to_string(),
+            })];
+            assert!(document_to_markdown(&document).ends_with("```\nx = 1\n```"));
+        }
+    }
+
+    #[test]
+    fn test_tilde_code_fence_option() {
+        let code = "~~~\nnot a fence end\n```";
+        let html = format!("<pre><code class=\"language-text\">{}</code></pre>", code);
+        let options = ConversionOptions {
+            code_fence: FenceChar::Tilde,
+            ..Default::default()
+        };
+        let markdown = convert_html_with_options(
+            &html,
+            "https://example.com",
+            OutputFormat::Markdown,
+            &options,
+        )
+        .unwrap();
+
+        assert!(markdown.contains("~~~~text\n~~~\n"));
+        assert_eq!(fenced_blocks(&markdown), vec![code]);
+
+        let plain = convert_to_markdown("<pre>x = 1</pre>", "https://example.com").unwrap();
+        assert!(plain.contains("```\nx = 1\n```"));
+    }
+
+    #[test]
+    fn test_preserves_document_order() {
+        let html = "<html><head><title>Post</title></head><body>\
+            <h2>Intro</h2><p>First paragraph.</p>\
+            <h2>Details</h2><p>Second paragraph.</p><ul><li>Point</li></ul></body></html>";
+
+        let markdown = convert_to_markdown(html, "https://example.com").unwrap();
+
+        assert!(markdown.contains(
+            "## Intro\n\nFirst paragraph.\n\n## Details\n\nSecond paragraph.\n\n- Point"
+        ));
+    }
+
+    #[test]
+    fn test_inline_emphasis() {
+        let html = "<p>This is <strong>very</strong> <em>nice</em></p>";
+        let markdown = convert_to_markdown(html, "https://example.com").unwrap();
+        assert!(markdown.contains("This is **very** *nice*"));
+
+        let html = "<p><strong><em>both</em></strong> and <del>gone</del> and <s>old</s></p>";
+        let markdown = convert_to_markdown(html, "https://example.com").unwrap();
+        assert!(markdown.contains("***both*** and ~~gone~~ and ~~old~~"));
+    }
+
+    #[test]
+    fn test_inline_emphasis_edge_cases() {
+        let html =
+            "<p>Empty<b> </b>tag, <b>adj</b><b>acent</b> runs, <em> padded </em>text<i>.</i></p>";
+        let markdown = convert_to_markdown(html, "https://example.com").unwrap();
+        assert!(markdown.contains("Empty tag, **adjacent** runs, *padded* text."));
+        assert!(!markdown.contains("** **"));
+    }
+
+    #[test]
+    fn test_inline_emphasis_flanking() {
+        // Plain punctuation moves outside delimiters that would not flank
+        let html = "<p><strong>bold,</strong>next and a<em>\"quoted\"</em>b</p>";
+        let markdown = convert_to_markdown(html, "https://example.com").unwrap();
+        assert!(markdown.contains("**bold**,next and a\"*quoted*\"b"));
+
+        // Other punctuation falls back to the HTML tag
+        let html = "<p><em>(x)</em>y and <strong><code>c</code></strong>z</p>";
+        let markdown = convert_to_markdown(html, "https://example.com").unwrap();
+        assert!(markdown.contains("<em>(x)</em>y and <strong>`c`</strong>z"));
+        let text = convert_html(html, "https://example.com", OutputFormat::Text).unwrap();
+        assert!(text.contains("(x)y and cz"));
+
+        // Flanking delimiters are left alone
+        let html =
+            "<p><strong>bold,</strong> next, <em>(x)</em> y, <strong><em>x</em></strong>y</p>";
+        let markdown = convert_to_markdown(html, "https://example.com").unwrap();
+        assert!(markdown.contains("**bold,** next, *(x)* y, ***x***y"));
+    }
+
+    #[test]
+    fn test_inline_links() {
+        let html = "<p>Read the <a href=\"/docs\">docs</a> here</p>\
+            <ul><li>See <a href=\"https://other.com/x\"><em>this</em></a></li></ul>\
+            <p><a href=\"/empty\"> </a></p>";
+        let markdown = convert_to_markdown(html, "https://example.com").unwrap();
+
+        assert!(markdown.contains("Read the [docs](https://example.com/docs) here"));
+        assert!(markdown.contains("- See [*this*](https://other.com/x)"));
+        assert!(markdown.contains("\n\nhttps://example.com/empty"));
+        assert!(!markdown.contains("## Links"));
+    }
+
+    #[test]
+    fn test_link_index_option() {
+        let html = "<p>Read the <a href=\"/docs\">docs</a> here</p>";
+        let options = ConversionOptions {
+            include_link_index: true,
+            ..Default::default()
+        };
+
+        let markdown = convert_html_with_options(
+            html,
+            "https://example.com",
+            OutputFormat::Markdown,
+            &options,
+        )
+        .unwrap();
+
+        assert!(markdown.contains("Read the [docs](https://example.com/docs) here"));
+        assert!(markdown.ends_with("## Links\n\n- [docs](https://example.com/docs)"));
+    }
+
+    #[test]
+    fn test_definition_lists() {
+        let html = "<dl><dt>CPU</dt><dd>Central <em>processing</em> unit</dd>\
+            <dt>RAM</dt><dd>Memory</dd><dd>Volatile storage</dd></dl>";
+
+        let markdown = convert_to_markdown(html, "https://example.com").unwrap();
+        assert!(markdown.contains(
+            "**CPU**\n  Central *processing* unit\n\n**RAM**\n  Memory\n  Volatile storage"
+        ));
+
+        let options = ConversionOptions {
+            definition_list_style: DefinitionListStyle::Colon,
+            ..Default::default()
+        };
+        let markdown = convert_html_with_options(
+            html,
+            "https://example.com",
+            OutputFormat::Markdown,
+            &options,
+        )
+        .unwrap();
+        assert!(
+            markdown
+                .contains("CPU\n: Central *processing* unit\n\nRAM\n: Memory\n: Volatile storage")
+        );
+    }
+
+    #[test]
+    fn test_task_list_items() {
+        let html = "<ul><li><input type=\"checkbox\" checked> Done thing</li>\
+            <li><input type=\"checkbox\"> Open thing</li>\
+            <li><input type=\"checkbox\" disabled checked=\"checked\"> Locked thing</li>\
+            <li>Plain <input type=\"checkbox\"> item</li></ul>";
+
+        let markdown = convert_to_markdown(html, "https://example.com").unwrap();
+
+        assert!(
+            markdown
+                .contains("- [x] Done thing\n- [ ] Open thing\n- [x] Locked thing\n- Plain item")
+        );
+    }
+
+    #[test]
+    fn test_horizontal_rules() {
+        let html = "<h2>Part one</h2><hr><p>First.</p><hr><hr/><p>Second.</p><hr>";
+
+        let markdown = convert_to_markdown(html, "https://example.com").unwrap();
+
+        assert!(markdown.contains("## Part one\n\n---\n\nFirst.\n\n---\n\nSecond.\n\n---"));
+        assert!(!markdown.contains("First.\n---"));
+    }
+
+    #[test]
+    fn test_horizontal_rule_in_json() {
+        let html = "<p>First.</p><hr><p>Second.</p>";
+
+        let json = convert_html(html, "https://example.com", OutputFormat::Json).unwrap();
+
+        assert!(json.contains("\"type\": \"hr\""));
+    }
+
+    #[test]
+    fn test_line_breaks() {
+        let html = "<p>Line one<br>Line two<br><br>New paragraph<br></p>\
+            <ul><li>Item<br>continued</li></ul><blockquote>Quote<br>more</blockquote>";
+
+        let markdown = convert_to_markdown(html, "https://example.com").unwrap();
+        assert!(markdown.contains("Line one  \nLine two\n\nNew paragraph\n\n"));
+        assert!(markdown.contains("- Item  \n  continued"));
+        assert!(markdown.contains("> Quote  \n> more"));
+
+        let options = ConversionOptions {
+            line_break_style: LineBreakStyle::Backslash,
+            ..Default::default()
+        };
+        let markdown = convert_html_with_options(
+            html,
+            "https://example.com",
+            OutputFormat::Markdown,
+            &options,
+        )
+        .unwrap();
+        assert!(markdown.contains("Line one\\\nLine two\n\nNew paragraph"));
+    }
+
+    #[test]
+    fn test_nested_blockquotes() {
+        let html = "<blockquote><p>a</p><blockquote><p>b</p></blockquote></blockquote>";
+
+        let markdown = convert_to_markdown(html, "https://example.com").unwrap();
+
+        assert!(markdown.ends_with("> a\n>\n> > b"));
+        assert_eq!(markdown.matches("b").count(), 1);
+    }
+
+    #[test]
+    fn test_blockquote_block_content() {
+        let html = "<blockquote><p>First</p><p>Second</p><ul><li>One</li><li>Two</li></ul>\
+            <pre><code>let x = 1;</code></pre></blockquote>";
+
+        let markdown = convert_to_markdown(html, "https://example.com").unwrap();
+
+        assert!(
+            markdown.contains(
+                "> First\n>\n> Second\n>\n> - One\n> - Two\n>\n> ```\n> let x = 1;\n> ```"
+            )
+        );
+    }
+
+    #[test]
+    fn test_figure_captions() {
+        let html = "<figure><img src=\"/flow.png\" alt=\"Flow\"><figcaption>Diagram of \"flow\"</figcaption></figure>";
+
+        let markdown = convert_to_markdown(html, "https://example.com").unwrap();
+        assert!(
+            markdown.contains("![Flow](https://example.com/flow.png)\n\n*Diagram of \"flow\"*")
+        );
+        assert_eq!(markdown.matches("Diagram").count(), 1);
+
+        let options = ConversionOptions {
+            caption_style: CaptionStyle::Title,
+            ..Default::default()
+        };
+        let markdown = convert_html_with_options(
+            html,
+            "https://example.com",
+            OutputFormat::Markdown,
+            &options,
+        )
+        .unwrap();
+        assert!(
+            markdown.contains("![Flow](https://example.com/flow.png \"Diagram of \\\"flow\\\"\")")
+        );
+
+        let json = convert_html(html, "https://example.com", OutputFormat::Json).unwrap();
+        assert!(json.contains("\"caption\": \"Diagram of \\\"flow\\\"\""));
+    }
+
+    #[test]
+    fn test_figure_with_multiple_images() {
+        let html = "<figure><img src=\"/a.png\" alt=\"A\"><img src=\"/b.png\" alt=\"B\">\
+            <figcaption>Two views</figcaption></figure>\
+            <figure><pre><code>x = 1</code></pre><figcaption>Listing 1</figcaption></figure>";
+
+        let markdown = convert_to_markdown(html, "https://example.com").unwrap();
+
+        assert!(markdown.contains(
+            "![A](https://example.com/a.png)\n\n![B](https://example.com/b.png)\n\n*Two views*"
+        ));
+        assert!(markdown.contains("```\nx = 1\n```\n\n*Listing 1*"));
+    }
+
+    #[test]
+    fn test_details_sections() {
+        let html = "<details><summary>Advanced options</summary><p>Use <code>--force</code>.</p>\
+            <pre><code>run --force</code></pre>\
+            <details><summary>Even more</summary><p>Nested body</p></details></details>";
+
+        let markdown = convert_to_markdown(html, "https://example.com").unwrap();
+        assert!(markdown.contains(
+            "**Advanced options**\n\nUse `--force`.\n\n```\nrun --force\n```\n\n**Even more**\n\nNested body"
+        ));
+
+        let options = ConversionOptions {
+            details_style: DetailsStyle::Html,
+            ..Default::default()
+        };
+        let markdown = convert_html_with_options(
+            html,
+            "https://example.com",
+            OutputFormat::Markdown,
+            &options,
+        )
+        .unwrap();
+        assert!(markdown.contains(
+            "<details>\n<summary>Advanced options</summary>\n\nUse `--force`.\n\n```\nrun --force\n```\n\n\
+             <details>\n<summary>Even more</summary>\n\nNested body\n\n</details>\n\n</details>"
+        ));
+    }
+
+    #[test]
+    fn test_heading_anchors() {
+        let html = "<h2 id=\"setup\">Setup</h2><h2><a name=\"usage\"></a>Usage</h2>\
+            <h2 id=\"setup\">Setup again</h2><h2>No anchor</h2>";
+
+        let markdown = convert_to_markdown(html, "https://example.com").unwrap();
+        assert!(markdown.contains("## Setup\n\n## Usage\n\n## Setup again\n\n## No anchor"));
+
+        let options = ConversionOptions {
+            anchor_style: AnchorStyle::Attribute,
+            ..Default::default()
+        };
+        let markdown = convert_html_with_options(
+            html,
+            "https://example.com",
+            OutputFormat::Markdown,
+            &options,
+        )
+        .unwrap();
+        assert!(markdown.contains(
+            "## Setup {#setup}\n\n## Usage {#usage}\n\n## Setup again {#setup-1}\n\n## No anchor"
+        ));
+
+        let options = ConversionOptions {
+            anchor_style: AnchorStyle::Html,
+            ..Default::default()
+        };
+        let markdown = convert_html_with_options(
+            html,
+            "https://example.com",
+            OutputFormat::Markdown,
+            &options,
+        )
+        .unwrap();
+        assert!(markdown.contains("## <a id=\"usage\"></a>Usage"));
+
+        // ids are escaped in the HTML anchor and slugified where they would break `{#id}`
+        let html = "<h2 id='x\"><img src=x onerror=alert(1)>'>Title</h2>\
+            <h2 id=\"a b}\">Spaced</h2><h2 id=\"{}\">Braces</h2>";
+        let markdown = convert_html_with_options(
+            html,
+            "https://example.com",
+            OutputFormat::Markdown,
+            &options,
+        )
+        .unwrap();
+        assert!(markdown.contains("## <a id=\"x&quot;><img src=x onerror=alert(1)>\"></a>Title"));
+        let options = ConversionOptions {
+            anchor_style: AnchorStyle::Attribute,
+            toc: true,
+            ..Default::default()
+        };
+        let markdown = convert_html_with_options(
+            html,
+            "https://example.com",
+            OutputFormat::Markdown,
+            &options,
+        )
+        .unwrap();
+        assert!(
+            markdown.ends_with("## Spaced {#a-b}\n\n## Braces"),
+            "{markdown}"
+        );
+        assert!(markdown.contains("[Spaced](#a-b)") && markdown.contains("[Braces](#braces)"));
+    }
+
+    #[test]
+    fn test_heading_ids_in_json() {
+        let html = "<h2 id=\"setup\">Setup</h2><h3>Plain</h3>";
+
+        let document = parse_html_to_document(html, "https://example.com").unwrap();
+
+        assert_eq!(document.headings[0].id.as_deref(), Some("setup"));
+        assert_eq!(document.headings[1].id, None);
+        let json = convert_html(html, "https://example.com", OutputFormat::Json).unwrap();
+        assert!(json.contains("\"id\": \"setup\""));
+    }
+
+    #[test]
+    fn test_mathjax_math() {
+        let html = "<p>Euler: <span class=\"MathJax_Preview\">e^{i\\pi}</span>\
+            <span class=\"MathJax\" id=\"MathJax-Element-1-Frame\"><nobr><span class=\"math\">\
+            <span class=\"mi\">e</span></span></nobr></span>\
+            <script type=\"math/tex\" id=\"MathJax-Element-1\">e^{i\\pi} + 1 = 0</script> holds.</p>\
+            <div class=\"MathJax_Display\"><span class=\"MathJax\">x</span></div>\
+            <script type=\"math/tex; mode=display\">\\int_0^1 x\\,dx</script>";
+
+        let markdown = convert_to_markdown(html, "https://example.com").unwrap();
+
+        assert!(markdown.contains("Euler: $e^{i\\pi} + 1 = 0$ holds."));
+        assert!(markdown.contains("\n\n$$\\int_0^1 x\\,dx$$"));
+        assert!(!markdown.contains("MathJax"));
+    }
+
+    #[test]
+    fn test_katex_math() {
+        let html = "<p>Area <span class=\"katex\"><span class=\"katex-mathml\"><math><semantics>\
+            <mrow><mi>π</mi><msup><mi>r</mi><mn>2</mn></msup></mrow>\
+            <annotation encoding=\"application/x-tex\">\\pi r^2</annotation></semantics></math></span>\
+            <span class=\"katex-html\" aria-hidden=\"true\">πr2</span></span> here.</p>\
+            <span class=\"katex-display\"><span class=\"katex\"><math><semantics><mi>E</mi>\
+            <annotation encoding=\"application/x-tex\">E = mc^2</annotation></semantics></math></span></span>\
+            <p>Plain <math><mi>a</mi><mo>+</mo><mi>b</mi></math> MathML.</p>";
+
+        let markdown = convert_to_markdown(html, "https://example.com").unwrap();
+
+        assert!(markdown.contains("Area $\\pi r^2$ here."));
+        assert!(markdown.contains("\n\n$$E = mc^2$$\n\n"));
+        assert!(markdown.contains("Plain `a+b` MathML."));
+    }
+
+    #[test]
+    fn test_image_srcset() {
+        let html = "<img src=\"thumb.jpg\" alt=\"Chart\" \
+            srcset=\"small.jpg 480w, large.jpg 1024w, medium.jpg 800w\">\
+            <img src=\"logo.png\" alt=\"Logo\" srcset=\"logo.png 1x, logo@3x.png 3x, logo@2x.png 2x\">\
+            <picture><source srcset=\"hero.avif 1600w, hero-small.avif 600w\" type=\"image/avif\">\
+            <source srcset=\"hero.webp 1200w\"><img src=\"hero.jpg\" alt=\"Hero\"></picture>";
+
+        let document = parse_html_to_document(html, "https://example.com/blog/").unwrap();
+
+        let chart = &document.images[0];
+        assert_eq!(chart.src, "https://example.com/blog/large.jpg");
+        assert_eq!(
+            chart.fallback_src.as_deref(),
+            Some("https://example.com/blog/thumb.jpg")
+        );
+        assert_eq!(
+            document.images[1].src,
+            "https://example.com/blog/logo@3x.png"
+        );
+        assert_eq!(document.images[2].src, "https://example.com/blog/hero.avif");
+
+        let markdown = convert_to_markdown(html, "https://example.com/blog/").unwrap();
+        assert!(markdown.contains("![Chart](https://example.com/blog/large.jpg)"));
+    }
+
+    #[test]
+    fn test_image_malformed_srcset() {
+        let html = "<img src=\"a.jpg\" alt=\"Empty\" srcset=\" , ,\">\
+            <img src=\"b.jpg\" alt=\"Bad\" srcset=\"big.jpg abcw, huge.jpg -2x, wide.jpg 100w 2x, odd.jpg 5é\">\
+            <img src=\"c.jpg\" alt=\"Mixed\" srcset=\"c1.jpg, c2.jpg 2x,, c3.jpg 0w\">\
+            <img alt=\"NoSrc\" srcset=\"only.jpg 300w\">";
+
+        let document = parse_html_to_document(html, "https://example.com/").unwrap();
+        let sources: Vec<&str> = document.images.iter().map(|i| i.src.as_str()).collect();
+
+        assert_eq!(
+            sources,
+            vec![
+                "https://example.com/a.jpg",
+                "https://example.com/b.jpg",
+                "https://example.com/c2.jpg",
+                "https://example.com/only.jpg",
+            ]
+        );
+        assert!(document.images[0].fallback_src.is_none());
+        assert!(document.images[3].fallback_src.is_none());
+    }
+
+    #[test]
+    fn test_media_embeds() {
+        let html = "<h2>Talk</h2>\
+            <iframe src=\"https://www.youtube.com/embed/dQw4w9WgXcQ?rel=0\" title=\"Launch talk\"></iframe>\
+            <video controls><source src=\"/media/demo.mp4\" type=\"video/mp4\">No video support</video>\
+            <p>Listen: <audio src=\"episode.mp3\" title=\"Episode 1\"></audio></p>\
+            <iframe src=\"https://maps.example.org/embed?q=1\"></iframe>";
+
+        let markdown = convert_to_markdown(html, "https://example.com/talks/").unwrap();
+
+        assert!(markdown.contains(
+            "## Talk\n\n[▶ Video: Launch talk](https://www.youtube.com/watch?v=dQw4w9WgXcQ)\n\n\
+             [▶ Video](https://example.com/media/demo.mp4)\n\n\
+             Listen:\n\n[♪ Audio: Episode 1](https://example.com/talks/episode.mp3)\n\n\
+             [Embedded content](https://maps.example.org/embed?q=1)"
+        ));
+        assert!(!markdown.contains("No video support"));
+
+        let json = convert_html(html, "https://example.com/talks/", OutputFormat::Json).unwrap();
+        assert!(json.contains("\"type\": \"embed\""));
+        assert!(json.contains("\"kind\": \"audio\""));
+    }
+
+    #[test]
+    fn test_inline_code_spans() {
+        let html = "<p>Press <kbd>Ctrl</kbd>+<kbd>C</kbd> to copy.</p>\
+            <p>Call <code>parse()</code> with <var>n</var>, output is <samp>ok</samp>.</p>\
+            <p>Quote with <code>a`b</code> and <code>`tick</code>.</p>";
+
+        let markdown = convert_to_markdown(html, "https://example.com").unwrap();
+
+        assert!(markdown.contains("Press `Ctrl`+`C` to copy."));
+        assert!(markdown.contains("Call `parse()` with `n`, output is `ok`."));
+        assert!(markdown.contains("Quote with ``a`b`` and `` `tick ``."));
+        assert!(!markdown.contains("```"));
+    }
+
+    #[test]
+    fn test_abbreviations() {
+        // Headings and table cells keep the bare abbreviation; running text expands it
+        let html = "<h2>Why <abbr title=\"HyperText Markup Language\">HTML</abbr> matters</h2>\
+            <table><tr><th>Sheet</th></tr>\
+            <tr><td><abbr title=\"Cascading Style Sheets\">CSS</abbr></td></tr></table>\
+            <p>Learn HTML: <abbr title=\"HyperText Markup Language\">HTML</abbr> is parsed. \
+            More <abbr title=\"HyperText Markup Language\">HTML</abbr> follows.</p>\
+            <p>Use <code>HTMLParser</code> or the <a href=\"/css\">CSS</a> guide on \
+            <abbr title=\"Cascading Style Sheets\">CSS</abbr>.</p>\
+            <p>Call <dfn title=\"*args [variadic]\">varargs</dfn>.</p>";
+
+        let markdown = convert_to_markdown(html, "https://example.com").unwrap();
+
+        assert!(markdown.contains("## Why HTML matters\n"));
+        assert!(markdown.contains("| Sheet |\n| --- |\n| CSS |"));
+        assert!(markdown.contains(
+            "Learn HTML: HTML (HyperText Markup Language) is parsed. More HTML follows."
+        ));
+        assert!(markdown.contains(
+            "Use `HTMLParser` or the [CSS](https://example.com/css) guide on CSS (Cascading Style Sheets)."
+        ));
+        assert!(markdown.contains("Call varargs (\\*args \\[variadic\\])."));
+
+        let options = ConversionOptions {
+            abbreviation_style: AbbreviationStyle::Definitions,
+            ..Default::default()
+        };
+        let markdown = convert_html_with_options(
+            html,
+            "https://example.com",
+            OutputFormat::Markdown,
+            &options,
+        )
+        .unwrap();
+
+        assert!(markdown.contains("Learn HTML: HTML is parsed. More HTML follows."));
+        assert!(markdown.ends_with(
+            "*[HTML]: HyperText Markup Language\n*[CSS]: Cascading Style Sheets\n\
+             *[varargs]: \\*args \\[variadic\\]"
+        ));
+    }
+
+    #[test]
+    fn test_sub_and_sup() {
+        let html = "<p>Water is H<sub>2</sub>O and area is x<sup>2</sup>, \
+            see C<sub>6</sub>H<sub>12</sub>O<sub>6</sub> and 10<sup>th</sup>.\
+            <sup><a href=\"#fn1\">1</a></sup></p>";
+        let convert = |options: &ConversionOptions| {
+            convert_html_with_options(html, "https://example.com", OutputFormat::Markdown, options)
+                .unwrap()
+        };
+
+        let markdown = convert(&ConversionOptions::default());
+        assert!(markdown.contains("Water is H2O and area is x2"));
+
+        let markdown = convert(&ConversionOptions {
+            sub_sup_style: SubSupStyle::Html,
+            ..Default::default()
+        });
+        assert!(markdown.contains("Water is H<sub>2</sub>O and area is x<sup>2</sup>"));
+
+        let markdown = convert(&ConversionOptions {
+            sub_sup_style: SubSupStyle::Unicode,
+            footnote_references: true,
+            ..Default::default()
+        });
+        assert!(
+            markdown.contains("Water is H₂O and area is x², see C₆H₁₂O₆ and 10<sup>th</sup>.[^1]")
+        );
+    }
+
+    #[test]
+    fn test_entity_decoding() {
+        let html = "<html><head><title>Caf&eacute; &amp; Bar</title></head><body>\
+            <h2>Tom&#8217;s &#x2014; guide</h2>\
+            <p>Fish&nbsp;&amp;&nbsp;chips, double-encoded &amp;#8217; and &amp;eacute;, \
+            markup &lt;b&gt;bold&lt;/b&gt; and 1 &lt; 2.</p>\
+            <p><a href=\"/menu\">Menu &#8211; &quot;daily&quot;</a></p></body></html>";
+
+        let markdown = convert_to_markdown(html, "https://example.com").unwrap();
+
+        assert!(markdown.starts_with("# Café & Bar"));
+        assert!(markdown.contains("## Tom’s — guide"));
+        assert!(
+            markdown.contains(
+                "Fish & chips, double-encoded \\&#8217; and \\&eacute;, markup \\<b>bold\\</b> and 1 < 2."
+            )
+        );
+        assert!(markdown.contains("[Menu – \"daily\"](https://example.com/menu)"));
+
+        let options = ConversionOptions {
+            preserve_nbsp: true,
+            ..Default::default()
+        };
+        let markdown = convert_html_with_options(
+            html,
+            "https://example.com",
+            OutputFormat::Markdown,
+            &options,
+        )
+        .unwrap();
+        assert!(markdown.contains("Fish\u{a0}&\u{a0}chips"));
+    }
+
+    #[test]
+    fn test_escaped_references_stay_literal() {
+        let html = "<html><head><title>Tips &amp;amp; &amp;lt;tricks&amp;gt;</title></head><body>\
+            <h2>Write &amp;amp; and &amp;lt;</h2>\
+            <p>Escape &amp;amp; as &amp;amp;amp; and &amp;lt; as &amp;amp;lt;, a &amp; b.</p>\
+            <p><a href=\"/entities\">&amp;amp; and &amp;lt; explained</a></p></body></html>";
+
+        let markdown = convert_to_markdown(html, "https://example.com").unwrap();
+        assert!(markdown.starts_with("# Tips \\&amp; \\&lt;tricks\\&gt;"));
+        assert!(markdown.contains("## Write \\&amp; and \\&lt;"));
+        assert!(
+            markdown.contains("Escape \\&amp; as \\&amp;amp; and \\&lt; as \\&amp;lt;, a & b.")
+        );
+        assert!(markdown.contains("[\\&amp; and \\&lt; explained](https://example.com/entities)"));
+
+        let json = convert_html(html, "https://example.com", OutputFormat::Json).unwrap();
+        let document: Document = serde_json::from_str(&json).unwrap();
+        assert_eq!(document.title, "Tips &amp; &lt;tricks&gt;");
+
+        let text = convert_html(html, "https://example.com", OutputFormat::Text).unwrap();
+        assert!(text.contains("Write &amp; and &lt;"));
+        assert!(text.contains("&amp; and &lt; explained"));
+    }
+
+    #[test]
+    fn test_title_mode() {
+        let convert = |html: &str, title_mode: TitleMode| {
+            let options = ConversionOptions {
+                title_mode,
+                ..Default::default()
+            };
+            convert_html_with_options(
+                html,
+                "https://example.com",
+                OutputFormat::Markdown,
+                &options,
+            )
+            .unwrap()
+        };
+        let identical = "<html><head><title>Release  Notes | Example</title></head>\
+            <body><h1>release notes</h1><p>Body</p></body></html>";
+        let differing = "<html><head><title>Example Blog</title></head>\
+            <body><h1>Release notes</h1><p>Body</p></body></html>";
+        let missing =
+            "<html><head><title>Example Blog</title></head><body><p>Body</p></body></html>";
+
+        assert!(convert(identical, TitleMode::IfNoH1).starts_with("# release notes\n\nBody"));
+        assert!(
+            convert(differing, TitleMode::IfNoH1).starts_with("# Example Blog\n\n# Release notes")
+        );
+        assert!(convert(missing, TitleMode::IfNoH1).starts_with("# Example Blog\n\nBody"));
+        assert!(convert(identical, TitleMode::Always).starts_with("# Release Notes | Example"));
+        assert_eq!(convert(missing, TitleMode::Never), "Body");
+    }
+
+    #[test]
+    fn test_link_and_image_titles() {
+        let html = "<p>See <a href=\"/pricing\" title=\"Opens the &quot;pricing&quot; page\">pricing</a>.</p>\
+            <img src=\"/chart.png\" alt=\"Chart\" title=\"Q3 revenue\" width=\"800\" height=\"600px\">";
+
+        let markdown = convert_to_markdown(html, "https://example.com").unwrap();
+        assert!(markdown.contains(
+            "See [pricing](https://example.com/pricing \"Opens the \\\"pricing\\\" page\")."
+        ));
+        assert!(markdown.contains("![Chart](https://example.com/chart.png \"Q3 revenue\")"));
+
+        let json = convert_html(html, "https://example.com", OutputFormat::Json).unwrap();
+        let document: Document = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            document.links[0].title.as_deref(),
+            Some("Opens the \"pricing\" page")
+        );
+        let image = &document.images[0];
+        assert_eq!(image.title.as_deref(), Some("Q3 revenue"));
+        assert_eq!((image.width, image.height), (Some(800), Some(600)));
+        assert_eq!(document_to_json(&document).unwrap(), json);
+    }
+
+    #[test]
+    fn test_lazy_loaded_images() {
+        let html = "<img src=\"/img/blank.gif\" data-src=\"/img/photo.jpg\" alt=\"Photo\">\
+            <img src=\"data:image/gif;base64,R0lGODlhAQABAAAAACw=\" data-lazy-src=\"/img/lazy.jpg\" alt=\"Lazy\">\
+            <img src=\"/img/spacer.gif\" data-srcset=\"/img/small.jpg 400w, /img/large.jpg 1200w\" alt=\"Set\">\
+            <img src=\"/img/pixel.gif\" data-hi-res=\"/img/custom.jpg\" data-original=\"/img/original.jpg\" alt=\"Custom\">\
+            <img src=\"/img/blank.gif\" alt=\"Placeholder only\">";
+
+        let document = parse_html_to_document(html, "https://example.com").unwrap();
+        let sources: Vec<&str> = document.images.iter().map(|i| i.src.as_str()).collect();
+        assert_eq!(
+            sources,
+            vec![
+                "https://example.com/img/photo.jpg",
+                "https://example.com/img/lazy.jpg",
+                "https://example.com/img/large.jpg",
+                "https://example.com/img/original.jpg",
+            ]
+        );
+
+        let options = ConversionOptions {
+            lazy_image_attributes: vec!["data-hi-res".to_string()],
+            ..Default::default()
+        };
+        let markdown = convert_html_with_options(
+            html,
+            "https://example.com",
+            OutputFormat::Markdown,
+            &options,
+        )
+        .unwrap();
+        assert!(markdown.contains("![Custom](https://example.com/img/custom.jpg)"));
+        assert!(!markdown.contains("blank.gif"));
+    }
+
+    #[test]
+    fn test_alt_fallback_modes() {
+        let html = "<p>Share <img src=\"/icons/twitter-logo_small.svg\"> \
+            <img src=\"/icons/rss.png?v=2\" alt=\"\"></p>\
+            <figure><img src=\"/img/chart.png\"><figcaption>Sales by region</figcaption></figure>\
+            <img src=\"/img/team.jpg\" alt=\"The team\">";
+        let convert = |alt_fallback: AltFallback| {
+            let options = ConversionOptions {
+                alt_fallback,
+                ..Default::default()
+            };
+            convert_html_with_options(
+                html,
+                "https://example.com",
+                OutputFormat::Markdown,
+                &options,
+            )
+            .unwrap()
+        };
+
+        let markdown = convert(AltFallback::Placeholder);
+        assert!(markdown.contains("![image](https://example.com/icons/twitter-logo_small.svg)"));
+        assert!(markdown.contains("![image](https://example.com/icons/rss.png?v=2)"));
+
+        let markdown = convert(AltFallback::Empty);
+        assert!(markdown.contains("![](https://example.com/icons/twitter-logo_small.svg)"));
+
+        let markdown = convert(AltFallback::FileName);
+        assert!(
+            markdown.contains(
+                "![twitter logo small](https://example.com/icons/twitter-logo_small.svg)"
+            )
+        );
+        assert!(markdown.contains("![rss](https://example.com/icons/rss.png?v=2)"));
+        assert!(markdown.contains("![chart](https://example.com/img/chart.png)"));
+
+        let markdown = convert(AltFallback::Caption);
+        assert!(markdown.contains("![Sales by region](https://example.com/img/chart.png)"));
+        assert!(markdown.contains("![](https://example.com/icons/rss.png?v=2)"));
+
+        let markdown = convert(AltFallback::Skip);
+        assert!(!markdown.contains("icons/"));
+        assert!(!markdown.contains("chart.png"));
+        assert!(markdown.contains("![The team](https://example.com/img/team.jpg)"));
+    }
+
+    #[test]
+    fn test_content_links_only() {
+        let nav_links: String = (1..=20)
+            .map(|i| format!("<li><a href=\"/section-{i}\">Section {i}</a></li>"))
+            .collect();
+        let html = format!(
+            "<div role=\"navigation\" class=\"site-nav\"><ul>{nav_links}</ul></div>\
+             <div class=\"breadcrumb\"><a href=\"/\">Home</a></div>\
+             <p>Read the <a href=\"/guide\">guide</a>, the <a href=\"/faq\">FAQ</a> \
+             and the <a href=\"/changelog\">changelog</a>.</p>"
+        );
+
+        let document = parse_html_to_document(&html, "https://example.com").unwrap();
+        assert_eq!(document.links.len(), 24);
+
+        let options = ConversionOptions {
+            content_links_only: true,
+            ..Default::default()
+        };
+        let document =
+            parse_html_to_document_with_options(&html, "https://example.com", &options).unwrap();
+        let urls: Vec<&str> = document.links.iter().map(|l| l.url.as_str()).collect();
+        assert_eq!(
+            urls,
+            vec![
+                "https://example.com/guide",
+                "https://example.com/faq",
+                "https://example.com/changelog",
+            ]
+        );
+    }
+
+    const FLAVOR_HTML: &str = "<p>Visit <a href=\"https://example.com/a\">https://example.com/a</a> \
+        or <a href=\"mailto:team@example.com\"></a>. <del>Old</del> New<br>line</p>\
+        <table><tr><th>Name</th><th>Role</th></tr><tr><td>Ada</td><td>Engineer</td></tr></table>";
+
+    fn convert_with_flavor(flavor: MarkdownFlavor) -> String {
+        let options = ConversionOptions {
+            flavor,
+            line_break_style: LineBreakStyle::Backslash,
+            title_mode: TitleMode::Never,
+            ..Default::default()
+        };
+        convert_html_with_options(
+            FLAVOR_HTML,
+            "https://example.com",
+            OutputFormat::Markdown,
+            &options,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_gfm_flavor() {
+        assert_eq!(
+            convert_with_flavor(MarkdownFlavor::Gfm).trim(),
+            "Visit https://example.com/a or <mailto:team@example.com>. ~~Old~~ New\\\n\
+             line\n\n\
+             | Name | Role |\n\
+             | --- | --- |\n\
+             | Ada | Engineer |"
+        );
+    }
+
+    #[test]
+    fn test_commonmark_flavor() {
+        assert_eq!(
+            convert_with_flavor(MarkdownFlavor::CommonMark).trim(),
+            "Visit <https://example.com/a> or <mailto:team@example.com>. Old New\\\n\
+             line\n\n\
+             **Ada**\n  Role: Engineer"
+        );
+    }
+
+    #[test]
+    fn test_mkdocs_flavor() {
+        assert_eq!(
+            convert_with_flavor(MarkdownFlavor::MkDocs).trim(),
+            "Visit <https://example.com/a> or <mailto:team@example.com>. Old New  \n\
+             line\n\n\
+             | Name | Role |\n\
+             | --- | --- |\n\
+             | Ada | Engineer |"
+        );
+    }
+
+    #[test]
+    fn test_setext_headings_and_offset() {
+        let html = "<html><head><title>Guide</title></head><body>\
+            <h1>Install</h1><p>Text.</p><h2>Linux</h2><h3>Debian</h3><h6>Notes</h6></body></html>";
+
+        let options = ConversionOptions {
+            heading_style: HeadingStyle::Setext,
+            ..Default::default()
+        };
+        let markdown = convert_html_with_options(
+            html,
+            "https://example.com",
+            OutputFormat::Markdown,
+            &options,
+        )
+        .unwrap();
+        assert!(markdown.starts_with("Guide\n=====\n\n"));
+        assert!(markdown.contains("Install\n=======\n\nText."));
+        assert!(markdown.contains("Linux\n-----\n\n### Debian"));
+
+        let options = ConversionOptions {
+            heading_offset: 1,
+            ..Default::default()
+        };
+        let markdown = convert_html_with_options(
+            html,
+            "https://example.com",
+            OutputFormat::Markdown,
+            &options,
+        )
+        .unwrap();
+        assert!(markdown.starts_with("## Guide\n\n"));
+        assert!(markdown.contains("## Install\n\n"));
+        assert!(markdown.contains("#### Debian\n\n"));
+        assert!(markdown.contains("\n###### Notes"));
+        assert!(!markdown.contains("#######"));
+
+        let options = ConversionOptions {
+            heading_offset: -2,
+            ..Default::default()
+        };
+        let markdown = convert_html_with_options(
+            html,
+            "https://example.com",
+            OutputFormat::Markdown,
+            &options,
+        )
+        .unwrap();
+        assert!(markdown.contains("\n# Install\n"));
+        assert!(markdown.contains("\n# Linux\n"));
+        assert!(markdown.contains("\n# Debian\n"));
+        assert!(markdown.contains("\n#### Notes"));
+    }
+
+    const NESTED_LIST_HTML: &str = "<ul><li>Fruit<ol><li>Apple</li><li>Pear</li></ol></li>\
+        <li>Vegetables<ul><li>Leek</li></ul></li></ul>";
+
+    fn convert_list(options: ConversionOptions) -> String {
+        convert_html_with_options(
+            NESTED_LIST_HTML,
+            "https://example.com",
+            OutputFormat::Markdown,
+            &ConversionOptions {
+                title_mode: TitleMode::Never,
+                ..options
+            },
+        )
+        .unwrap()
+        .trim()
+        .to_string()
+    }
+
+    #[test]
+    fn test_nested_lists_default_style() {
+        assert_eq!(
+            convert_list(ConversionOptions::default()),
+            "- Fruit\n  1. Apple\n  2. Pear\n- Vegetables\n  - Leek"
+        );
+
+        let document = parse_html_to_document(NESTED_LIST_HTML, "https://example.com").unwrap();
+        assert_eq!(document.lists.len(), 1);
+        assert_eq!(document.lists[0].items[0].text, "Fruit");
+        assert_eq!(document.lists[0].items[0].children[0].items.len(), 2);
+    }
+
+    #[test]
+    fn test_bullet_char_options() {
+        for (bullet, marker) in [
+            (BulletChar::Dash, '-'),
+            (BulletChar::Asterisk, '*'),
+            (BulletChar::Plus, '+'),
+        ] {
+            let markdown = convert_list(ConversionOptions {
+                bullet_char: bullet,
+                ..Default::default()
+            });
+            assert_eq!(
+                markdown,
+                format!(
+                    "{marker} Fruit\n  1. Apple\n  2. Pear\n{marker} Vegetables\n  {marker} Leek"
+                )
+            );
+        }
+    }
+
+    #[test]
+    fn test_ordered_style_and_indent_width() {
+        let markdown = convert_list(ConversionOptions {
+            ordered_style: OrderedListStyle::AllOnes,
+            ..Default::default()
+        });
+        assert!(markdown.contains("  1. Apple\n  1. Pear"));
+
+        let markdown = convert_list(ConversionOptions {
+            bullet_char: BulletChar::Asterisk,
+            ordered_style: OrderedListStyle::AllOnes,
+            indent_width: 4,
+            ..Default::default()
+        });
+        assert_eq!(
+            markdown,
+            "* Fruit\n    1. Apple\n    1. Pear\n* Vegetables\n    * Leek"
+        );
+
+        let markdown = convert_list(ConversionOptions {
+            indent_width: 4,
+            ..Default::default()
+        });
+        assert!(markdown.contains("- Fruit\n    1. Apple\n    2. Pear"));
+    }
+
+    #[test]
+    fn test_front_matter_option() {
+        let html = "<html><head><title>Rust: \"Fearless\" #1</title></head><body>\
+            <p>Three short words <a href=\"/a\">here</a>.</p><img src=\"/x.png\" alt=\"x\"></body></html>";
+        let options = ConversionOptions {
+            front_matter: true,
+            ..Default::default()
+        };
+        let markdown = convert_html_with_options(
+            html,
+            "https://example.com/post",
+            OutputFormat::Markdown,
+            &options,
+        )
+        .unwrap();
+
+        let mut lines = markdown.lines();
+        assert_eq!(lines.next(), Some("---"));
+        assert_eq!(lines.next(), Some(r#"title: "Rust: \"Fearless\" #1""#));
+        assert_eq!(
+            lines.next(),
+            Some(r#"source_url: "https://example.com/post""#)
+        );
+        let fetched_at = lines.next().unwrap();
+        assert!(fetched_at.starts_with("fetched_at: \"20") && fetched_at.ends_with("Z\""));
+        assert_eq!(
+            fetched_at.len(),
+            "fetched_at: \"2024-01-01T00:00:00Z\"".len()
+        );
+        assert_eq!(lines.next(), Some("word_count: 4"));
+        assert_eq!(lines.next(), Some("link_count: 1"));
+        assert_eq!(lines.next(), Some("image_count: 1"));
+        assert_eq!(lines.next(), Some("---"));
+        assert_eq!(lines.next(), Some(""));
+        assert_eq!(lines.next(), Some("# Rust: \"Fearless\" #1"));
+
+        let plain = convert_to_markdown(html, "https://example.com/post").unwrap();
+        assert!(!plain.starts_with("---"));
+
+        let json: serde_json::Value = serde_json::from_str(
+            &convert_html(html, "https://example.com/post", OutputFormat::Json).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(json["meta"]["title"], "Rust: \"Fearless\" #1");
+        assert_eq!(json["meta"]["source_url"], "https://example.com/post");
+        assert_eq!(json["meta"]["word_count"], 4);
+        assert_eq!(json["meta"]["link_count"], 1);
+        assert_eq!(json["meta"]["image_count"], 1);
+    }
+
+    #[test]
+    fn test_reference_link_style() {
+        let html = "<p>Read the <a href=\"/docs\">docs</a>, then the \
+            <a href=\"/wiki/Rust_(language)\" title=\"About Rust\">wiki</a>.</p>\
+            <p>The <a href=\"/docs\">manual</a> again, and <code>[x](y)</code>.</p>\
+            <p>Back <a href=\"/home\">home</a>.</p><img src=\"/logo.png\" alt=\"Logo\">\
+            <img src=\"/logo.png\" alt=\"Logo again\">";
+        let options = ConversionOptions {
+            link_style: LinkStyle::Reference,
+            title_mode: TitleMode::Never,
+            ..Default::default()
+        };
+        let render = || {
+            convert_html_with_options(
+                html,
+                "https://example.com",
+                OutputFormat::Markdown,
+                &options,
+            )
+            .unwrap()
+        };
+        let markdown = render();
+
+        assert_eq!(
+            markdown,
+            "Read the [docs][1], then the [wiki][2].\n\n\
+             The [manual][1] again, and `[x](y)`.\n\n\
+             Back [home][3].\n\n\
+             ![Logo][img1]\n\n\
+             ![Logo again][img1]\n\n\
+             [1]: https://example.com/docs\n\
+             [2]: https://example.com/wiki/Rust_(language) \"About Rust\"\n\
+             [3]: https://example.com/home\n\
+             [img1]: https://example.com/logo.png"
+        );
+        assert_eq!(markdown, render());
+    }
+
+    #[test]
+    fn test_reference_link_style_keeps_literal_brackets() {
+        let html = "<html><head><title>[Draft](v2)</title></head><body>\
+            <p>Literal [brackets](not-a-link) and <a href=\"/x\">a [real] link</a></p>\
+            <img src=\"/p.png\" alt=\"[a](b)\"></body></html>";
+        let options = ConversionOptions {
+            link_style: LinkStyle::Reference,
+            ..Default::default()
+        };
+        let markdown = convert_html_with_options(
+            html,
+            "https://example.com",
+            OutputFormat::Markdown,
+            &options,
+        )
+        .unwrap();
+
+        assert_eq!(
+            markdown,
+            "# \\[Draft\\](v2)\n\n\
+             Literal \\[brackets\\](not-a-link) and [a \\[real\\] link][1]\n\n\
+             ![\\[a\\](b)][img1]\n\n\
+             [1]: https://example.com/x\n\
+             [img1]: https://example.com/p.png"
+        );
+    }
+
+    #[test]
+    fn test_convert_to_yaml() {
+        let html = "<html><head><title>Notes</title></head><body>\
+            <h1>Setup</h1><p>First line<br>second line</p>\
+            <pre><code class=\"language-sh\">make\nmake install</code></pre></body></html>";
+        let yaml = convert_html(html, "https://example.com", OutputFormat::Yaml).unwrap();
+
+        assert!(yaml.contains("title: Notes\n"));
+        assert!(yaml.contains("- level: 1\n  text: Setup\n"));
+        assert!(yaml.contains("  code: |-\n    make\n    make install\n"));
+        assert!(yaml.contains("- |-\n  First line\n  second line\n"));
+
+        let document: Document = serde_yaml::from_str(&yaml).unwrap();
+        assert_eq!(document.code_blocks[0].code, "make\nmake install");
+        assert_eq!(document.paragraphs[0], "First line\nsecond line");
+    }
+
+    #[test]
+    fn test_convert_to_text() {
+        let html = "<html><head><title>Guide</title></head><body>\
+            <h2>Install <em>now</em></h2>\
+            <p>Run <code>make</code>, see <a href=\"/docs\">the **docs**</a> or \
+            <a href=\"https://example.com/raw\"></a>. It costs 5 * 3 ~ 15, <del>not</del> <strong>less</strong>.</p>\
+            <img src=\"/x.png\" alt=\"X\">\
+            <ul><li>One<ul><li>Nested <b>two</b></li></ul></li></ul>\
+            <pre><code>let *p = &amp;x;\n    indented</code></pre></body></html>";
+        let text = convert_html(html, "https://example.com", OutputFormat::Text).unwrap();
+
+        assert_eq!(
+            text,
+            "Guide\n\n\
+             Install now\n\n\
+             Run make, see the **docs** or https://example.com/raw. It costs 5 * 3 ~ 15, not less.\n\n\
+             - One\n  - Nested two\n\n\
+             let *p = &x;\n    indented"
+        );
+    }
+
+    #[test]
+    fn test_text_keeps_literal_markup() {
+        let html = "<p>Literal [brackets](not-a-link), *stars*, __dunder__, snake_case, \
+            a\\*b, `tick` and <a href=\"/x\">real [link]</a>.</p>";
+
+        let markdown = convert_to_markdown(html, "https://example.com").unwrap();
+        assert!(markdown.contains(
+            "Literal \\[brackets\\](not-a-link), \\*stars\\*, \\_\\_dunder\\_\\_, snake_case, \
+             a\\\\\\*b, \\`tick\\` and [real \\[link\\]](https://example.com/x)."
+        ));
+        let text = convert_html(html, "https://example.com", OutputFormat::Text).unwrap();
+        assert!(text.ends_with(
+            "Literal [brackets](not-a-link), *stars*, __dunder__, snake_case, a\\*b, `tick` \
+             and real [link]."
+        ));
+    }
+
+    #[test]
+    fn test_text_format_snapshot() {
+        let html = include_str!("../test_data/medium.html");
+        let text = convert_html(html, "https://example.com", OutputFormat::Text).unwrap();
+        assert_eq!(text, include_str!("../test_data/medium.txt"));
+    }
+
+    #[test]
+    fn test_convert_to_rst() {
+        let html = "<html><head><title>Guide</title></head><body>\
+            <h2>Install</h2><p>Run <code>make</code> and read <a href=\"/docs\">the <em>docs</em></a>, \
+            <del>not</del> <strong>now</strong>.<br>Next line</p>\
+            <ol><li>One<ul><li>Nested</li></ul></li><li>Two</li></ol>\
+            <blockquote><p>Quoted</p></blockquote>\
+            <table><tr><th>Name</th><th>Role</th></tr><tr><td>Ada</td><td><code>dev</code></td></tr></table>\
+            <figure><img src=\"/a.png\" alt=\"Chart\" width=\"300\"><figcaption>Growth</figcaption></figure>\
+            <pre><code class=\"language-rust\">fn main() {\n    run();\n}</code></pre></body></html>";
+        let rst = convert_html(html, "https://example.com", OutputFormat::Rst).unwrap();
+
+        assert_eq!(
+            rst,
+            "=====\nGuide\n=====\n\n\
+             Install\n-------\n\n\
+             | Run ``make`` and read `the docs <https://example.com/docs>`__, not **now**.\n\
+             | Next line\n\n\
+             1. One\n\n   - Nested\n\n2. Two\n\n\
+             \x20   Quoted\n\n\
+             .. list-table::\n   :header-rows: 1\n\n   * - Name\n     - Role\n   * - Ada\n     - dev\n\n\
+             .. figure:: https://example.com/a.png\n   :alt: Chart\n   :width: 300px\n\n   Growth\n\n\
+             .. code-block:: rust\n\n   fn main() {\n       run();\n   }\n"
+        );
+    }
+
+    #[test]
+    fn test_rst_links_and_literal_markup() {
+        let html = "<p>See <a href=\"/a\">here</a> and <a href=\"/b\">here</a>, \
+            <a href=\"/c\">a_b <i>*</i></a>.</p>\
+            <p>Literal *stars*, `ticks`, trailing_ name_, |sub| and a\\b.</p>";
+        let rst = convert_html(html, "https://example.com", OutputFormat::Rst).unwrap();
+
+        assert!(rst.contains(
+            "See `here <https://example.com/a>`__ and `here <https://example.com/b>`__, \
+             `a\\_b \\* <https://example.com/c>`__."
+        ));
+        assert!(rst.contains(
+            "Literal \\*stars\\*, \\`ticks\\`, trailing\\_ name\\_, \\|sub\\| and a\\\\b."
+        ));
+    }
+
+    #[test]
+    fn test_rst_title_and_alt_escaping() {
+        let html = "<html><head><title>Use *args and `x`_ here</title></head><body>\
+            <img src=\"/a.png\" alt=\"Chart\n:target: https://evil.example\"></body></html>";
+        let rst = convert_html(html, "https://example.com", OutputFormat::Rst).unwrap();
+
+        assert_eq!(
+            rst,
+            "===========================\nUse \\*args and \\`x\\`\\_ here\n===========================\n\n\
+             .. image:: https://example.com/a.png\n   :alt: Chart :target: https://evil.example\n"
+        );
+    }
+
+    #[test]
+    fn test_rst_adornments_span_wide_characters() {
+        let html = "<html><head><title>入门指南</title></head><body>\
+            <h2>安装</h2><p>文本</p><h3>Café 設定</h3></body></html>";
+        let rst = convert_html(html, "https://example.com", OutputFormat::Rst).unwrap();
+
+        assert_eq!(
+            rst,
+            "========\n入门指南\n========\n\n安装\n----\n\n文本\n\n\
+             Café 設定\n~~~~~~~~~\n"
+        );
+    }
+
+    #[test]
+    fn test_output_format_from_str() {
+        assert_eq!("rst".parse::<OutputFormat>().unwrap(), OutputFormat::Rst);
+        assert_eq!("RST".parse::<OutputFormat>().unwrap(), OutputFormat::Rst);
+        assert_eq!(
+            "md".parse::<OutputFormat>().unwrap(),
+            OutputFormat::Markdown
+        );
+        assert_eq!("yml".parse::<OutputFormat>().unwrap(), OutputFormat::Yaml);
+        assert_eq!("txt".parse::<OutputFormat>().unwrap(), OutputFormat::Text);
+        let error = "markdwon".parse::<OutputFormat>().unwrap_err();
+        assert!(matches!(error, MarkdownError::UnknownFormat(ref name) if name == "markdwon"));
+        assert!(error.to_string().contains("expected one of: markdown (md)"));
+    }
+
+    #[test]
+    fn test_rst_format_snapshot() {
+        let html = include_str!("../test_data/medium.html");
+        let rst = convert_html(html, "https://example.com", OutputFormat::Rst).unwrap();
+        assert_eq!(rst, include_str!("../test_data/medium.rst"));
+    }
+
+    #[test]
+    fn test_convert_to_sanitized_html() {
+        let html = "<html><head><title>Post &amp; more</title><script>track()</script></head><body>\
+            <nav><a href=\"/home\">Home</a></nav>\
+            <article><h1 onclick=\"evil()\">Post</h1>\
+            <p>See <a href=\"../docs?a=1&amp;b=2\">docs</a>, <a href=\"#top\">top</a> \
+            and <a href=\"javascript:void(0)\">this</a>.</p>\
+            <script>alert(1)</script><div class=\"ad\">Buy</div>\
+            <img src=\"img/a.png\" srcset=\"img/a.png 1x, /img/a@2x.png 2x\" alt=\"A &lt;b&gt;\"></article></body></html>";
+        let output =
+            convert_html(html, "https://example.com/blog/post", OutputFormat::Html).unwrap();
+
+        assert!(output.starts_with("<!DOCTYPE html>\n<html>\n<head>"));
+        assert!(output.contains("<title>Post &amp; more</title>"));
+        assert!(output.contains("<body>\n<article><h1>Post</h1>"));
+        assert!(output.contains("<a href=\"https://example.com/docs?a=1&amp;b=2\">docs</a>"));
+        assert!(output.contains("<a href=\"#top\">top</a>"));
+        assert!(output.contains("<a>this</a>"));
+        assert!(output.contains(
+            "<img alt=\"A <b>\" src=\"https://example.com/blog/img/a.png\" \
+             srcset=\"https://example.com/blog/img/a.png 1x, https://example.com/img/a@2x.png 2x\">"
+        ));
+        assert!(output.ends_with("</article>\n</body>\n</html>\n"));
+        for removed in ["<script", "Home", "Buy", "onclick", "javascript:"] {
+            assert!(!output.contains(removed), "{removed} survived");
+        }
+    }
+
+    #[test]
+    fn test_convert_to_sanitized_html_drops_embedded_documents() {
+        let html = "<body><main><p>Text</p>\
+            <iframe srcdoc=\"<script>alert(1)</script>\"></iframe>\
+            <object data=\"javascript:alert(2)\"></object>\
+            <embed src=\"/movie.swf\"><frame src=\"/frame.html\">\
+            <div srcdoc=\"&lt;script&gt;alert(3)&lt;/script&gt;\" data=\"javascript:alert(4)\">kept</div>\
+            </main></body>";
+        let output = convert_html(html, "https://example.com", OutputFormat::Html).unwrap();
+
+        assert!(
+            output.contains("<main><p>Text</p><div>kept</div></main>"),
+            "{output}"
+        );
+        for removed in [
+            "alert", "srcdoc", "<iframe", "<object", "<embed", "movie", "frame",
+        ] {
+            assert!(!output.contains(removed), "{removed} survived in {output}");
+        }
+    }
+
+    /// Text of every `name` element in `xml`, read back with an XML parser
+    fn xml_element_texts(xml: &str, name: &str) -> Vec<String> {
+        use quick_xml::events::Event;
+
+        let mut reader = quick_xml::Reader::from_str(xml);
+        let mut texts = Vec::new();
+        let mut current: Option<String> = None;
+        loop {
+            match reader.read_event().unwrap() {
+                Event::Start(e) if e.name().as_ref() == name.as_bytes() => {
+                    current = Some(String::new())
+                }
+                Event::Empty(e) if e.name().as_ref() == name.as_bytes() => {
+                    texts.push(String::new())
+                }
+                Event::Text(t) => {
+                    if let Some(text) = current.as_mut() {
+                        text.push_str(&t.unescape().unwrap());
+                    }
+                }
+                Event::CData(c) => {
+                    if let Some(text) = current.as_mut() {
+                        text.push_str(std::str::from_utf8(&c.into_inner()).unwrap());
+                    }
+                }
+                Event::End(e) if e.name().as_ref() == name.as_bytes() => {
+                    texts.extend(current.take())
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+        texts
+    }
+
+    #[test]
+    fn test_xml_round_trip() {
+        let html = "<html><head><title>Tips &amp; tricks</title></head><body>\
+            <h2 id=\"intro\">Intro</h2><p>Use <a href=\"/a?x=1&amp;y=2\" title=\"A &quot;link&quot;\">this</a>.</p>\
+            <ul><li>One<ul><li>Nested</li></ul></li></ul>\
+            <table><tr><th>K</th></tr><tr><td>V</td></tr></table>\
+            <pre><code class=\"language-xml\">&lt;a&gt;&lt;![CDATA[x]]&gt;&lt;/a&gt;</code></pre></body></html>";
+        let document = parse_html_to_document(html, "https://example.com").unwrap();
+        let xml = convert_html(html, "https://example.com", OutputFormat::Xml).unwrap();
+
+        assert!(xml.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?><document>"));
+        assert!(xml.contains("<heading level=\"2\" id=\"intro\">Intro</heading>"));
+        assert!(xml.contains(
+            "<code_block language=\"xml\"><![CDATA[<a><![CDATA[x]]]]><![CDATA[></a>]]></code_block>"
+        ));
+        assert!(xml.contains("<images/>"));
+
+        let headings: Vec<String> = document.headings.iter().map(|h| h.text.clone()).collect();
+        let links: Vec<String> = document.links.iter().map(|l| l.text.clone()).collect();
+        let code: Vec<String> = document
+            .code_blocks
+            .iter()
+            .map(|c| c.code.clone())
+            .collect();
+        assert_eq!(
+            xml_element_texts(&xml, "heading"),
+            [headings.clone(), headings].concat()
+        );
+        assert_eq!(xml_element_texts(&xml, "link"), links);
+        assert_eq!(
+            xml_element_texts(&xml, "code_block"),
+            [code.clone(), code].concat()
+        );
+        assert_eq!(
+            xml_element_texts(&xml, "code_block")[0],
+            "<a><![CDATA[x]]></a>"
+        );
+        assert_eq!(xml_element_texts(&xml, "title")[0], "Tips & tricks");
+        assert_eq!(
+            xml_element_texts(&xml, "paragraph"),
+            [document.paragraphs.clone(), document.paragraphs.clone()].concat()
+        );
+        assert_eq!(xml_element_texts(&xml, "cell"), vec!["K", "V", "K", "V"]);
+        assert_eq!(
+            xml_element_texts(&xml, "text"),
+            vec!["One", "Nested", "One", "Nested"]
+        );
+    }
+
+    #[test]
+    fn test_xml_special_characters() {
+        let html = "<html><head><title>A &lt; B &amp; C 🚀</title></head><body>\
+            <p>Fish &amp; chips &lt;tag&gt; 🦀 \u{1}done</p></body></html>";
+        let xml = convert_html(html, "https://example.com", OutputFormat::Xml).unwrap();
+
+        assert!(xml.contains("<title>A &lt; B &amp; C 🚀</title>"));
+        assert!(!xml.contains('\u{1}'));
+        let paragraphs = xml_element_texts(&xml, "paragraph");
+        assert!(paragraphs[0].starts_with("Fish & chips \\<tag> 🦀"));
+
+        let empty = convert_html(
+            "<html><body></body></html>",
+            "https://example.com",
+            OutputFormat::Xml,
+        )
+        .unwrap();
+        assert!(empty.contains("<headings/><paragraphs/><links/>"));
+        assert!(empty.ends_with("<blocks/></document>"));
+    }
+
+    #[test]
+    fn test_links_csv_export() {
+        let html = "<p><a href=\"/docs\">Docs, guides</a> and \
+            <a href=\"https://other.org/x\">The \"best\" site</a> and \
+            <a href=\"https://sub.example.com/\">Sub</a></p>\
+            <img src=\"/logo.png\" alt=\"Logo\">";
+        let document = parse_html_to_document(html, "https://example.com/page").unwrap();
+
+        assert_eq!(
+            document_to_links_csv(&document, b','),
+            "kind,text,url,scope\r\n\
+             link,\"Docs, guides\",https://example.com/docs,internal\r\n\
+             link,\"The \"\"best\"\" site\",https://other.org/x,external\r\n\
+             link,Sub,https://sub.example.com/,external\r\n\
+             image,Logo,https://example.com/logo.png,internal\r\n"
+        );
+        assert!(
+            document_to_links_csv(&document, b'\t')
+                .contains("link\tDocs, guides\thttps://example.com/docs\tinternal\r\n")
+        );
+    }
+
+    const TEMPLATE_HTML: &str = "<html><head><title>Guide</title></head><body>\
+        <h2>Setup</h2><p>Install <a href=\"/pkg\">the package</a>.</p>\
+        <ol><li>Download</li><li>Run</li></ol>\
+        <pre><code class=\"language-sh\">make</code></pre>\
+        <img src=\"/shot.png\" alt=\"Screenshot\"></body></html>";
+
+    #[test]
+    fn test_builtin_templates() {
+        let default =
+            convert_html_with_template(TEMPLATE_HTML, "https://example.com", "default").unwrap();
+        assert_eq!(
+            default,
+            "# Guide\n\n\
+             ## Setup\n\n\
+             Install [the package](https://example.com/pkg).\n\n\
+             1. Download\n2. Run\n\n\
+             ```sh\nmake\n```\n\n\
+             ## Links\n\n- [the package](https://example.com/pkg)\n\n\
+             ## Images\n\n![Screenshot](https://example.com/shot.png)"
+        );
+
+        let compact =
+            convert_html_with_template(TEMPLATE_HTML, "https://example.com", "compact").unwrap();
+        assert_eq!(
+            compact,
+            "# Guide\n\n\
+             ## Setup\n\n\
+             Install [the package](https://example.com/pkg).\n\n\
+             1. Download\n2. Run\n\n\
+             ```sh\nmake\n```"
+        );
+    }
+
+    #[test]
+    fn test_custom_and_invalid_templates() {
+        let template = "{{ title | upper }}: {{ headings | length }} heading(s), \
+            {% for link in links %}{{ link.url }}{% endfor %}";
+        let output =
+            convert_html_with_template(TEMPLATE_HTML, "https://example.com", template).unwrap();
+        assert_eq!(output, "GUIDE: 1 heading(s), https://example.com/pkg");
+
+        for invalid in [
+            "{% for x in headings %}",
+            "{{ missing_field }}",
+            "{{ title | nope }}",
+        ] {
+            match convert_html_with_template(TEMPLATE_HTML, "https://example.com", invalid) {
+                Err(MarkdownError::TemplateError(message)) => assert!(!message.is_empty()),
+                other => panic!("expected a template error for {invalid:?}, got {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_json_styles_and_schema_version() {
+        let html = "<html><head><title>Doc</title></head><body>\
+            <h1>Heading</h1><p>Some text.</p></body></html>";
+        let compact = convert_html_with_options(
+            html,
+            "https://example.com",
+            OutputFormat::Json,
+            &ConversionOptions {
+                json_style: JsonStyle::Compact,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let pretty = convert_html(html, "https://example.com", OutputFormat::Json).unwrap();
+
+        assert!(!compact.contains('\n'));
+        assert!(!compact.contains(": ") && !compact.contains(", "));
+        assert!(compact.starts_with("{\"schema_version\":1,\"generator\":\"markdown_lab_rs/"));
+        assert!(pretty.contains('\n'));
+
+        for json in [&compact, &pretty] {
+            let value: serde_json::Value = serde_json::from_str(json).unwrap();
+            assert_eq!(value["schema_version"], 1);
+            assert_eq!(
+                value["generator"],
+                format!("markdown_lab_rs/{}", env!("CARGO_PKG_VERSION"))
+            );
+            assert_eq!(value["title"], "Doc");
+        }
+        let compact_value: serde_json::Value = serde_json::from_str(&compact).unwrap();
+        let pretty_value: serde_json::Value = serde_json::from_str(&pretty).unwrap();
+        assert_eq!(compact_value["headings"], pretty_value["headings"]);
+    }
+
+    #[test]
+    fn test_document_json_round_trip() {
+        let html = std::fs::read_to_string("test_data/medium.html").unwrap();
+        let options = ConversionOptions {
+            link_style: LinkStyle::Reference,
+            ..Default::default()
+        };
+        let direct = convert_html_with_options(
+            &html,
+            "https://example.com",
+            OutputFormat::Markdown,
+            &options,
+        )
+        .unwrap();
+
+        let document = parse_html_to_document(&html, "https://example.com").unwrap();
+        let json = document_to_json(&document).unwrap();
+        let restored = Document::from_json(&json).unwrap();
+        assert_eq!(
+            render_document(&restored, OutputFormat::Markdown, &options).unwrap(),
+            direct
+        );
+        assert_eq!(
+            render_document(&restored, OutputFormat::Rst, &options).unwrap(),
+            convert_html(&html, "https://example.com", OutputFormat::Rst).unwrap()
+        );
+        assert!(render_document(&restored, OutputFormat::Html, &options).is_err());
+    }
+
+    #[test]
+    fn test_document_from_partial_json() {
+        let json = r#"{
+            "schema_version": 99,
+            "title": "Cached",
+            "future_field": {"nested": true},
+            "blocks": [
+                {"type": "paragraph", "text": "Hello"},
+                {"type": "code_block", "code": "x = 1"}
+            ]
+        }"#;
+        let document = Document::from_json(json).unwrap();
+        assert!(document.links.is_empty());
+        assert_eq!(
+            render_document(
+                &document,
+                OutputFormat::Markdown,
+                &ConversionOptions::default()
+            )
+            .unwrap(),
+            "# Cached\n\nHello\n\n```\nx = 1\n```"
+        );
+
+        assert!(matches!(
+            Document::from_json("{\"title\": 1}"),
+            Err(MarkdownError::SerializationError(_))
+        ));
+    }
+
+    #[cfg(feature = "cache")]
+    #[test]
+    fn test_document_binary_round_trip() {
+        use crate::markdown_converter::{document_from_bytes, document_to_bytes};
+
+        let html = std::fs::read_to_string("test_data/medium.html").unwrap();
+        let document = parse_html_to_document(&html, "https://example.com").unwrap();
+        let bytes = document_to_bytes(&document).unwrap();
+        let restored = document_from_bytes(&bytes).unwrap();
+        assert_eq!(
+            document_to_json(&restored).unwrap(),
+            document_to_json(&document).unwrap()
+        );
+
+        let mut wrong_version = bytes.clone();
+        wrong_version[0] = 0xff;
+        for corrupted in [
+            &[][..],
+            &wrong_version[..],
+            &bytes[..bytes.len() / 2],
+            &[1, 0xc1, 0x00, 0x13],
+        ] {
+            assert!(matches!(
+                document_from_bytes(corrupted),
+                Err(MarkdownError::SerializationError(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_meta_tags() {
+        let html = "<html><head><title>Post</title>\
+            <meta name=\"Description\" content=\"  A short\n summary. \">\
+            <meta name=\"description\" content=\"Ignored second description\">\
+            <meta name=\"keywords\" content=\"rust, html ,, markdown\">\
+            <meta name=\"author\" content=\"\">\
+            <meta name=\"author\" content=\"Jo Doe\">\
+            <meta property=\"og:title\" content=\"Not a named meta\">\
+            </head><body><p>Text</p></body></html>";
+        let document = parse_html_to_document(html, "https://example.com").unwrap();
+        let meta = &document.meta;
+        assert_eq!(meta.description.as_deref(), Some("A short summary."));
+        assert_eq!(meta.keywords, ["rust", "html", "markdown"]);
+        assert_eq!(meta.author.as_deref(), Some("Jo Doe"));
+        assert_eq!(meta.generator, None);
+
+        let options = ConversionOptions {
+            front_matter: true,
+            ..Default::default()
+        };
+        let markdown = convert_html_with_options(
+            html,
+            "https://example.com",
+            OutputFormat::Markdown,
+            &options,
+        )
+        .unwrap();
+        assert!(markdown.contains(
+            "image_count: 0\ndescription: \"A short summary.\"\n\
+             keywords: [\"rust\", \"html\", \"markdown\"]\nauthor: \"Jo Doe\"\n---"
+        ));
+
+        let json: serde_json::Value = serde_json::from_str(
+            &convert_html(html, "https://example.com", OutputFormat::Json).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(json["meta"]["keywords"][1], "html");
+        assert!(json["meta"].get("generator").is_none());
+
+        let xml = convert_html(html, "https://example.com", OutputFormat::Xml).unwrap();
+        assert!(xml.contains(
+            "<description>A short summary.</description><keywords><keyword>rust</keyword>"
+        ));
+
+        // No head at all
+        let bare = parse_html_to_document("<p>Just text</p>", "https://example.com").unwrap();
+        assert!(bare.meta.description.is_none() && bare.meta.keywords.is_empty());
+    }
+
+    #[test]
+    fn test_social_metadata() {
+        let og_only = "<html><head>\
+            <meta property=\"og:title\" content=\"Launch  day\">\
+            <meta property=\"og:title\" content=\"Second title\">\
+            <meta property=\"og:description\" content=\"We shipped.\">\
+            <meta property=\"og:image\" content=\"../img/hero.png\">\
+            <meta property=\"og:type\" content=\"article\">\
+            <meta property=\"og:site_name\" content=\"Blog\">\
+            </head><body><p>Text</p></body></html>";
+        let social = extract_social_metadata(og_only, "https://example.com/blog/post").unwrap();
+        assert_eq!(
+            social,
+            SocialMeta {
+                og_title: Some("Launch day".to_string()),
+                og_description: Some("We shipped.".to_string()),
+                og_image: Some("https://example.com/img/hero.png".to_string()),
+                og_type: Some("article".to_string()),
+                og_site_name: Some("Blog".to_string()),
+                ..Default::default()
+            }
+        );
+
+        let twitter_only = "<head><meta name=\"twitter:card\" content=\"summary_large_image\">\
+            <meta name=\"twitter:image\" content=\"https://cdn.example.org/card.jpg\"></head>";
+        let social = extract_social_metadata(twitter_only, "https://example.com").unwrap();
+        assert_eq!(social.twitter_card.as_deref(), Some("summary_large_image"));
+        assert_eq!(
+            social.twitter_image.as_deref(),
+            Some("https://cdn.example.org/card.jpg")
+        );
+        assert!(social.og_title.is_none());
+
+        let neither = extract_social_metadata("<p>Plain</p>", "https://example.com").unwrap();
+        assert!(neither.is_empty());
+
+        // Merged into the document only on request, with og:title standing in for <title>
+        let options = ConversionOptions {
+            social_metadata: true,
+            ..Default::default()
+        };
+        let document =
+            parse_html_to_document_with_options(og_only, "https://example.com/blog/post", &options)
+                .unwrap();
+        assert_eq!(document.title, "Launch day");
+        assert_eq!(document.meta.social.og_type.as_deref(), Some("article"));
+
+        let plain = parse_html_to_document(og_only, "https://example.com/blog/post").unwrap();
+        assert_eq!(plain.title, "No Title");
+        assert!(plain.meta.social.is_empty());
+
+        let titled = "<html><head><title>Real title</title>\
+            <meta property=\"og:title\" content=\"OG\"></head><body></body></html>";
+        let document =
+            parse_html_to_document_with_options(titled, "https://example.com", &options).unwrap();
+        assert_eq!(document.title, "Real title");
+        let xml =
+            convert_html_with_options(titled, "https://example.com", OutputFormat::Xml, &options)
+                .unwrap();
+        assert!(xml.contains("<social><og_title>OG</og_title></social>"));
+    }
+
+    #[test]
+    fn test_json_ld_extraction() {
+        let html = r#"<html><head>
+            <script type="application/ld+json">
+                {"@context": "https://schema.org", "@type": "Article",
+                 "headline": "Commas, \"quoted\", ]", "keywords": ["a", "b",],}
+            </script>
+            <script type="application/ld+json">[
+                {"@type": "Product", "name": "Widget"},
+                {"@type": ["FAQPage", "WebPage"]}
+            ]</script>
+            <script type="application/ld+json">{"@type": "Broken" "name": 1}</script>
+            <script type="application/ld+json">{"@graph": [{"@type": "Article"}, {"@type": "Person"}]}</script>
+            <script type="text/javascript">var x = {"@type": "NotJsonLd"};</script>
+            </head><body></body></html>"#;
+
+        let values = extract_json_ld(html);
+        assert_eq!(values.len(), 4);
+        assert_eq!(values[0]["headline"], "Commas, \"quoted\", ]");
+        assert_eq!(values[0]["keywords"], serde_json::json!(["a", "b"]));
+        assert_eq!(values[1]["name"], "Widget");
+        assert_eq!(
+            json_ld_types(&values),
+            ["Article", "Product", "FAQPage", "WebPage", "Person"]
+        );
+
+        assert!(extract_json_ld("<p>No structured data</p>").is_empty());
+    }
+
+    #[test]
+    fn test_base_tag_and_canonical_url() {
+        let html = "<html><head><title>Docs</title>\
+            <base href=\"https://example.com/docs/v2/\">\
+            <link rel=\"alternate canonical\" href=\"guide\">\
+            </head><body><p>Read the <a href=\"intro\">intro</a> or \
+            <a href=\"/about\">about us</a>.</p><img src=\"img/logo.png\" alt=\"Logo\"></body></html>";
+        let caller = "https://cdn.example.net/cache/abc/page.html";
+
+        let document = parse_html_to_document(html, caller).unwrap();
+        let urls: Vec<&str> = document.links.iter().map(|l| l.url.as_str()).collect();
+        assert_eq!(
+            urls,
+            [
+                "https://example.com/docs/v2/intro",
+                "https://example.com/about"
+            ]
+        );
+        assert_eq!(
+            document.images[0].src,
+            "https://example.com/docs/v2/img/logo.png"
+        );
+        assert_eq!(
+            document.meta.canonical_url.as_deref(),
+            Some("https://example.com/docs/v2/guide")
+        );
+        assert_eq!(document.base_url, caller);
+        assert_eq!(
+            extract_links(html, caller).unwrap(),
+            [
+                "https://example.com/about",
+                "https://example.com/docs/v2/intro"
+            ]
+        );
+
+        let options = ConversionOptions {
+            ignore_base_tag: true,
+            ..Default::default()
+        };
+        let document = parse_html_to_document_with_options(html, caller, &options).unwrap();
+        assert_eq!(
+            document.links[0].url,
+            "https://cdn.example.net/cache/abc/intro"
+        );
+        assert_eq!(
+            document.meta.canonical_url.as_deref(),
+            Some("https://cdn.example.net/cache/abc/guide")
+        );
+
+        // A relative base resolves against the caller's URL
+        let relative = "<head><base href=\"/v3/\"></head><body><a href=\"x\">x</a></body>";
+        let document = parse_html_to_document(relative, caller).unwrap();
+        assert_eq!(document.links[0].url, "https://cdn.example.net/v3/x");
+        assert!(document.meta.canonical_url.is_none());
+    }
+
+    #[test]
+    fn test_declared_language() {
+        let language = |html: &str| {
+            parse_html_to_document(html, "https://example.com")
+                .unwrap()
+                .meta
+                .language
+        };
+        let http_equiv = "<meta http-equiv=\"Content-Language\" content=\"de-AT, en\">";
+        let og_locale = "<meta property=\"og:locale\" content=\"fr_FR\">";
+
+        assert_eq!(
+            language(&format!(
+                "<html lang=\"EN-us\"><head>{http_equiv}{og_locale}</head></html>"
+            )),
+            Some("en".to_string())
+        );
+        assert_eq!(
+            language(&format!(
+                "<html><head>{http_equiv}{og_locale}</head></html>"
+            )),
+            Some("de".to_string())
+        );
+        assert_eq!(
+            language(&format!("<html lang=\"\"><head>{og_locale}</head></html>")),
+            Some("fr".to_string())
+        );
+        assert_eq!(language("<html lang=\"x-klingon\"><p>Hi</p></html>"), None);
+
+        let options = ConversionOptions {
+            front_matter: true,
+            ..Default::default()
+        };
+        let html =
+            "<html lang=\"pt-BR\"><head><title>T</title></head><body><p>Olá</p></body></html>";
+        let markdown = convert_html_with_options(
+            html,
+            "https://example.com",
+            OutputFormat::Markdown,
+            &options,
+        )
+        .unwrap();
+        assert!(markdown.contains("\nlanguage: \"pt\"\n"));
+        let xml = convert_html(html, "https://example.com", OutputFormat::Xml).unwrap();
+        assert!(xml.contains("<language>pt</language>"));
+    }
+
+    #[cfg(feature = "language_detection")]
+    #[test]
+    fn test_detect_language_option() {
+        let html = "<html><body><p>Der schnelle braune Fuchs springt über den faulen Hund, \
+            während die Katze auf dem warmen Fensterbrett schläft und träumt.</p></body></html>";
+        let options = ConversionOptions {
+            detect_language: true,
+            ..Default::default()
+        };
+        let detected =
+            parse_html_to_document_with_options(html, "https://example.com", &options).unwrap();
+        assert_eq!(detected.meta.language.as_deref(), Some("de"));
+
+        let undetected = parse_html_to_document(html, "https://example.com").unwrap();
+        assert_eq!(undetected.meta.language, None);
+    }
+
+    #[test]
+    fn test_publication_dates() {
+        // Only a human-readable <time>
+        let html = "<article><h1>Post</h1><time>Thursday, 29 February 2024</time></article>";
+        assert_eq!(
+            extract_publication_date(html).as_deref(),
+            Some("2024-02-29T00:00:00Z")
+        );
+        assert_eq!(extract_modified_date(html), None);
+
+        // Meta wins over conflicting JSON-LD; JSON-LD still supplies what meta lacks
+        let html = r#"<html><head>
+            <meta property="article:published_time" content="2024-05-01T08:15:00+02:00">
+            <script type="application/ld+json">{"@graph": [{"@type": "Article",
+                "datePublished": "2023-01-01", "dateModified": "2024-05-02T09:00:00.5Z"}]}</script>
+            </head><body><p>Text</p>
+            <span itemprop="dateModified" content="2020-01-01">Jan 1</span></body></html>"#;
+        assert_eq!(
+            extract_publication_date(html).as_deref(),
+            Some("2024-05-01T08:15:00+02:00")
+        );
+        assert_eq!(
+            extract_modified_date(html).as_deref(),
+            Some("2024-05-02T09:00:00Z")
+        );
+
+        // Unparseable candidates fall through to the next source
+        let html = "<time datetime=\"soon\">Coming soon</time>\
+            <div itemprop=\"datePublished\">2022-12-24</div>";
+        assert_eq!(
+            extract_publication_date(html).as_deref(),
+            Some("2022-12-24T00:00:00Z")
+        );
+        assert_eq!(extract_publication_date("<p>No dates, 12 May</p>"), None);
+
+        let options = ConversionOptions {
+            front_matter: true,
+            ..Default::default()
+        };
+        let markdown = convert_html_with_options(
+            "<html><head><title>T</title><meta name=\"article:modified_time\" \
+             content=\"2024-06-01\"></head><body><time datetime=\"2024-05-31\"></time>\
+             <p>Hi</p></body></html>",
+            "https://example.com",
+            OutputFormat::Markdown,
+            &options,
+        )
+        .unwrap();
+        assert!(markdown.contains(
+            "published_at: \"2024-05-31T00:00:00Z\"\nmodified_at: \"2024-06-01T00:00:00Z\"\n"
+        ));
+    }
+
+    #[test]
+    fn test_discover_feeds() {
+        let html = r#"<html><head>
+            <link rel="alternate" type="application/rss+xml" href="feed.xml" title="All  posts">
+            <link rel="alternate" type="Application/Atom+XML; charset=utf-8" href="/atom.xml">
+            <link rel="alternate" type="application/feed+json" href="https://cdn.example.org/feed.json">
+            <link rel="alternate" type="application/rss+xml" href="/blog/feed.xml" title="Duplicate">
+            <link rel="alternate" hreflang="de" href="/de/">
+            <link rel="stylesheet" type="text/css" href="/style.css">
+            <link rel="sitemap" type="application/xml" href="/sitemap.xml">
+            </head><body><a rel="alternate" type="application/rss+xml" href="/not-a-link-tag">x</a></body></html>"#;
+
+        let feeds = discover_feeds(html, "https://example.com/blog/").unwrap();
+        let summary: Vec<(&str, FeedKind, Option<&str>)> = feeds
+            .iter()
+            .map(|feed| (feed.url.as_str(), feed.kind, feed.title.as_deref()))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (
+                    "https://example.com/blog/feed.xml",
+                    FeedKind::Rss,
+                    Some("All posts")
+                ),
+                ("https://example.com/atom.xml", FeedKind::Atom, None),
+                (
+                    "https://cdn.example.org/feed.json",
+                    FeedKind::JsonFeed,
+                    None
+                ),
+                ("https://example.com/sitemap.xml", FeedKind::Sitemap, None),
+            ]
+        );
+
+        assert!(
+            discover_feeds("<p>No head</p>", "https://example.com")
+                .unwrap()
+                .is_empty()
+        );
+        assert!(discover_feeds(html, "not a url").is_err());
+    }
+
+    #[test]
+    fn test_document_stats() {
+        let html = "<html><head><title>Stats</title></head><body>\
+            <h1>Intro</h1><p>One two <a href=\"/three\">three</a> four.</p>\
+            <h2>Setup</h2><h2>Usage</h2>\
+            <ul><li>Five six</li></ul>\
+            <blockquote><p>Seven</p><pre><code>let not_prose = 1;\nlet also = 2;</code></pre></blockquote>\
+            <pre><code>fn main() {\n}\n</code></pre>\
+            <img src=\"/a.png\" alt=\"Alt words\"></body></html>";
+        let document = parse_html_to_document(html, "https://example.com").unwrap();
+        let stats = document.stats();
+
+        assert_eq!(
+            stats,
+            DocumentStats {
+                // Intro, One two three four., Setup, Usage, Five six, Seven
+                word_count: 10,
+                reading_time_minutes: 1,
+                headings_by_level: [1, 2, 0, 0, 0, 0],
+                link_count: 1,
+                image_count: 1,
+                code_line_count: 4,
+            }
+        );
+
+        let json: serde_json::Value = serde_json::from_str(
+            &convert_html(html, "https://example.com", OutputFormat::Json).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(json["stats"]["word_count"], 10);
+        assert_eq!(json["meta"]["word_count"], 10);
+        assert_eq!(json["stats"]["headings_by_level"][1], 2);
+
+        let long = format!("<p>{}</p>", "word ".repeat(401));
+        let document = parse_html_to_document(&long, "https://example.com").unwrap();
+        assert_eq!(document.stats().reading_time_minutes, 3);
+        let empty = parse_html_to_document("", "https://example.com").unwrap();
+        assert_eq!(empty.stats(), DocumentStats::default());
+    }
+
+    #[test]
+    fn test_robots_directives() {
+        let combined = extract_robots_directives(
+            "<head><meta name=\"ROBOTS\" content=\"NoIndex,NOARCHIVE\">\
+             <meta name=\"googlebot\" content=\"nofollow\"></head>",
+        );
+        assert_eq!(
+            combined,
+            RobotsDirectives {
+                noindex: true,
+                nofollow: true,
+                noarchive: true,
+            }
+        );
+        assert!(!combined.is_indexable());
+
+        let googlebot_none =
+            extract_robots_directives("<meta name=\"Googlebot\" content=\"none\">");
+        assert!(googlebot_none.noindex && googlebot_none.nofollow && !googlebot_none.noarchive);
+
+        let header = extract_robots_directives(
+            "<meta http-equiv=\"X-Robots-Tag\" content=\"googlebot: noarchive, max-snippet:-1\">",
+        );
+        assert!(header.noarchive && header.is_indexable());
+
+        // Unrelated meta and non-restricting values leave the page indexable
+        let open = extract_robots_directives(
+            "<meta name=\"description\" content=\"noindex\">\
+             <meta name=\"robots\" content=\"index, follow\">",
+        );
+        assert!(open.is_empty());
+
+        let page = "<html><head><meta name=\"robots\" content=\"noindex, nofollow\"></head>\
+            <body><a href=\"/a\">A</a><a rel=\"NoFollow\" href=\"/b\">B</a></body></html>";
+        assert_eq!(extract_links(page, "https://example.com").unwrap().len(), 2);
+        assert!(
+            extract_follow_links(page, "https://example.com")
+                .unwrap()
+                .is_empty()
+        );
+        let followable = page.replace("noindex, nofollow", "noindex");
+        assert_eq!(
+            extract_follow_links(&followable, "https://example.com").unwrap(),
+            ["https://example.com/a"]
+        );
+
+        let document = parse_html_to_document(page, "https://example.com").unwrap();
+        assert!(!document.meta.robots.is_indexable());
+        let xml = convert_html(page, "https://example.com", OutputFormat::Xml).unwrap();
+        assert!(xml.contains("<robots noindex=\"true\" nofollow=\"true\" noarchive=\"false\"/>"));
+    }
+
+    #[test]
+    fn test_hreflang_alternates() {
+        let html = r#"<html><head>
+            <link rel="alternate" hreflang="de-AT" href="../de-at/page">
+            <link rel="alternate" hreflang="x-default" href="/page">
+            <link rel="alternate" hreflang="english!" href="/en/page">
+            <link rel="alternate" hreflang="de-AT" href="../de-at/page">
+            <link rel="alternate" hreflang="fr" href="">
+            <link rel="canonical" hreflang="es" href="/es/page">
+            <link rel="alternate" type="application/rss+xml" href="/feed.xml">
+            </head><body><p>Text</p></body></html>"#;
+        let expected = [
+            ("de-AT", "https://example.com/de-at/page"),
+            ("x-default", "https://example.com/page"),
+            ("english!", "https://example.com/en/page"),
+        ];
+
+        let alternates = extract_alternates(html, "https://example.com/en/page").unwrap();
+        let pairs: Vec<(&str, &str)> = alternates
+            .iter()
+            .map(|(lang, url)| (lang.as_str(), url.as_str()))
+            .collect();
+        assert_eq!(pairs, expected);
+
+        let json: serde_json::Value = serde_json::from_str(
+            &convert_html(html, "https://example.com/en/page", OutputFormat::Json).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            json["meta"]["alternates"][0],
+            serde_json::json!({"hreflang": "de-AT", "url": "https://example.com/de-at/page"})
+        );
+        assert_eq!(json["meta"]["alternates"].as_array().unwrap().len(), 3);
+
+        assert!(
+            extract_alternates("<p>None</p>", "https://example.com")
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_breadcrumbs() {
+        let crumb = |text: &str, url: Option<&str>| Crumb {
+            text: text.to_string(),
+            url: url.map(str::to_string),
+        };
+
+        let class_based = r#"<html><body>
+            <div class="breadcrumbs">
+                <a href="/">Home</a> &rsaquo; <a href="../">Docs</a> &rsaquo; <a href="./">API</a>
+            </div>
+            <main><p>Reference</p></main></body></html>"#;
+        let expected = vec![
+            crumb("Home", Some("https://example.com/")),
+            crumb("Docs", Some("https://example.com/docs/")),
+            crumb("API", Some("https://example.com/docs/api/")),
+        ];
+        assert_eq!(
+            extract_breadcrumbs(class_based, "https://example.com/docs/api/page").unwrap(),
+            expected
+        );
+
+        let json_ld_only = r#"<html><head><script type="application/ld+json">
+            {"@context": "https://schema.org", "@graph": [
+                {"@type": "WebPage", "name": "API"},
+                {"@type": "BreadcrumbList", "itemListElement": [
+                    {"@type": "ListItem", "position": 3, "name": "API"},
+                    {"@type": "ListItem", "position": 1, "name": "Home", "item": "https://example.com/"},
+                    {"@type": "ListItem", "position": "2", "item": {"@id": "/docs/", "name": "Docs"}}
+                ]}
+            ]}
+            </script></head><body><p>Reference</p></body></html>"#;
+        let crumbs = extract_breadcrumbs(json_ld_only, "https://example.com/docs/api").unwrap();
+        assert_eq!(
+            crumbs,
+            vec![
+                crumb("Home", Some("https://example.com/")),
+                crumb("Docs", Some("https://example.com/docs/")),
+                crumb("API", None),
+            ]
+        );
+
+        let options = ConversionOptions {
+            front_matter: true,
+            ..Default::default()
+        };
+        let markdown = convert_html_with_options(
+            json_ld_only,
+            "https://example.com/docs/api",
+            OutputFormat::Markdown,
+            &options,
+        )
+        .unwrap();
+        assert!(markdown.contains("\npath: \"Home > Docs > API\"\n"));
+        let json: serde_json::Value = serde_json::from_str(
+            &convert_html(
+                class_based,
+                "https://example.com/docs/api/page",
+                OutputFormat::Json,
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(json["meta"]["breadcrumbs"][1]["text"], "Docs");
+
+        assert!(
+            extract_breadcrumbs("<nav><a href=\"/\">Home</a></nav>", "https://example.com")
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_hidden_elements() {
+        let html = r#"<html><body><main>
+            <h2>FAQ <span class="visually-hidden">section</span></h2>
+            <div class="accordion">
+                <button aria-expanded="true">How do I sign up?</button>
+                <div class="panel"><p>Use the form.</p></div>
+                <button aria-expanded="false">Can I cancel?</button>
+                <div class="panel" hidden><p>Any time from settings.</p></div>
+                <button aria-expanded="false">Is there a trial?</button>
+                <div class="panel" style="display:none"><p>Yes, for 14 days.</p></div>
+            </div>
+            <nav aria-hidden="true"><p>Home About</p></nav>
+        </main></body></html>"#;
+
+        let markdown = convert_to_markdown(html, "https://example.com").unwrap();
+        assert!(markdown.contains("## FAQ\n"));
+        assert!(markdown.contains("Use the form."));
+        for hidden in ["section", "Any time from settings.", "14 days"] {
+            assert!(!markdown.contains(hidden), "{hidden} survived");
+        }
+
+        let options = ConversionOptions {
+            include_hidden: true,
+            ..Default::default()
+        };
+        let markdown = convert_html_with_options(
+            html,
+            "https://example.com",
+            OutputFormat::Markdown,
+            &options,
+        )
+        .unwrap();
+        assert!(markdown.contains("## FAQ section"));
+        assert!(markdown.contains("Any time from settings."));
+        assert!(markdown.contains("Yes, for 14 days."));
+    }
+
+    #[test]
+    fn test_convert_html_bytes_charsets() {
+        let page = |charset: &str, body: &[u8]| {
+            let mut html = format!(
+                "<html><head><meta charset=\"{charset}\"><title>Menu</title></head><body><p>"
+            )
+            .into_bytes();
+            html.extend_from_slice(body);
+            html.extend_from_slice(b"</p></body></html>");
+            html
+        };
+        // \u{201c}Caf\u{e9}\u{201d} \u{2013} 5\u{20ac} in windows-1252
+        let cp1252 = b"\x93Caf\xe9\x94 \x96 5\x80";
+        let expected = "\u{201c}Caf\u{e9}\u{201d} \u{2013} 5\u{20ac}";
+
+        let html = page("windows-1252", cp1252);
+        let markdown =
+            convert_html_bytes(&html, "https://example.com", OutputFormat::Markdown, None).unwrap();
+        assert!(markdown.contains(expected), "{markdown}");
+        assert_eq!(decode_html_bytes(&html, None).1, "windows-1252");
+        // latin1 and ascii are windows-1252 labels too
+        assert_eq!(
+            decode_html_bytes(&page("ISO-8859-1", cp1252), None).1,
+            "windows-1252"
+        );
+
+        // declared UTF-8, but the bytes are not
+        let (text, encoding) = decode_html_bytes(&page("utf-8", cp1252), None);
+        assert_eq!(encoding, "windows-1252");
+        assert!(text.contains(expected));
+        // declared windows-1252, but the bytes are UTF-8
+        let (text, encoding) = decode_html_bytes(&page("windows-1252", expected.as_bytes()), None);
+        assert_eq!(encoding, "UTF-8");
+        assert!(text.contains(expected));
+
+        // shift_jis from the hint when the page declares nothing; the BOM beats everything
+        let sjis = b"<p>\x93\xfa\x96\x7b</p>";
+        assert_eq!(
+            decode_html_bytes(sjis, Some("shift_jis")),
+            ("<p>\u{65e5}\u{672c}</p>".to_string(), "Shift_JIS")
+        );
+        assert_eq!(
+            decode_html_bytes(sjis, Some("no-such-charset")).1,
+            "windows-1252"
+        );
+        let bom = [
+            b"\xef\xbb\xbf".as_slice(),
+            &page("shift_jis", "\u{e9}".as_bytes()),
+        ]
+        .concat();
+        let (text, encoding) = decode_html_bytes(&bom, Some("shift_jis"));
+        assert_eq!(encoding, "UTF-8");
+        assert!(text.starts_with("<html>") && text.contains("<p>\u{e9}</p>"));
+
+        // invalid sequences are replaced rather than failing the conversion
+        let (text, encoding) = decode_html_bytes(b"<p>ok \x81 ok</p>", Some("shift_jis"));
+        assert_eq!(encoding, "Shift_JIS");
+        assert!(text.contains("ok \u{fffd}"), "{text}");
+    }
+
+    #[test]
+    fn test_noscript_fallback() {
+        let html = r#"<html><body><main>
+            <h1>Gallery</h1>
+            <figure>
+                <img class="lazy" src="data:image/gif;base64,R0lGODlhAQABAAAAACw=" alt="">
+                <noscript><img src="/photos/harbour.jpg" alt="Harbour at dawn"></noscript>
+            </figure>
+            <p>Intro <noscript><a href="/static">static version</a></noscript></p>
+            <noscript><p>Enable &amp; reload for comments.</p></noscript>
+        </main></body></html>"#;
+        let base_url = "https://example.com/";
+
+        let markdown = convert_to_markdown(html, base_url).unwrap();
+        assert!(!markdown.contains("harbour.jpg"));
+        assert!(!markdown.contains("static version"));
+
+        let options = ConversionOptions {
+            use_noscript_fallback: true,
+            ..Default::default()
+        };
+        let document = parse_html_to_document_with_options(html, base_url, &options).unwrap();
+        assert_eq!(document.images.len(), 1);
+        assert_eq!(
+            document.images[0].src,
+            "https://example.com/photos/harbour.jpg"
+        );
+        assert_eq!(document.images[0].alt, "Harbour at dawn");
+
+        let markdown =
+            convert_html_with_options(html, base_url, OutputFormat::Markdown, &options).unwrap();
+        assert!(markdown.contains("![Harbour at dawn](https://example.com/photos/harbour.jpg)"));
+        assert!(markdown.contains("Intro [static version](https://example.com/static)"));
+        assert!(markdown.contains("Enable & reload for comments."));
+        assert!(!markdown.contains("noscript"));
+    }
+
+    #[test]
+    fn test_iframe_frames() {
+        let html = r#"<html><body><main>
+            <h1>Docs</h1>
+            <iframe src="frames/body.html"></iframe>
+            <iframe src="https://widgets.example.net/chat.html" title="Chat"></iframe>
+        </main></body></html>"#;
+        let base_url = "https://docs.example.com/guide/";
+        let body_url = "https://docs.example.com/guide/frames/body.html";
+        let nested_url = "https://docs.example.com/guide/frames/nested/part.html";
+        let chat_url = "https://widgets.example.net/chat.html";
+
+        assert_eq!(
+            extract_iframe_sources(html, base_url).unwrap(),
+            [body_url, chat_url]
+        );
+
+        let frames = HashMap::from([
+            (
+                body_url.to_string(),
+                r#"<html><body><p>Body text <a href="../install">Install</a></p>
+                <iframe src="nested/part.html"></iframe></body></html>"#
+                    .to_string(),
+            ),
+            (
+                nested_url.to_string(),
+                r#"<body><p>Nested part <img src="diagram.png" alt="Diagram"></p></body>"#
+                    .to_string(),
+            ),
+            (
+                chat_url.to_string(),
+                "<body><p>Cross-origin chat</p></body>".to_string(),
+            ),
+        ]);
+        let options = ConversionOptions {
+            collect_frames: true,
+            ..Default::default()
+        };
+        let document =
+            parse_html_to_document_with_frames(html, base_url, &options, &frames).unwrap();
+        let markdown = render_document(&document, OutputFormat::Markdown, &options).unwrap();
+
+        assert!(markdown.contains("Body text [Install](https://docs.example.com/guide/install)"));
+        assert!(markdown.contains(
+            "Nested part\n\n![Diagram](https://docs.example.com/guide/frames/nested/diagram.png)"
+        ));
+        assert!(!markdown.contains("Cross-origin chat"));
+        assert_eq!(document.frames, [chat_url]);
+        assert_eq!(document.embeds.len(), 1);
+        assert_eq!(document.embeds[0].src, chat_url);
+
+        // without fetched content, every frame is recorded and none is merged
+        let document = parse_html_to_document_with_options(html, base_url, &options).unwrap();
+        assert_eq!(document.frames, [body_url, chat_url]);
+        assert!(
+            parse_html_to_document(html, base_url)
+                .unwrap()
+                .frames
+                .is_empty()
+        );
+
+        // a frame that embeds itself stops after a few levels
+        let looping = HashMap::from([(
+            body_url.to_string(),
+            r#"<body><p>Again</p><iframe src="body.html"></iframe></body>"#.to_string(),
+        )]);
+        let document =
+            parse_html_to_document_with_frames(html, base_url, &options, &looping).unwrap();
+        assert_eq!(
+            document.paragraphs.iter().filter(|p| *p == "Again").count(),
+            4
+        );
+    }
+
+    #[test]
+    fn test_table_of_contents() {
+        let html = "<html><head><title>Guide</title></head><body>\
+            <h1>Guide</h1>\
+            <h2>Install</h2><p>a</p>\
+            <h3>From source</h3><p>b</p>\
+            <h2>Usage &amp; Examples!</h2>\
+            <h4>Deep</h4>\
+            <h2>Install</h2>\
+            <h2>Install</h2>\
+            <h2>Install 1</h2>\
+            </body></html>";
+        let options = ConversionOptions {
+            toc: true,
+            ..Default::default()
+        };
+        let markdown = convert_html_with_options(
+            html,
+            "https://example.com",
+            OutputFormat::Markdown,
+            &options,
+        )
+        .unwrap();
+        assert!(markdown.starts_with(
+            "# Guide\n\n\
+             - [Install](#install)\n  \
+               - [From source](#from-source)\n\
+             - [Usage & Examples!](#usage--examples)\n  \
+               - [Deep](#deep)\n\
+             - [Install](#install-1)\n\
+             - [Install](#install-2)\n\
+             - [Install 1](#install-1-1)\n\n\
+             # Guide\n\n## Install"
+        ));
+
+        let shallow = ConversionOptions {
+            toc: true,
+            toc_max_level: 2,
+            title_mode: TitleMode::IfNoH1,
+            ..Default::default()
+        };
+        let markdown = convert_html_with_options(
+            html,
+            "https://example.com",
+            OutputFormat::Markdown,
+            &shallow,
+        )
+        .unwrap();
+        assert!(
+            markdown
+                .starts_with("- [Install](#install)\n- [Usage & Examples!](#usage--examples)\n")
+        );
+        assert!(!markdown.contains("(#from-source)") && !markdown.contains("(#deep)"));
+
+        let plain = convert_to_markdown(html, "https://example.com").unwrap();
+        assert!(!plain.contains("(#install)"));
+    }
+
+    #[test]
+    fn test_grouped_layout_option() {
+        let html = "<h2>Intro</h2><p>First paragraph.</p><h2>Details</h2><p>Second paragraph.</p>";
+        let options = ConversionOptions {
+            layout: MarkdownLayout::Grouped,
+            ..Default::default()
+        };
+
+        let markdown = convert_html_with_options(
+            html,
+            "https://example.com",
+            OutputFormat::Markdown,
+            &options,
+        )
+        .unwrap();
+
+        assert!(
+            markdown.contains("## Intro\n\n## Details\n\nFirst paragraph.\n\nSecond paragraph.")
+        );
+    }
+}
+
+#[cfg(test)]
+mod chunker_tests {
+    use crate::chunker::{
+        ChunkIterator, ChunkManifest, ChunkOptions, ChunkStrategy, ChunkerConfig, ChunkerError,
+        MANIFEST_VERSION, OverlapSpec, SizeUnit, TruncationStrategy, chunk_html, chunk_id,
+        chunk_markdown_to_manifest, create_semantic_chunks, create_semantic_chunks_sized,
+        create_semantic_chunks_with_metadata, create_semantic_chunks_with_options,
+    };
+    use once_cell::sync::Lazy;
+    use regex::Regex;
+
+    static SENTENCE_END: Lazy<Regex> = Lazy::new(|| Regex::new(r"[.!?]\s+").unwrap());
+
+    fn last_sentence(chunk: &str) -> &str {
+        let chunk = chunk.trim_end();
+        let start = SENTENCE_END
+            .find_iter(chunk)
+            .last()
+            .map_or(0, |mat| mat.end());
+        &chunk[start..]
+    }
+
+    #[test]
+    fn test_basic_chunking() {
+        let markdown = "# Title\n\n## Section 1\n\nThis is a test paragraph.\n\n## Section 2\n\n* List item 1\n* List item 2";
+
+        let chunks = create_semantic_chunks(markdown, 500, 50).unwrap();
+        assert!(!chunks.is_empty());
+        assert!(chunks[0].contains("# Title"));
+    }
+
+    #[test]
+    fn test_chunk_overlap() {
+        let markdown = "# First\n\nContent 1\n\n# Second\n\nContent 2\n\n# Third\n\nContent 3";
+
+        let chunks = create_semantic_chunks(markdown, 20, 10).unwrap();
+        assert!(chunks.len() > 1);
+
+        // Check for overlap
+        if chunks.len() >= 2 {
+            let first_chunk = &chunks[0];
+            let second_chunk = &chunks[1];
+
+            assert!(first_chunk.contains("First"));
+            assert!(second_chunk.contains("Second"));
+        }
+    }
+
+    #[test]
+    fn test_chunks_with_metadata() {
+        let markdown = "# First\n\nContent 1\n\n# Second\n\nContent 2\n\n# Third\n\nContent 3";
+
+        let chunks = create_semantic_chunks_with_metadata(markdown, 20, 10).unwrap();
+        let contents = create_semantic_chunks(markdown, 20, 10).unwrap();
+        assert_eq!(chunks.len(), contents.len());
+
+        for (index, chunk) in chunks.iter().enumerate() {
+            assert_eq!(chunk.content, contents[index]);
+            assert_eq!(chunk.metadata.position, index);
+            assert_eq!(chunk.metadata.char_count, chunk.content.chars().count());
+            assert_eq!(
+                chunk.metadata.word_count,
+                chunk.content.split_whitespace().count()
+            );
+        }
+        assert_eq!(chunks[0].metadata.heading.as_deref(), Some("First"));
+        assert_eq!(chunks[0].metadata.level, 1);
+    }
+
+    #[test]
+    fn test_chunk_heading_paths() {
+        let markdown = "Intro before any heading.\n\n\
+            # Installation\n\nInstall it.\n\n\
+            ## Linux\n\nUse the package.\n\n\
+            ### Troubleshooting\n\nCheck the logs.\n\n\
+            ## macOS\n\nUse brew.\n\n\
+            #### Apple silicon\n\nSkips a level.\n\n\
+            # Usage\n\nRun it.";
+
+        let chunks = create_semantic_chunks_with_metadata(markdown, 500, 50).unwrap();
+        let paths: Vec<Vec<&str>> = chunks
+            .iter()
+            .map(|chunk| {
+                chunk
+                    .metadata
+                    .heading_path
+                    .iter()
+                    .map(String::as_str)
+                    .collect()
+            })
+            .collect();
+
+        assert_eq!(
+            paths,
+            vec![
+                vec![],
+                vec!["Installation"],
+                vec!["Installation", "Linux"],
+                vec!["Installation", "Linux", "Troubleshooting"],
+                vec!["Installation", "macOS"],
+                vec!["Installation", "macOS", "Apple silicon"],
+                vec!["Usage"],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_small_chunks_are_merged() {
+        let markdown: String = (1..=10)
+            .map(|n| format!("## Step {n}\n\nDo thing {n}.\n\n"))
+            .collect();
+
+        let chunks = create_semantic_chunks_with_metadata(&markdown, 100, 0).unwrap();
+        assert_eq!(chunks.len(), 10);
+
+        let options = ChunkOptions {
+            min_chunk_size: 50,
+            ..ChunkOptions::default()
+        };
+        let merged = create_semantic_chunks_with_options(&markdown, 100, 0, &options).unwrap();
+        assert!(merged.len() < 10);
+        for (position, chunk) in merged.iter().enumerate() {
+            let chars = chunk.content.chars().count();
+            assert!(chars >= 50, "{:?} is too small", chunk.content);
+            assert!(chars <= 150, "{:?} is too large", chunk.content);
+            assert_eq!(chunk.metadata.position, position);
+            assert_eq!(
+                chunk.metadata.heading.as_ref(),
+                chunk.metadata.headings.first()
+            );
+        }
+
+        // every section survives once, in order
+        let headings: Vec<String> = merged
+            .iter()
+            .flat_map(|chunk| chunk.metadata.headings.clone())
+            .collect();
+        let expected: Vec<String> = (1..=10).map(|n| format!("Step {n}")).collect();
+        assert_eq!(headings, expected);
+        let text: String = merged.iter().map(|chunk| chunk.content.as_str()).collect();
+        for n in 1..=10 {
+            assert_eq!(text.matches(&format!("Do thing {n}.")).count(), 1);
+        }
+
+        // the merge cap of chunk_size * 1.5 keeps small chunks apart when it must
+        let options = ChunkOptions {
+            min_chunk_size: 25,
+            ..ChunkOptions::default()
+        };
+        let capped = create_semantic_chunks_with_options(&markdown, 26, 0, &options).unwrap();
+        assert!(
+            capped
+                .iter()
+                .all(|chunk| chunk.content.chars().count() <= 39)
+        );
+    }
+
+    #[test]
+    fn test_chunks_share_overlap() {
+        let markdown = "# Notes\n\n\
+            The first sentence is here. Another short one follows. \
+            Then a third sentence comes. The fourth one is next. \
+            A fifth sentence appears. The sixth ends the paragraph.\n\n\
+            A new paragraph begins now. It has a second sentence. \
+            And a third to finish it off.";
+
+        let chunks = create_semantic_chunks(markdown, 120, 40).unwrap();
+        assert!(chunks.len() > 1);
+        for pair in chunks.windows(2) {
+            let sentence = last_sentence(&pair[0]);
+            assert!(!sentence.is_empty());
+            assert!(
+                pair[1].starts_with(sentence),
+                "{:?} should start with {:?}",
+                pair[1],
+                sentence
+            );
+        }
+    }
+
+    #[test]
+    fn test_overlap_across_sections() {
+        let markdown = "# A\n\nOne sentence is here. Final words of A.\n\n# B\n\nBody of B.";
+
+        let chunks = create_semantic_chunks(markdown, 500, 30).unwrap();
+        assert_eq!(chunks.len(), 2);
+        assert!(chunks[1].starts_with("# B"));
+
+        let options = ChunkOptions {
+            overlap_across_sections: true,
+            ..ChunkOptions::default()
+        };
+        let chunks = create_semantic_chunks_with_options(markdown, 500, 30, &options).unwrap();
+        assert_eq!(chunks.len(), 2);
+        assert!(
+            chunks[1]
+                .content
+                .starts_with(last_sentence(&chunks[0].content))
+        );
+        assert!(chunks[1].content.starts_with("Final words of A.\n\n# B"));
+        assert_eq!(chunks[1].metadata.heading.as_deref(), Some("B"));
+    }
+
+    #[test]
+    fn test_size_units() {
+        assert_eq!(SizeUnit::Chars.count("héllo wörld"), 11);
+        assert_eq!(SizeUnit::Words.count("  héllo   wörld\n"), 2);
+        // one token per four bytes of a word, one per punctuation mark or CJK character
+        assert_eq!(SizeUnit::Tokens.count("internationalization"), 5);
+        assert_eq!(SizeUnit::Tokens.count("fn main() {}"), 6);
+        assert_eq!(SizeUnit::Tokens.count("日本語です"), 5);
+        assert_eq!(SizeUnit::Tokens.count("a b"), 2);
+        assert_eq!(SizeUnit::Tokens.count("a         b"), 4);
+    }
+
+    #[test]
+    fn test_chunks_stay_within_token_budget() {
+        let prose = "Semantic chunking keeps related sentences together. \
+            Each chunk should fit into the context window of a language model. \
+            Mixed content like code and CJK text needs more tokens per character. \
+            これは日本語の文章です。トークンの数が多くなります。";
+        let code = "```rust\nfn main() {\n    let values: Vec<u32> = (0..10).map(|x| x * 2).collect();\n    println!(\"{:?}\", values);\n}\n```";
+        let markdown = format!(
+            "# Guide\n\n{prose}\n\n{code}\n\n## Details\n\n{prose} {prose}\n\n{code}\n\n{prose}"
+        );
+
+        for (max_units, overlap_units) in [(40, 10), (64, 16), (100, 0)] {
+            let chunks =
+                create_semantic_chunks_sized(&markdown, max_units, overlap_units, SizeUnit::Tokens)
+                    .unwrap();
+            assert!(chunks.len() > 1);
+            for chunk in &chunks {
+                let tokens = SizeUnit::Tokens.count(chunk);
+                assert!(tokens <= max_units, "{} tokens in {:?}", tokens, chunk);
+            }
+            // nothing is lost between chunks
+            assert!(chunks.iter().any(|chunk| chunk.contains("collect()")));
+            assert!(
+                chunks
+                    .last()
+                    .unwrap()
+                    .contains("トークンの数が多くなります。")
+            );
+        }
+
+        let chunks = create_semantic_chunks_sized(&markdown, 30, 5, SizeUnit::Words).unwrap();
+        assert!(
+            chunks
+                .iter()
+                .all(|chunk| SizeUnit::Words.count(chunk) <= 30)
+        );
+    }
+
+    #[test]
+    fn test_recursive_strategy() {
+        let options = ChunkOptions {
+            strategy: ChunkStrategy::Recursive,
+            ..ChunkOptions::default()
+        };
+        let markdown = "# Guide\n\n\
+            First paragraph is short.\n\n\
+            Second paragraph is short too.\n\n\
+            ## Long part\n\n\
+            This paragraph has sentences. It is too long for one chunk. So it splits on sentence ends.\n\n\
+            nopunctuation here just words going on and on well past the budget of sixty\n\n\
+            Supercalifragilisticexpialidocious-and-then-some-more-letters-to-cut-hard";
+
+        let chunks = create_semantic_chunks_with_options(markdown, 60, 0, &options).unwrap();
+        let contents: Vec<&str> = chunks.iter().map(|chunk| chunk.content.as_str()).collect();
+        assert!(contents.iter().all(|chunk| chunk.chars().count() <= 60));
+        // blank lines first, packing paragraphs that fit together
+        assert_eq!(contents[0], "# Guide\n\nFirst paragraph is short.");
+        assert_eq!(
+            contents[1],
+            "Second paragraph is short too.\n\n## Long part"
+        );
+        // then sentence ends
+        assert_eq!(
+            contents[2],
+            "This paragraph has sentences. It is too long for one chunk."
+        );
+        // then whitespace, still packing pieces up to the budget
+        assert_eq!(
+            contents[3],
+            "So it splits on sentence ends.\n\nnopunctuation here just"
+        );
+        assert_eq!(
+            contents[4],
+            "words going on and on well past the budget of sixty"
+        );
+        // and a hard cut when nothing else is left
+        assert_eq!(
+            contents[5],
+            "Supercalifragilisticexpialidocious-and-then-some-more-letter"
+        );
+        assert_eq!(contents[6], "s-to-cut-hard");
+
+        // metadata comes from the section each chunk starts in
+        assert_eq!(chunks[0].metadata.heading.as_deref(), Some("Guide"));
+        assert_eq!(chunks[1].metadata.heading.as_deref(), Some("Guide"));
+        assert_eq!(chunks[2].metadata.heading_path, vec!["Guide", "Long part"]);
+        assert_eq!(chunks[2].metadata.level, 2);
+        assert_eq!(chunks[6].metadata.position, 6);
+
+        // the overlap is made of whole trailing pieces
+        let chunks = create_semantic_chunks_with_options(markdown, 60, 35, &options).unwrap();
+        let contents: Vec<&str> = chunks.iter().map(|chunk| chunk.content.as_str()).collect();
+        assert!(contents.iter().all(|chunk| chunk.chars().count() <= 60));
+        assert_eq!(contents[0], "# Guide\n\nFirst paragraph is short.");
+        assert!(contents[1].starts_with("First paragraph is short.\n\nSecond"));
+        assert!(contents[3].starts_with("## Long part\n\nThis paragraph"));
+        assert!(contents[6].starts_with("nopunctuation here just words"));
+    }
+
+    #[test]
+    fn test_chunk_ids() {
+        let markdown = "# A\n\nFirst section text.\n\n# B\n\nSecond section text.";
+        let ids = |markdown: &str, source_id: Option<&str>| -> Vec<String> {
+            let options = ChunkOptions {
+                source_id: source_id.map(str::to_string),
+                ..ChunkOptions::default()
+            };
+            create_semantic_chunks_with_options(markdown, 500, 50, &options)
+                .unwrap()
+                .into_iter()
+                .map(|chunk| chunk.metadata.id)
+                .collect()
+        };
+
+        let first = ids(markdown, None);
+        assert_eq!(first.len(), 2);
+        assert_eq!(first[0].len(), 32);
+        assert!(first[0].chars().all(|c| c.is_ascii_hexdigit()));
+        assert_ne!(first[0], first[1]);
+        // re-chunking identical content gives identical IDs
+        assert_eq!(ids(markdown, None), first);
+        // trivial reformatting does not change them
+        let reformatted = "# A\n\nFirst   section\ttext.  \n\n# B\n\nSecond section\ntext.";
+        assert_eq!(ids(reformatted, None), first);
+        // one changed character does
+        let changed = ids(
+            "# A\n\nFirst section text!\n\n# B\n\nSecond section text.",
+            None,
+        );
+        assert_ne!(changed[0], first[0]);
+        assert_eq!(changed[1], first[1]);
+        // and so does the source
+        let sourced = ids(markdown, Some("https://example.com/a"));
+        assert_ne!(sourced[0], first[0]);
+        assert_eq!(ids(markdown, Some("https://example.com/a")), sourced);
+        assert_eq!(
+            sourced[1],
+            chunk_id("# B\n\nSecond section text.", Some("https://example.com/a"))
+        );
+    }
+
+    #[test]
+    fn test_chunk_html() {
+        use crate::markdown_converter::{
+            ConversionOptions, OutputFormat, convert_html_with_options,
+        };
+
+        let html = r#"<html><head><title>Guide</title></head><body>
+            <nav><a href="/">Home</a> <a href="/about">About</a></nav>
+            <main>
+                <h1>Install</h1><p>Download the <a href="/pkg">package</a> first.</p>
+                <h2>Linux</h2><p>Use the tarball.</p>
+            </main>
+            <footer>Copyright</footer>
+        </body></html>"#;
+        let base_url = "https://example.com/docs/";
+        let conversion = ConversionOptions::default();
+
+        let chunks = chunk_html(
+            html,
+            base_url,
+            500,
+            50,
+            &conversion,
+            &ChunkOptions::default(),
+        )
+        .unwrap();
+
+        // the same chunks as converting first and chunking the markdown
+        let markdown =
+            convert_html_with_options(html, base_url, OutputFormat::Markdown, &conversion).unwrap();
+        let options = ChunkOptions {
+            source_id: Some(base_url.to_string()),
+            ..ChunkOptions::default()
+        };
+        let two_calls = create_semantic_chunks_with_options(&markdown, 500, 50, &options).unwrap();
+        let contents: Vec<&str> = chunks.iter().map(|chunk| chunk.content.as_str()).collect();
+        let expected: Vec<&str> = two_calls
+            .iter()
+            .map(|chunk| chunk.content.as_str())
+            .collect();
+        assert_eq!(contents, expected);
+
+        assert!(
+            contents
+                .iter()
+                .any(|chunk| chunk.contains("https://example.com/pkg"))
+        );
+        assert!(contents.iter().all(|chunk| !chunk.contains("Copyright")));
+        let linux = chunks
+            .iter()
+            .find(|chunk| chunk.metadata.heading.as_deref() == Some("Linux"))
+            .unwrap();
+        assert_eq!(linux.metadata.source_id.as_deref(), Some(base_url));
+        assert_eq!(linux.metadata.id, chunk_id(&linux.content, Some(base_url)));
+
+        // an explicit source wins over the base URL
+        let options = ChunkOptions {
+            source_id: Some("doc-42".to_string()),
+            ..ChunkOptions::default()
+        };
+        let chunks = chunk_html(html, base_url, 500, 50, &conversion, &options).unwrap();
+        assert_eq!(chunks[0].metadata.source_id.as_deref(), Some("doc-42"));
+    }
+
+    #[test]
+    fn test_by_heading_strategy() {
+        let markdown = "Preamble text.\n\n\
+            # Manual\n\nOverview.\n\n\
+            ## Install\n\nGet it.\n\n### Linux\n\nUse apt.\n\n#### Debian\n\nOld apt.\n\n\
+            ## Usage\n\nRun it. It is quick. It is also safe. It prints a summary.\n\n\
+            ### Flags\n\nPass --help to see them all.";
+        let by_heading = |level: u8, max_size: Option<usize>| {
+            let options = ChunkOptions {
+                strategy: ChunkStrategy::ByHeading { level, max_size },
+                ..ChunkOptions::default()
+            };
+            create_semantic_chunks_with_options(markdown, 20, 10, &options).unwrap()
+        };
+
+        let chunks = by_heading(2, None);
+        let headings: Vec<Option<&str>> = chunks
+            .iter()
+            .map(|chunk| chunk.metadata.heading.as_deref())
+            .collect();
+        assert_eq!(
+            headings,
+            vec![None, Some("Manual"), Some("Install"), Some("Usage")]
+        );
+        let levels: Vec<usize> = chunks.iter().map(|chunk| chunk.metadata.level).collect();
+        assert_eq!(levels, vec![0, 1, 2, 2]);
+        // deeper sections stay inside their h2, whatever the chunk size
+        assert!(chunks[2].content.starts_with("## Install"));
+        assert!(chunks[2].content.contains("### Linux\n\nUse apt."));
+        assert!(chunks[2].content.contains("#### Debian"));
+        assert!(chunks[3].content.contains("### Flags"));
+        assert_eq!(chunks[3].metadata.heading_path, vec!["Manual", "Usage"]);
+
+        // level 1 keeps the whole manual together
+        let chunks = by_heading(1, None);
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[1].metadata.heading.as_deref(), Some("Manual"));
+
+        // oversized sections are split further but keep their section heading
+        let chunks = by_heading(2, Some(40));
+        assert!(chunks.len() > 4);
+        for chunk in chunks.iter().skip(2) {
+            assert!(chunk.content.chars().count() <= 40, "{:?}", chunk.content);
+            assert!(matches!(
+                chunk.metadata.heading.as_deref(),
+                Some("Install" | "Usage")
+            ));
+            assert_eq!(chunk.metadata.level, 2);
+        }
+        assert!(
+            chunks
+                .iter()
+                .enumerate()
+                .all(|(i, chunk)| chunk.metadata.position == i)
+        );
+    }
+
+    #[test]
+    fn test_non_english_sentence_splits() {
+        let japanese = "# 日本語\n\n\
+            これは最初の文です。二番目の文はここにあります。\
+            三番目の文も続きます！本当にそうですか？はい、彼はそう言った。\
+            最後の文で終わります。";
+        for strategy in [ChunkStrategy::Semantic, ChunkStrategy::Recursive] {
+            let options = ChunkOptions {
+                strategy,
+                ..ChunkOptions::default()
+            };
+            let chunks = create_semantic_chunks_with_options(japanese, 30, 0, &options).unwrap();
+            assert!(chunks.len() > 2);
+            for chunk in &chunks[1..] {
+                assert!(
+                    chunk.content.trim_end().ends_with(['。', '！', '？']),
+                    "{:?} does not end a sentence",
+                    chunk.content
+                );
+            }
+        }
+
+        let german = "Äpfel sind im Herbst reif. Übermorgen regnet es wieder. \
+            Öfter als gedacht kommt die Sonne heraus! Überraschend, nicht wahr? \
+            Der 3. oktober ist ein Feiertag in Deutschland. ¿Y en España? ¡Claro que sí!";
+        let options = ChunkOptions {
+            strategy: ChunkStrategy::Recursive,
+            ..ChunkOptions::default()
+        };
+        let chunks = create_semantic_chunks_with_options(german, 50, 0, &options).unwrap();
+        let contents: Vec<&str> = chunks.iter().map(|chunk| chunk.content.as_str()).collect();
+        assert_eq!(
+            contents,
+            vec![
+                "Äpfel sind im Herbst reif.",
+                "Übermorgen regnet es wieder.",
+                "Öfter als gedacht kommt die Sonne heraus!",
+                "Überraschend, nicht wahr?",
+                "Der 3. oktober ist ein Feiertag in Deutschland.",
+                "¿Y en España? ¡Claro que sí!",
+            ]
+        );
+
+        // the built-in rules also end a sentence where a capital follows without a space
+        #[cfg(not(feature = "unicode_sentences"))]
+        {
+            let chunks = create_semantic_chunks_with_options(
+                "Er war müde.Übermorgen ist frei.",
+                20,
+                0,
+                &options,
+            )
+            .unwrap();
+            assert_eq!(chunks[0].content, "Er war müde.");
+        }
+    }
+
+    #[test]
+    fn test_chunk_iterator_streams_large_documents() {
+        let section = "## Section\n\nStreaming keeps memory flat. Each line is read once!\n\
+                       A chunk is only kept until it is taken? Then it is dropped.\r\n\n";
+        let options = ChunkOptions {
+            source_id: Some("stream".to_string()),
+            ..ChunkOptions::default()
+        };
+
+        // Matches the collecting API chunk for chunk
+        let sample = section.repeat(40);
+        let expected = create_semantic_chunks_with_options(&sample, 120, 30, &options).unwrap();
+        let streamed: Vec<_> = ChunkIterator::new(sample.as_str(), 120, 30, &options)
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(streamed.len(), expected.len());
+        for (streamed, expected) in streamed.iter().zip(&expected) {
+            assert_eq!(streamed.content, expected.content);
+            assert_eq!(streamed.metadata.id, expected.metadata.id);
+            assert_eq!(streamed.metadata.position, expected.metadata.position);
+            assert_eq!(
+                streamed.metadata.heading_path,
+                expected.metadata.heading_path
+            );
+        }
+
+        // 50 MB never holds more than a chunk or two of text
+        let document = section.repeat(50 * 1024 * 1024 / section.len());
+        let mut chunks = ChunkIterator::new(document, 4000, 200, &ChunkOptions::default());
+        let mut count = 0;
+        let mut max_buffered = 0;
+        while let Some(chunk) = chunks.next() {
+            assert!(chunk.unwrap().content.chars().count() <= 4000);
+            max_buffered = max_buffered.max(chunks.buffered_bytes());
+            count += 1;
+        }
+        assert!(count > 10_000);
+        assert!(max_buffered < 4 * 4000, "buffered {} bytes", max_buffered);
+
+        // Options that need the whole document are refused up front
+        let merging = ChunkOptions {
+            min_chunk_size: 10,
+            ..ChunkOptions::default()
+        };
+        let mut refused = ChunkIterator::new("# Title", 100, 10, &merging);
+        assert!(matches!(refused.next(), Some(Err(ChunkerError::Other(_)))));
+        assert!(refused.next().is_none());
+        let mut refused = ChunkIterator::new("# Title", 100, 100, &ChunkOptions::default());
+        assert!(matches!(
+            refused.next(),
+            Some(Err(ChunkerError::InvalidOverlap { .. }))
+        ));
+    }
+
+    #[test]
+    fn test_chunk_source_offsets() {
+        let markdown = "# Café\n\nLe café est noir. Il fait très chaud ici. Nous buvons du thé vert.\n\
+                        Le marché ouvre à midi.\n\n## 日本\n\n東京は大きい都市です。京都は古い都市です。\n\n\
+                        Sushi and ramen are popular. Everyone likes them.";
+        let slice = |start: usize, end: usize| -> String {
+            markdown.chars().skip(start).take(end - start).collect()
+        };
+
+        // Heading and size splits, with overlap inside sections, all slice back out exactly
+        let strategies = [
+            ChunkStrategy::Semantic,
+            ChunkStrategy::Recursive,
+            ChunkStrategy::ByHeading {
+                level: 1,
+                max_size: Some(60),
+            },
+        ];
+        for strategy in strategies {
+            let options = ChunkOptions {
+                strategy,
+                ..ChunkOptions::default()
+            };
+            let chunks = create_semantic_chunks_with_options(markdown, 90, 40, &options).unwrap();
+            assert!(chunks.len() > 2);
+            for chunk in &chunks {
+                let metadata = &chunk.metadata;
+                assert_eq!(
+                    slice(metadata.start_offset, metadata.end_offset),
+                    chunk.content,
+                    "{:?}",
+                    strategy
+                );
+            }
+        }
+
+        // Overlap carried past a heading keeps its own offsets, so the slice runs from there
+        let options = ChunkOptions {
+            overlap_across_sections: true,
+            ..ChunkOptions::default()
+        };
+        let chunks = create_semantic_chunks_with_options(markdown, 90, 40, &options).unwrap();
+        let carried = chunks
+            .iter()
+            .find(|chunk| chunk.content.contains("\n\n## 日本"))
+            .unwrap();
+        let (overlap, rest) = carried.content.split_once("\n\n").unwrap();
+        let source = slice(carried.metadata.start_offset, carried.metadata.end_offset);
+        assert!(source.starts_with(overlap));
+        assert!(source.ends_with(rest));
+
+        // Streaming reports the same offsets
+        let streamed: Vec<_> = ChunkIterator::new(markdown, 90, 40, &options)
+            .map(|chunk| chunk.unwrap().metadata)
+            .map(|metadata| (metadata.start_offset, metadata.end_offset))
+            .collect();
+        let collected: Vec<_> = chunks
+            .iter()
+            .map(|chunk| (chunk.metadata.start_offset, chunk.metadata.end_offset))
+            .collect();
+        assert_eq!(streamed, collected);
+    }
+
+    #[test]
+    fn test_overlap_across_sections_offsets_stay_on_char_boundaries() {
+        let markdown = "x\n# t\n🎉\n# e\n# 🎉\n.\n# `\ne\n\n` 。";
+        let total = markdown.chars().count();
+        for unit in [SizeUnit::Words, SizeUnit::Chars] {
+            let options = ChunkOptions {
+                overlap_across_sections: true,
+                unit,
+                ..Default::default()
+            };
+            let chunks = create_semantic_chunks_with_options(markdown, 13, 11, &options).unwrap();
+            assert!(chunks.iter().any(|chunk| chunk.content.contains('。')));
+            for chunk in &chunks {
+                let metadata = &chunk.metadata;
+                assert!(metadata.start_offset <= metadata.end_offset);
+                assert!(metadata.end_offset <= total, "{:?}", chunk);
+            }
+        }
+    }
+
+    #[test]
+    fn test_overlap_across_sections_offsets_on_random_input() {
+        const WORDS: [&str; 8] = ["word", "🎉", "日本", "é", "。", "`", ".", "a b"];
+        const LINE_STARTS: [&str; 4] = ["", "", "# ", "## "];
+        // xorshift, so failures reproduce
+        let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut next = move |bound: usize| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state % bound as u64) as usize
+        };
+
+        for _ in 0..500 {
+            let lines: Vec<String> = (0..next(12) + 1)
+                .map(|_| {
+                    let words: Vec<&str> = (0..next(3)).map(|_| WORDS[next(WORDS.len())]).collect();
+                    format!(
+                        "{}{}",
+                        LINE_STARTS[next(LINE_STARTS.len())],
+                        words.join(" ")
+                    )
+                })
+                .collect();
+            let markdown = lines.join(["\n", "\r\n"][next(2)]);
+            let total = markdown.chars().count();
+            let chunk_size = next(20) + 1;
+            let overlap = next(chunk_size + 1);
+            for unit in [SizeUnit::Words, SizeUnit::Chars] {
+                let options = ChunkOptions {
+                    overlap_across_sections: true,
+                    unit,
+                    ..Default::default()
+                };
+                let Ok(chunks) =
+                    create_semantic_chunks_with_options(&markdown, chunk_size, overlap, &options)
+                else {
+                    continue;
+                };
+                for chunk in &chunks {
+                    let metadata = &chunk.metadata;
+                    assert!(
+                        metadata.start_offset <= metadata.end_offset
+                            && metadata.end_offset <= total,
+                        "{:?} in {:?}",
+                        chunk,
+                        markdown
+                    );
+                }
+                let streamed: Vec<_> = ChunkIterator::new(&markdown, chunk_size, overlap, &options)
+                    .map(|chunk| chunk.unwrap().metadata)
+                    .map(|metadata| (metadata.start_offset, metadata.end_offset))
+                    .collect();
+                let collected: Vec<_> = chunks
+                    .iter()
+                    .map(|chunk| (chunk.metadata.start_offset, chunk.metadata.end_offset))
+                    .collect();
+                assert_eq!(streamed, collected, "{:?}", markdown);
+            }
+        }
+    }
+
+    #[test]
+    fn test_chunker_config_builder() {
+        let markdown = "# Guide\n\nFirst paragraph is short.\n\n## Part\n\n\
+                        A longer paragraph follows here. It has a few sentences. They add up.";
+        let config = ChunkerConfig::new()
+            .chunk_size(60)
+            .overlap(20)
+            .unit(SizeUnit::Chars)
+            .strategy(ChunkStrategy::Recursive)
+            .min_chunk_size(10)
+            .overlap_across_sections(true)
+            .source_id("guide");
+        assert_eq!(config.options.source_id.as_deref(), Some("guide"));
+        assert_eq!(ChunkerConfig::new(), ChunkerConfig::default());
+
+        // The free functions are the same chunker underneath
+        let chunker = config.clone().build().unwrap();
+        let chunks = chunker.chunk(markdown).unwrap();
+        let expected =
+            create_semantic_chunks_with_options(markdown, 60, 20, &config.options).unwrap();
+        assert_eq!(
+            chunks
+                .iter()
+                .map(|chunk| &chunk.metadata.id)
+                .collect::<Vec<_>>(),
+            expected
+                .iter()
+                .map(|chunk| &chunk.metadata.id)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(chunker.config(), &config);
+
+        let semantic = ChunkerConfig::new()
+            .chunk_size(60)
+            .overlap(20)
+            .build()
+            .unwrap();
+        let streamed: Vec<String> = semantic
+            .stream(markdown)
+            .map(|chunk| chunk.unwrap().content)
+            .collect();
+        assert_eq!(streamed, create_semantic_chunks(markdown, 60, 20).unwrap());
+
+        let html = semantic
+            .chunk_html(
+                "<h1>Guide</h1><p>Text.</p>",
+                "https://example.com",
+                &Default::default(),
+            )
+            .unwrap();
+        assert_eq!(
+            html[0].metadata.source_id.as_deref(),
+            Some("https://example.com")
+        );
+
+        // Building checks the sizes once, up front
+        assert!(matches!(
+            ChunkerConfig::new().overlap(1000).build(),
+            Err(ChunkerError::InvalidOverlap { .. })
+        ));
+        assert!(matches!(
+            ChunkerConfig::new().chunk_size(0).build(),
+            Err(ChunkerError::ParsingError(_))
+        ));
+    }
+
+    #[test]
+    fn test_lists_split_between_items() {
+        let items: Vec<String> = (1..=500)
+            .map(|n| format!("- Item {} of the long list. It has two sentences.", n))
+            .collect();
+        let markdown = format!("## Tasks\n\n{}\n\nDone.", items.join("\n"));
+
+        // Without overlap every item lands whole in exactly one chunk
+        let chunks = create_semantic_chunks(&markdown, 300, 0).unwrap();
+        assert!(chunks.len() > 50);
+        for item in &items {
+            let holding = chunks.iter().filter(|chunk| chunk.contains(item.as_str()));
+            assert_eq!(holding.count(), 1, "{}", item);
+        }
+        assert!(chunks[1..].iter().all(|chunk| chunk.starts_with("- Item")));
+
+        // Overlap repeats whole items too
+        let chunks = create_semantic_chunks(&markdown, 300, 80).unwrap();
+        assert!(chunks[1..].iter().all(|chunk| chunk.starts_with("- Item")));
+
+        let options = ChunkOptions {
+            list_continuation: true,
+            ..ChunkOptions::default()
+        };
+        let chunks = create_semantic_chunks_with_options(&markdown, 300, 0, &options).unwrap();
+        assert!(!chunks[0].content.starts_with('…'));
+        let marked = chunks
+            .iter()
+            .filter(|chunk| {
+                chunk
+                    .content
+                    .starts_with("…continued from list under Tasks\n- Item")
+            })
+            .count();
+        assert_eq!(marked, chunks.len() - 1);
+    }
+
+    #[test]
+    fn test_lists_with_multi_line_items() {
+        let markdown = "# Steps\n\nIntro paragraph.\n\n\
+            1. Download the installer. Check its checksum\n   against the published one.\n\
+            2. Run the installer. Accept the licence.\n   Pick a folder. Wait for it.\n\n   \
+            It can take a few minutes.\n\
+            3. Start the app.\n   Sign in with your account.\n\
+            4. Open the settings. Turn on sync.\n\
+            The end of the list comes right after it.\n\n\
+            Closing words follow the list.";
+        let at_boundary = |end: usize| {
+            let rest = &markdown[end..];
+            rest.is_empty()
+                || rest.trim_start().starts_with(char::is_numeric)
+                || rest.trim_start().starts_with("Closing")
+        };
+
+        for chunk_size in [120, 150, 200] {
+            let chunks = create_semantic_chunks_with_metadata(markdown, chunk_size, 0).unwrap();
+            for chunk in &chunks {
+                assert!(chunk.content.chars().count() <= chunk_size);
+                assert!(
+                    at_boundary(chunk.metadata.end_offset),
+                    "{:?}",
+                    chunk.content
+                );
+            }
+        }
+
+        // An item over budget on its own still gets cut, at its sentences
+        let chunks = create_semantic_chunks(markdown, 60, 0).unwrap();
+        assert!(chunks.iter().all(|chunk| chunk.chars().count() <= 60));
+        assert!(chunks.iter().any(|chunk| chunk.starts_with("Wait for it.")));
+    }
+
+    #[test]
+    fn test_max_chunks() {
+        let markdown: String = (1..=1000)
+            .map(|n| format!("## Section {}\n\nBody of section {}.\n\n", n, n))
+            .collect();
+        let chunker = |truncation| {
+            ChunkerConfig::new()
+                .chunk_size(200)
+                .overlap(0)
+                .max_chunks(100)
+                .truncation(truncation)
+                .build()
+                .unwrap()
+        };
+
+        let (chunks, report) = chunker(TruncationStrategy::Truncate)
+            .chunk_with_report(&markdown)
+            .unwrap();
+        assert_eq!((report.produced, report.returned), (1000, 100));
+        assert!(report.truncated);
+        assert_eq!(report.strategy, TruncationStrategy::Truncate);
+        assert_eq!(chunks[99].metadata.heading.as_deref(), Some("Section 100"));
+
+        let (chunks, report) = chunker(TruncationStrategy::Sample)
+            .chunk_with_report(&markdown)
+            .unwrap();
+        assert_eq!(chunks.len(), 100);
+        assert!(report.truncated);
+        assert_eq!(chunks[0].metadata.heading.as_deref(), Some("Section 1"));
+        assert_eq!(chunks[99].metadata.heading.as_deref(), Some("Section 1000"));
+        assert!(
+            chunks
+                .windows(2)
+                .all(|pair| pair[1].metadata.start_offset - pair[0].metadata.start_offset > 200)
+        );
+
+        let (chunks, report) = chunker(TruncationStrategy::Merge)
+            .chunk_with_report(&markdown)
+            .unwrap();
+        assert_eq!(chunks.len(), 100);
+        assert_eq!(report.strategy, TruncationStrategy::Merge);
+        assert!(
+            chunks
+                .iter()
+                .all(|chunk| chunk.metadata.headings.len() == 10)
+        );
+        for n in [1, 500, 1000] {
+            let body = format!("Body of section {}.", n);
+            assert_eq!(
+                chunks
+                    .iter()
+                    .filter(|chunk| chunk.content.contains(&body))
+                    .count(),
+                1
+            );
+        }
+        let positions: Vec<usize> = chunks.iter().map(|chunk| chunk.metadata.position).collect();
+        assert_eq!(positions, (0..100).collect::<Vec<_>>());
+
+        // Under the limit nothing changes
+        let (chunks, report) = chunker(TruncationStrategy::Merge)
+            .chunk_with_report("# Short\n\nText.")
+            .unwrap();
+        assert_eq!(chunks.len(), 1);
+        assert!(!report.truncated);
+
+        // Streaming can only truncate
+        let options = chunker(TruncationStrategy::Truncate)
+            .config()
+            .options
+            .clone();
+        assert_eq!(
+            ChunkIterator::new(markdown.as_str(), 200, 0, &options).count(),
+            100
+        );
+        let options = chunker(TruncationStrategy::Sample).config().options.clone();
+        let mut refused = ChunkIterator::new(markdown.as_str(), 200, 0, &options);
+        assert!(matches!(refused.next(), Some(Err(ChunkerError::Other(_)))));
+        assert!(ChunkerConfig::new().max_chunks(0).build().is_err());
+    }
+
+    #[test]
+    fn test_split_points_on_multi_byte_text() {
+        let markdown = "# 表情😀\n\n😀😃😄 你好世界。👍🏽👨‍👩‍👧 中文文本没有空格😀😀😀😀😀\n\
+                        🎉🎉🎉🎉。東京タワー🗼と富士山🗻！"
+            .repeat(3);
+        let strategies = [
+            ChunkStrategy::Semantic,
+            ChunkStrategy::Recursive,
+            ChunkStrategy::ByHeading {
+                level: 1,
+                max_size: Some(5),
+            },
+        ];
+        for unit in [SizeUnit::Chars, SizeUnit::Words, SizeUnit::Tokens] {
+            for strategy in strategies {
+                for (chunk_size, chunk_overlap) in [(1, 0), (2, 1), (3, 0), (4, 2), (5, 4)] {
+                    let options = ChunkOptions {
+                        unit,
+                        strategy,
+                        ..ChunkOptions::default()
+                    };
+                    let chunks = create_semantic_chunks_with_options(
+                        &markdown,
+                        chunk_size,
+                        chunk_overlap,
+                        &options,
+                    )
+                    .unwrap();
+                    assert!(chunks.iter().all(|chunk| !chunk.content.is_empty()));
+                    assert!(chunks.last().unwrap().content.ends_with('！'));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_overlap_ratio() {
+        let resolved = |config: ChunkerConfig| config.build().unwrap().chunk_overlap();
+        assert_eq!(resolved(ChunkerConfig::new().overlap(150)), 150);
+        assert_eq!(resolved(ChunkerConfig::new().overlap_ratio(0.1)), 100);
+        assert_eq!(
+            resolved(ChunkerConfig::new().overlap(OverlapSpec::Ratio(0.1))),
+            100
+        );
+        assert_eq!(resolved(ChunkerConfig::new().overlap_ratio(0.0)), 0);
+        // Rounded down, in the unit chunk_size counts
+        assert_eq!(
+            resolved(ChunkerConfig::new().chunk_size(7).overlap_ratio(0.5)),
+            3
+        );
+        assert_eq!(
+            resolved(
+                ChunkerConfig::new()
+                    .chunk_size(512)
+                    .unit(SizeUnit::Tokens)
+                    .overlap_ratio(0.25)
+            ),
+            128
+        );
+
+        for ratio in [0.51, 1.0, -0.1, f32::NAN] {
+            assert!(matches!(
+                ChunkerConfig::new().overlap_ratio(ratio).build(),
+                Err(ChunkerError::ParsingError(_))
+            ));
+        }
+
+        // A ratio chunks exactly as the overlap it resolves to
+        let markdown = "# Notes\n\nOne sentence here. Another one follows it. \
+                        A third sentence makes the paragraph long enough to split twice.";
+        let ids = |chunks: Vec<crate::chunker::Chunk>| {
+            chunks
+                .into_iter()
+                .map(|chunk| chunk.metadata.id)
+                .collect::<Vec<_>>()
+        };
+        let ratio = ChunkerConfig::new().chunk_size(50).overlap_ratio(0.2);
+        let absolute = ChunkerConfig::new().chunk_size(50).overlap(10);
+        assert_eq!(
+            ids(ratio.build().unwrap().chunk(markdown).unwrap()),
+            ids(absolute.build().unwrap().chunk(markdown).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_paragraph_chunking() {
+        // A forum thread: the `#` lines are comments and shouting, not headings
+        let markdown = "alice: has anyone seen this error?\n\n\
+                        bob: try this\n\n\
+                        ```python\n# install first\nimport os\n\n# then run\nos.system('make')\n```\n\n\
+                        # yes that fixed it\n\n\
+                        carol: thanks!\n\n\
+                        ## dave: same here";
+        let chunk = |per_chunk: usize, overlap: usize| {
+            ChunkerConfig::new()
+                .strategy(ChunkStrategy::Paragraphs { per_chunk })
+                .overlap(overlap)
+                .build()
+                .unwrap()
+                .chunk(markdown)
+                .unwrap()
+        };
+
+        let chunks = chunk(2, 0);
+        assert_eq!(
+            chunks
+                .iter()
+                .map(|chunk| chunk.content.as_str())
+                .collect::<Vec<_>>(),
+            [
+                "alice: has anyone seen this error?\n\nbob: try this",
+                "```python\n# install first\nimport os\n\n# then run\nos.system('make')\n```\n\n\
+                 # yes that fixed it",
+                "carol: thanks!\n\n## dave: same here",
+            ]
+        );
+        for chunk in &chunks {
+            assert_eq!(chunk.metadata.heading, None);
+            assert_eq!(chunk.metadata.level, 0);
+            assert!(chunk.metadata.heading_path.is_empty());
+            let (start, end) = (chunk.metadata.start_offset, chunk.metadata.end_offset);
+            assert_eq!(&markdown[start..end], chunk.content);
+        }
+        assert_eq!(
+            chunks
+                .iter()
+                .map(|chunk| chunk.metadata.paragraphs.clone().unwrap())
+                .collect::<Vec<_>>(),
+            [0..2, 2..4, 4..6]
+        );
+
+        // Overlap repeats whole paragraphs
+        let overlapping = chunk(3, 1);
+        assert_eq!(
+            overlapping
+                .iter()
+                .map(|chunk| chunk.metadata.paragraphs.clone().unwrap())
+                .collect::<Vec<_>>(),
+            [0..3, 2..5, 4..6]
+        );
+        assert!(overlapping[1].content.starts_with("```python"));
+        assert!(overlapping[2].content.starts_with("carol: thanks!"));
+
+        // Merging drops the repeated paragraphs again
+        let merged = ChunkerConfig::new()
+            .strategy(ChunkStrategy::Paragraphs { per_chunk: 3 })
+            .overlap(1)
+            .max_chunks(1)
+            .truncation(TruncationStrategy::Merge)
+            .build()
+            .unwrap()
+            .chunk(markdown)
+            .unwrap();
+        assert_eq!(merged[0].content, markdown);
+        assert_eq!(merged[0].metadata.paragraphs, Some(0..6));
+
+        // A ratio is of the paragraphs per chunk
+        let ratio = ChunkerConfig::new()
+            .strategy(ChunkStrategy::Paragraphs { per_chunk: 4 })
+            .overlap_ratio(0.5)
+            .build()
+            .unwrap();
+        assert_eq!(ratio.chunk_overlap(), 2);
+
+        let paragraphs = |per_chunk: usize, overlap: usize| {
+            ChunkerConfig::new()
+                .strategy(ChunkStrategy::Paragraphs { per_chunk })
+                .overlap(overlap)
+                .build()
+        };
+        assert!(matches!(
+            paragraphs(0, 0),
+            Err(ChunkerError::ParsingError(_))
+        ));
+        assert!(matches!(
+            paragraphs(2, 2),
+            Err(ChunkerError::InvalidOverlap { .. })
+        ));
+        assert!(paragraphs(1, 0).unwrap().chunk("").unwrap().is_empty());
+
+        // `chunk_size` is not used here, so a small one does not limit the overlap
+        for chunk_size in [0, 1] {
+            let small = ChunkerConfig::new()
+                .chunk_size(chunk_size)
+                .strategy(ChunkStrategy::Paragraphs { per_chunk: 3 })
+                .overlap(1)
+                .build()
+                .unwrap();
+            let contents = |chunks: &[crate::chunker::Chunk]| {
+                chunks
+                    .iter()
+                    .map(|chunk| chunk.content.clone())
+                    .collect::<Vec<_>>()
+            };
+            assert_eq!(
+                contents(&small.chunk(markdown).unwrap()),
+                contents(&overlapping)
+            );
+        }
+        let by_heading = ChunkerConfig::new()
+            .chunk_size(1)
+            .strategy(ChunkStrategy::ByHeading {
+                level: 2,
+                max_size: Some(500),
+            })
+            .overlap(50);
+        assert!(by_heading.build().is_ok());
+    }
+
+    #[test]
+    fn test_chunk_manifest() {
+        let markdown = "# Guide\n\nAn introduction to the guide. It covers the basics.\n\n\
+                        ## Setup\n\nInstall the tool, then configure it. Run it once to check.\n\n\
+                        ## Usage\n\nCall it from the command line with a file name.";
+        let config = ChunkerConfig::new()
+            .chunk_size(60)
+            .overlap(10)
+            .source_id("guide.md")
+            .max_chunks(3);
+        let (texts, json) = chunk_markdown_to_manifest(markdown, &config).unwrap();
+
+        let manifest: ChunkManifest = serde_json::from_str(&json).unwrap();
+        assert_eq!(manifest.version, MANIFEST_VERSION);
+        assert_eq!(manifest.source_id.as_deref(), Some("guide.md"));
+        assert_eq!(manifest.char_count, markdown.chars().count());
+        assert_eq!(manifest.word_count, markdown.split_whitespace().count());
+        assert_eq!(manifest.report.returned, texts.len());
+        assert!(manifest.report.truncated);
+
+        // Entry i describes text i, exactly as chunking with metadata does
+        let chunks = config.build().unwrap().chunk(markdown).unwrap();
+        assert_eq!(manifest.chunks.len(), texts.len());
+        for (i, (text, metadata)) in texts.iter().zip(&manifest.chunks).enumerate() {
+            assert_eq!(text, &chunks[i].content);
+            assert_eq!(metadata, &chunks[i].metadata);
+            assert_eq!(metadata.position, i);
+            assert_eq!(metadata.id, chunk_id(text, Some("guide.md")));
+        }
+
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["version"], 1);
+        assert!(value["chunks"][0]["heading_path"].is_array());
+    }
+
+    #[test]
+    fn test_invalid_chunk_sizes() {
+        let markdown = "# Title\n\nSome text that is long enough to need splitting at least once.";
+
+        for (chunk_size, chunk_overlap) in [(0, 0), (0, 5)] {
+            assert!(matches!(
+                create_semantic_chunks(markdown, chunk_size, chunk_overlap),
+                Err(ChunkerError::ParsingError(_))
+            ));
+        }
+        for (chunk_size, chunk_overlap) in [(10, 10), (10, 11), (1, usize::MAX)] {
+            assert!(matches!(
+                create_semantic_chunks(markdown, chunk_size, chunk_overlap),
+                Err(ChunkerError::InvalidOverlap { .. })
+            ));
+        }
+
+        // A section limit gets the same checks, and sizes fail before any HTML is converted
+        let by_heading = ChunkOptions {
+            strategy: ChunkStrategy::ByHeading {
+                level: 2,
+                max_size: Some(0),
+            },
+            ..ChunkOptions::default()
+        };
+        assert!(matches!(
+            create_semantic_chunks_with_options(markdown, 100, 0, &by_heading),
+            Err(ChunkerError::ParsingError(_))
+        ));
+        assert!(matches!(
+            chunk_html(
+                "<p>Text</p>",
+                "https://example.com",
+                0,
+                0,
+                &Default::default(),
+                &ChunkOptions::default()
+            ),
+            Err(ChunkerError::ParsingError(_))
+        ));
+        assert!(matches!(
+            ChunkIterator::new(markdown, 0, 0, &ChunkOptions::default()).next(),
+            Some(Err(ChunkerError::ParsingError(_)))
+        ));
+
+        // The largest valid overlap still makes progress in every unit
+        for unit in [SizeUnit::Chars, SizeUnit::Words, SizeUnit::Tokens] {
+            let chunks = create_semantic_chunks_sized(markdown, 4, 3, unit).unwrap();
+            assert!(chunks.iter().all(|chunk| unit.count(chunk) <= 4));
+            assert!(chunks.last().unwrap().ends_with("nce."));
+        }
+    }
+
+    #[test]
+    fn test_overlap_must_be_smaller_than_chunk_size() {
+        assert!(matches!(
+            create_semantic_chunks("# Title\n\nText", 20, 30),
+            Err(ChunkerError::InvalidOverlap {
+                overlap: 30,
+                chunk_size: 20
+            })
+        ));
+        assert!(create_semantic_chunks("# Title\n\nText", 20, 20).is_err());
     }
 }
@@ -128,7 +128,7 @@ class TestComprehensiveConversion:
         )
 
         # Verify XML structure (case-sensitive)
-        assert "<Document>" in xml_output
+        assert "<document>" in xml_output
         assert "<title>Test Document</title>" in xml_output
         assert "<headings>" in xml_output
         assert "<paragraphs>" in xml_output
@@ -154,21 +154,14 @@ class TestRustConversionErrors:
         backend = RustBackend(fallback_enabled=False)
 
         if backend.is_available():
-            # Test with invalid format - should be handled gracefully by Rust
+            # Unknown formats are rejected by Rust rather than defaulting to markdown
             html = "<html><body><h1>Test</h1></body></html>"
             base_url = "https://example.com"
 
-            # Depending on Rust implementation, this might raise error or default to markdown
-            try:
-                result = backend.convert_html_to_format(
-                    html, base_url, "invalid_format"
-                )
-                # If it doesn't raise an error, it should return some content
-                assert isinstance(result, str)
-                assert len(result) > 0
-            except RustIntegrationError:
-                # This is also acceptable behavior
-                pass
+            with pytest.raises(RustIntegrationError) as exc_info:
+                backend.convert_html_to_format(html, base_url, "invalid_format")
+
+            assert isinstance(exc_info.value.cause, ValueError)
 
     def test_convert_html_malformed_input(self):
         """Test conversion with malformed HTML."""
@@ -1,3 +1,5 @@
+import json
+
 import pytest
 
 from markdown_lab import markdown_lab_rs
@@ -21,6 +23,478 @@ def test_convert_html_to_markdown():
     assert "This is a test paragraph." in markdown
 
 
+def test_convert_html_to_format_title_mode():
+    if not markdown_lab_rs.RUST_AVAILABLE:
+        pytest.skip("conversion options require the Rust extension")
+
+    html = """
+        <html>
+            <head><title>Main Title</title></head>
+            <body><h1>Main Title</h1><p>Body text.</p></body>
+        </html>
+    """
+    markdown = markdown_lab_rs.convert_html_to_format(
+        html, "https://example.com", "markdown", title_mode="if_no_h1"
+    )
+
+    assert markdown.count("# Main Title") == 1
+    assert "Body text." in markdown
+
+
+def test_convert_html_to_format_flavor():
+    if not markdown_lab_rs.RUST_AVAILABLE:
+        pytest.skip("conversion options require the Rust extension")
+
+    html = "<p><del>Old</del> New</p>"
+    gfm = markdown_lab_rs.convert_html_to_format(html, "https://example.com", "markdown")
+    commonmark = markdown_lab_rs.convert_html_to_format(
+        html, "https://example.com", "markdown", flavor="commonmark"
+    )
+
+    assert "~~Old~~ New" in gfm
+    assert "Old New" in commonmark
+    with pytest.raises(ValueError):
+        markdown_lab_rs.convert_html_to_format(
+            html, "https://example.com", "markdown", flavor="asciidoc"
+        )
+
+
+def test_convert_html_to_format_compact_json():
+    if not markdown_lab_rs.RUST_AVAILABLE:
+        pytest.skip("conversion options require the Rust extension")
+
+    import json
+
+    html = "<h1>Title</h1><p>Body</p>"
+    compact = markdown_lab_rs.convert_html_to_format(
+        html, "https://example.com", "json", compact=True
+    )
+    pretty = markdown_lab_rs.convert_html_to_format(html, "https://example.com", "json")
+
+    assert "\n" not in compact
+    assert "\n" in pretty
+    assert json.loads(compact)["schema_version"] == 1
+    assert json.loads(pretty)["schema_version"] == 1
+
+
+def test_render_document_json_round_trip():
+    if not markdown_lab_rs.RUST_AVAILABLE:
+        pytest.skip("document rendering requires the Rust extension")
+
+    rust = markdown_lab_rs._rust_module
+    html = "<h1>Title</h1><p>Body with <a href='/x'>a link</a></p>"
+    doc_json = rust.convert_html_to_format(html, "https://example.com", "json")
+
+    assert rust.render_document_json(doc_json, "markdown") == (
+        rust.convert_html_to_format(html, "https://example.com", "markdown")
+    )
+    with pytest.raises(ValueError):
+        rust.render_document_json("not json", "markdown")
+
+
+def test_serialize_document_round_trip():
+    if not markdown_lab_rs.RUST_AVAILABLE:
+        pytest.skip("document caching requires the Rust extension")
+
+    rust = markdown_lab_rs._rust_module
+    if not hasattr(rust, "serialize_document"):
+        pytest.skip("extension built without the cache feature")
+
+    html = "<h1>Title</h1><p>Body</p>"
+    data = rust.serialize_document(html, "https://example.com")
+
+    assert isinstance(data, bytes)
+    assert rust.deserialize_document(data, "markdown") == (
+        rust.convert_html_to_format(html, "https://example.com", "markdown")
+    )
+    with pytest.raises(ValueError):
+        rust.deserialize_document(data[: len(data) // 2])
+    with pytest.raises(ValueError):
+        rust.deserialize_document(b"")
+
+
+def test_extract_social_metadata():
+    if not markdown_lab_rs.RUST_AVAILABLE:
+        pytest.skip("social metadata requires the Rust extension")
+
+    html = (
+        '<head><meta property="og:title" content="Launch">'
+        '<meta property="og:image" content="/hero.png"></head>'
+    )
+    social = markdown_lab_rs._rust_module.extract_social_metadata(
+        html, "https://example.com"
+    )
+
+    assert social["og_title"] == "Launch"
+    assert social["og_image"] == "https://example.com/hero.png"
+    assert social["twitter_card"] is None
+
+
+def test_extract_json_ld():
+    if not markdown_lab_rs.RUST_AVAILABLE:
+        pytest.skip("JSON-LD extraction requires the Rust extension")
+
+    rust = markdown_lab_rs._rust_module
+    html = (
+        '<script type="application/ld+json">'
+        '{"@type": "Article", "headline": "Hi", "wordCount": 3, "draft": false}'
+        '</script><script type="application/ld+json">{not json}</script>'
+    )
+
+    assert rust.extract_json_ld(html) == [
+        {"@type": "Article", "headline": "Hi", "wordCount": 3, "draft": False}
+    ]
+    assert rust.json_ld_types(html) == ["Article"]
+
+
+def test_extract_publication_date():
+    if not markdown_lab_rs.RUST_AVAILABLE:
+        pytest.skip("date extraction requires the Rust extension")
+
+    rust = markdown_lab_rs._rust_module
+    html = '<time datetime="2024-03-05">March 5</time>'
+
+    assert rust.extract_publication_date(html) == "2024-03-05T00:00:00Z"
+    assert rust.extract_modified_date(html) is None
+
+
+def test_discover_feeds():
+    if not markdown_lab_rs.RUST_AVAILABLE:
+        pytest.skip("feed discovery requires the Rust extension")
+
+    html = (
+        '<link rel="alternate" type="application/atom+xml" href="/atom.xml" title="News">'
+        '<link rel="sitemap" href="sitemap.xml">'
+    )
+    feeds = markdown_lab_rs._rust_module.discover_feeds(html, "https://example.com/")
+
+    assert feeds == [
+        ("https://example.com/atom.xml", "atom", "News"),
+        ("https://example.com/sitemap.xml", "sitemap", None),
+    ]
+
+
+def test_extract_robots_directives():
+    if not markdown_lab_rs.RUST_AVAILABLE:
+        pytest.skip("robots directives require the Rust extension")
+
+    rust = markdown_lab_rs._rust_module
+    html = (
+        '<meta name="robots" content="NOINDEX">'
+        '<a href="/a">A</a><a rel="nofollow" href="/b">B</a>'
+    )
+
+    assert rust.extract_robots_directives(html) == {
+        "noindex": True,
+        "nofollow": False,
+        "noarchive": False,
+        "indexable": False,
+    }
+    assert rust.extract_links(html, "https://example.com", respect_nofollow=True) == [
+        "https://example.com/a"
+    ]
+
+
+def test_extract_alternates():
+    if not markdown_lab_rs.RUST_AVAILABLE:
+        pytest.skip("hreflang extraction requires the Rust extension")
+
+    html = '<link rel="alternate" hreflang="x-default" href="/">'
+    assert markdown_lab_rs._rust_module.extract_alternates(
+        html, "https://example.com/de/"
+    ) == [("x-default", "https://example.com/")]
+
+
+def test_extract_breadcrumbs():
+    if not markdown_lab_rs.RUST_AVAILABLE:
+        pytest.skip("breadcrumb extraction requires the Rust extension")
+
+    html = '<ol class="breadcrumb"><li><a href="/">Home</a></li><li>Docs</li></ol>'
+    assert markdown_lab_rs._rust_module.extract_breadcrumbs(
+        html, "https://example.com/docs/"
+    ) == [("Home", "https://example.com/"), ("Docs", None)]
+
+
+def test_clean_html_advanced_selectors():
+    if not markdown_lab_rs.RUST_AVAILABLE:
+        pytest.skip("selector options require the Rust extension")
+
+    rust = markdown_lab_rs._rust_module
+    html = '<header>Site</header><div id="paywall">Pay</div><p>Story</p>'
+    cleaned = rust.clean_html_advanced(
+        html, extra_remove_selectors=["#paywall"], keep_selectors=["header"]
+    )
+    assert "Site" in cleaned and "Story" in cleaned
+    assert "Pay" not in cleaned
+    with pytest.raises(ValueError, match="div\\["):
+        rust.clean_html_advanced(html, keep_selectors=["div["])
+
+
+def test_clean_html_advanced_attributes():
+    if not markdown_lab_rs.RUST_AVAILABLE:
+        pytest.skip("attribute options require the Rust extension")
+
+    rust = markdown_lab_rs._rust_module
+    html = '<p onclick="x()" style="color:red" class="lead">Hi</p><a href="javascript:x()">Go</a>'
+    assert rust.clean_html_advanced(html, strip_style=True, strip_class=True).count(
+        "<p>Hi</p><a>Go</a>"
+    ) == 1
+
+
+def test_sanitize_html_policy():
+    if not markdown_lab_rs.RUST_AVAILABLE:
+        pytest.skip("sanitization requires the Rust extension")
+
+    rust = markdown_lab_rs._rust_module
+    html = '<div><p onclick="x()">Hi <span>there</span></p><a href="javascript:x()">a</a></div>'
+    assert rust.sanitize_html(html) == "<p>Hi there</p><a>a</a>"
+    assert rust.sanitize_html(html, allowed_tags={"span": []}) == "Hi <span>there</span>a"
+
+
+def test_extract_links_detailed():
+    if not markdown_lab_rs.RUST_AVAILABLE:
+        pytest.skip("detailed link extraction requires the Rust extension")
+
+    html = '<p><a href="/a" rel="nofollow">A</a><a href="/a" title="t">Again</a></p>'
+    assert markdown_lab_rs._rust_module.extract_links_detailed(
+        html, "https://example.com/"
+    ) == [
+        {
+            "url": "https://example.com/a",
+            "text": "A | Again",
+            "title": "t",
+            "rel": ["nofollow"],
+            "is_external": False,
+            "kind": "internal",
+            "dom_path_hint": "body > p > a",
+        }
+    ]
+
+
+def test_classify_links():
+    if not markdown_lab_rs.RUST_AVAILABLE:
+        pytest.skip("link classification requires the Rust extension")
+
+    urls = [
+        "https://example.co.uk/a",
+        "https://blog.example.co.uk/",
+        "https://other.co.uk/",
+        "https://example.co.uk/f.zip",
+    ]
+    assert markdown_lab_rs._rust_module.classify_links(
+        urls, "https://www.example.co.uk/"
+    ) == list(zip(urls, ["internal", "subdomain", "external", "asset"]))
+
+
+def test_extract_links_filtered():
+    if not markdown_lab_rs.RUST_AVAILABLE:
+        pytest.skip("link filtering requires the Rust extension")
+
+    rust = markdown_lab_rs._rust_module
+    html = (
+        '<a href="/a">A</a><a href="/a.zip">Zip</a>'
+        '<a href="/private/b">B</a><a href="https://other.org/">Other</a>'
+    )
+    assert rust.extract_links_filtered(
+        html,
+        "https://example.com/",
+        same_domain_only=True,
+        exclude_extensions=["zip"],
+        exclude_patterns=["/private/"],
+    ) == ["https://example.com/a"]
+    with pytest.raises(ValueError):
+        rust.extract_links_filtered(html, "https://example.com/", include_patterns=["("])
+
+
+def test_normalize_url():
+    if not markdown_lab_rs.RUST_AVAILABLE:
+        pytest.skip("URL normalization requires the Rust extension")
+
+    rust = markdown_lab_rs._rust_module
+    url = "HTTPS://Example.com:443//a?b=1&utm_source=x&a=2#top"
+    assert rust.normalize_url(url) == "https://example.com/a?a=2&b=1"
+    assert (
+        rust.normalize_url(url, strip_fragment=False, removed_params=["b"])
+        == "https://example.com/a?a=2&utm_source=x#top"
+    )
+    html = '<a href="/a#x">A</a><a href="/a?fbclid=1">B</a>'
+    assert rust.extract_links(html, "https://example.com", normalize=True) == [
+        "https://example.com/a"
+    ]
+
+
+def test_extract_links_kind_options():
+    if not markdown_lab_rs.RUST_AVAILABLE:
+        pytest.skip("link options require the Rust extension")
+
+    rust = markdown_lab_rs._rust_module
+    html = '<a href="mailto:a@example.com">M</a><a href="tel:1">T</a><a href="#faq">F</a>'
+    assert rust.extract_links(html, "https://example.com/p") == ["mailto:a@example.com", "tel:1"]
+    assert rust.extract_links(
+        html,
+        "https://example.com/p",
+        include_mailto=False,
+        include_tel=False,
+        include_fragments=True,
+    ) == ["https://example.com/p#faq"]
+    assert rust.extract_links(
+        html,
+        "https://example.com/p",
+        include_fragments=True,
+        resolve_fragments_against_base=False,
+    ) == ["#faq", "mailto:a@example.com", "tel:1"]
+
+
+def test_extract_images_detailed():
+    if not markdown_lab_rs.RUST_AVAILABLE:
+        pytest.skip("image extraction requires the Rust extension")
+
+    html = '<img src="blank.gif" data-src="/a.jpg" alt="A" width="10"><img srcset="b.png 2x">'
+    assert markdown_lab_rs._rust_module.extract_images_detailed(
+        html, "https://example.com/"
+    ) == [
+        {
+            "url": "https://example.com/a.jpg",
+            "alt": "A",
+            "width": 10,
+            "height": None,
+            "source": "data-src",
+        },
+        {
+            "url": "https://example.com/b.png",
+            "alt": "",
+            "width": None,
+            "height": None,
+            "source": "srcset",
+        },
+    ]
+
+
+def test_extract_tables():
+    if not markdown_lab_rs.RUST_AVAILABLE:
+        pytest.skip("table extraction requires the Rust extension")
+
+    rust = markdown_lab_rs._rust_module
+    html = "<table><tr><th>A</th><th>B</th></tr><tr><td colspan=2>x</td></tr></table>"
+    assert rust.extract_tables(html) == [
+        {"caption": None, "headers": ["A", "B"], "rows": [["x", "x"]]}
+    ]
+    assert rust.extract_tables_csv(html) == ["A,B\r\nx,x\r\n"]
+
+
+def test_extract_main_content_hidden():
+    if not markdown_lab_rs.RUST_AVAILABLE:
+        pytest.skip("hidden element filtering requires the Rust extension")
+
+    rust = markdown_lab_rs._rust_module
+    html = "<main><p>Shown</p><div hidden>Answer</div></main>"
+    assert "Answer" not in rust.extract_main_content(html)
+    assert "Answer" in rust.extract_main_content(html, include_hidden=True)
+
+
+def test_extract_headings():
+    if not markdown_lab_rs.RUST_AVAILABLE:
+        pytest.skip("heading outlines require the Rust extension")
+
+    rust = markdown_lab_rs._rust_module
+    html = '<h1 id="top">Guide</h1><h3>Setup</h3><h2>Use <a href="/api">the API</a></h2>'
+    outline = rust.extract_headings(html)
+    assert [h["text"] for h in outline] == ["Guide"]
+    assert outline[0]["id"] == "top"
+    assert [h["text"] for h in outline[0]["children"]] == ["Setup", "Use the API"]
+    assert rust.extract_headings(html, flat=True)[2] == {
+        "level": 2,
+        "text": "Use the API",
+        "id": None,
+    }
+
+
+def test_extract_text_content():
+    if not markdown_lab_rs.RUST_AVAILABLE:
+        pytest.skip("plain text extraction requires the Rust extension")
+
+    html = "<p>One<br>Two</p><script>skip()</script><p>Three</p>"
+    text = markdown_lab_rs._rust_module.extract_text_content(html)
+    assert text == "One\nTwo\n\nThree"
+
+
+def test_convert_html_bytes():
+    if not markdown_lab_rs.RUST_AVAILABLE:
+        pytest.skip("charset detection requires the Rust extension")
+
+    html = b'<meta charset="windows-1252"><p>\x93Caf\xe9\x94</p>'
+    markdown = markdown_lab_rs._rust_module.convert_html_bytes(
+        html, "https://example.com", title_mode="never"
+    )
+    assert "\u201cCaf\u00e9\u201d" in markdown
+    sjis = "<p>\u65e5\u672c</p>".encode("shift_jis")
+    assert "\u65e5\u672c" in markdown_lab_rs._rust_module.convert_html_bytes(
+        sjis, "https://example.com", encoding="shift_jis"
+    )
+
+
+def test_convert_html_with_frames():
+    if not markdown_lab_rs.RUST_AVAILABLE:
+        pytest.skip("frame splicing requires the Rust extension")
+
+    rust = markdown_lab_rs._rust_module
+    html = '<main><iframe src="/body.html"></iframe></main>'
+    sources = rust.extract_iframe_sources(html, "https://example.com/")
+    assert sources == ["https://example.com/body.html"]
+    markdown = rust.convert_html_with_frames(
+        html,
+        "https://example.com/",
+        {sources[0]: "<body><p>Framed content</p></body>"},
+        title_mode="never",
+    )
+    assert markdown.strip() == "Framed content"
+
+
+def test_extract_forms_and_removal():
+    if not markdown_lab_rs.RUST_AVAILABLE:
+        pytest.skip("form handling requires the Rust extension")
+
+    rust = markdown_lab_rs._rust_module
+    html = '<p>Story</p><form action="/s"><input name="q"><button>Go</button></form>'
+    assert "Go" not in rust.clean_html(html)
+    assert "Go" in rust.clean_html_advanced(html, keep_forms=True)
+    forms = rust.extract_forms(html)
+    assert forms[0]["action"] == "/s" and forms[0]["method"] == "GET"
+    assert [field["name"] for field in forms[0]["fields"]] == ["q"]
+
+
+def test_output_format_aliases():
+    if not markdown_lab_rs.RUST_AVAILABLE:
+        pytest.skip("format parsing requires the Rust extension")
+
+    rust_format = markdown_lab_rs._rust_module.OutputFormat
+    assert rust_format.from_str("md") == rust_format.Markdown
+    assert rust_format.from_str("Markdown") == rust_format.Markdown
+    assert rust_format.from_str("JSON") == rust_format.Json
+    assert rust_format.from_str("xml") == rust_format.Xml
+    assert rust_format.from_str("yml") == rust_format.Yaml
+    assert str(rust_format.Json) == "json"
+    assert repr(rust_format.Json) == "OutputFormat.JSON"
+
+    html = "<h1>Title</h1>"
+    assert markdown_lab_rs.convert_html_to_format(html, "https://example.com", "MD") == (
+        markdown_lab_rs.convert_html_to_format(html, "https://example.com", "markdown")
+    )
+
+
+def test_output_format_rejects_unknown():
+    if not markdown_lab_rs.RUST_AVAILABLE:
+        pytest.skip("format parsing requires the Rust extension")
+
+    rust_format = markdown_lab_rs._rust_module.OutputFormat
+    with pytest.raises(ValueError, match="expected one of: markdown"):
+        rust_format.from_str("markdwon")
+    with pytest.raises(ValueError, match="markdwon"):
+        markdown_lab_rs.convert_html_to_format(
+            "<p>Hi</p>", "https://example.com", "markdwon"
+        )
+
+
 def test_chunk_markdown():
     markdown = """
 # Title
@@ -42,6 +516,295 @@ This is a test paragraph.
     assert any("## Section 2" in chunk for chunk in chunks)
 
 
+def test_chunk_markdown_with_metadata():
+    if not markdown_lab_rs.RUST_AVAILABLE:
+        pytest.skip("Rust extension not available")
+
+    markdown = "# Title\n\nIntro text.\n\n## Section 1\n\nThis is a test paragraph."
+    chunks = markdown_lab_rs._rust_module.chunk_markdown_with_metadata(markdown, 500, 50)
+    contents = markdown_lab_rs._rust_module.chunk_markdown(markdown, 500, 50)
+
+    assert [chunk["content"] for chunk in chunks] == contents
+    assert chunks[0]["heading"] == "Title"
+    assert chunks[0]["level"] == 1
+    assert chunks[0]["heading_path"] == ["Title"]
+    assert chunks[-1]["heading_path"] == ["Title", "Section 1"]
+
+    again = markdown_lab_rs._rust_module.chunk_markdown_with_metadata(markdown, 500, 50)
+    assert [chunk["id"] for chunk in again] == [chunk["id"] for chunk in chunks]
+    sourced = markdown_lab_rs._rust_module.chunk_markdown_with_metadata(
+        markdown, 500, 50, source_id="https://example.com"
+    )
+    assert sourced[0]["id"] != chunks[0]["id"]
+    assert [chunk["position"] for chunk in chunks] == list(range(len(chunks)))
+    for chunk in chunks:
+        assert chunk["char_count"] == len(chunk["content"])
+        assert isinstance(chunk["semantic_density"], float)
+
+    accented = "# Café\n\nLe café est noir. Il fait très chaud ici. Nous buvons du thé vert."
+    for chunk in markdown_lab_rs._rust_module.chunk_markdown_with_metadata(accented, 40, 10):
+        assert accented[chunk["start_offset"] : chunk["end_offset"]] == chunk["content"]
+
+
+def test_chunk_markdown_overlap():
+    if not markdown_lab_rs.RUST_AVAILABLE:
+        pytest.skip("Rust extension not available")
+
+    chunk_markdown = markdown_lab_rs._rust_module.chunk_markdown
+    markdown = "# A\n\nOne sentence is here. Final words of A.\n\n# B\n\nBody of B."
+
+    assert chunk_markdown(markdown, 500, 30)[1].startswith("# B")
+    chunks = chunk_markdown(markdown, 500, 30, overlap_across_sections=True)
+    assert chunks[1].startswith("Final words of A.")
+
+    with pytest.raises(ValueError):
+        chunk_markdown(markdown, 20, 30)
+    with pytest.raises(ValueError, match="greater than zero"):
+        chunk_markdown(markdown, 0, 0)
+    assert chunk_markdown(markdown, 31, 30)
+
+    sections = "".join(f"## Step {n}\n\nDo thing {n}.\n\n" for n in range(1, 11))
+    assert len(chunk_markdown(sections, 100, 0)) == 10
+    merged = chunk_markdown(sections, 100, 0, min_chunk_size=50)
+    assert 1 < len(merged) < 10
+    assert all(len(chunk) >= 50 for chunk in merged)
+
+
+def test_chunk_html():
+    if not markdown_lab_rs.RUST_AVAILABLE:
+        pytest.skip("Rust extension not available")
+
+    chunk_html = markdown_lab_rs._rust_module.chunk_html
+    html = (
+        "<html><body><nav>Menu</nav><main><h1>Install</h1><p>Download it.</p>"
+        "<h2>Linux</h2><p>Use the tarball.</p></main></body></html>"
+    )
+    base_url = "https://example.com/docs/"
+
+    chunks = chunk_html(html, base_url, 500, 50)
+    markdown = markdown_lab_rs._rust_module.convert_html_to_format(html, base_url)
+    assert chunks == markdown_lab_rs._rust_module.chunk_markdown(markdown, 500, 50)
+
+    detailed = chunk_html(html, base_url, 500, 50, metadata=True)
+    assert [chunk["content"] for chunk in detailed] == chunks
+    assert all(chunk["source_id"] == base_url for chunk in detailed)
+    assert detailed[-1]["heading_path"] == ["Install", "Linux"]
+
+
+def test_chunk_markdown_recursive_strategy():
+    if not markdown_lab_rs.RUST_AVAILABLE:
+        pytest.skip("Rust extension not available")
+
+    chunk_markdown = markdown_lab_rs._rust_module.chunk_markdown
+    markdown = "# Guide\n\nFirst paragraph.\n\nSecond one. It has two sentences."
+
+    chunks = chunk_markdown(markdown, 40, 0, strategy="recursive")
+    assert chunks == ["# Guide\n\nFirst paragraph.", "Second one. It has two sentences."]
+
+    with pytest.raises(ValueError, match="strategy"):
+        chunk_markdown(markdown, 40, 0, strategy="fixed")
+
+
+def test_chunk_markdown_heading_strategy():
+    if not markdown_lab_rs.RUST_AVAILABLE:
+        pytest.skip("Rust extension not available")
+
+    markdown = (
+        "# Manual\n\nOverview.\n\n"
+        "## Install\n\nGet it.\n\n### Linux\n\nUse apt.\n\n"
+        "## Usage\n\nRun it.\n\n### Flags\n\nPass --help."
+    )
+    rust = markdown_lab_rs._rust_module
+
+    chunks = rust.chunk_markdown(markdown, 20, 0, strategy="heading", heading_level=2)
+    assert len(chunks) == 3
+    assert chunks[1].startswith("## Install") and "### Linux" in chunks[1]
+    assert chunks[2].startswith("## Usage") and "### Flags" in chunks[2]
+
+    detailed = rust.chunk_markdown_with_metadata(markdown, 20, 0, strategy="heading")
+    assert [(chunk["heading"], chunk["level"]) for chunk in detailed] == [
+        ("Manual", 1),
+        ("Install", 2),
+        ("Usage", 2),
+    ]
+    assert len(rust.chunk_markdown(markdown, 20, 0, strategy="heading", heading_level=1)) == 1
+
+    with pytest.raises(ValueError, match="heading_level"):
+        rust.chunk_markdown(markdown, 20, 0, strategy="heading", heading_level=7)
+
+
+def test_chunk_markdown_sized():
+    if not markdown_lab_rs.RUST_AVAILABLE:
+        pytest.skip("Rust extension not available")
+
+    chunk_markdown_sized = markdown_lab_rs._rust_module.chunk_markdown_sized
+    sentence = "Each chunk should fit into the context window of a language model. "
+    markdown = "# Title\n\n" + sentence * 20
+
+    chunks = chunk_markdown_sized(markdown, 50, 10, unit="words")
+    assert len(chunks) > 1
+    assert all(len(chunk.split()) <= 50 for chunk in chunks)
+    assert len(chunk_markdown_sized(markdown, 50, 10)) > len(chunks)
+
+    with pytest.raises(ValueError, match="unit"):
+        chunk_markdown_sized(markdown, 50, 10, unit="bytes")
+
+
+def test_chunker_options():
+    if not markdown_lab_rs.RUST_AVAILABLE:
+        pytest.skip("Rust extension not available")
+
+    rust = markdown_lab_rs._rust_module
+    markdown = "# Guide\n\n" + "Each sentence adds a little more text to the guide. " * 20
+
+    options = rust.ChunkerOptions(chunk_size=200, chunk_overlap=40, strategy="recursive")
+    assert (options.chunk_size, options.chunk_overlap) == (200, 40)
+    assert rust.chunk_markdown(markdown, options=options) == rust.chunk_markdown(
+        markdown, 200, 40, strategy="recursive"
+    )
+    words = rust.ChunkerOptions(chunk_size=30, chunk_overlap=5, unit="words")
+    assert all(len(chunk.split()) <= 30 for chunk in rust.chunk_markdown(markdown, options=words))
+
+    steps = "## Steps\n\n" + "\n".join(f"- Step {n} of the long procedure." for n in range(100))
+    marked = rust.ChunkerOptions(chunk_size=200, chunk_overlap=0, list_continuation=True)
+    chunks = rust.chunk_markdown(steps, options=marked)
+    assert all(chunk.startswith("…continued from list under Steps\n- Step") for chunk in chunks[1:])
+
+    with pytest.raises(ValueError):
+        rust.ChunkerOptions(chunk_size=100, chunk_overlap=100)
+    with pytest.raises(ValueError):
+        rust.chunk_markdown(markdown, 200, 40, options=options)
+    with pytest.raises(TypeError):
+        rust.chunk_markdown(markdown)
+
+
+def test_chunk_markdown_to_manifest():
+    if not markdown_lab_rs.RUST_AVAILABLE:
+        pytest.skip("Rust extension not available")
+
+    rust = markdown_lab_rs._rust_module
+    markdown = "# Guide\n\n" + "Each sentence adds a little more text to the guide. " * 20
+    options = rust.ChunkerOptions(chunk_size=200, chunk_overlap=40, source_id="guide.md")
+
+    chunks, manifest = rust.chunk_markdown_to_manifest(markdown, options)
+    manifest = json.loads(manifest)
+    assert manifest["version"] == 1
+    assert manifest["source_id"] == "guide.md"
+    assert manifest["char_count"] == len(markdown)
+    assert manifest["report"]["truncated"] is False
+    assert chunks == rust.chunk_markdown(markdown, options=options)
+
+    detailed = rust.chunk_markdown_with_report(markdown, options, metadata=True)[0]
+    assert len(manifest["chunks"]) == len(chunks)
+    for i, (chunk, entry) in enumerate(zip(chunks, manifest["chunks"])):
+        assert entry["position"] == i
+        assert entry["id"] == detailed[i]["id"]
+        assert markdown[entry["start_offset"] : entry["end_offset"]].endswith(chunk[-10:])
+
+
+def test_paragraph_strategy():
+    if not markdown_lab_rs.RUST_AVAILABLE:
+        pytest.skip("Rust extension not available")
+
+    rust = markdown_lab_rs._rust_module
+    markdown = (
+        "alice: has anyone seen this error?\n\n"
+        "bob: try this\n\n"
+        "```python\n# install first\nimport os\n\n# then run\nos.system('make')\n```\n\n"
+        "# yes that fixed it\n\n"
+        "carol: thanks!\n\n"
+        "## dave: same here"
+    )
+
+    chunks = rust.chunk_markdown(markdown, 1000, 0, strategy="paragraphs", paragraphs_per_chunk=2)
+    assert len(chunks) == 3
+    assert chunks[1].startswith("```python\n# install first")
+    assert chunks[1].endswith("# yes that fixed it")
+
+    detailed = rust.chunk_markdown_with_metadata(
+        markdown, 1000, 1, strategy="paragraphs", paragraphs_per_chunk=3
+    )
+    assert [chunk["paragraphs"] for chunk in detailed] == [(0, 3), (2, 5), (4, 6)]
+    assert all(chunk["heading"] is None for chunk in detailed)
+    assert rust.chunk_markdown_with_metadata(markdown, 1000, 0)[0]["paragraphs"] is None
+
+    options = rust.ChunkerOptions(chunk_overlap=1, strategy="paragraphs", paragraphs_per_chunk=4)
+    assert len(rust.chunk_markdown(markdown, options=options)) == 2
+    with pytest.raises(ValueError):
+        rust.ChunkerOptions(chunk_overlap=4, strategy="paragraphs", paragraphs_per_chunk=4)
+
+
+def test_overlap_ratio():
+    if not markdown_lab_rs.RUST_AVAILABLE:
+        pytest.skip("Rust extension not available")
+
+    rust = markdown_lab_rs._rust_module
+    markdown = "# Guide\n\n" + "Each sentence adds a little more text to the guide. " * 20
+
+    # A float is a fraction of chunk_size, an int a count
+    assert rust.ChunkerOptions(chunk_size=1000, chunk_overlap=0.1).chunk_overlap == 100
+    assert rust.ChunkerOptions(chunk_size=1000, chunk_overlap=100).chunk_overlap == 100
+    assert rust.ChunkerOptions(chunk_size=7, chunk_overlap=0.5).chunk_overlap == 3
+    tokens = rust.ChunkerOptions(chunk_size=512, chunk_overlap=0.25, unit="tokens")
+    assert tokens.chunk_overlap == 128
+    assert rust.chunk_markdown(markdown, 200, 0.2) == rust.chunk_markdown(markdown, 200, 40)
+
+    for ratio in [0.6, -0.1]:
+        with pytest.raises(ValueError):
+            rust.ChunkerOptions(chunk_overlap=ratio)
+        with pytest.raises(ValueError):
+            rust.chunk_markdown(markdown, 200, ratio)
+
+
+def test_chunk_markdown_with_report():
+    if not markdown_lab_rs.RUST_AVAILABLE:
+        pytest.skip("Rust extension not available")
+
+    rust = markdown_lab_rs._rust_module
+    markdown = "".join(f"## Section {n}\n\nBody of section {n}.\n\n" for n in range(1, 1001))
+
+    for truncation in ["truncate", "sample", "merge"]:
+        options = rust.ChunkerOptions(
+            chunk_size=200, chunk_overlap=0, max_chunks=100, truncation=truncation
+        )
+        chunks, report = rust.chunk_markdown_with_report(markdown, options)
+        assert len(chunks) == 100
+        assert report == {
+            "produced": 1000,
+            "returned": 100,
+            "max_chunks": 100,
+            "truncated": True,
+            "strategy": truncation,
+        }
+
+    chunks, report = rust.chunk_markdown_with_report(
+        markdown, rust.ChunkerOptions(chunk_size=200, chunk_overlap=0), metadata=True
+    )
+    assert len(chunks) == 1000 and chunks[0]["heading"] == "Section 1"
+    assert not report["truncated"]
+
+    with pytest.raises(ValueError, match="truncation"):
+        rust.ChunkerOptions(max_chunks=10, truncation="drop")
+
+
+def test_iter_chunks():
+    if not markdown_lab_rs.RUST_AVAILABLE:
+        pytest.skip("Rust extension not available")
+
+    rust = markdown_lab_rs._rust_module
+    markdown = "# Log\n\n" + "Another line was written to the log. " * 200
+
+    stream = rust.iter_chunks(markdown, 300, 50)
+    assert iter(stream) is stream
+    assert list(stream) == rust.chunk_markdown(markdown, 300, 50)
+
+    first = next(rust.iter_chunks(markdown, 300, 50, source_id="log", metadata=True))
+    assert first == rust.chunk_markdown_with_metadata(markdown, 300, 50, source_id="log")[0]
+
+    with pytest.raises(ValueError):
+        rust.iter_chunks(markdown, 50, 50)
+
+
 @pytest.mark.integration
 def test_render_js_page():
     url = "https://httpbin.org/html"  # More reliable test endpoint
@@ -55,6 +818,39 @@ def test_render_js_page():
         pytest.skip("Network request failed, which is acceptable in test environment")
 
 
+def test_render_js_page_wait_for_selector():
+    if not markdown_lab_rs.RUST_AVAILABLE:
+        pytest.skip("Rust extension not available")
+
+    rust = markdown_lab_rs._rust_module
+    page = "inline://<html><body><div id='app'>Loaded</div></body></html>"
+    try:
+        html = rust.render_js_page(page, 100, wait_for_selector="#app")
+    except RuntimeError:
+        pytest.skip("inline:// pages need the offline_tests feature")
+    assert "Loaded" in html
+
+    with pytest.raises(TimeoutError):
+        rust.render_js_page(page, 100, wait_for_selector="#missing")
+    with pytest.raises(TimeoutError):
+        markdown_lab_rs.render_js_page(page, 100, wait_for_selector="#missing")
+
+
+def test_render_js_page_exec():
+    if not markdown_lab_rs.RUST_AVAILABLE:
+        pytest.skip("Rust extension not available")
+
+    rust = markdown_lab_rs._rust_module
+    page = "inline://<html><body><div id='app'>Loaded</div></body></html>"
+    try:
+        html, result = rust.render_js_page_exec(page, "document.title", 0)
+    except RuntimeError:
+        pytest.skip("inline:// pages need the offline_tests feature")
+    assert "Loaded" in html
+    # Inline pages are served as-is, without running scripts
+    assert result is None
+
+
 def test_error_handling():
     with pytest.raises(TypeError):
         markdown_lab_rs.convert_html_to_markdown(None, "https://example.com")